@@ -29,6 +29,16 @@ enum Commands {
         #[arg(short, long, default_value = "false")]
         once: bool,
     },
+    /// Lists the pages that show any of the given items
+    PagesForItem {
+        #[arg(required = true)]
+        items: Vec<String>,
+    },
+    /// Queues the pages that show any of the given items for an early update
+    PrioritizeItem {
+        #[arg(required = true)]
+        items: Vec<String>,
+    },
 }
 
 #[tokio::main(flavor = "multi_thread")]
@@ -56,6 +66,8 @@ async fn main() -> Result<()> {
         Commands::Page { server, page } => main.process_page(&server, &page).await,
         Commands::Wikidata => main.run_wikidata_bot().await,
         Commands::SingleWiki { once } => main.run_single_wiki_bot(once).await,
+        Commands::PagesForItem { items } => main.pages_for_items(&items).await,
+        Commands::PrioritizeItem { items } => main.prioritize_items(&items).await,
     }
 }
 
@@ -4,13 +4,15 @@
 //! further requests immediately for `RECOVERY_SECS`, giving the downstream
 //! endpoint time to recover. After that window one probe request is allowed
 //! through (half-open state); a success resets the breaker, a failure
-//! re-opens it.
+//! re-opens it. Since pages deferred by an open breaker are not run,
+//! [`CircuitBreaker::take_probe_due`] tells the bot when to release one of
+//! them as that probe.
 //!
 //! All state is stored in lock-free atomics, making `CircuitBreaker` cheap to
 //! clone behind an `Arc` and safe to share across async tasks.
 
 use std::future::Future;
-use std::sync::atomic::{AtomicI64, AtomicU32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU32, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Consecutive failures before the circuit opens.
@@ -23,10 +25,16 @@ const RECOVERY_SECS: i64 = 60;
 /// State is encoded in two atomics:
 /// - `consecutive_failures`: incremented on each failure, reset to 0 on success.
 /// - `opened_at_secs`: unix-second timestamp when the circuit was opened; 0 = closed.
+///
+/// `recovered` is set when a success closes a circuit that had opened, until
+/// [`CircuitBreaker::take_recovered`] picks it up. `probe_at_secs` is when
+/// [`CircuitBreaker::take_probe_due`] last asked for a probe page.
 #[derive(Debug, Default)]
 pub struct CircuitBreaker {
     consecutive_failures: AtomicU32,
     opened_at_secs: AtomicI64,
+    recovered: AtomicBool,
+    probe_at_secs: AtomicI64,
 }
 
 impl CircuitBreaker {
@@ -69,8 +77,37 @@ impl CircuitBreaker {
 
     /// Record a successful request. Resets the failure counter and closes the circuit.
     pub fn record_success(&self) {
-        self.consecutive_failures.store(0, Ordering::Relaxed);
+        let failures = self.consecutive_failures.swap(0, Ordering::Relaxed);
         self.opened_at_secs.store(0, Ordering::Relaxed);
+        if failures >= FAILURE_THRESHOLD {
+            self.recovered.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns `true` once after the circuit closed again following an
+    /// outage, so pages deferred by it can be picked up.
+    pub fn take_recovered(&self) -> bool {
+        self.recovered.swap(false, Ordering::Relaxed)
+    }
+
+    /// Returns `true` once every `RECOVERY_SECS` while the circuit is open
+    /// and due for its half-open probe. Pages deferred by the breaker are not
+    /// run, so without one of them let through as the probe, a breaker whose
+    /// pages are all deferred would never see the success that closes it.
+    pub fn take_probe_due(&self) -> bool {
+        let opened_at = self.opened_at_secs.load(Ordering::Relaxed);
+        if opened_at == 0 {
+            return false;
+        }
+        let now = Self::now_secs();
+        let probe_at = self.probe_at_secs.load(Ordering::Relaxed);
+        if now - opened_at.max(probe_at) < RECOVERY_SECS {
+            return false;
+        }
+        // CAS so that only one caller releases a probe per window
+        self.probe_at_secs
+            .compare_exchange(probe_at, now, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
     }
 
     /// Record a failed request. Opens the circuit once the failure threshold is reached.
@@ -259,6 +296,46 @@ mod tests {
         assert!(cb.is_open(), "failed probe must re-open the circuit");
     }
 
+    #[test]
+    fn test_take_recovered_after_outage() {
+        let cb = CircuitBreaker::new();
+        cb.record_failure();
+        cb.record_success();
+        assert!(!cb.take_recovered(), "no outage, nothing to recover");
+
+        open_and_age_past_recovery(&cb);
+        assert!(!cb.is_open());
+        assert!(!cb.take_recovered(), "the probe has not succeeded yet");
+        cb.record_success();
+        assert!(cb.take_recovered());
+        assert!(!cb.take_recovered(), "reported only once");
+    }
+
+    #[test]
+    fn test_probe_due_without_requests() {
+        let cb = CircuitBreaker::new();
+        assert!(!cb.take_probe_due(), "closed, no probe needed");
+        for _ in 0..FAILURE_THRESHOLD {
+            cb.record_failure();
+        }
+        assert!(!cb.take_probe_due(), "still inside the recovery window");
+
+        // All pages of the breaker are deferred, so no request ever calls
+        // is_open(); the breaker still asks for one probe per window
+        open_and_age_past_recovery(&cb);
+        assert!(cb.take_probe_due());
+        assert!(!cb.take_probe_due(), "one probe per window");
+        let aged = CircuitBreaker::now_secs() - RECOVERY_SECS - 1;
+        cb.probe_at_secs.store(aged, Ordering::Relaxed);
+        assert!(cb.take_probe_due(), "the probe never ran, ask again");
+
+        // The probe goes through and succeeds
+        assert!(!cb.is_open());
+        cb.record_success();
+        assert!(cb.take_recovered());
+        assert!(!cb.take_probe_due());
+    }
+
     // ── with_breaker ──────────────────────────────────────────────────────
 
     #[tokio::test]
@@ -0,0 +1,462 @@
+//! Per-wiki citation template mapping.
+//!
+//! A reference is rendered through one of three templates, chosen by its
+//! shape: `web` (a URL with a title), `publication` (a "stated in" source
+//! with page, volume, issue, author or publication date) and `item` (a bare
+//! "stated in" source). Each template maps the fields Listeria extracts to
+//! the wiki's own parameter names, so `{{cite web}}`, `{{Internetquelle}}`
+//! and `{{Lien web}}` can all be fed from the same reference. Shapes without
+//! a template fall back to a bare URL or a link to the source item.
+//!
+//! Every wiki starts from what the `use_invoke`/`use_cite_web` flags of its
+//! row in the `wikis` table select (see [`CitationTemplates::from_wiki_flags`]);
+//! the `citation_templates` config only overrides that.
+
+use serde_json::Value;
+
+/// What a reference consists of, which decides the template used for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceShape {
+    Web,
+    Publication,
+    Item,
+}
+
+/// A reference field that can be passed to a citation template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CitationField {
+    /// The "stated in" item ID, for templates like `{{Cite Q}}`.
+    Item,
+    Url,
+    Title,
+    Author,
+    /// The "stated in" source, as a link.
+    Website,
+    /// Publication date.
+    Date,
+    Volume,
+    Issue,
+    Page,
+    /// Retrieval date.
+    AccessDate,
+}
+
+impl CitationField {
+    /// All fields, in the order they are written into a template.
+    pub const ALL: [Self; 10] = [
+        Self::Item,
+        Self::Url,
+        Self::Title,
+        Self::Author,
+        Self::Website,
+        Self::Date,
+        Self::Volume,
+        Self::Issue,
+        Self::Page,
+        Self::AccessDate,
+    ];
+
+    const fn key(self) -> &'static str {
+        match self {
+            Self::Item => "item",
+            Self::Url => "url",
+            Self::Title => "title",
+            Self::Author => "author",
+            Self::Website => "website",
+            Self::Date => "date",
+            Self::Volume => "volume",
+            Self::Issue => "issue",
+            Self::Page => "page",
+            Self::AccessDate => "access_date",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.key() == key)
+    }
+}
+
+/// `{{cite web}}`-style parameter names, used when a template gives none.
+const DEFAULT_PARAMS: &[(CitationField, &str)] = &[
+    (CitationField::Url, "url"),
+    (CitationField::Title, "title"),
+    (CitationField::Author, "author"),
+    (CitationField::Website, "website"),
+    (CitationField::Date, "date"),
+    (CitationField::Volume, "volume"),
+    (CitationField::Issue, "issue"),
+    (CitationField::Page, "page"),
+    (CitationField::AccessDate, "access-date"),
+];
+
+/// The `{{cite web}}` parameters selected by the `use_cite_web` flag.
+const CITE_WEB_PARAMS: &[(CitationField, &str)] = &[
+    (CitationField::Url, "url"),
+    (CitationField::Title, "title"),
+    (CitationField::Website, "website"),
+    (CitationField::AccessDate, "access-date"),
+];
+
+/// One citation template and the parameter name for each field it takes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CitationTemplate {
+    /// Template name as written after `{{`; may be a parser function such as
+    /// `#invoke:cite|web`.
+    name: String,
+    params: Vec<(CitationField, String)>,
+}
+
+impl CitationTemplate {
+    fn new(name: &str, params: &[(CitationField, &str)]) -> Self {
+        Self {
+            name: name.to_string(),
+            params: params
+                .iter()
+                .map(|(field, param)| (*field, (*param).to_string()))
+                .collect(),
+        }
+    }
+
+    /// Parses `"cite web"` (default parameter names) or
+    /// `{"template": "Internetquelle", "params": {"title": "titel", …}}`.
+    /// With `params`, only the listed fields are passed to the template.
+    fn from_json(shape: &str, j: &Value) -> Option<Self> {
+        if let Some(name) = j.as_str() {
+            return Some(Self::new(name, DEFAULT_PARAMS));
+        }
+        let Some(name) = j["template"].as_str() else {
+            log::warn!("Ignoring citation_templates.{shape} without a template name: {j}");
+            return None;
+        };
+        let Some(o) = j["params"].as_object() else {
+            return Some(Self::new(name, DEFAULT_PARAMS));
+        };
+        let mut params = Vec::with_capacity(o.len());
+        for (key, param) in o {
+            match (CitationField::from_key(key), param.as_str()) {
+                (Some(field), Some(param)) => params.push((field, param.to_string())),
+                _ => log::warn!("Ignoring citation_templates.{shape}.params.{key}: {param}"),
+            }
+        }
+        Some(Self {
+            name: name.to_string(),
+            params,
+        })
+    }
+
+    /// Renders the template from `values`, in [`CitationField::ALL`] order.
+    /// Fields without a value or without a parameter are left out; a
+    /// parameter named `1` is written positionally.
+    #[must_use]
+    pub fn render(&self, values: &[(CitationField, String)]) -> String {
+        let mut ret = format!("{{{{{}", self.name);
+        for field in CitationField::ALL {
+            let Some((_, param)) = self.params.iter().find(|(f, _)| *f == field) else {
+                continue;
+            };
+            let Some((_, value)) = values.iter().find(|(f, v)| *f == field && !v.is_empty()) else {
+                continue;
+            };
+            let value = Self::escape_pipes(value);
+            if param == "1" {
+                ret += &format!("|{value}");
+            } else {
+                ret += &format!("|{param}={value}");
+            }
+        }
+        ret += "}}";
+        ret
+    }
+
+    /// Escapes the pipes in `value` that would end the parameter; those in
+    /// links and templates are left alone.
+    fn escape_pipes(value: &str) -> String {
+        let mut ret = String::with_capacity(value.len());
+        let mut depth: usize = 0;
+        let mut rest = value;
+        while let Some(c) = rest.chars().next() {
+            if rest.starts_with("[[") || rest.starts_with("{{") {
+                depth += 1;
+                ret.push_str(&rest[..2]);
+                rest = &rest[2..];
+                continue;
+            }
+            if rest.starts_with("]]") || rest.starts_with("}}") {
+                depth = depth.saturating_sub(1);
+                ret.push_str(&rest[..2]);
+                rest = &rest[2..];
+                continue;
+            }
+            if c == '|' && depth == 0 {
+                ret.push_str("{{!}}");
+            } else {
+                ret.push(c);
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+        ret
+    }
+
+    /// Whether the template takes `field` at all.
+    #[must_use]
+    pub fn uses(&self, field: CitationField) -> bool {
+        self.params.iter().any(|(f, _)| *f == field)
+    }
+}
+
+/// The citation templates of one wiki, by reference shape.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CitationTemplates {
+    web: Option<CitationTemplate>,
+    publication: Option<CitationTemplate>,
+    item: Option<CitationTemplate>,
+}
+
+impl CitationTemplates {
+    /// Parses a `citation_templates` config object.
+    #[must_use]
+    pub fn from_json(j: &Value) -> Self {
+        Self::default().merged_with(j)
+    }
+
+    /// The templates the `use_invoke`/`use_cite_web` flags of a wiki select:
+    /// web references through `{{#invoke:cite|web}}` or `{{cite web}}` (with
+    /// `url`, `title`, `website` and `access-date`) if `use_cite_web` is set,
+    /// and no template for anything else.
+    #[must_use]
+    pub fn from_wiki_flags(use_invoke: bool, use_cite_web: bool) -> Self {
+        let name = if use_invoke {
+            "#invoke:cite|web"
+        } else {
+            "cite web"
+        };
+        Self {
+            web: use_cite_web.then(|| CitationTemplate::new(name, CITE_WEB_PARAMS)),
+            publication: None,
+            item: None,
+        }
+    }
+
+    /// Applies the shapes present in `j` over `self`; `null` or `false`
+    /// removes a shape's template. The `preset` key is left to the caller
+    /// (see [`Self::preset`]).
+    #[must_use]
+    pub fn merged_with(mut self, j: &Value) -> Self {
+        let Some(o) = j.as_object() else {
+            if !j.is_null() {
+                log::warn!("Ignoring non-object citation_templates config: {j}");
+            }
+            return self;
+        };
+        for (shape, v) in o {
+            if shape == "preset" {
+                continue;
+            }
+            let template = match v {
+                Value::Null | Value::Bool(false) => None,
+                v => match CitationTemplate::from_json(shape, v) {
+                    Some(template) => Some(template),
+                    None => continue,
+                },
+            };
+            match shape.as_str() {
+                "web" => self.web = template,
+                "publication" => self.publication = template,
+                "item" => self.item = template,
+                _ => log::warn!("Ignoring unknown citation_templates shape: {shape}"),
+            }
+        }
+        self
+    }
+
+    /// Built-in templates for wikis whose citation templates are well known,
+    /// used where the config sets `"preset": true`.
+    #[must_use]
+    pub fn preset(wiki: &str) -> Option<Self> {
+        use CitationField::{
+            AccessDate, Author, Date, Issue, Item, Page, Title, Url, Volume, Website,
+        };
+        match wiki {
+            "enwiki" => Some(Self {
+                web: Some(CitationTemplate::new("#invoke:cite|web", DEFAULT_PARAMS)),
+                publication: Some(CitationTemplate::new(
+                    "Cite Q",
+                    &[
+                        (Item, "1"),
+                        (Volume, "volume"),
+                        (Issue, "issue"),
+                        (Page, "page"),
+                        (Url, "url"),
+                        (AccessDate, "access-date"),
+                    ],
+                )),
+                item: None,
+            }),
+            "dewiki" => Some(Self {
+                web: Some(CitationTemplate::new(
+                    "Internetquelle",
+                    &[
+                        (Url, "url"),
+                        (Title, "titel"),
+                        (Author, "autor"),
+                        (Website, "werk"),
+                        (Date, "datum"),
+                        (Page, "seiten"),
+                        (AccessDate, "abruf"),
+                    ],
+                )),
+                publication: Some(CitationTemplate::new(
+                    "Literatur",
+                    &[
+                        (Author, "Autor"),
+                        (Title, "Titel"),
+                        (Website, "Sammelwerk"),
+                        (Volume, "Band"),
+                        (Issue, "Nummer"),
+                        (Date, "Datum"),
+                        (Page, "Seiten"),
+                        (Url, "Online"),
+                        (AccessDate, "Abruf"),
+                    ],
+                )),
+                item: None,
+            }),
+            "frwiki" => Some(Self {
+                web: Some(CitationTemplate::new(
+                    "Lien web",
+                    &[
+                        (Url, "url"),
+                        (Title, "titre"),
+                        (Author, "auteur"),
+                        (Website, "site"),
+                        (Date, "date"),
+                        (Page, "page"),
+                        (AccessDate, "consulté le"),
+                    ],
+                )),
+                publication: Some(CitationTemplate::new(
+                    "Article",
+                    &[
+                        (Author, "auteur"),
+                        (Title, "titre"),
+                        (Website, "périodique"),
+                        (Volume, "volume"),
+                        (Issue, "numéro"),
+                        (Date, "date"),
+                        (Page, "pages"),
+                        (Url, "lire en ligne"),
+                        (AccessDate, "consulté le"),
+                    ],
+                )),
+                item: None,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The template for `shape`. Publications without their own template
+    /// use the `item` one.
+    #[must_use]
+    pub fn get(&self, shape: ReferenceShape) -> Option<&CitationTemplate> {
+        match shape {
+            ReferenceShape::Web => self.web.as_ref(),
+            ReferenceShape::Publication => self.publication.as_ref().or(self.item.as_ref()),
+            ReferenceShape::Item => self.item.as_ref(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // ── render ─────────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_render_in_field_order() {
+        let t = CitationTemplate::new("cite web", DEFAULT_PARAMS);
+        let values = vec![
+            (CitationField::AccessDate, "2024-01-01".to_string()),
+            (CitationField::Title, "A | B".to_string()),
+            (CitationField::Url, "https://example.com".to_string()),
+            (CitationField::Page, String::new()),
+        ];
+        assert_eq!(
+            t.render(&values),
+            "{{cite web|url=https://example.com|title=A {{!}} B|access-date=2024-01-01}}"
+        );
+    }
+
+    #[test]
+    fn test_render_positional_and_unmapped() {
+        let t = CitationTemplates::preset("enwiki").unwrap();
+        let t = t.get(ReferenceShape::Publication).unwrap();
+        let values = vec![
+            (CitationField::Item, "Q42".to_string()),
+            (CitationField::Title, "ignored".to_string()),
+            (CitationField::Page, "12".to_string()),
+        ];
+        assert_eq!(t.render(&values), "{{Cite Q|Q42|page=12}}");
+        assert!(!t.uses(CitationField::Title));
+    }
+
+    // ── config ─────────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_from_json() {
+        let t = CitationTemplates::from_json(&json!({
+            "web": "cite web",
+            "item": { "template": "Cite Q", "params": { "item": "1", "bogus": "x" } },
+            "other": "ignored"
+        }));
+        assert_eq!(
+            t.get(ReferenceShape::Web).unwrap().name,
+            "cite web".to_string()
+        );
+        // Publications fall back to the item template
+        let item = t.get(ReferenceShape::Publication).unwrap();
+        assert_eq!(item.name, "Cite Q");
+        assert_eq!(item.params, vec![(CitationField::Item, "1".to_string())]);
+    }
+
+    #[test]
+    fn test_merged_with_removes_shape() {
+        let t = CitationTemplates::preset("dewiki")
+            .unwrap()
+            .merged_with(&json!({ "publication": null }));
+        assert!(t.get(ReferenceShape::Publication).is_none());
+        assert_eq!(t.get(ReferenceShape::Web).unwrap().name, "Internetquelle");
+    }
+
+    #[test]
+    fn test_from_wiki_flags() {
+        let values = vec![
+            (CitationField::Url, "https://example.com".to_string()),
+            (CitationField::Title, "Example".to_string()),
+            (CitationField::Author, "ignored".to_string()),
+            (CitationField::Website, "[[Q1|Example site]]".to_string()),
+            (CitationField::AccessDate, "2024-01-01".to_string()),
+        ];
+        let invoke = CitationTemplates::from_wiki_flags(true, true);
+        assert_eq!(
+            invoke.get(ReferenceShape::Web).unwrap().render(&values),
+            "{{#invoke:cite|web|url=https://example.com|title=Example|website=[[Q1|Example site]]|access-date=2024-01-01}}"
+        );
+        assert!(invoke.get(ReferenceShape::Publication).is_none());
+        assert!(invoke.get(ReferenceShape::Item).is_none());
+        let cite_web = CitationTemplates::from_wiki_flags(false, true);
+        assert_eq!(cite_web.get(ReferenceShape::Web).unwrap().name, "cite web");
+        assert_eq!(
+            CitationTemplates::from_wiki_flags(true, false),
+            CitationTemplates::default()
+        );
+    }
+
+    #[test]
+    fn test_default_has_no_templates() {
+        let t = CitationTemplates::default();
+        assert!(t.get(ReferenceShape::Web).is_none());
+        assert!(CitationTemplates::preset("huwiki").is_none());
+    }
+}
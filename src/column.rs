@@ -36,6 +36,12 @@ impl Column {
         &self.label
     }
 
+    /// Whether the label was given in the column spec (`P569:born`).
+    #[must_use]
+    pub const fn has_label(&self) -> bool {
+        self.has_label
+    }
+
     #[must_use]
     pub const fn obj(&self) -> &ColumnType {
         &self.obj
@@ -439,4 +445,27 @@ mod tests {
         assert_eq!(col5.label(), "Column heading");
         assert!(col5.has_label);
     }
+
+    #[test]
+    fn test_lexeme_column_types() {
+        for (spec, expected) in [
+            ("lemma", ColumnType::Lemma),
+            ("Lexical_Category", ColumnType::LexicalCategory),
+            ("lexeme_language", ColumnType::LexemeLanguage),
+            ("FORMS", ColumnType::Forms),
+            ("senses", ColumnType::Senses),
+        ] {
+            let column_type = ColumnType::new(spec);
+            assert_eq!(column_type, expected);
+            assert_eq!(ColumnType::new(&column_type.as_key()), expected);
+        }
+        assert_eq!(
+            ColumnType::new("P5137/L7-S1/P18"),
+            ColumnType::PropertyQualifierValue((
+                "P5137".to_string(),
+                "L7-S1".to_string(),
+                "P18".to_string()
+            ))
+        );
+    }
 }
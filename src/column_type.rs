@@ -6,12 +6,17 @@
 //! that live inside this file — callers (`ResultCell`) need no changes.
 
 use crate::entity_container_wrapper::{EntityContainerWrapper, EntityEntry};
+use crate::lexeme::{self, Lexeme};
+use crate::messages::Message;
 use crate::reference::Reference;
 use crate::render_context::RenderContext;
-use crate::result_cell_part::{AutoDesc, EntityInfo, LinkTarget, LocalLinkInfo, PartWithReference, ResultCellPart};
+use crate::result_cell_part::{
+    AutoDesc, EntityInfo, LexemeFormInfo, LinkTarget, LocalLinkInfo, PartWithReference,
+    ResultCellPart,
+};
 use crate::template_params::ReferencesParameter;
 use wikimisc::sparql_table_vec::SparqlTableVec;
-use wikimisc::wikibase::{Statement, entity::EntityTrait};
+use wikimisc::wikibase::{Statement, Value, entity::EntityTrait};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ColumnType {
@@ -27,6 +32,15 @@ pub enum ColumnType {
     PropertyQualifierValue((String, String, String)),
     Field(String),
     Sitelink(String),
+    /// Lexeme rows: the lemma.
+    Lemma,
+    LexicalCategory,
+    /// Lexeme rows: the language item.
+    LexemeLanguage,
+    /// Lexeme rows: every form (form rows: that form), with its features.
+    Forms,
+    /// Lexeme rows: every sense's gloss (sense rows: that sense).
+    Senses,
     Unknown,
 }
 
@@ -51,6 +65,15 @@ impl ColumnType {
         }
     }
 
+    /// An item or a lexeme, form or sense ID, uppercased.
+    fn parse_entity_id(s: &str) -> Option<String> {
+        if let Some(q) = Self::parse_pq_id(s, b'Q') {
+            return Some(q);
+        }
+        let upper = s.to_uppercase();
+        lexeme::is_lexeme_id(&upper).then_some(upper)
+    }
+
     /// Try to parse a slash-separated compound like "P31/P580" or "P39/Q41582/P580"
     /// from already-trimmed parts.
     fn parse_slash_compound(s: &str) -> Option<Self> {
@@ -65,7 +88,7 @@ impl ColumnType {
             }
             3 => {
                 let p1 = Self::parse_pq_id(parts[0], b'P')?;
-                let q1 = Self::parse_pq_id(parts[1], b'Q')?;
+                let q1 = Self::parse_entity_id(parts[1])?;
                 let p2 = Self::parse_pq_id(parts[2], b'P')?;
                 Some(ColumnType::PropertyQualifierValue((p1, q1, p2)))
             }
@@ -85,6 +108,11 @@ impl ColumnType {
             "description" => return ColumnType::Description(Vec::new()),
             "item" => return ColumnType::Item,
             "qid" => return ColumnType::Qid,
+            "lemma" => return ColumnType::Lemma,
+            "lexical_category" | "lexicalcategory" => return ColumnType::LexicalCategory,
+            "lexeme_language" => return ColumnType::LexemeLanguage,
+            "forms" => return ColumnType::Forms,
+            "senses" => return ColumnType::Senses,
             _ => {}
         }
 
@@ -168,6 +196,11 @@ impl ColumnType {
             }
             Self::Field(f) => f.to_lowercase(),
             Self::Sitelink(wiki) => format!("sitelink/{wiki}"),
+            Self::Lemma => "lemma".to_string(),
+            Self::LexicalCategory => "lexical_category".to_string(),
+            Self::LexemeLanguage => "lexeme_language".to_string(),
+            Self::Forms => "forms".to_string(),
+            Self::Senses => "senses".to_string(),
             Self::Unknown => "unknown".to_string(),
         }
     }
@@ -223,7 +256,13 @@ impl ColumnType {
                 Self::render_label_lang(&entity, list, language, &mut parts);
             }
             Self::AliasLang(language) => {
-                Self::render_alias_lang(&entity, language, &mut parts);
+                Self::render_alias_lang(&entity, list, language, &mut parts);
+            }
+            Self::Label if lexeme::is_lexeme_id(entity_id) => {
+                parts.push(PartWithReference::new(
+                    ResultCellPart::Entity(EntityInfo::new(entity_id.to_owned(), true)),
+                    None,
+                ));
             }
             Self::Label => {
                 Self::render_label(entity, list, entity_id, &mut parts, &mut wdedit_class);
@@ -231,6 +270,15 @@ impl ColumnType {
             Self::Sitelink(wiki) => {
                 Self::render_sitelink(&entity, wiki, list, &mut parts);
             }
+            Self::Lemma
+            | Self::LexicalCategory
+            | Self::LexemeLanguage
+            | Self::Forms
+            | Self::Senses => {
+                if let Some(lexeme) = list.ecw().get_lexeme(entity_id) {
+                    self.render_lexeme(&lexeme, list, entity_id, &mut parts);
+                }
+            }
             Self::Unknown => {} // nothing to render
         }
 
@@ -245,18 +293,25 @@ impl ColumnType {
         wdedit_class: &mut Option<String>,
     ) {
         let Some(e) = entity else { return };
-        let description = if langs.is_empty() {
-            e.description_in_locale(list.language())
+        // An explicit language list is taken literally; otherwise walk the
+        // list language's fallback chain and mark fallback values.
+        let chain = if langs.is_empty() {
+            list.language_chain(list.language())
         } else {
-            langs.iter().find_map(|lang| e.description_in_locale(lang))
+            langs.to_vec()
         };
-        match description {
-            Some(s) => {
+        match EntityContainerWrapper::description_in_chain(e, &chain) {
+            Some((s, lang)) => {
                 *wdedit_class = list
                     .header_template()
                     .is_none()
                     .then(|| "wd_desc".to_string());
                 let s = Self::fix_wikitext_for_output(s);
+                let s = if langs.is_empty() {
+                    list.mark_language_fallback(&s, lang, list.language())
+                } else {
+                    s
+                };
                 parts.push(PartWithReference::new(ResultCellPart::Text(s), None));
             }
             None => {
@@ -280,20 +335,61 @@ impl ColumnType {
             .header_template()
             .is_none()
             .then(|| format!("wd_{}", property.to_lowercase()));
-        for statement in list.get_filtered_claims(e, property) {
-            let references = match list.get_reference_parameter() {
-                ReferencesParameter::All => {
-                    Self::get_references_for_statement(&statement, list.language())
-                }
-                _ => None,
+        let no_value = list.message(Message::NoValue, &[]);
+        let chain = list.language_chain(list.language());
+        let (statements, by_language) =
+            Self::pick_monolingual_statements(list.get_filtered_claims(e, property), &chain);
+        for statement in statements {
+            let references = Self::get_references_for_statement(
+                &statement,
+                list.language(),
+                *list.get_reference_parameter(),
+            );
+            let part = match Self::monolingual_text(&statement) {
+                Some((text, lang)) if by_language => ResultCellPart::Text(
+                    list.mark_language_fallback(&text, &lang, list.language()),
+                ),
+                _ => ResultCellPart::from_snak_localized(statement.main_snak(), &no_value),
             };
-            parts.push(PartWithReference::new(
-                ResultCellPart::from_snak(statement.main_snak()),
-                references,
-            ));
+            parts.push(PartWithReference::new(part, references));
+        }
+    }
+
+    /// Language and text of a monolingual-text statement.
+    fn monolingual_text(statement: &Statement) -> Option<(String, String)> {
+        let dv = statement.main_snak().data_value().as_ref()?;
+        match dv.value() {
+            Value::MonoLingual(v) => Some((v.text().to_string(), v.language().to_string())),
+            _ => None,
         }
     }
 
+    /// For monolingual-text properties, keeps only the statements in the first
+    /// language along `chain` that has any. Returns the statements unchanged
+    /// (and `false`) if none of them is in a chain language, so e.g. a title
+    /// only known in Latin still shows up as `la:…`.
+    fn pick_monolingual_statements(
+        statements: Vec<Statement>,
+        chain: &[String],
+    ) -> (Vec<Statement>, bool) {
+        let best = chain.iter().find(|lang| {
+            statements
+                .iter()
+                .any(|s| Self::monolingual_text(s).is_some_and(|(_, l)| l == **lang))
+        });
+        let Some(best) = best else {
+            return (statements, false);
+        };
+        let picked = statements
+            .into_iter()
+            .filter(|s| match Self::monolingual_text(s) {
+                Some((_, l)) => l == *best,
+                None => true,
+            })
+            .collect();
+        (picked, true)
+    }
+
     fn render_label(
         entity: Option<EntityEntry>,
         list: &impl RenderContext,
@@ -306,8 +402,14 @@ impl ColumnType {
             .header_template()
             .is_none()
             .then(|| "wd_label".to_string());
-        let label =
-            EntityContainerWrapper::label_with_fallback_from_entity(&e, list.language(), entity_id);
+        let chain = list.language_chain(list.language());
+        let (label, label_language) = match EntityContainerWrapper::label_in_chain(&e, &chain) {
+            Some((label, lang)) => (label.to_string(), Some(lang.to_string())),
+            None => (
+                EntityContainerWrapper::label_with_fallback_from_entity(&e, &chain, entity_id),
+                None,
+            ),
+        };
         let local_page = e.sitelinks().as_ref().and_then(|sl| {
             sl.iter()
                 .find(|s| *s.site() == *list.wiki())
@@ -315,13 +417,68 @@ impl ColumnType {
         });
         let part = match local_page {
             Some(page) => {
-                ResultCellPart::LocalLink(LocalLinkInfo::new(page, label, LinkTarget::Page))
+                let link = LocalLinkInfo::new(page, label, LinkTarget::Page);
+                ResultCellPart::LocalLink(match &label_language {
+                    Some(lang) => link.with_label_language(lang),
+                    None => link,
+                })
             }
             None => ResultCellPart::Entity(EntityInfo::new(entity_id.to_string(), true)),
         };
         parts.push(PartWithReference::new(part, None));
     }
 
+    fn render_lexeme(
+        &self,
+        lexeme: &Lexeme,
+        list: &impl RenderContext,
+        entity_id: &str,
+        parts: &mut Vec<PartWithReference>,
+    ) {
+        let entity_part = |id: &str| ResultCellPart::Entity(EntityInfo::new(id.to_string(), true));
+        let chain = list.language_chain(list.language());
+        let new_parts: Vec<ResultCellPart> = match self {
+            Self::Lemma => vec![entity_part(&lexeme.id)],
+            Self::LexicalCategory => lexeme
+                .lexical_category
+                .iter()
+                .map(|q| entity_part(q))
+                .collect(),
+            Self::LexemeLanguage => lexeme.language.iter().map(|q| entity_part(q)).collect(),
+            Self::Forms => lexeme
+                .forms
+                .iter()
+                .filter(|form| lexeme.form(entity_id).is_none() || form.id == entity_id)
+                .filter_map(|form| {
+                    let (representation, _) = lexeme::term_in_chain(&form.representations, &chain)?;
+                    Some(ResultCellPart::LexemeForm(LexemeFormInfo::new(
+                        representation.to_string(),
+                        form.grammatical_features.clone(),
+                    )))
+                })
+                .collect(),
+            Self::Senses => lexeme
+                .senses
+                .iter()
+                .filter(|sense| lexeme.sense(entity_id).is_none() || sense.id == entity_id)
+                .filter_map(|sense| {
+                    let (gloss, lang) = lexeme::term_in_chain(&sense.glosses, &chain)?;
+                    Some(ResultCellPart::Text(list.mark_language_fallback(
+                        gloss,
+                        lang,
+                        list.language(),
+                    )))
+                })
+                .collect(),
+            _ => vec![],
+        };
+        parts.extend(
+            new_parts
+                .into_iter()
+                .map(|part| PartWithReference::new(part, None)),
+        );
+    }
+
     fn render_sitelink(
         entity: &Option<EntityEntry>,
         wiki: &str,
@@ -369,8 +526,9 @@ impl ColumnType {
         parts: &mut Vec<PartWithReference>,
     ) {
         let Some(e) = entity else { return };
+        let no_value = list.message(Message::NoValue, &[]);
         for statement in list.get_filtered_claims(e, p1) {
-            for part in Self::get_parts_p_p(&statement, p2) {
+            for part in Self::get_parts_p_p(&statement, p2, &no_value) {
                 parts.push(PartWithReference::new(part, None));
             }
         }
@@ -385,8 +543,9 @@ impl ColumnType {
         parts: &mut Vec<PartWithReference>,
     ) {
         let Some(e) = entity else { return };
+        let no_value = list.message(Message::NoValue, &[]);
         for statement in list.get_filtered_claims(e, p1) {
-            for part in Self::get_parts_p_q_p(&statement, q1, p2) {
+            for part in Self::get_parts_p_q_p(&statement, q1, p2, &no_value) {
                 parts.push(PartWithReference::new(part, None));
             }
         }
@@ -399,29 +558,27 @@ impl ColumnType {
         parts: &mut Vec<PartWithReference>,
     ) {
         let Some(e) = entity else { return };
-        let label = e
-            .label_in_locale(language)
-            .or_else(|| e.label_in_locale(list.language()))
-            .map(|s| s.to_string());
-        if let Some(s) = label {
+        let chain = list.language_chain(language);
+        if let Some((label, lang)) = EntityContainerWrapper::label_in_chain(e, &chain) {
+            let s = list.mark_language_fallback(label, lang, language);
             parts.push(PartWithReference::new(ResultCellPart::Text(s), None));
         }
     }
 
     fn render_alias_lang(
         entity: &Option<EntityEntry>,
+        list: &impl RenderContext,
         language: &str,
         parts: &mut Vec<PartWithReference>,
     ) {
         let Some(e) = entity else { return };
-        let mut aliases: Vec<String> = e
-            .aliases()
-            .iter()
-            .filter(|alias| alias.language() == language)
-            .map(|alias| alias.value().to_string())
-            .collect();
+        let chain = list.language_chain(language);
+        let Some((mut aliases, lang)) = EntityContainerWrapper::aliases_in_chain(e, &chain) else {
+            return;
+        };
         aliases.sort();
         for alias in aliases {
+            let alias = list.mark_language_fallback(&alias, lang, language);
             parts.push(PartWithReference::new(ResultCellPart::Text(alias), None));
         }
     }
@@ -439,15 +596,18 @@ impl ColumnType {
         }
     }
 
-    fn get_parts_p_p(statement: &Statement, property: &str) -> Vec<ResultCellPart> {
+    fn get_parts_p_p(statement: &Statement, property: &str, no_value: &str) -> Vec<ResultCellPart> {
         statement
             .qualifiers()
             .iter()
             .filter(|snak| *snak.property() == *property)
             .map(|snak| {
                 ResultCellPart::SnakList(vec![
-                    PartWithReference::new(ResultCellPart::from_snak(statement.main_snak()), None),
-                    PartWithReference::new(ResultCellPart::from_snak(snak), None),
+                    PartWithReference::new(
+                        ResultCellPart::from_snak_localized(statement.main_snak(), no_value),
+                        None,
+                    ),
+                    PartWithReference::new(ResultCellPart::from_snak_localized(snak, no_value), None),
                 ])
             })
             .collect()
@@ -457,6 +617,7 @@ impl ColumnType {
         statement: &Statement,
         target_item: &str,
         property: &str,
+        no_value: &str,
     ) -> Vec<ResultCellPart> {
         let links_to_target = match statement.main_snak().data_value() {
             Some(dv) => match dv.value() {
@@ -474,22 +635,31 @@ impl ColumnType {
             .filter(|snak| *snak.property() == *property)
             .map(|snak| {
                 ResultCellPart::SnakList(vec![PartWithReference::new(
-                    ResultCellPart::from_snak(snak),
+                    ResultCellPart::from_snak_localized(snak, no_value),
                     None,
                 )])
             })
             .collect()
     }
 
+    /// The statement's references, as selected by the `references=` mode.
     fn get_references_for_statement(
         statement: &Statement,
         language: &str,
+        mode: ReferencesParameter,
     ) -> Option<Vec<Reference>> {
-        let ret: Vec<Reference> = statement
+        if mode == ReferencesParameter::None {
+            return None;
+        }
+        let references = statement
             .references()
             .iter()
             .filter_map(|r| Reference::new_from_snaks(r.snaks(), language))
-            .collect();
+            .filter(|r| mode != ReferencesParameter::External || !r.is_imported());
+        let ret: Vec<Reference> = match mode {
+            ReferencesParameter::First => references.take(1).collect(),
+            _ => references.collect(),
+        };
         if ret.is_empty() { None } else { Some(ret) }
     }
 }
@@ -598,4 +768,66 @@ mod tests {
         assert_eq!(ColumnType::wiki_id_to_interwiki_prefix("commonswiki"), "commons");
         assert_eq!(ColumnType::wiki_id_to_interwiki_prefix("wikidatawiki"), "d");
     }
+
+    // --- pick_monolingual_statements ---
+
+    #[test]
+    fn test_pick_monolingual_statements_follows_chain() {
+        use wikimisc::wikibase::Snak;
+        let statements: Vec<Statement> = [("Titel", "de"), ("Title", "en"), ("Titulus", "la")]
+            .iter()
+            .map(|(text, lang)| {
+                Statement::new_normal(
+                    Snak::new_monolingual_text("P1476", text, lang),
+                    vec![],
+                    vec![],
+                )
+            })
+            .collect();
+        let chain = crate::language_fallback::LanguageFallbacks::default().chain("de-ch");
+        let (picked, by_language) =
+            ColumnType::pick_monolingual_statements(statements.clone(), &chain);
+        assert!(by_language);
+        assert_eq!(picked.len(), 1);
+        assert_eq!(
+            ColumnType::monolingual_text(&picked[0]),
+            Some(("Titel".to_string(), "de".to_string()))
+        );
+
+        // No statement in a chain language: keep everything as before
+        let (picked, by_language) =
+            ColumnType::pick_monolingual_statements(statements, &["fr".to_string()]);
+        assert!(!by_language);
+        assert_eq!(picked.len(), 3);
+    }
+
+    // --- get_references_for_statement ---
+
+    #[test]
+    fn test_get_references_for_statement_modes() {
+        use wikimisc::wikibase::{Reference as WbReference, Snak};
+        let statement = Statement::new_normal(
+            Snak::new_item("P31", "Q5"),
+            vec![],
+            vec![
+                WbReference::new(vec![
+                    Snak::new_item("P143", "Q328"),
+                    Snak::new_string("P854", "https://en.wikipedia.org/"),
+                ]),
+                WbReference::new(vec![Snak::new_item("P248", "Q36578")]),
+            ],
+        );
+        let count = |mode| {
+            ColumnType::get_references_for_statement(&statement, "en", mode)
+                .map_or(0, |refs| refs.len())
+        };
+        assert_eq!(count(ReferencesParameter::None), 0);
+        assert_eq!(count(ReferencesParameter::All), 2);
+        assert_eq!(count(ReferencesParameter::First), 1);
+        let external =
+            ColumnType::get_references_for_statement(&statement, "en", ReferencesParameter::External)
+                .unwrap();
+        assert_eq!(external.len(), 1);
+        assert_eq!(external[0].stated_in(), &Some("Q36578".to_string()));
+    }
 }
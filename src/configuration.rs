@@ -5,6 +5,13 @@
 
 use crate::circuit_breaker::CircuitBreaker;
 use crate::database_pool::DatabasePool;
+use crate::citation_templates::CitationTemplates;
+use crate::date_format::DateFormat;
+use crate::fair_scheduler::FairSchedulingSettings;
+use crate::language_fallback::LanguageFallbacks;
+use crate::messages::{Messages, OnwikiMessages};
+use crate::recent_changes::RecentChangesSettings;
+use crate::storage::{FileStorage, MysqlStorage, StorageBackend};
 use crate::wiki::Wiki;
 use anyhow::{Result, anyhow};
 use dashmap::DashMap;
@@ -23,6 +30,10 @@ use wikimisc::wikibase::entity_container::EntityContainer;
 /// hammering through every page's entity load.
 pub const MW_API_ENTITIES_KEY: &str = "wikidata_entities";
 
+/// Key in `apis` of the Wikibase holding MediaInfo (`M…`) entities. Lists
+/// querying it still use the default API's items and properties.
+pub const MEDIAINFO_API_KEY: &str = "commons";
+
 /// Boolean kill-switches for expensive pipeline sub-stages.
 ///
 /// All flags default to `true` so behaviour is identical to pre-flag code
@@ -59,7 +70,14 @@ impl Default for FeatureFlags {
 
 impl FeatureFlags {
     fn from_json(j: &Value) -> Self {
-        let defaults = Self::default();
+        Self::default().merged_with(j)
+    }
+
+    /// Returns a copy of `self` with every flag present in `j` replaced.
+    /// Absent keys keep their current value, so a per-wiki block only needs
+    /// to name the flags it actually changes.
+    fn merged_with(self, j: &Value) -> Self {
+        let defaults = self;
         Self {
             enable_autodesc: j["enable_autodesc"]
                 .as_bool()
@@ -84,6 +102,28 @@ pub enum NamespaceGroup {
 }
 
 impl NamespaceGroup {
+    /// Parses a namespace block value: `"*"` blocks everything, an array of
+    /// integers blocks those namespaces. Any other string is a config error;
+    /// other JSON types are ignored (`Ok(None)`).
+    fn from_json(key: &str, v: &Value) -> Result<Option<Self>> {
+        if let Some(s) = v.as_str() {
+            if s == "*" {
+                return Ok(Some(Self::All));
+            }
+            return Err(anyhow!(
+                "Unrecognized string value for namespace_blocks[{key}]:{v}"
+            ));
+        }
+        Ok(v.as_array().map(|a| {
+            Self::List(
+                a.iter()
+                    .filter_map(|x| x.as_u64())
+                    .filter_map(|x| x.try_into().ok())
+                    .collect(),
+            )
+        }))
+    }
+
     #[must_use]
     pub fn can_edit_namespace(&self, nsid: i64) -> bool {
         match self {
@@ -93,19 +133,173 @@ impl NamespaceGroup {
     }
 }
 
+/// Raw `wikis.<dbname>` override block for a single wiki.
+///
+/// Every field is optional; `None` inherits the global value. The legacy
+/// per-wiki maps (`location_templates`, `namespace_blocks`,
+/// `shadow_images_check`, `location_regions`) are folded into these blocks at
+/// load time, so older config files resolve exactly as before.
+#[derive(Debug, Clone, Default)]
+struct WikiOverrides {
+    prefer_preferred: Option<bool>,
+    default_language: Option<String>,
+    default_thumbnail_size: Option<u64>,
+    location_template: Option<String>,
+    namespace_block: Option<NamespaceGroup>,
+    shadow_images_check: Option<bool>,
+    location_regions: Option<bool>,
+    ms_delay_after_edit: Option<u64>,
+    load_onwiki_messages: Option<bool>,
+    mark_language_fallbacks: Option<bool>,
+    date_format: Option<DateFormat>,
+    annotate_julian_dates: Option<bool>,
+    inline_diagnostics: Option<bool>,
+    expand_template_params: Option<bool>,
+    /// Partial `citation_templates` object, merged over the wiki's defaults.
+    citation_templates: Option<Value>,
+    /// Partial `feature_flags` object, merged over the global flags.
+    feature_flags: Option<Value>,
+}
+
+impl WikiOverrides {
+    /// Applies the keys present in a `wikis.<dbname>` JSON object. Unknown
+    /// keys are ignored; keys with the wrong JSON type are skipped with a
+    /// warning, matching the lenient parsing of the global settings.
+    fn merge_json(&mut self, wiki: &str, j: &Value) -> Result<()> {
+        let Some(o) = j.as_object() else {
+            log::warn!("Ignoring non-object wikis.{wiki} config block: {j}");
+            return Ok(());
+        };
+        for (key, v) in o {
+            let applied = match key.as_str() {
+                "prefer_preferred" => v.as_bool().map(|b| self.prefer_preferred = Some(b)),
+                "default_language" => v
+                    .as_str()
+                    .map(|s| self.default_language = Some(s.to_string())),
+                "default_thumbnail_size" => {
+                    v.as_u64().map(|u| self.default_thumbnail_size = Some(u))
+                }
+                "location_template" => v
+                    .as_str()
+                    .map(|s| self.location_template = Some(s.to_string())),
+                "namespace_block" | "namespace_blocks" => NamespaceGroup::from_json(wiki, v)?
+                    .map(|nsg| self.namespace_block = Some(nsg)),
+                "shadow_images_check" => {
+                    v.as_bool().map(|b| self.shadow_images_check = Some(b))
+                }
+                "location_regions" => v.as_bool().map(|b| self.location_regions = Some(b)),
+                "ms_delay_after_edit" => v.as_u64().map(|u| self.ms_delay_after_edit = Some(u)),
+                "load_onwiki_messages" => {
+                    v.as_bool().map(|b| self.load_onwiki_messages = Some(b))
+                }
+                "mark_language_fallbacks" => {
+                    v.as_bool().map(|b| self.mark_language_fallbacks = Some(b))
+                }
+                "date_format" => v
+                    .as_str()
+                    .and_then(DateFormat::new)
+                    .map(|f| self.date_format = Some(f)),
+                "annotate_julian_dates" => {
+                    v.as_bool().map(|b| self.annotate_julian_dates = Some(b))
+                }
+                "inline_diagnostics" => v.as_bool().map(|b| self.inline_diagnostics = Some(b)),
+                "expand_template_params" => {
+                    v.as_bool().map(|b| self.expand_template_params = Some(b))
+                }
+                "citation_templates" => v.as_object().map(|templates| {
+                    let mut merged = self
+                        .citation_templates
+                        .take()
+                        .and_then(|t| t.as_object().cloned())
+                        .unwrap_or_default();
+                    merged.extend(templates.clone());
+                    self.citation_templates = Some(Value::Object(merged));
+                }),
+                "feature_flags" => v.as_object().map(|flags| {
+                    let mut merged = self
+                        .feature_flags
+                        .take()
+                        .and_then(|f| f.as_object().cloned())
+                        .unwrap_or_default();
+                    merged.extend(flags.clone());
+                    self.feature_flags = Some(Value::Object(merged));
+                }),
+                // The bot's threads are shared by all wikis
+                "max_threads" => {
+                    log::warn!(
+                        "Ignoring wikis.{wiki}.max_threads, which is global; use fair_scheduling.max_running to limit a wiki's running pages"
+                    );
+                    Some(())
+                }
+                _ => Some(()),
+            };
+            if applied.is_none() {
+                log::warn!("Ignoring wikis.{wiki}.{key} with unexpected type: {v}");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Effective per-page settings for one wiki: the global configuration with
+/// that wiki's `wikis.<dbname>` overrides applied on top.
+///
+/// Obtain one via [`Configuration::wiki_settings`]; `PageParams` resolves it
+/// once per page so the list pipeline never consults the global values for
+/// anything a wiki is allowed to override.
+#[derive(Debug, Clone)]
+pub struct WikiSettings {
+    pub prefer_preferred: bool,
+    pub default_language: String,
+    pub thumbnail_size: u64,
+    pub location_template: String,
+    pub namespace_block: Option<NamespaceGroup>,
+    pub shadow_images_check: bool,
+    pub location_regions: bool,
+    pub ms_delay_after_edit: Option<u64>,
+    /// Whether to fetch `MediaWiki:Listeria-*` message overrides from the wiki.
+    pub load_onwiki_messages: bool,
+    /// Wrap labels taken from a fallback language in `lang=` spans.
+    pub mark_language_fallbacks: bool,
+    /// Date format used unless a list or column sets `date_format=`.
+    pub date_format: DateFormat,
+    /// Add a "Julian" note to dates given in the Julian calendar.
+    pub annotate_julian_dates: bool,
+    /// Show problems with a list in a box above it, unless the list sets
+    /// `diagnostics=`.
+    pub inline_diagnostics: bool,
+    /// Expand templates and magic words in list parameters (other than
+    /// `sparql`, which is always expanded) with `action=expandtemplates`.
+    pub expand_template_params: bool,
+    /// Templates used to render references.
+    pub citation_templates: CitationTemplates,
+    pub feature_flags: FeatureFlags,
+}
+
+impl WikiSettings {
+    /// Checks if editing is allowed in the given namespace.
+    #[must_use]
+    pub fn can_edit_namespace(&self, nsid: i64) -> bool {
+        self.namespace_block
+            .as_ref()
+            .is_none_or(|nsg| nsg.can_edit_namespace(nsid))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Configuration {
     wb_apis: HashMap<String, Arc<Api>>,
-    namespace_blocks: HashMap<String, NamespaceGroup>,
     default_api: String,
     prefer_preferred: bool,
     default_language: String,
     template_start_sites: HashMap<String, String>,
     template_end_sites: HashMap<String, String>,
-    location_templates: HashMap<String, String>,
-    shadow_images_check: Vec<String>,
+    /// Global coordinate template (`location_templates.default`).
+    location_template: String,
     default_thumbnail_size: Option<u64>,
-    location_regions: Vec<String>,
+    /// Per-wiki `wikis.<dbname>` overrides, including entries migrated from
+    /// the legacy per-wiki maps. Resolved via [`Configuration::wiki_settings`].
+    wiki_overrides: HashMap<String, WikiOverrides>,
     mysql: Option<Value>,
     oauth2_token: String,
     template_start_q: String,
@@ -125,9 +319,16 @@ pub struct Configuration {
     /// slow `pagestatus` UPDATE would otherwise hang the dispatcher hot path
     /// (`prepare_next_single_page`) indefinitely.
     db_query_timeout_sec: u64,
+    /// How long running pages may take to finish after SIGTERM/SIGINT
+    /// (seconds), before they are released back to the queue.
+    shutdown_grace_sec: u64,
     ms_delay_after_edit: Option<u64>,
     max_threads: usize,
     pool: Option<Arc<DatabasePool>>,
+    /// Where the page queue, wiki list and profiling data are kept: the
+    /// MySQL tables if `mysql` is configured, else the `storage_file`, else
+    /// (single-wiki mode only) memory.
+    storage: Option<StorageBackend>,
     max_sparql_simultaneous: u64,
     profiling: bool,
     wikis: HashMap<String, Wiki>,
@@ -139,6 +340,8 @@ pub struct Configuration {
     status_server_port: Option<u16>,         // For single wiki mode, the port for the status server
     sparql_prefix: Option<String>, // For single wiki mode, a prefix for all SPARQL queries
     main_item_prefix: String,      // For single wiki mode, the prefix for items
+    /// SPARQL endpoint of lists with `wikibase=commons`; they fail without.
+    commons_query_endpoint: Option<String>,
     /// Per-endpoint SPARQL semaphores. Each endpoint URL gets its own semaphore
     /// of `max_sparql_simultaneous` permits, so a slow Commons-Query-Service
     /// can't starve calls to the Wikidata-Query-Service of permits.
@@ -166,22 +369,40 @@ pub struct Configuration {
     case_sensitive_wikis: HashSet<String>,
     /// Per-stage feature flags — see [`FeatureFlags`].
     feature_flags: FeatureFlags,
+    /// Translations of bot-generated strings from the `messages` config map.
+    messages: Arc<Messages>,
+    /// `messages` with the on-wiki overrides of each wiki, shared by clones.
+    onwiki_messages: Arc<OnwikiMessages>,
+    /// Fetch `MediaWiki:Listeria-*` message overrides from each wiki.
+    load_onwiki_messages: bool,
+    /// Language fallback chains for labels, descriptions and messages.
+    language_fallbacks: LanguageFallbacks,
+    /// Mark values rendered from a fallback language (default: off).
+    mark_language_fallbacks: bool,
+    date_format: DateFormat,
+    annotate_julian_dates: bool,
+    inline_diagnostics: bool,
+    expand_template_params: bool,
+    /// The `citation_templates` object, applied over each wiki's templates.
+    citation_templates: Value,
+    recent_changes: RecentChangesSettings,
+    fair_scheduling: FairSchedulingSettings,
+    /// Store which entities each page lists (default: off).
+    item_page_index: bool,
 }
 
 impl Default for Configuration {
     fn default() -> Self {
         Self {
             wb_apis: HashMap::new(),
-            namespace_blocks: HashMap::new(),
             default_api: String::new(),
             prefer_preferred: false,
             default_language: String::new(),
             template_start_sites: HashMap::new(),
             template_end_sites: HashMap::new(),
-            location_templates: HashMap::new(),
-            shadow_images_check: Vec::new(),
+            location_template: String::new(),
             default_thumbnail_size: None,
-            location_regions: Vec::new(),
+            wiki_overrides: HashMap::new(),
             mysql: None,
             oauth2_token: String::new(),
             template_start_q: String::new(),
@@ -194,9 +415,11 @@ impl Default for Configuration {
             api_timeout: 0,
             page_timeout_sec: 0,
             db_query_timeout_sec: 0,
+            shutdown_grace_sec: 0,
             ms_delay_after_edit: None,
             max_threads: 0,
             pool: None,
+            storage: None,
             max_sparql_simultaneous: 0,
             profiling: false,
             wikis: HashMap::new(),
@@ -205,6 +428,7 @@ impl Default for Configuration {
             wiki_page_pattern: None,
             delay_after_page_check_sec: None,
             query_endpoint: None,
+            commons_query_endpoint: None,
             status_server_port: None,
             sparql_prefix: None,
             main_item_prefix: String::new(),
@@ -214,6 +438,19 @@ impl Default for Configuration {
             wiki_name_aliases: Self::default_wiki_name_aliases(),
             case_sensitive_wikis: HashSet::new(),
             feature_flags: FeatureFlags::default(),
+            messages: Arc::new(Messages::default()),
+            onwiki_messages: Arc::new(OnwikiMessages::default()),
+            load_onwiki_messages: false,
+            language_fallbacks: LanguageFallbacks::default(),
+            mark_language_fallbacks: false,
+            date_format: DateFormat::default(),
+            annotate_julian_dates: false,
+            inline_diagnostics: false,
+            expand_template_params: false,
+            citation_templates: Value::Null,
+            recent_changes: RecentChangesSettings::default(),
+            fair_scheduling: FairSchedulingSettings::default(),
+            item_page_index: false,
         }
     }
 }
@@ -318,9 +555,16 @@ impl Configuration {
         ret.new_from_json_locations(&j);
         ret.new_from_json_wikibase_apis(&j).await?;
         ret.new_from_json_namespace_blocks(&j)?;
+        ret.new_from_json_wiki_overrides(&j)?;
         ret.new_from_json_start_end_tempate_mappings(&j).await?;
         if j["mysql"].as_object().is_some() {
-            ret.pool = Some(Arc::new(DatabasePool::new(&ret)?));
+            let pool = DatabasePool::new(&ret)?;
+            ret.storage = Some(StorageBackend::Mysql(MysqlStorage::new(pool.clone())));
+            ret.pool = Some(Arc::new(pool));
+        } else if let Some(path) = j["storage_file"].as_str() {
+            ret.storage = Some(StorageBackend::File(FileStorage::open(path).await?));
+        } else if ret.is_single_wiki {
+            ret.storage = Some(StorageBackend::File(FileStorage::in_memory()));
         }
         ret.validate()?;
         Ok(ret)
@@ -331,42 +575,113 @@ impl Configuration {
         self.query_endpoint.to_owned()
     }
 
+    #[must_use]
+    pub fn commons_query_endpoint(&self) -> Option<&str> {
+        self.commons_query_endpoint.as_deref()
+    }
+
     pub fn sparql_prefix(&self) -> Option<&str> {
         self.sparql_prefix.as_deref()
     }
 
     fn new_from_json_namespace_blocks(&mut self, j: &Value) -> Result<()> {
-        // Namespace blocks on wikis
+        // Legacy top-level namespace blocks; folded into per-wiki overrides
         if let Some(o) = j["namespace_blocks"].as_object() {
             for (k, v) in o.iter() {
-                // Check for string value ("*")
-                if let Some(s) = v.as_str() {
-                    if s == "*" {
-                        // All namespaces
-                        self.namespace_blocks
-                            .insert(k.to_string(), NamespaceGroup::All);
-                    } else {
-                        return Err(anyhow!(
-                            "Unrecognized string value for namespace_blocks[{k}]:{v}"
-                        ));
-                    }
+                if let Some(nsg) = NamespaceGroup::from_json(k, v)? {
+                    self.overrides_mut(k).namespace_block = Some(nsg);
                 }
+            }
+        }
+        Ok(())
+    }
 
-                // Check for array of integers
-                if let Some(a) = v.as_array() {
-                    let nsids: Vec<i64> = a
-                        .iter()
-                        .filter_map(|x| x.as_u64())
-                        .filter_map(|x| x.try_into().ok())
-                        .collect();
-                    self.namespace_blocks
-                        .insert(k.to_string(), NamespaceGroup::List(nsids));
-                }
+    /// Parses the `wikis` object: one block of setting overrides per wiki
+    /// database name. Runs after the legacy per-wiki maps so an explicit
+    /// `wikis.<dbname>` entry wins over the old special-case keys.
+    fn new_from_json_wiki_overrides(&mut self, j: &Value) -> Result<()> {
+        if let Some(o) = j["wikis"].as_object() {
+            for (wiki, block) in o {
+                self.overrides_mut(wiki).merge_json(wiki, block)?;
             }
         }
         Ok(())
     }
 
+    fn overrides_mut(&mut self, wiki: &str) -> &mut WikiOverrides {
+        self.wiki_overrides.entry(wiki.to_string()).or_default()
+    }
+
+    /// Resolves the effective per-page settings for `wiki`, applying its
+    /// `wikis.<dbname>` overrides (if any) over the global values. This is
+    /// the single lookup point for every setting a wiki may override.
+    #[must_use]
+    pub fn wiki_settings(&self, wiki: &str) -> WikiSettings {
+        let none = WikiOverrides::default();
+        let o = self.wiki_overrides.get(wiki).unwrap_or(&none);
+        WikiSettings {
+            prefer_preferred: o.prefer_preferred.unwrap_or(self.prefer_preferred),
+            default_language: o
+                .default_language
+                .clone()
+                .unwrap_or_else(|| self.default_language.clone()),
+            thumbnail_size: o
+                .default_thumbnail_size
+                .unwrap_or_else(|| self.default_thumbnail_size()),
+            location_template: o
+                .location_template
+                .clone()
+                .unwrap_or_else(|| self.location_template.clone()),
+            namespace_block: o.namespace_block.clone(),
+            shadow_images_check: o.shadow_images_check.unwrap_or(false),
+            location_regions: o.location_regions.unwrap_or(false),
+            ms_delay_after_edit: o.ms_delay_after_edit.or(self.ms_delay_after_edit),
+            load_onwiki_messages: o
+                .load_onwiki_messages
+                .unwrap_or(self.load_onwiki_messages),
+            mark_language_fallbacks: o
+                .mark_language_fallbacks
+                .unwrap_or(self.mark_language_fallbacks),
+            date_format: o.date_format.unwrap_or(self.date_format),
+            annotate_julian_dates: o
+                .annotate_julian_dates
+                .unwrap_or(self.annotate_julian_dates),
+            inline_diagnostics: o.inline_diagnostics.unwrap_or(self.inline_diagnostics),
+            expand_template_params: o
+                .expand_template_params
+                .unwrap_or(self.expand_template_params),
+            citation_templates: self.citation_templates_for(wiki, o),
+            feature_flags: o
+                .feature_flags
+                .as_ref()
+                .map_or(self.feature_flags, |f| self.feature_flags.merged_with(f)),
+        }
+    }
+
+    /// The templates the `use_invoke`/`use_cite_web` flags of the wiki select
+    /// (both set for wikis not in the `wikis` table), with the global
+    /// `citation_templates` and then the `wikis.<dbname>.citation_templates`
+    /// block applied on top. `"preset": true` in either replaces what came
+    /// before with the wiki's built-in preset, if it has one.
+    fn citation_templates_for(&self, wiki: &str, o: &WikiOverrides) -> CitationTemplates {
+        let mut ret = self.wikis.get(wiki).map_or_else(
+            || CitationTemplates::from_wiki_flags(true, true),
+            |w| CitationTemplates::from_wiki_flags(w.use_invoke(), w.use_cite_web()),
+        );
+        for j in [Some(&self.citation_templates), o.citation_templates.as_ref()]
+            .into_iter()
+            .flatten()
+        {
+            if j["preset"].as_bool() == Some(true)
+                && let Some(preset) = CitationTemplates::preset(wiki)
+            {
+                ret = preset;
+            }
+            ret = ret.merged_with(j);
+        }
+        ret
+    }
+
     pub const fn max_sparql_simultaneous(&self) -> u64 {
         self.max_sparql_simultaneous
     }
@@ -410,6 +725,40 @@ impl Configuration {
             .clone()
     }
 
+    /// Which circuit breakers closed again since the last call: the wikis
+    /// whose MediaWiki API recovered, and whether a SPARQL endpoint or the
+    /// entity-loading API did (those are shared by all wikis).
+    pub fn take_recovered_circuits(&self) -> (Vec<String>, bool) {
+        self.circuits_where(CircuitBreaker::take_recovered)
+    }
+
+    /// Like [`Self::take_recovered_circuits`], for open circuits that are due
+    /// for a probe; see [`CircuitBreaker::take_probe_due`].
+    pub fn take_probe_circuits(&self) -> (Vec<String>, bool) {
+        self.circuits_where(CircuitBreaker::take_probe_due)
+    }
+
+    /// The wikis whose API breaker passes `check`, and whether a shared
+    /// (SPARQL or entity) breaker does. Every breaker is checked.
+    fn circuits_where(&self, check: impl Fn(&CircuitBreaker) -> bool) -> (Vec<String>, bool) {
+        let mut wikis = vec![];
+        let mut shared = false;
+        for entry in self.mw_api_circuit_breakers.iter() {
+            if check(entry.value()) {
+                if entry.key() == MW_API_ENTITIES_KEY {
+                    shared = true;
+                } else {
+                    wikis.push(entry.key().to_owned());
+                }
+            }
+        }
+        for entry in self.sparql_circuit_breakers.iter() {
+            shared |= check(entry.value());
+        }
+        wikis.sort();
+        (wikis, shared)
+    }
+
     pub const fn profiling(&self) -> bool {
         self.profiling
     }
@@ -435,6 +784,29 @@ impl Configuration {
             .ok_or_else(|| anyhow!("Database pool not configured"))
     }
 
+    /// Settings of the recent-changes watcher.
+    pub const fn recent_changes(&self) -> &RecentChangesSettings {
+        &self.recent_changes
+    }
+
+    /// How the multi-wiki bot shares its threads between wikis.
+    pub const fn fair_scheduling(&self) -> &FairSchedulingSettings {
+        &self.fair_scheduling
+    }
+
+    /// Whether to record the entities each page lists after a successful
+    /// run. The recent-changes item watch needs it, so it wins over the flag.
+    pub const fn item_page_index(&self) -> bool {
+        self.item_page_index || self.recent_changes.watch_items
+    }
+
+    /// Returns the storage backend if configured.
+    pub fn storage(&self) -> Result<&StorageBackend> {
+        self.storage
+            .as_ref()
+            .ok_or_else(|| anyhow!("Storage not configured"))
+    }
+
     pub const fn max_threads(&self) -> usize {
         self.max_threads
     }
@@ -453,6 +825,11 @@ impl Configuration {
         Duration::from_secs(self.page_timeout_sec)
     }
 
+    /// Time running pages get to finish on shutdown.
+    pub const fn shutdown_grace(&self) -> Duration {
+        Duration::from_secs(self.shutdown_grace_sec)
+    }
+
     /// Wall-clock budget for an individual DB operation
     /// (connection checkout + query execution combined).
     pub const fn db_query_timeout(&self) -> Duration {
@@ -465,6 +842,21 @@ impl Configuration {
         &self.feature_flags
     }
 
+    /// Message translations configured in the `messages` config map.
+    pub const fn messages(&self) -> &Arc<Messages> {
+        &self.messages
+    }
+
+    /// Cache of [`Self::messages`] with on-wiki overrides, per wiki and language.
+    pub fn onwiki_messages(&self) -> &OnwikiMessages {
+        &self.onwiki_messages
+    }
+
+    /// Language fallback chains from the `language_fallbacks` config map.
+    pub const fn language_fallbacks(&self) -> &LanguageFallbacks {
+        &self.language_fallbacks
+    }
+
     pub fn oauth2_token(&self) -> &str {
         &self.oauth2_token
     }
@@ -497,7 +889,7 @@ impl Configuration {
     }
 
     pub fn check_for_shadow_images(&self, wiki: &str) -> bool {
-        self.shadow_images_check.iter().any(|w| w == wiki)
+        self.wiki_settings(wiki).shadow_images_check
     }
 
     /// Helper method to extract template title from a template map
@@ -539,17 +931,14 @@ impl Configuration {
 
     /// Checks if editing is allowed in the given namespace on this wiki.
     pub fn can_edit_namespace(&self, wiki: &str, nsid: i64) -> bool {
-        self.namespace_blocks
+        self.wiki_overrides
             .get(wiki)
-            .map_or_else(|| true, |nsg| nsg.can_edit_namespace(nsid))
+            .and_then(|o| o.namespace_block.as_ref())
+            .is_none_or(|nsg| nsg.can_edit_namespace(nsid))
     }
 
     pub fn get_location_template(&self, wiki: &str) -> String {
-        self.location_templates
-            .get(wiki)
-            .or_else(|| self.location_templates.get("default"))
-            .cloned()
-            .unwrap_or_default()
+        self.wiki_settings(wiki).location_template
     }
 
     pub fn get_template_start_q(&self) -> String {
@@ -568,10 +957,6 @@ impl Configuration {
         self.default_thumbnail_size.unwrap_or(128)
     }
 
-    pub const fn location_regions(&self) -> &Vec<String> {
-        &self.location_regions
-    }
-
     pub async fn wbapi_login(&mut self, key: &str) -> bool {
         let oauth2_token = self.oauth2_token().to_owned();
         match self.wb_apis.get_mut(key) {
@@ -714,25 +1099,26 @@ impl Configuration {
         // than panicking at startup. Other config-array fields use the same
         // lenient pattern (audit F5.3).
         if let Some(lr) = j["location_regions"].as_array() {
-            self.location_regions = lr
-                .iter()
-                .filter_map(|s| match s.as_str() {
-                    Some(s) => Some(s.to_string()),
+            for s in lr {
+                match s.as_str() {
+                    Some(wiki) => self.overrides_mut(wiki).location_regions = Some(true),
                     None => {
-                        log::warn!(
-                            "Ignoring non-string entry in location_regions config: {s}"
-                        );
-                        None
+                        log::warn!("Ignoring non-string entry in location_regions config: {s}");
                     }
-                })
-                .collect();
+                }
+            }
         }
 
-        // Location template patterns
+        // Location template patterns; "default" is the global template,
+        // every other key is a legacy per-wiki override
         if let Some(o) = j["location_templates"].as_object() {
             for (k, v) in o.iter() {
                 if let (k, Some(v)) = (k.as_str(), v.as_str()) {
-                    self.location_templates.insert(k.to_string(), v.to_string());
+                    if k == "default" {
+                        self.location_template = v.to_string();
+                    } else {
+                        self.overrides_mut(k).location_template = Some(v.to_string());
+                    }
                 }
             }
         }
@@ -744,6 +1130,7 @@ impl Configuration {
             .and_then(|u| u.try_into().ok());
         self.default_api = j["default_api"].as_str().unwrap_or_default().to_string();
         self.query_endpoint = j["query_endpoint"].as_str().map(|s| s.to_string());
+        self.commons_query_endpoint = j["commons_query_endpoint"].as_str().map(|s| s.to_string());
         self.default_language = j["default_language"]
             .as_str()
             .unwrap_or_default()
@@ -762,6 +1149,7 @@ impl Configuration {
         self.api_timeout = j["api_timeout"].as_u64().unwrap_or(360);
         self.page_timeout_sec = j["page_timeout_sec"].as_u64().unwrap_or(600);
         self.db_query_timeout_sec = j["db_query_timeout_sec"].as_u64().unwrap_or(30);
+        self.shutdown_grace_sec = j["shutdown_grace_sec"].as_u64().unwrap_or(8);
         self.ms_delay_after_edit = j["ms_delay_after_edit"].as_u64();
         self.delay_after_page_check_sec = j["delay_after_page_check_sec"].as_u64();
         self.max_threads = j["max_threads"]
@@ -772,6 +1160,9 @@ impl Configuration {
             .as_u64()
             .and_then(|u| u.try_into().ok());
         self.profiling = j["profiling"].as_bool().unwrap_or_default();
+        self.recent_changes = RecentChangesSettings::from_json(&j["recent_changes"]);
+        self.fair_scheduling = FairSchedulingSettings::from_json(&j["fair_scheduling"]);
+        self.item_page_index = j["item_page_index"].as_bool().unwrap_or_default();
         self.quiet = j["quiet"].as_bool().unwrap_or_default();
         if let Some(obj) = j["wiki_name_aliases"].as_object() {
             // Merge over the built-in defaults so JSON entries can both
@@ -804,22 +1195,36 @@ impl Configuration {
             .unwrap_or_default()
             .to_string();
         self.sparql_prefix = j["sparql_prefix"].as_str().map(|s| s.to_string());
+        self.language_fallbacks = LanguageFallbacks::from_json(&j["language_fallbacks"]);
+        self.mark_language_fallbacks = j["mark_language_fallbacks"].as_bool().unwrap_or_default();
+        if let Some(s) = j["date_format"].as_str() {
+            match DateFormat::new(s) {
+                Some(f) => self.date_format = f,
+                None => log::warn!("Ignoring unknown date_format: {s}"),
+            }
+        }
+        self.annotate_julian_dates = j["annotate_julian_dates"].as_bool().unwrap_or_default();
+        self.inline_diagnostics = j["inline_diagnostics"].as_bool().unwrap_or_default();
+        self.expand_template_params = j["expand_template_params"].as_bool().unwrap_or_default();
+        self.citation_templates = j["citation_templates"].clone();
+        self.messages = Arc::new(
+            Messages::from_json(&j["messages"]).with_fallbacks(self.language_fallbacks.clone()),
+        );
+        self.load_onwiki_messages = j["load_onwiki_messages"].as_bool().unwrap_or_default();
         if let Some(obj) = j["feature_flags"].as_object() {
             self.feature_flags = FeatureFlags::from_json(&Value::Object(obj.clone()));
         }
         if let Some(sic) = j["shadow_images_check"].as_array() {
-            self.shadow_images_check = sic
-                .iter()
-                .filter_map(|s| match s.as_str() {
-                    Some(s) => Some(s.to_string()),
+            for s in sic {
+                match s.as_str() {
+                    Some(wiki) => self.overrides_mut(wiki).shadow_images_check = Some(true),
                     None => {
                         log::warn!(
                             "Ignoring non-string entry in shadow_images_check config: {s}"
                         );
-                        None
                     }
-                })
-                .collect();
+                }
+            }
         }
     }
 }
@@ -999,20 +1404,17 @@ mod tests {
 
     #[test]
     fn test_check_for_shadow_images_found() {
-        let config = Configuration {
-            shadow_images_check: vec!["enwiki".to_string(), "dewiki".to_string()],
-            ..Default::default()
-        };
+        let mut config = Configuration::default();
+        config.overrides_mut("enwiki").shadow_images_check = Some(true);
+        config.overrides_mut("dewiki").shadow_images_check = Some(true);
         assert!(config.check_for_shadow_images("enwiki"));
         assert!(config.check_for_shadow_images("dewiki"));
     }
 
     #[test]
     fn test_check_for_shadow_images_not_found() {
-        let config = Configuration {
-            shadow_images_check: vec!["enwiki".to_string()],
-            ..Default::default()
-        };
+        let mut config = Configuration::default();
+        config.overrides_mut("enwiki").shadow_images_check = Some(true);
         assert!(!config.check_for_shadow_images("frwiki"));
         assert!(!config.check_for_shadow_images(""));
     }
@@ -1028,9 +1430,7 @@ mod tests {
     #[test]
     fn test_get_location_template_specific_wiki() {
         let mut config = Configuration::default();
-        config
-            .location_templates
-            .insert("enwiki".to_string(), "{{Coord|$1|$2}}".to_string());
+        config.overrides_mut("enwiki").location_template = Some("{{Coord|$1|$2}}".to_string());
         assert_eq!(
             config.get_location_template("enwiki"),
             "{{Coord|$1|$2}}"
@@ -1039,10 +1439,10 @@ mod tests {
 
     #[test]
     fn test_get_location_template_falls_back_to_default() {
-        let mut config = Configuration::default();
-        config
-            .location_templates
-            .insert("default".to_string(), "{{Coord|$1|$2|default}}".to_string());
+        let config = Configuration {
+            location_template: "{{Coord|$1|$2|default}}".to_string(),
+            ..Default::default()
+        };
         assert_eq!(
             config.get_location_template("frwiki"),
             "{{Coord|$1|$2|default}}"
@@ -1051,13 +1451,11 @@ mod tests {
 
     #[test]
     fn test_get_location_template_specific_overrides_default() {
-        let mut config = Configuration::default();
-        config
-            .location_templates
-            .insert("default".to_string(), "default_tmpl".to_string());
-        config
-            .location_templates
-            .insert("enwiki".to_string(), "enwiki_tmpl".to_string());
+        let mut config = Configuration {
+            location_template: "default_tmpl".to_string(),
+            ..Default::default()
+        };
+        config.overrides_mut("enwiki").location_template = Some("enwiki_tmpl".to_string());
         assert_eq!(config.get_location_template("enwiki"), "enwiki_tmpl");
         assert_eq!(config.get_location_template("dewiki"), "default_tmpl");
     }
@@ -1081,9 +1479,7 @@ mod tests {
     #[test]
     fn test_can_edit_namespace_wiki_with_all_block() {
         let mut config = Configuration::default();
-        config
-            .namespace_blocks
-            .insert("enwiki".to_string(), NamespaceGroup::All);
+        config.overrides_mut("enwiki").namespace_block = Some(NamespaceGroup::All);
         assert!(!config.can_edit_namespace("enwiki", 0));
         assert!(!config.can_edit_namespace("enwiki", 10));
         // Another wiki is still unrestricted
@@ -1093,10 +1489,7 @@ mod tests {
     #[test]
     fn test_can_edit_namespace_wiki_with_list_block() {
         let mut config = Configuration::default();
-        config.namespace_blocks.insert(
-            "enwiki".to_string(),
-            NamespaceGroup::List(vec![1, 3]),
-        );
+        config.overrides_mut("enwiki").namespace_block = Some(NamespaceGroup::List(vec![1, 3]));
         assert!(!config.can_edit_namespace("enwiki", 1));
         assert!(!config.can_edit_namespace("enwiki", 3));
         assert!(config.can_edit_namespace("enwiki", 0));
@@ -1181,6 +1574,12 @@ mod tests {
         assert!(config.pool().is_err());
     }
 
+    #[test]
+    fn test_storage_accessor_no_storage_is_err() {
+        let config = Configuration::default();
+        assert!(config.storage().is_err());
+    }
+
     // ── default_thumbnail_size ─────────────────────────────────────────────
 
     #[test]
@@ -1373,9 +1772,11 @@ mod tests {
         // Now it's silently skipped so the rest of the array parses fine.
         let mut config = Configuration::default();
         config.new_from_json_locations(&serde_json::json!({
-            "location_regions": ["US", 42, "DE", null]
+            "location_regions": ["enwiki", 42, "dewiki", null]
         }));
-        assert_eq!(config.location_regions, vec!["US", "DE"]);
+        assert!(config.wiki_settings("enwiki").location_regions);
+        assert!(config.wiki_settings("dewiki").location_regions);
+        assert!(!config.wiki_settings("frwiki").location_regions);
     }
 
     #[test]
@@ -1384,7 +1785,9 @@ mod tests {
         config.new_from_json_misc(&serde_json::json!({
             "shadow_images_check": ["enwiki", 7, "dewiki"]
         }));
-        assert_eq!(config.shadow_images_check, vec!["enwiki", "dewiki"]);
+        assert!(config.check_for_shadow_images("enwiki"));
+        assert!(config.check_for_shadow_images("dewiki"));
+        assert!(!config.check_for_shadow_images("7"));
     }
 
     #[test]
@@ -1395,17 +1798,14 @@ mod tests {
                 "default": "{{Coord|$1|$2}}",
                 "enwiki": "{{Coord|$1|$2|display=title}}"
             },
-            "location_regions": ["US", "DE"]
+            "location_regions": ["enwiki"]
         }));
+        assert_eq!(config.get_location_template("dewiki"), "{{Coord|$1|$2}}");
         assert_eq!(
-            config.location_templates.get("default").unwrap(),
-            "{{Coord|$1|$2}}"
-        );
-        assert_eq!(
-            config.location_templates.get("enwiki").unwrap(),
+            config.get_location_template("enwiki"),
             "{{Coord|$1|$2|display=title}}"
         );
-        assert_eq!(config.location_regions, vec!["US", "DE"]);
+        assert!(config.wiki_settings("enwiki").location_regions);
     }
 
     // ── new_from_json_namespace_blocks ─────────────────────────────────────
@@ -1416,8 +1816,8 @@ mod tests {
         let j = serde_json::json!({ "namespace_blocks": { "enwiki": "*" } });
         config.new_from_json_namespace_blocks(&j).unwrap();
         assert!(matches!(
-            config.namespace_blocks.get("enwiki").unwrap(),
-            NamespaceGroup::All
+            config.wiki_settings("enwiki").namespace_block,
+            Some(NamespaceGroup::All)
         ));
     }
 
@@ -1427,8 +1827,8 @@ mod tests {
         let j = serde_json::json!({ "namespace_blocks": { "enwiki": [1, 3] } });
         config.new_from_json_namespace_blocks(&j).unwrap();
         assert!(matches!(
-            config.namespace_blocks.get("enwiki").unwrap(),
-            NamespaceGroup::List(_)
+            config.wiki_settings("enwiki").namespace_block,
+            Some(NamespaceGroup::List(_))
         ));
     }
 
@@ -1439,6 +1839,314 @@ mod tests {
         assert!(config.new_from_json_namespace_blocks(&j).is_err());
     }
 
+    // ── wikis.<dbname> overrides ───────────────────────────────────────────
+
+    fn config_with_overrides(j: serde_json::Value) -> Configuration {
+        let mut config = Configuration::default();
+        config.new_from_json_misc(&j);
+        config.new_from_json_locations(&j);
+        config.new_from_json_namespace_blocks(&j).unwrap();
+        config.new_from_json_wiki_overrides(&j).unwrap();
+        config
+    }
+
+    #[test]
+    fn test_wiki_settings_without_overrides_uses_globals() {
+        let config = config_with_overrides(serde_json::json!({
+            "prefer_preferred": true,
+            "default_language": "en",
+            "default_thumbnail_size": 200,
+            "ms_delay_after_edit": 100,
+            "location_templates": { "default": "{{Coord|$LAT$|$LON$}}" }
+        }));
+        let settings = config.wiki_settings("enwiki");
+        assert!(settings.prefer_preferred);
+        assert_eq!(settings.default_language, "en");
+        assert_eq!(settings.thumbnail_size, 200);
+        assert_eq!(settings.ms_delay_after_edit, Some(100));
+        assert_eq!(settings.location_template, "{{Coord|$LAT$|$LON$}}");
+        assert!(settings.namespace_block.is_none());
+        assert!(!settings.shadow_images_check);
+        assert!(!settings.location_regions);
+        assert!(settings.feature_flags.enable_autodesc);
+    }
+
+    #[test]
+    fn test_wiki_settings_block_overrides_only_named_wiki() {
+        let config = config_with_overrides(serde_json::json!({
+            "prefer_preferred": true,
+            "default_thumbnail_size": 128,
+            "wikis": {
+                "dewiki": {
+                    "prefer_preferred": false,
+                    "default_thumbnail_size": 250,
+                    "default_language": "de",
+                    "ms_delay_after_edit": 500,
+                    "location_template": "{{Coordinate|NS=$LAT$|EW=$LON$}}",
+                    "shadow_images_check": true,
+                    "location_regions": true
+                }
+            }
+        }));
+        let de = config.wiki_settings("dewiki");
+        assert!(!de.prefer_preferred);
+        assert_eq!(de.thumbnail_size, 250);
+        assert_eq!(de.default_language, "de");
+        assert_eq!(de.ms_delay_after_edit, Some(500));
+        assert_eq!(de.location_template, "{{Coordinate|NS=$LAT$|EW=$LON$}}");
+        assert!(de.shadow_images_check);
+        assert!(de.location_regions);
+
+        let en = config.wiki_settings("enwiki");
+        assert!(en.prefer_preferred);
+        assert_eq!(en.thumbnail_size, 128);
+        assert!(!en.shadow_images_check);
+    }
+
+    #[test]
+    fn test_wiki_settings_block_wins_over_legacy_maps() {
+        let config = config_with_overrides(serde_json::json!({
+            "location_templates": { "enwiki": "legacy" },
+            "namespace_blocks": { "enwiki": [0] },
+            "shadow_images_check": ["enwiki"],
+            "wikis": {
+                "enwiki": {
+                    "location_template": "new",
+                    "namespace_block": [4],
+                    "shadow_images_check": false
+                }
+            }
+        }));
+        assert_eq!(config.get_location_template("enwiki"), "new");
+        assert!(config.can_edit_namespace("enwiki", 0));
+        assert!(!config.can_edit_namespace("enwiki", 4));
+        assert!(!config.check_for_shadow_images("enwiki"));
+    }
+
+    #[test]
+    fn test_wiki_settings_legacy_maps_still_apply() {
+        let config = config_with_overrides(serde_json::json!({
+            "location_templates": { "enwiki": "legacy" },
+            "namespace_blocks": { "enwiki": "*" },
+            "wikis": { "enwiki": { "prefer_preferred": true } }
+        }));
+        assert_eq!(config.get_location_template("enwiki"), "legacy");
+        assert!(!config.can_edit_namespace("enwiki", 0));
+        assert!(config.wiki_settings("enwiki").prefer_preferred);
+    }
+
+    #[test]
+    fn test_wiki_settings_feature_flags_merge_over_global() {
+        let config = config_with_overrides(serde_json::json!({
+            "feature_flags": { "enable_references": false },
+            "wikis": { "frwiki": { "feature_flags": { "enable_autodesc": false } } }
+        }));
+        let fr = config.wiki_settings("frwiki").feature_flags;
+        assert!(!fr.enable_autodesc);
+        assert!(!fr.enable_references); // inherited from the global block
+        assert!(fr.enable_regions);
+        let en = config.wiki_settings("enwiki").feature_flags;
+        assert!(en.enable_autodesc);
+        assert!(!en.enable_references);
+    }
+
+    #[test]
+    fn test_wiki_settings_load_onwiki_messages() {
+        let config = config_with_overrides(serde_json::json!({
+            "load_onwiki_messages": true,
+            "messages": { "de": { "misc": "Weitere" } },
+            "wikis": { "dewiki": { "load_onwiki_messages": false } }
+        }));
+        assert!(config.wiki_settings("enwiki").load_onwiki_messages);
+        assert!(!config.wiki_settings("dewiki").load_onwiki_messages);
+        assert_eq!(
+            config
+                .messages()
+                .get(crate::messages::Message::MiscSection, "de"),
+            "Weitere"
+        );
+    }
+
+    #[test]
+    fn test_language_fallbacks_config() {
+        let config = config_with_overrides(serde_json::json!({
+            "language_fallbacks": { "gsw": ["de-ch", "de"] },
+            "messages": { "de-ch": { "misc": "Übriges" } },
+            "wikis": { "alswiki": { "mark_language_fallbacks": true } }
+        }));
+        assert_eq!(
+            &config.language_fallbacks().chain("gsw")[..3],
+            &["gsw", "de-ch", "de"]
+        );
+        // Messages follow the configured chain too
+        assert_eq!(
+            config
+                .messages()
+                .get(crate::messages::Message::MiscSection, "gsw"),
+            "Übriges"
+        );
+        // Marking is opt-in
+        assert!(!config.wiki_settings("dewiki").mark_language_fallbacks);
+        assert!(config.wiki_settings("alswiki").mark_language_fallbacks);
+    }
+
+    #[test]
+    fn test_date_format_config() {
+        let config = config_with_overrides(serde_json::json!({
+            "date_format": "long",
+            "wikis": {
+                "enwiki": { "date_format": "iso", "annotate_julian_dates": true },
+                "frwiki": { "date_format": "fancy" }
+            }
+        }));
+        assert_eq!(config.wiki_settings("dewiki").date_format, DateFormat::Long);
+        assert!(!config.wiki_settings("dewiki").annotate_julian_dates);
+        assert_eq!(config.wiki_settings("enwiki").date_format, DateFormat::Iso);
+        assert!(config.wiki_settings("enwiki").annotate_julian_dates);
+        // Unknown formats are skipped, keeping the global value
+        assert_eq!(config.wiki_settings("frwiki").date_format, DateFormat::Long);
+    }
+
+    #[test]
+    fn test_inline_diagnostics_config() {
+        let config = config_with_overrides(serde_json::json!({
+            "inline_diagnostics": true,
+            "wikis": { "dewiki": { "inline_diagnostics": false } }
+        }));
+        assert!(config.wiki_settings("enwiki").inline_diagnostics);
+        assert!(!config.wiki_settings("dewiki").inline_diagnostics);
+    }
+
+    #[test]
+    fn test_expand_template_params_config() {
+        let config = config_with_overrides(serde_json::json!({
+            "wikis": { "dewiki": { "expand_template_params": true } }
+        }));
+        assert!(!config.wiki_settings("enwiki").expand_template_params);
+        assert!(config.wiki_settings("dewiki").expand_template_params);
+    }
+
+    #[test]
+    fn test_citation_templates_config() {
+        use crate::citation_templates::ReferenceShape;
+        let config = config_with_overrides(serde_json::json!({
+            "citation_templates": { "item": "Cite Q" },
+            "wikis": {
+                "dewiki": { "citation_templates": { "preset": true, "publication": null } },
+                "nlwiki": { "citation_templates": { "item": "Citeer Q" } }
+            }
+        }));
+        let wiki = |name: &str, use_invoke, use_cite_web| {
+            let row = (
+                1,
+                name.to_string(),
+                "active".to_string(),
+                String::new(),
+                use_invoke,
+                use_cite_web,
+            );
+            (name.to_string(), Wiki::from_row(row).unwrap())
+        };
+        let config = config.with_wikis(
+            [
+                wiki("enwiki", true, true),
+                wiki("frwiki", false, true),
+                wiki("huwiki", false, false),
+                wiki("dewiki", false, false),
+            ]
+            .into(),
+        );
+        let web_name = |wiki: &str| {
+            config
+                .wiki_settings(wiki)
+                .citation_templates
+                .get(ReferenceShape::Web)
+                .map(|t| t.render(&[]))
+        };
+        // The `wikis` table flags pick the web template, as they always did
+        assert_eq!(web_name("enwiki").as_deref(), Some("{{#invoke:cite|web}}"));
+        assert_eq!(web_name("frwiki").as_deref(), Some("{{cite web}}"));
+        assert_eq!(web_name("huwiki"), None);
+        // Wikis not in the table get both flags
+        assert_eq!(web_name("wiki").as_deref(), Some("{{#invoke:cite|web}}"));
+        // The config adds shapes on top
+        let en = config.wiki_settings("enwiki").citation_templates;
+        assert!(en.get(ReferenceShape::Item).is_some());
+        assert!(en.get(ReferenceShape::Publication).is_some());
+        // A preset replaces the flags; overrides apply per shape
+        let de = config.wiki_settings("dewiki").citation_templates;
+        assert_eq!(
+            de,
+            CitationTemplates::preset("dewiki")
+                .unwrap()
+                .merged_with(&serde_json::json!({ "publication": null }))
+        );
+        let nl = config.wiki_settings("nlwiki").citation_templates;
+        assert!(nl.get(ReferenceShape::Web).is_some());
+        assert!(nl.get(ReferenceShape::Item).is_some());
+    }
+
+    #[test]
+    fn test_item_page_index_config() {
+        assert!(!config_with_overrides(serde_json::json!({})).item_page_index());
+        let on = config_with_overrides(serde_json::json!({ "item_page_index": true }));
+        assert!(on.item_page_index());
+        // The item watch needs the index
+        let watched = config_with_overrides(serde_json::json!({
+            "recent_changes": { "enabled": true, "watch_items": true }
+        }));
+        assert!(watched.item_page_index());
+    }
+
+    #[test]
+    fn test_shutdown_grace_config() {
+        let config = config_with_overrides(serde_json::json!({}));
+        assert_eq!(config.shutdown_grace(), Duration::from_secs(8));
+        let config = config_with_overrides(serde_json::json!({ "shutdown_grace_sec": 5 }));
+        assert_eq!(config.shutdown_grace(), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_fair_scheduling_config() {
+        let config = config_with_overrides(serde_json::json!({
+            "fair_scheduling": { "weights": { "enwiki": 3 }, "max_running_per_wiki": 2 }
+        }));
+        assert_eq!(config.fair_scheduling().weights.get("enwiki"), Some(&3));
+        assert_eq!(config.fair_scheduling().max_running_per_wiki, Some(2));
+        let config = config_with_overrides(serde_json::json!({}));
+        assert_eq!(config.fair_scheduling(), &FairSchedulingSettings::default());
+    }
+
+    #[test]
+    fn test_wiki_overrides_wrong_types_are_skipped() {
+        let config = config_with_overrides(serde_json::json!({
+            "default_thumbnail_size": 128,
+            "wikis": {
+                "enwiki": { "default_thumbnail_size": "big", "unknown_key": 1 },
+                "dewiki": 42
+            }
+        }));
+        assert_eq!(config.wiki_settings("enwiki").thumbnail_size, 128);
+        assert_eq!(config.wiki_settings("dewiki").thumbnail_size, 128);
+    }
+
+    #[test]
+    fn test_wiki_overrides_bad_namespace_block_is_err() {
+        let mut config = Configuration::default();
+        let j = serde_json::json!({ "wikis": { "enwiki": { "namespace_block": "bad" } } });
+        assert!(config.new_from_json_wiki_overrides(&j).is_err());
+    }
+
+    #[test]
+    fn test_wiki_settings_can_edit_namespace() {
+        let mut config = Configuration::default();
+        config.overrides_mut("enwiki").namespace_block = Some(NamespaceGroup::List(vec![2]));
+        let settings = config.wiki_settings("enwiki");
+        assert!(settings.can_edit_namespace(0));
+        assert!(!settings.can_edit_namespace(2));
+        assert!(config.wiki_settings("dewiki").can_edit_namespace(2));
+    }
+
     // ── consuming builder (with_*) ─────────────────────────────────────────
 
     #[test]
@@ -0,0 +1,511 @@
+//! Locale-aware rendering of Wikidata time values.
+//!
+//! A [`TimeInfo`] is formatted according to a [`DateFormat`] (chosen per
+//! wiki, per list or per column via `date_format=`) and the first language
+//! along the list's fallback chain that has a date locale. Languages without
+//! one use English wording.
+
+use crate::result_cell_part::TimeInfo;
+
+/// How a time value is written out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DateFormat {
+    /// Numeric `1879-03-14` / `1879-03` / `1879`; coarser precisions and
+    /// BCE markers in words.
+    #[default]
+    Iso,
+    /// Month names in the list language: `14 March 1879`, `14. März 1879`.
+    Long,
+    /// The year only, whatever the precision (down to decades).
+    Year,
+}
+
+impl DateFormat {
+    #[must_use]
+    pub fn new(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "iso" | "numeric" => Some(Self::Iso),
+            "long" | "text" => Some(Self::Long),
+            "year" => Some(Self::Year),
+            _ => None,
+        }
+    }
+
+    /// Formats `t` using the first supported language in `languages`.
+    #[must_use]
+    pub fn format(self, t: &TimeInfo, languages: &[String]) -> String {
+        let locale = DateLocale::for_languages(languages);
+        let bce = t.year < 0;
+        let abs = t.year.unsigned_abs().max(1);
+        let with_era = |s: String| {
+            if bce {
+                format!("{s} {}", locale.bce())
+            } else {
+                s
+            }
+        };
+        match t.precision {
+            6 => with_era(locale.millennium((abs - 1) / 1000 + 1)),
+            7 => with_era(locale.century((abs - 1) / 100 + 1)),
+            8 => with_era(locale.decade(abs - abs % 10)),
+            p if p <= 9 || self == Self::Year => self.year(locale, abs, bce),
+            10 => match (self, locale.month_name(t.month)) {
+                (Self::Long, Some(month)) => locale.month_year(month, &self.year(locale, abs, bce)),
+                (_, Some(_)) => with_era(format!("{abs:04}-{:02}", t.month)),
+                (_, None) => self.year(locale, abs, bce),
+            },
+            _ => match (self, locale.month_name(t.month)) {
+                (_, None) => self.year(locale, abs, bce),
+                (Self::Long, Some(month)) if t.day == 0 => {
+                    locale.month_year(month, &self.year(locale, abs, bce))
+                }
+                (Self::Long, Some(month)) => {
+                    locale.day_month_year(t.day, month, &self.year(locale, abs, bce))
+                }
+                (_, Some(_)) if t.day == 0 => with_era(format!("{abs:04}-{:02}", t.month)),
+                (_, Some(_)) => with_era(format!("{abs:04}-{:02}-{:02}", t.month, t.day)),
+            },
+        }
+    }
+
+    /// A year with its era marker. The long format also marks early
+    /// common-era years (`33 CE`), which are easily mistaken otherwise.
+    fn year(self, locale: DateLocale, abs: u32, bce: bool) -> String {
+        if bce {
+            format!("{abs} {}", locale.bce())
+        } else if self == Self::Long && abs < 1000 {
+            format!("{abs} {}", locale.ce())
+        } else {
+            abs.to_string()
+        }
+    }
+}
+
+/// Date wording for one language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DateLocale {
+    En,
+    De,
+    Fr,
+    Es,
+    It,
+    Nl,
+}
+
+impl DateLocale {
+    fn for_languages(languages: &[String]) -> Self {
+        languages
+            .iter()
+            .find_map(|l| Self::new(l))
+            .unwrap_or(Self::En)
+    }
+
+    fn new(language: &str) -> Option<Self> {
+        match language {
+            "en" => Some(Self::En),
+            "de" => Some(Self::De),
+            "fr" => Some(Self::Fr),
+            "es" => Some(Self::Es),
+            "it" => Some(Self::It),
+            "nl" => Some(Self::Nl),
+            _ => None,
+        }
+    }
+
+    const fn months(self) -> [&'static str; 12] {
+        match self {
+            Self::En => [
+                "January",
+                "February",
+                "March",
+                "April",
+                "May",
+                "June",
+                "July",
+                "August",
+                "September",
+                "October",
+                "November",
+                "December",
+            ],
+            Self::De => [
+                "Januar",
+                "Februar",
+                "März",
+                "April",
+                "Mai",
+                "Juni",
+                "Juli",
+                "August",
+                "September",
+                "Oktober",
+                "November",
+                "Dezember",
+            ],
+            Self::Fr => [
+                "janvier",
+                "février",
+                "mars",
+                "avril",
+                "mai",
+                "juin",
+                "juillet",
+                "août",
+                "septembre",
+                "octobre",
+                "novembre",
+                "décembre",
+            ],
+            Self::Es => [
+                "enero",
+                "febrero",
+                "marzo",
+                "abril",
+                "mayo",
+                "junio",
+                "julio",
+                "agosto",
+                "septiembre",
+                "octubre",
+                "noviembre",
+                "diciembre",
+            ],
+            Self::It => [
+                "gennaio",
+                "febbraio",
+                "marzo",
+                "aprile",
+                "maggio",
+                "giugno",
+                "luglio",
+                "agosto",
+                "settembre",
+                "ottobre",
+                "novembre",
+                "dicembre",
+            ],
+            Self::Nl => [
+                "januari",
+                "februari",
+                "maart",
+                "april",
+                "mei",
+                "juni",
+                "juli",
+                "augustus",
+                "september",
+                "oktober",
+                "november",
+                "december",
+            ],
+        }
+    }
+
+    fn month_name(self, month: u8) -> Option<&'static str> {
+        let index = usize::from(month).checked_sub(1)?;
+        self.months().get(index).copied()
+    }
+
+    const fn bce(self) -> &'static str {
+        match self {
+            Self::En => "BCE",
+            Self::De => "v. Chr.",
+            Self::Fr => "av. J.-C.",
+            Self::Es => "a. C.",
+            Self::It => "a.C.",
+            Self::Nl => "v.Chr.",
+        }
+    }
+
+    const fn ce(self) -> &'static str {
+        match self {
+            Self::En => "CE",
+            Self::De => "n. Chr.",
+            Self::Fr => "apr. J.-C.",
+            Self::Es => "d. C.",
+            Self::It => "d.C.",
+            Self::Nl => "n.Chr.",
+        }
+    }
+
+    fn day_month_year(self, day: u8, month: &str, year: &str) -> String {
+        match self {
+            Self::De => format!("{day}. {month} {year}"),
+            Self::Fr if day == 1 => format!("1er {month} {year}"),
+            Self::Es => format!("{day} de {month} de {year}"),
+            _ => format!("{day} {month} {year}"),
+        }
+    }
+
+    fn month_year(self, month: &str, year: &str) -> String {
+        match self {
+            Self::Es => format!("{month} de {year}"),
+            _ => format!("{month} {year}"),
+        }
+    }
+
+    fn decade(self, start: u32) -> String {
+        match self {
+            Self::En => format!("{start}s"),
+            Self::De => format!("{start}er"),
+            Self::Fr => format!("années {start}"),
+            Self::Es => format!("década de {start}"),
+            Self::It => format!("anni {start}"),
+            Self::Nl => format!("jaren {start}"),
+        }
+    }
+
+    fn century(self, n: u32) -> String {
+        match self {
+            Self::En => format!("{} century", english_ordinal(n)),
+            Self::De => format!("{n}. Jahrhundert"),
+            Self::Fr => format!("{} siècle", french_ordinal(n)),
+            Self::Es => format!("siglo {}", roman(n)),
+            Self::It => format!("{} secolo", roman(n)),
+            Self::Nl => format!("{n}e eeuw"),
+        }
+    }
+
+    fn millennium(self, n: u32) -> String {
+        match self {
+            Self::En => format!("{} millennium", english_ordinal(n)),
+            Self::De => format!("{n}. Jahrtausend"),
+            Self::Fr => format!("{} millénaire", french_ordinal(n)),
+            Self::Es => format!("milenio {}", roman(n)),
+            Self::It => format!("{} millennio", roman(n)),
+            Self::Nl => format!("{n}e millennium"),
+        }
+    }
+}
+
+/// `1` → `1st`, `12` → `12th`, `22` → `22nd`.
+fn english_ordinal(n: u32) -> String {
+    let suffix = if (11..=13).contains(&(n % 100)) {
+        "th"
+    } else {
+        match n % 10 {
+            1 => "st",
+            2 => "nd",
+            3 => "rd",
+            _ => "th",
+        }
+    };
+    format!("{n}{suffix}")
+}
+
+/// `1` → `Ier`, `19` → `XIXe`.
+fn french_ordinal(n: u32) -> String {
+    if n == 1 {
+        "Ier".to_string()
+    } else {
+        format!("{}e", roman(n))
+    }
+}
+
+fn roman(mut n: u32) -> String {
+    const NUMERALS: [(u32, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let mut ret = String::new();
+    for (value, numeral) in NUMERALS {
+        while n >= value {
+            ret += numeral;
+            n -= value;
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn langs(l: &str) -> Vec<String> {
+        vec![l.to_string()]
+    }
+
+    fn time(year: i32, month: u8, day: u8, precision: u8) -> TimeInfo {
+        TimeInfo::new(year, month, day, precision)
+    }
+
+    // ── parsing ────────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_date_format_new() {
+        assert_eq!(DateFormat::new("long"), Some(DateFormat::Long));
+        assert_eq!(DateFormat::new(" ISO "), Some(DateFormat::Iso));
+        assert_eq!(DateFormat::new("year"), Some(DateFormat::Year));
+        assert_eq!(DateFormat::new("fancy"), None);
+    }
+
+    // ── iso ────────────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_iso_is_numeric_in_every_language() {
+        let t = time(1879, 3, 14, 11);
+        assert_eq!(DateFormat::Iso.format(&t, &langs("en")), "1879-03-14");
+        assert_eq!(DateFormat::Iso.format(&t, &langs("de")), "1879-03-14");
+        assert_eq!(
+            DateFormat::Iso.format(&time(1879, 3, 14, 10), &langs("fr")),
+            "1879-03"
+        );
+    }
+
+    #[test]
+    fn test_iso_coarse_precisions_are_localised() {
+        let century = time(1900, 0, 0, 7);
+        assert_eq!(
+            DateFormat::Iso.format(&century, &langs("en")),
+            "19th century"
+        );
+        assert_eq!(
+            DateFormat::Iso.format(&century, &langs("de")),
+            "19. Jahrhundert"
+        );
+        assert_eq!(
+            DateFormat::Iso.format(&century, &langs("fr")),
+            "XIXe siècle"
+        );
+        assert_eq!(DateFormat::Iso.format(&century, &langs("es")), "siglo XIX");
+        let decade = time(1987, 0, 0, 8);
+        assert_eq!(DateFormat::Iso.format(&decade, &langs("en")), "1980s");
+        assert_eq!(DateFormat::Iso.format(&decade, &langs("de")), "1980er");
+        let millennium = time(2000, 0, 0, 6);
+        assert_eq!(
+            DateFormat::Iso.format(&millennium, &langs("en")),
+            "2nd millennium"
+        );
+        assert_eq!(
+            DateFormat::Iso.format(&millennium, &langs("it")),
+            "II millennio"
+        );
+    }
+
+    #[test]
+    fn test_bce_markers() {
+        assert_eq!(
+            DateFormat::Iso.format(&time(-44, 0, 0, 9), &langs("en")),
+            "44 BCE"
+        );
+        assert_eq!(
+            DateFormat::Iso.format(&time(-99, 0, 0, 7), &langs("de")),
+            "1. Jahrhundert v. Chr."
+        );
+        assert_eq!(
+            DateFormat::Iso.format(&time(-99, 0, 0, 7), &langs("fr")),
+            "Ier siècle av. J.-C."
+        );
+        assert_eq!(
+            DateFormat::Long.format(&time(-44, 3, 15, 11), &langs("en")),
+            "15 March 44 BCE"
+        );
+    }
+
+    // ── long ───────────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_long_day_month_order() {
+        let t = time(1879, 3, 14, 11);
+        assert_eq!(DateFormat::Long.format(&t, &langs("en")), "14 March 1879");
+        assert_eq!(DateFormat::Long.format(&t, &langs("de")), "14. März 1879");
+        assert_eq!(
+            DateFormat::Long.format(&t, &langs("es")),
+            "14 de marzo de 1879"
+        );
+        assert_eq!(
+            DateFormat::Long.format(&time(1879, 3, 1, 11), &langs("fr")),
+            "1er mars 1879"
+        );
+        assert_eq!(
+            DateFormat::Long.format(&time(1879, 3, 0, 10), &langs("nl")),
+            "maart 1879"
+        );
+    }
+
+    #[test]
+    fn test_long_marks_early_common_era_years() {
+        assert_eq!(
+            DateFormat::Long.format(&time(33, 0, 0, 9), &langs("en")),
+            "33 CE"
+        );
+        assert_eq!(
+            DateFormat::Iso.format(&time(33, 0, 0, 9), &langs("en")),
+            "33"
+        );
+    }
+
+    #[test]
+    fn test_locale_follows_language_chain() {
+        let chain = vec!["gsw".to_string(), "de".to_string(), "en".to_string()];
+        assert_eq!(
+            DateFormat::Long.format(&time(1879, 3, 14, 11), &chain),
+            "14. März 1879"
+        );
+        assert_eq!(
+            DateFormat::Long.format(&time(1879, 3, 14, 11), &langs("ja")),
+            "14 March 1879"
+        );
+    }
+
+    // ── year ───────────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_year_format() {
+        assert_eq!(
+            DateFormat::Year.format(&time(1879, 3, 14, 11), &langs("de")),
+            "1879"
+        );
+        // Coarser than a year is kept as is
+        assert_eq!(
+            DateFormat::Year.format(&time(1900, 0, 0, 7), &langs("en")),
+            "19th century"
+        );
+    }
+
+    #[test]
+    fn test_invalid_month_falls_back_to_year() {
+        assert_eq!(
+            DateFormat::Long.format(&time(1879, 0, 0, 11), &langs("en")),
+            "1879"
+        );
+    }
+
+    // ── ordinals ───────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_english_ordinal() {
+        assert_eq!(english_ordinal(1), "1st");
+        assert_eq!(english_ordinal(2), "2nd");
+        assert_eq!(english_ordinal(3), "3rd");
+        assert_eq!(english_ordinal(4), "4th");
+        assert_eq!(english_ordinal(11), "11th");
+        assert_eq!(english_ordinal(12), "12th");
+        assert_eq!(english_ordinal(13), "13th");
+        assert_eq!(english_ordinal(21), "21st");
+        assert_eq!(english_ordinal(22), "22nd");
+        assert_eq!(english_ordinal(101), "101st");
+        assert_eq!(english_ordinal(111), "111th");
+        assert_eq!(english_ordinal(0), "0th");
+    }
+
+    #[test]
+    fn test_roman() {
+        assert_eq!(roman(1), "I");
+        assert_eq!(roman(4), "IV");
+        assert_eq!(roman(19), "XIX");
+        assert_eq!(roman(21), "XXI");
+        assert_eq!(roman(1994), "MCMXCIV");
+    }
+}
@@ -0,0 +1,201 @@
+//! Problems with a list, shown to editors in a box above the list with
+//! `diagnostics=yes` (or the `inline_diagnostics` setting).
+//!
+//! The box is written on a single line with a fixed start, so that the next
+//! run can find and replace it. When a list fails, the box takes the place
+//! of the old one and the last good list stays below it.
+
+use crate::messages::Message;
+use crate::param_validation::ParamWarning;
+
+const BOX_START: &str = "<div class=\"listeria-diagnostics";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Diagnostic {
+    /// The list could not be updated; the error.
+    Failed(String),
+    /// A template parameter, or column spec, that is not understood.
+    Parameter(ParamWarning),
+    /// A parameter without effect, and what it needs to have one.
+    IgnoredParameter {
+        name: &'static str,
+        requires: &'static str,
+    },
+    /// How many items could not be loaded.
+    UnloadedItems(usize),
+    /// The query returned as many rows as its `LIMIT`, so there may be more.
+    TruncatedResults(usize),
+}
+
+impl Diagnostic {
+    #[must_use]
+    pub const fn is_error(&self) -> bool {
+        matches!(self, Self::Failed(_))
+    }
+
+    fn as_text(&self, message: &impl Fn(Message, &[&str]) -> String) -> String {
+        match self {
+            Self::Failed(error) => message(Message::DiagnosticsFailed, &[&escape(error)]),
+            Self::Parameter(ParamWarning::UnknownColumn { spec, suggestion }) => {
+                let mut spec = format!("<code>{}</code>", escape(spec));
+                if let Some(suggestion) = suggestion {
+                    spec += &format!(" (<code>{}</code>?)", escape(suggestion));
+                }
+                message(Message::DiagnosticsUnknownColumn, &[&spec])
+            }
+            Self::Parameter(warning) => message(
+                Message::DiagnosticsParameter,
+                &[&escape(&warning.to_string())],
+            ),
+            Self::IgnoredParameter { name, requires } => message(
+                Message::DiagnosticsIgnoredParameter,
+                &[
+                    &format!("<code>{name}</code>"),
+                    &format!("<code>{requires}</code>"),
+                ],
+            ),
+            Self::UnloadedItems(count) => {
+                message(Message::DiagnosticsUnloadedItems, &[&count.to_string()])
+            }
+            Self::TruncatedResults(limit) => {
+                message(Message::DiagnosticsTruncatedResults, &[&limit.to_string()])
+            }
+        }
+    }
+}
+
+/// Text from outside (errors, column specs) must not add markup or break
+/// the single-line box.
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace(['[', ']', '{', '}', '|'], "")
+        .replace(['\n', '\r'], " ")
+}
+
+/// The box for `diagnostics`, on one line; empty if there are none.
+/// `message` gives the localised texts.
+#[must_use]
+pub fn render_box(
+    diagnostics: &[Diagnostic],
+    message: impl Fn(Message, &[&str]) -> String,
+) -> String {
+    if diagnostics.is_empty() {
+        return String::new();
+    }
+    let (class, colors) = if diagnostics.iter().any(Diagnostic::is_error) {
+        ("error", "border:1px solid #d33;background:#fee7e6")
+    } else {
+        ("warning", "border:1px solid #fc3;background:#fef6e7")
+    };
+    let items: String = diagnostics
+        .iter()
+        .map(|d| format!("<li>{}</li>", d.as_text(&message)))
+        .collect();
+    format!(
+        "{BOX_START} listeria-diagnostics-{class}\" style=\"{colors};padding:0.5em;margin-bottom:0.5em\">{}<ul>{items}</ul></div>",
+        message(Message::DiagnosticsHeading, &[])
+    )
+}
+
+/// `wikitext` without a box left by an earlier run.
+#[must_use]
+pub fn strip_box(wikitext: &str) -> &str {
+    let trimmed = wikitext.trim_start();
+    if !trimmed.starts_with(BOX_START) {
+        return wikitext;
+    }
+    match trimmed.find("</div>") {
+        Some(end) => trimmed[end + "</div>".len()..].trim_start_matches(['\r', '\n']),
+        None => wikitext,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(message: Message, args: &[&str]) -> String {
+        let mut ret = message.default_text().to_string();
+        for (num, arg) in args.iter().enumerate() {
+            ret = ret.replace(&format!("${}", num + 1), arg);
+        }
+        ret
+    }
+
+    #[test]
+    fn test_render_box() {
+        assert_eq!(render_box(&[], message), "");
+        let wikitext = render_box(
+            &[
+                Diagnostic::Parameter(ParamWarning::UnknownColumn {
+                    spec: "P31x".to_string(),
+                    suggestion: None,
+                }),
+                Diagnostic::IgnoredParameter {
+                    name: "pattern",
+                    requires: "format=list",
+                },
+            ],
+            message,
+        );
+        assert!(wikitext.starts_with(BOX_START));
+        assert!(wikitext.contains("listeria-diagnostics-warning"));
+        assert!(wikitext.contains("<li>Unknown column: <code>P31x</code></li>"));
+        assert!(wikitext.contains(
+            "<li>The parameter <code>pattern</code> is ignored without <code>format=list</code>.</li>"
+        ));
+        assert!(!wikitext.contains('\n'));
+    }
+
+    #[test]
+    fn test_render_box_parameters_and_truncation() {
+        let wikitext = render_box(
+            &[
+                Diagnostic::Parameter(ParamWarning::UnknownColumn {
+                    spec: "P31x".to_string(),
+                    suggestion: Some("P31".to_string()),
+                }),
+                Diagnostic::Parameter(ParamWarning::UnknownParameter {
+                    name: "sort_oder".to_string(),
+                    suggestion: Some("sort_order".to_string()),
+                }),
+                Diagnostic::TruncatedResults(500),
+            ],
+            message,
+        );
+        assert!(
+            wikitext.contains("<li>Unknown column: <code>P31x</code> (<code>P31</code>?)</li>")
+        );
+        assert!(wikitext.contains(
+            "<li>Template parameter: unknown parameter 'sort_oder' (did you mean 'sort_order'?)</li>"
+        ));
+        assert!(wikitext.contains("LIMIT 500"));
+    }
+
+    #[test]
+    fn test_render_box_escapes_errors() {
+        let wikitext = render_box(
+            &[Diagnostic::Failed(
+                "Bad query <x>\n[[Category:Foo]]".to_string(),
+            )],
+            message,
+        );
+        assert!(wikitext.contains("listeria-diagnostics-error"));
+        assert!(wikitext.contains("(Bad query &lt;x&gt; Category:Foo)"));
+        assert!(!wikitext.contains('\n'));
+    }
+
+    #[test]
+    fn test_strip_box() {
+        let table = "{| class=\"wikitable\"\n|}";
+        assert_eq!(strip_box(table), table);
+        let boxed = format!(
+            "{}\n{table}",
+            render_box(&[Diagnostic::UnloadedItems(2)], message)
+        );
+        assert_eq!(strip_box(&boxed), table);
+        assert_eq!(strip_box(&format!("\n{boxed}")), table);
+    }
+}
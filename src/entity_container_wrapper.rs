@@ -1,6 +1,9 @@
 //! Wrapper for entity container with caching and batch loading.
 
 use crate::circuit_breaker::{CircuitBreaker, with_breaker};
+use crate::lexeme::{self, Lexeme};
+use crate::listeria_error::ListeriaError;
+use crate::mediainfo;
 use crate::my_entity::MyEntity;
 use crate::render_context::RenderContext;
 
@@ -39,6 +42,7 @@ const EXTERNAL_ID_ESCAPE: &AsciiSet = &CONTROLS
     .add(b'+')
     .add(b'%');
 use rand::seq::SliceRandom;
+use std::collections::HashMap;
 #[cfg(test)]
 use std::fs::File;
 #[cfg(test)]
@@ -65,6 +69,11 @@ const MAX_LOAD_RETRIES: usize = 3;
 /// from a transient overload. Doubles each attempt for consistency with the
 /// retry policy in `retry::retry_with_backoff`.
 const RETRY_INITIAL_BACKOFF_MS: u64 = 200;
+/// IDs per `wbgetentities` request when checking missing ids for redirects.
+const REDIRECT_CHECK_CHUNK_SIZE: usize = 50;
+/// Lexemes and MediaInfo entities per `wbgetentities` request; the API's
+/// limit without bot rights.
+const DIRECT_LOAD_CHUNK_SIZE: usize = 50;
 
 /// Per-page in-memory entity store.
 ///
@@ -89,6 +98,15 @@ pub struct EntityContainerWrapper {
     /// caller wires this from `Configuration::mw_api_circuit_breaker(MW_API_ENTITIES_KEY)`
     /// so a flapping Wikidata API doesn't keep hammering every entity load.
     circuit_breaker: Option<Arc<CircuitBreaker>>,
+    /// Redirected (merged) entity ID → the ID it redirects to. The upstream
+    /// answers a request for a redirect with the target entity, so without
+    /// this map the source ID looks like it never loaded.
+    redirects: Arc<DashMap<String, String>>,
+    /// Lexemes by lexeme ID; forms and senses are looked up through them.
+    lexemes: Arc<DashMap<String, Arc<Lexeme>>>,
+    /// The API that MediaInfo (`M…`) IDs are loaded from, i.e. Commons. When
+    /// `None`, they are loaded from the API passed to `load_entities`.
+    mediainfo_api: Option<Arc<Api>>,
 }
 
 /// Parses `test_entities.json` exactly once for the entire test run.
@@ -126,6 +144,9 @@ impl EntityContainerWrapper {
             entities: Arc::new(DashMap::new()),
             max_concurrent_entry_queries: max_concurrent_entry_queries.max(1),
             circuit_breaker: None,
+            redirects: Arc::new(DashMap::new()),
+            lexemes: Arc::new(DashMap::new()),
+            mediainfo_api: None,
         };
         // Pre-cache test entities — clones Arc pointers from the once-parsed
         // static rather than re-reading the 8 MB JSON file.
@@ -144,6 +165,14 @@ impl EntityContainerWrapper {
         self
     }
 
+    /// Loads MediaInfo entities from `api` rather than from the API the
+    /// items come from.
+    #[must_use]
+    pub fn with_mediainfo_api(mut self, api: Arc<Api>) -> Self {
+        self.mediainfo_api = Some(api);
+        self
+    }
+
     #[cfg(test)]
     fn load_test_entities(entities: &DashMap<String, Arc<MyEntity>>) {
         for entry in TEST_ENTITIES_CACHE.iter() {
@@ -161,6 +190,95 @@ impl EntityContainerWrapper {
         Ok(())
     }
 
+    /// Caches a lexeme from its `wbgetentities` JSON, and the entity too if
+    /// it parses as one, so statement columns work for lexeme rows.
+    pub fn set_lexeme_from_json(&self, json: &serde_json::Value) -> Result<()> {
+        let lexeme = Lexeme::from_json(json).ok_or_else(|| anyhow!("Not a lexeme"))?;
+        if let Err(e) = self.set_entity_from_json(json) {
+            log::debug!("Lexeme {} has no entity model: {e}", lexeme.id);
+        }
+        self.lexemes.insert(lexeme.id.clone(), Arc::new(lexeme));
+        Ok(())
+    }
+
+    /// The lexeme of a lexeme, form or sense ID, if loaded.
+    #[must_use]
+    pub fn get_lexeme(&self, id: &str) -> Option<Arc<Lexeme>> {
+        let base = lexeme::lexeme_base_id(id)?;
+        self.lexemes.get(base).map(|l| l.value().clone())
+    }
+
+    /// Caches a MediaInfo entity from its `wbgetentities` JSON.
+    pub fn set_mediainfo_from_json(&self, json: &serde_json::Value) -> Result<()> {
+        let entity_json = mediainfo::entity_json(json).ok_or_else(|| anyhow!("Not a MediaInfo"))?;
+        self.set_entity_from_json(&entity_json)
+    }
+
+    /// The entity JSON of `ids`, straight from `wbgetentities`, for entity
+    /// types the upstream entity container does not know.
+    async fn get_entities_json(api: &Api, ids: &[String]) -> Result<Vec<serde_json::Value>> {
+        let mut ret = vec![];
+        for chunk in ids.chunks(DIRECT_LOAD_CHUNK_SIZE) {
+            let params: HashMap<String, String> = [
+                ("action", "wbgetentities"),
+                ("ids", chunk.join("|").as_str()),
+            ]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+            let mut j = api.get_query_api_json(&params).await?;
+            if let Some(entities) = j["entities"].as_object_mut() {
+                ret.extend(std::mem::take(entities).into_iter().map(|(_, json)| json));
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Loads lexemes, by lexeme ID.
+    async fn load_lexemes(&self, api: &Api, ids: &[String]) -> Result<()> {
+        for json in Self::get_entities_json(api, ids).await? {
+            // Missing lexemes come back without a type and are skipped
+            let _ = self.set_lexeme_from_json(&json);
+        }
+        Ok(())
+    }
+
+    /// Loads MediaInfo entities, from the MediaInfo API if there is one.
+    async fn load_mediainfo(&self, api: &Api, ids: &[String]) -> Result<()> {
+        let api = self.mediainfo_api.as_deref().unwrap_or(api);
+        for json in Self::get_entities_json(api, ids).await? {
+            // Deleted files come back as missing and are skipped
+            let _ = self.set_mediainfo_from_json(&json);
+        }
+        Ok(())
+    }
+
+    /// Lexeme IDs of `ids` (forms and senses mapped to their lexeme) that
+    /// are not loaded yet.
+    fn filter_lexeme_ids(&self, ids: &[String]) -> Vec<String> {
+        let mut ret: Vec<String> = ids
+            .iter()
+            .filter_map(|id| lexeme::lexeme_base_id(id))
+            .filter(|id| !self.lexemes.contains_key(*id))
+            .map(str::to_string)
+            .collect();
+        ret.sort_unstable();
+        ret.dedup();
+        ret
+    }
+
+    async fn load_into_cache(
+        &self,
+        api: &Api,
+        ids: &[String],
+        lexeme_ids: &[String],
+        mediainfo_ids: &[String],
+    ) -> Result<()> {
+        self.load_lexemes(api, lexeme_ids).await?;
+        self.load_mediainfo(api, mediainfo_ids).await?;
+        self.load_entities_into_entity_cache(api, ids).await
+    }
+
     async fn load_entities_into_entity_cache(&self, api: &Api, ids: &[String]) -> Result<()> {
         let chunks: Vec<Vec<String>> =
             ids.chunks(LOAD_CHUNK_SIZE).map(<[String]>::to_vec).collect();
@@ -225,12 +343,16 @@ impl EntityContainerWrapper {
 
             let missing: Vec<String> = to_load
                 .iter()
-                .filter(|id| !self.entities.contains_key(*id))
+                .filter(|id| !self.entities.contains_key(&self.resolve_id(id)))
                 .cloned()
                 .collect();
             if missing.is_empty() {
                 return Ok(());
             }
+            let missing = self.resolve_redirects(api, &missing).await;
+            if missing.is_empty() {
+                return Ok(());
+            }
             if attempt == MAX_LOAD_RETRIES {
                 log::warn!(
                     "Could not load {} entities after {} retries: {:?}",
@@ -257,6 +379,11 @@ impl EntityContainerWrapper {
                 if let Some(entity) = entity_container.get_entity(entity_id) {
                     let json: serde_json::Value = entity.to_json();
                     self2.set_entity_from_json(&json)?;
+                    if let Some(id) = json["id"].as_str()
+                        && id != entity_id
+                    {
+                        self2.redirects.insert(entity_id.to_owned(), id.to_string());
+                    }
                 }
             }
             Ok(())
@@ -265,13 +392,81 @@ impl EntityContainerWrapper {
         .map_err(|e| anyhow!("spawn_blocking join error: {e}"))?
     }
 
+    /// Asks the API which of `ids` are redirects and records them. Returns
+    /// the IDs that still need loading: those that are not redirects, and
+    /// redirect targets that are not cached yet. A failed lookup only costs
+    /// the redirect detection, so it is logged.
+    async fn resolve_redirects(&self, api: &Api, ids: &[String]) -> Vec<String> {
+        for chunk in ids.chunks(REDIRECT_CHECK_CHUNK_SIZE) {
+            let params: HashMap<String, String> = [
+                ("action", "wbgetentities"),
+                ("props", "info"),
+                ("ids", chunk.join("|").as_str()),
+            ]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+            match api.get_query_api_json(&params).await {
+                Ok(j) => {
+                    for (from, to) in Self::redirects_from_json(&j) {
+                        self.redirects.insert(from, to);
+                    }
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Could not check {} entities for redirects: {e}",
+                        chunk.len()
+                    )
+                }
+            }
+        }
+        let mut ret: Vec<String> = ids
+            .iter()
+            .map(|id| self.resolve_id(id))
+            .filter(|id| !self.entities.contains_key(id))
+            .collect();
+        ret.sort_unstable();
+        ret.dedup();
+        ret
+    }
+
+    /// `(from, to)` of every redirect in a `wbgetentities` response.
+    fn redirects_from_json(j: &serde_json::Value) -> Vec<(String, String)> {
+        let Some(entities) = j["entities"].as_object() else {
+            return vec![];
+        };
+        entities
+            .iter()
+            .filter_map(|(key, entity)| {
+                let redirects = &entity["redirects"];
+                match (redirects["from"].as_str(), redirects["to"].as_str()) {
+                    (Some(from), Some(to)) => Some((from.to_string(), to.to_string())),
+                    _ => {
+                        let id = entity["id"].as_str()?;
+                        (id != key).then(|| (key.to_string(), id.to_string()))
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// The ID `entity_id` redirects to, or `entity_id` itself.
+    #[must_use]
+    pub fn resolve_id(&self, entity_id: &str) -> String {
+        self.redirects
+            .get(entity_id)
+            .map(|to| to.value().to_owned())
+            .unwrap_or_else(|| entity_id.to_string())
+    }
+
     /// Removes IDs that are already loaded, removes duplicates, and shuffles
-    /// the remaining IDs to average load times.
+    /// the remaining IDs to average load times. Known redirects are replaced
+    /// by their targets.
     async fn filter_ids(&self, original_ids: &[String]) -> Result<Vec<String>> {
         let new_ids: Vec<String> = original_ids
             .iter()
-            .filter(|id| !self.entities.contains_key(*id))
-            .map(|id| id.to_owned())
+            .map(|id| self.resolve_id(id))
+            .filter(|id| !self.entities.contains_key(id))
             .collect();
         tokio::task::spawn_blocking(move || Self::unique_shuffle_entity_ids(&new_ids))
             .await
@@ -296,10 +491,18 @@ impl EntityContainerWrapper {
         self.len() == 0
     }
 
-    /// Loads the entities for the given IDs
+    /// Loads the entities for the given IDs; failures are
+    /// [`ListeriaError::EntityLoad`].
     pub async fn load_entities(&self, api: &Api, ids: &[String]) -> Result<()> {
-        let ids = self.filter_ids(ids).await?;
-        if ids.is_empty() {
+        let (lexeme_ids, ids): (Vec<String>, Vec<String>) =
+            ids.iter().cloned().partition(|id| lexeme::is_lexeme_id(id));
+        let (mediainfo_ids, ids): (Vec<String>, Vec<String>) = ids
+            .into_iter()
+            .partition(|id| mediainfo::is_mediainfo_id(id));
+        let lexeme_ids = self.filter_lexeme_ids(&lexeme_ids);
+        let mediainfo_ids = self.filter_ids(&mediainfo_ids).await?;
+        let ids = self.filter_ids(&ids).await?;
+        if ids.is_empty() && lexeme_ids.is_empty() && mediainfo_ids.is_empty() {
             return Ok(());
         }
         if cfg!(test) {
@@ -314,12 +517,26 @@ impl EntityContainerWrapper {
             Some(breaker) => {
                 with_breaker(
                     breaker,
-                    || anyhow!("entity-loading circuit open"),
-                    || async { self.load_entities_into_entity_cache(api, &ids).await },
+                    || ListeriaError::EntityCircuitOpen.into(),
+                    || async {
+                        self.load_into_cache(api, &ids, &lexeme_ids, &mediainfo_ids)
+                            .await
+                            .map_err(Self::entity_load_error)
+                    },
                 )
                 .await
             }
-            None => self.load_entities_into_entity_cache(api, &ids).await,
+            None => self
+                .load_into_cache(api, &ids, &lexeme_ids, &mediainfo_ids)
+                .await
+                .map_err(Self::entity_load_error),
+        }
+    }
+
+    fn entity_load_error(e: anyhow::Error) -> anyhow::Error {
+        match ListeriaError::from_anyhow(&e) {
+            Some(_) => e,
+            None => ListeriaError::EntityLoad(e.to_string()).into(),
         }
     }
 
@@ -327,7 +544,11 @@ impl EntityContainerWrapper {
         if cfg!(test) {
             println!("{entity_id}\tentity_loaded");
         }
-        self.entities.get(entity_id).map(|e| e.value().clone())
+        if let Some(entity) = self.entities.get(entity_id) {
+            return Some(entity.value().clone());
+        }
+        let target = self.redirects.get(entity_id)?.value().to_owned();
+        self.entities.get(&target).map(|e| e.value().clone())
     }
 
     pub async fn get_local_entity_label(
@@ -338,33 +559,34 @@ impl EntityContainerWrapper {
         entity.label_in_locale(language).map(|s| s.to_string())
     }
 
-    pub async fn get_entity_label_with_fallback(&self, entity_id: &str, language: &str) -> String {
+    /// Returns the entity's label along `chain` (see
+    /// [`crate::language_fallback::LanguageFallbacks::chain`]), or the id.
+    pub async fn get_entity_label_with_fallback(&self, entity_id: &str, chain: &[String]) -> String {
+        if let Some(lexeme) = self.get_lexeme(entity_id) {
+            return lexeme
+                .label(entity_id, chain)
+                .map_or_else(|| entity_id.to_string(), |(label, _)| label.to_string());
+        }
         let Some(entity) = self.get_entity(entity_id).await else {
             return entity_id.to_string();
         };
-        Self::label_with_fallback_from_entity(&entity, language, entity_id)
+        Self::label_with_fallback_from_entity(&entity, chain, entity_id)
     }
 
-    /// Returns an entity's label, falling back through `language` → `mul` →
-    /// a built-in language list → any available label → the entity id.
+    /// Returns an entity's label, falling back through the languages in
+    /// `chain` → any available label → the entity id.
     ///
     /// Takes an already-resolved entity so callers in synchronous contexts
     /// (e.g. [`ct_label`]) can avoid the async round-trip.
     pub fn label_with_fallback_from_entity(
         entity: &MyEntity,
-        language: &str,
+        chain: &[String],
         entity_id: &str,
     ) -> String {
-        if let Some(label) = entity.label_in_locale(language) {
+        if let Some((label, _lang)) = Self::label_in_chain(entity, chain) {
             return label.to_string();
         }
 
-        for lang in ["mul", "en", "de", "fr", "es", "it", "el", "nl"] {
-            if let Some(label) = entity.label_in_locale(lang) {
-                return label.to_string();
-            }
-        }
-
         if let Some(label) = entity.labels().first() {
             return label.value().to_string();
         }
@@ -372,6 +594,45 @@ impl EntityContainerWrapper {
         entity_id.to_string()
     }
 
+    /// The first label found along `chain`, with the language it was found in.
+    pub fn label_in_chain<'a>(
+        entity: &'a MyEntity,
+        chain: &'a [String],
+    ) -> Option<(&'a str, &'a str)> {
+        chain
+            .iter()
+            .find_map(|lang| entity.label_in_locale(lang).map(|l| (l, lang.as_str())))
+    }
+
+    /// The first description found along `chain`, with its language.
+    pub fn description_in_chain<'a>(
+        entity: &'a MyEntity,
+        chain: &'a [String],
+    ) -> Option<(&'a str, &'a str)> {
+        chain.iter().find_map(|lang| {
+            entity
+                .description_in_locale(lang)
+                .map(|d| (d, lang.as_str()))
+        })
+    }
+
+    /// All aliases in the first language along `chain` that has any, with
+    /// that language.
+    pub fn aliases_in_chain<'a>(
+        entity: &'a MyEntity,
+        chain: &'a [String],
+    ) -> Option<(Vec<String>, &'a str)> {
+        chain.iter().find_map(|lang| {
+            let aliases: Vec<String> = entity
+                .aliases()
+                .iter()
+                .filter(|alias| alias.language() == lang)
+                .map(|alias| alias.value().to_string())
+                .collect();
+            (!aliases.is_empty()).then_some((aliases, lang.as_str()))
+        })
+    }
+
     pub async fn entity_to_local_link(
         &self,
         item: &str,
@@ -391,11 +652,9 @@ impl EntityContainerWrapper {
             .await
             .unwrap_or_else(|| page.to_string());
 
-        Some(ResultCellPart::LocalLink(LocalLinkInfo::new(
-            page,
-            label,
-            LinkTarget::Page,
-        )))
+        Some(ResultCellPart::LocalLink(
+            LocalLinkInfo::new(page, label, LinkTarget::Page).with_item(item),
+        ))
     }
 
     pub async fn get_result_row(
@@ -502,6 +761,9 @@ impl EntityContainerWrapper {
                 ResultCellPart::Quantity(_, Some(unit_id)) => {
                     out.push(unit_id.to_owned());
                 }
+                ResultCellPart::LexemeForm(form) => {
+                    out.extend(form.grammatical_features.iter().cloned());
+                }
                 _ => {}
             }
         }
@@ -667,4 +929,266 @@ mod tests {
             "label must be readable from the cached entity"
         );
     }
+
+    fn chain_test_entity() -> MyEntity {
+        let json = serde_json::json!({
+            "type": "item",
+            "id": "Q72",
+            "labels": {
+                "de": {"language": "de", "value": "Zürich"},
+                "en": {"language": "en", "value": "Zurich"}
+            },
+            "descriptions": {"de": {"language": "de", "value": "Stadt in der Schweiz"}},
+            "aliases": {"de": [
+                {"language": "de", "value": "Zuerich"},
+                {"language": "de", "value": "Stadt Zürich"}
+            ]},
+            "claims": {},
+            "sitelinks": {}
+        });
+        MyEntity::from(Entity::new_from_json(&json).expect("entity from json failed"))
+    }
+
+    #[test]
+    fn test_label_in_chain_uses_first_available_language() {
+        let entity = chain_test_entity();
+        let chain = crate::language_fallback::LanguageFallbacks::default().chain("de-ch");
+        assert_eq!(
+            EntityContainerWrapper::label_in_chain(&entity, &chain),
+            Some(("Zürich", "de"))
+        );
+        assert_eq!(
+            EntityContainerWrapper::description_in_chain(&entity, &chain),
+            Some(("Stadt in der Schweiz", "de"))
+        );
+        let (aliases, lang) = EntityContainerWrapper::aliases_in_chain(&entity, &chain).unwrap();
+        assert_eq!(lang, "de");
+        assert_eq!(aliases.len(), 2);
+    }
+
+    #[test]
+    fn test_label_with_fallback_from_entity_falls_back_to_id() {
+        let entity = chain_test_entity();
+        let chain = vec!["fr".to_string()];
+        // Not in the chain, but any label beats the bare id
+        assert_ne!(
+            EntityContainerWrapper::label_with_fallback_from_entity(&entity, &chain, "Q72"),
+            "Q72"
+        );
+        assert_eq!(
+            EntityContainerWrapper::description_in_chain(&entity, &chain),
+            None
+        );
+    }
+
+    // ── redirects ──────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_redirects_from_json() {
+        let j = serde_json::json!({"entities": {
+            "Q9999989": {"type": "item", "id": "Q9999990",
+                "redirects": {"from": "Q9999989", "to": "Q9999990"}},
+            "Q9999991": {"type": "item", "id": "Q9999992"},
+            "Q9999993": {"type": "item", "id": "Q9999993"},
+            "Q9999994": {"id": "Q9999994", "missing": ""}
+        }});
+        let mut redirects = EntityContainerWrapper::redirects_from_json(&j);
+        redirects.sort();
+        assert_eq!(
+            redirects,
+            vec![
+                ("Q9999989".to_string(), "Q9999990".to_string()),
+                ("Q9999991".to_string(), "Q9999992".to_string()),
+            ]
+        );
+        assert!(EntityContainerWrapper::redirects_from_json(&serde_json::json!({})).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_redirects_maps_source_to_cached_target() {
+        use wiremock::matchers::{method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/w/api.php"))
+            .and(query_param("meta", "siteinfo"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "query": { "general": { "sitename": "Test Wiki" } }
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/w/api.php"))
+            .and(query_param("action", "wbgetentities"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "entities": {
+                    "Q9999989": {"type": "item", "id": "Q9999990",
+                        "redirects": {"from": "Q9999989", "to": "Q9999990"}},
+                    "Q9999988": {"id": "Q9999988", "missing": ""}
+                }
+            })))
+            .mount(&server)
+            .await;
+        let api = Api::new(&format!("{}/w/api.php", server.uri()))
+            .await
+            .unwrap();
+
+        let ecw = EntityContainerWrapper::new(5).await.unwrap();
+        ecw.set_entity_from_json(&serde_json::json!({
+            "type": "item",
+            "id": "Q9999990",
+            "labels": {"en": {"language": "en", "value": "merge target"}},
+            "descriptions": {},
+            "aliases": {},
+            "claims": {},
+            "sitelinks": {}
+        }))
+        .unwrap();
+
+        let ids = vec!["Q9999989".to_string(), "Q9999988".to_string()];
+        let still_missing = ecw.resolve_redirects(&api, &ids).await;
+        assert_eq!(still_missing, vec!["Q9999988".to_string()]);
+        assert_eq!(ecw.resolve_id("Q9999989"), "Q9999990");
+        assert_eq!(ecw.resolve_id("Q9999988"), "Q9999988");
+        let entity = ecw.get_entity("Q9999989").await.unwrap();
+        assert_eq!(entity.label_in_locale("en"), Some("merge target"));
+        // Known redirects are not requested again
+        assert!(ecw.filter_ids(&ids[..1]).await.unwrap().is_empty());
+    }
+
+    // ── lexemes ────────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_load_entities_fetches_lexeme_of_form() {
+        use wiremock::matchers::{method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/w/api.php"))
+            .and(query_param("meta", "siteinfo"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "query": { "general": { "sitename": "Test Wiki" } }
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/w/api.php"))
+            .and(query_param("action", "wbgetentities"))
+            .and(query_param("ids", "L7"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "entities": {"L7": {
+                    "type": "lexeme",
+                    "id": "L7",
+                    "lemmas": {"en": {"language": "en", "value": "cat"}},
+                    "lexicalCategory": "Q1084",
+                    "language": "Q1860",
+                    "claims": {},
+                    "forms": [{"id": "L7-F2",
+                        "representations": {"en": {"language": "en", "value": "cats"}},
+                        "grammaticalFeatures": ["Q146786"], "claims": {}}],
+                    "senses": []
+                }}
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let api = Api::new(&format!("{}/w/api.php", server.uri()))
+            .await
+            .unwrap();
+
+        let ecw = EntityContainerWrapper::new(5).await.unwrap();
+        let ids = vec!["L7-F2".to_string(), "L7".to_string()];
+        ecw.load_entities(&api, &ids).await.unwrap();
+        // Already cached: no second request
+        ecw.load_entities(&api, &ids).await.unwrap();
+
+        let lexeme = ecw.get_lexeme("L7-F2").expect("lexeme must be cached");
+        assert_eq!(lexeme.lexical_category.as_deref(), Some("Q1084"));
+        let chain = vec!["de".to_string()];
+        assert_eq!(
+            ecw.get_entity_label_with_fallback("L7", &chain).await,
+            "cat"
+        );
+        assert_eq!(
+            ecw.get_entity_label_with_fallback("L7-F2", &chain).await,
+            "cats"
+        );
+        assert_eq!(
+            ecw.get_entity_label_with_fallback("L7-S1", &chain).await,
+            "L7-S1"
+        );
+    }
+
+    // ── MediaInfo ──────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_load_entities_fetches_mediainfo_from_mediainfo_api() {
+        use wiremock::matchers::{method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        async fn mock_api(server: &MockServer) -> Api {
+            Mock::given(method("GET"))
+                .and(path("/w/api.php"))
+                .and(query_param("meta", "siteinfo"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                    "query": { "general": { "sitename": "Test Wiki" } }
+                })))
+                .mount(server)
+                .await;
+            Api::new(&format!("{}/w/api.php", server.uri()))
+                .await
+                .unwrap()
+        }
+
+        let commons = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/w/api.php"))
+            .and(query_param("action", "wbgetentities"))
+            .and(query_param("ids", "M42"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "entities": {"M42": {
+                    "type": "mediainfo",
+                    "id": "M42",
+                    "title": "File:Example.jpg",
+                    "labels": {"en": {"language": "en", "value": "An example"}},
+                    "statements": {"P180": [{
+                        "mainsnak": {
+                            "snaktype": "value",
+                            "property": "P180",
+                            "datavalue": {
+                                "value": {"entity-type": "item", "numeric-id": 146, "id": "Q146"},
+                                "type": "wikibase-entityid"
+                            }
+                        },
+                        "type": "statement",
+                        "id": "M42$1",
+                        "rank": "normal"
+                    }]}
+                }}
+            })))
+            .expect(1)
+            .mount(&commons)
+            .await;
+        let commons_api = mock_api(&commons).await;
+        // Nothing is mocked for wbgetentities here: a request would fail
+        let wikidata = MockServer::start().await;
+        let wikidata_api = mock_api(&wikidata).await;
+
+        let ecw = EntityContainerWrapper::new(5)
+            .await
+            .unwrap()
+            .with_mediainfo_api(Arc::new(commons_api));
+        let ids = vec!["M42".to_string()];
+        ecw.load_entities(&wikidata_api, &ids).await.unwrap();
+        ecw.load_entities(&wikidata_api, &ids).await.unwrap();
+
+        let entity = ecw
+            .get_entity("M42")
+            .await
+            .expect("MediaInfo must be cached");
+        assert_eq!(entity.label_in_locale("en"), Some("An example"));
+        assert_eq!(entity.claims_with_property("P180").len(), 1);
+    }
 }
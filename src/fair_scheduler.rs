@@ -0,0 +1,451 @@
+//! Fair share of the multi-wiki bot's threads between wikis.
+//!
+//! Taking the globally oldest page lets a wiki with tens of thousands of
+//! lists fill every thread, so small wikis wait and the big wiki hits its
+//! `max_mw_apis_per_wiki` limit. The scheduler instead picks the wiki first,
+//! by smooth weighted round-robin over the wikis that have pages ready and
+//! are below their cap of running pages, and then takes that wiki's oldest
+//! page. Priority pages still go first, regardless of wiki.
+//!
+//! Only pages last updated at least `min_age_sec` seconds ago are ready, so
+//! a wiki whose few pages were just updated does not get them again on its
+//! next turn. Queue depths are counted in storage every `refresh_sec`
+//! seconds, and updated in between as pages are started. While every wiki with pages
+//! ready is at its cap, the dispatcher waits for a page to finish instead
+//! of asking again.
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::sync::{Mutex, Notify};
+
+/// No page can be started because every wiki with pages ready is at its cap.
+#[derive(Debug, Clone, Copy, Error)]
+#[error("every wiki with pages ready is at its cap of running pages")]
+pub struct AllWikisAtCap;
+
+/// The `fair_scheduling` config block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FairSchedulingSettings {
+    /// Weight of wikis not in `weights`.
+    pub default_weight: u32,
+    /// Weight by wiki; a wiki of weight 2 gets twice the turns of one of
+    /// weight 1 while both have pages ready.
+    pub weights: HashMap<String, u32>,
+    /// Running pages per wiki. Defaults to `max_mw_apis_per_wiki`.
+    pub max_running_per_wiki: Option<usize>,
+    /// Running pages by wiki, over `max_running_per_wiki`.
+    pub max_running: HashMap<String, usize>,
+    /// Seconds between queue depth counts.
+    pub refresh_sec: u64,
+    /// Seconds since its last update before a page is ready again.
+    pub min_age_sec: u64,
+}
+
+impl Default for FairSchedulingSettings {
+    fn default() -> Self {
+        Self {
+            default_weight: 1,
+            weights: HashMap::new(),
+            max_running_per_wiki: None,
+            max_running: HashMap::new(),
+            refresh_sec: 60,
+            min_age_sec: 3600,
+        }
+    }
+}
+
+impl FairSchedulingSettings {
+    /// Weights of 0 and caps of 0 are ignored, as they would park a wiki forever.
+    #[must_use]
+    pub fn from_json(j: &Value) -> Self {
+        let defaults = Self::default();
+        let weights = j["weights"]
+            .as_object()
+            .map(|o| {
+                o.iter()
+                    .filter_map(|(wiki, v)| {
+                        let weight = u32::try_from(v.as_u64()?).ok().filter(|w| *w > 0)?;
+                        Some((wiki.clone(), weight))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let max_running = j["max_running"]
+            .as_object()
+            .map(|o| {
+                o.iter()
+                    .filter_map(|(wiki, v)| {
+                        let cap = usize::try_from(v.as_u64()?).ok().filter(|c| *c > 0)?;
+                        Some((wiki.clone(), cap))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            default_weight: j["default_weight"]
+                .as_u64()
+                .and_then(|u| u32::try_from(u).ok())
+                .filter(|w| *w > 0)
+                .unwrap_or(defaults.default_weight),
+            weights,
+            max_running_per_wiki: j["max_running_per_wiki"]
+                .as_u64()
+                .and_then(|u| usize::try_from(u).ok())
+                .filter(|c| *c > 0),
+            max_running,
+            refresh_sec: j["refresh_sec"]
+                .as_u64()
+                .filter(|sec| *sec > 0)
+                .unwrap_or(defaults.refresh_sec),
+            min_age_sec: j["min_age_sec"].as_u64().unwrap_or(defaults.min_age_sec),
+        }
+    }
+
+    #[must_use]
+    pub const fn refresh_interval(&self) -> Duration {
+        Duration::from_secs(self.refresh_sec)
+    }
+
+    #[must_use]
+    pub const fn min_age(&self) -> Duration {
+        Duration::from_secs(self.min_age_sec)
+    }
+}
+
+/// One row of the status server's queue table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WikiQueue {
+    pub wiki: String,
+    /// Pages ready to run, as of the last count.
+    pub queued: usize,
+    pub running: usize,
+    pub weight: u32,
+    pub max_running: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+struct SchedulerState {
+    queued: HashMap<String, usize>,
+    running: HashMap<String, usize>,
+    /// Smooth weighted round-robin credit of the wikis in the last round.
+    credit: HashMap<String, i64>,
+    refreshed: Option<Instant>,
+}
+
+#[derive(Debug)]
+pub struct FairScheduler {
+    settings: FairSchedulingSettings,
+    state: Mutex<SchedulerState>,
+    finished: Notify,
+}
+
+impl FairScheduler {
+    /// `max_mw_apis_per_wiki` is the cap for wikis without a configured one.
+    #[must_use]
+    pub fn new(mut settings: FairSchedulingSettings, max_mw_apis_per_wiki: Option<usize>) -> Self {
+        settings.max_running_per_wiki = settings.max_running_per_wiki.or(max_mw_apis_per_wiki);
+        Self {
+            settings,
+            state: Mutex::new(SchedulerState::default()),
+            finished: Notify::new(),
+        }
+    }
+
+    #[must_use]
+    pub const fn settings(&self) -> &FairSchedulingSettings {
+        &self.settings
+    }
+
+    #[must_use]
+    pub fn weight(&self, wiki: &str) -> u32 {
+        self.settings
+            .weights
+            .get(wiki)
+            .copied()
+            .unwrap_or(self.settings.default_weight)
+    }
+
+    #[must_use]
+    pub fn max_running(&self, wiki: &str) -> Option<usize> {
+        self.settings
+            .max_running
+            .get(wiki)
+            .copied()
+            .or(self.settings.max_running_per_wiki)
+    }
+
+    /// Whether the queue depths are due for a recount.
+    pub async fn needs_refresh(&self) -> bool {
+        self.state
+            .lock()
+            .await
+            .refreshed
+            .is_none_or(|t| t.elapsed() >= self.settings.refresh_interval())
+    }
+
+    /// Replaces the queue depths with a fresh count from storage.
+    pub async fn set_queue_depths(&self, queued: HashMap<String, usize>) {
+        let mut state = self.state.lock().await;
+        state.queued = queued;
+        state.refreshed = Some(Instant::now());
+    }
+
+    /// Forgets the pages counted for `wiki`, which turned out to have none
+    /// ready, until the next recount.
+    pub async fn mark_empty(&self, wiki: &str) {
+        self.state.lock().await.queued.remove(wiki);
+    }
+
+    /// Wikis that have pages ready and are below their cap, sorted by name.
+    fn eligible(&self, state: &SchedulerState) -> Vec<String> {
+        let mut wikis: Vec<String> = state
+            .queued
+            .iter()
+            .filter(|(_, queued)| **queued > 0)
+            .filter(|(wiki, _)| {
+                let running = state.running.get(*wiki).copied().unwrap_or_default();
+                self.max_running(wiki).is_none_or(|cap| running < cap)
+            })
+            .map(|(wiki, _)| wiki.clone())
+            .collect();
+        wikis.sort();
+        wikis
+    }
+
+    /// The wikis to take the next page from, best first. Try them in order
+    /// and report the one that had a page to [`Self::start`].
+    pub async fn candidates(&self) -> Vec<String> {
+        let state = self.state.lock().await;
+        let mut wikis = self.eligible(&state);
+        // Stable sort: ties go to the wiki first by name
+        wikis.sort_by_key(|wiki| {
+            let credit = state.credit.get(wiki).copied().unwrap_or_default();
+            std::cmp::Reverse(credit + i64::from(self.weight(wiki)))
+        });
+        wikis
+    }
+
+    /// Records that a page of `wiki` is running. Ends a round-robin round
+    /// if `wiki` was a candidate; priority pages just count as running.
+    pub async fn start(&self, wiki: &str) {
+        let mut state = self.state.lock().await;
+        let eligible = self.eligible(&state);
+        if eligible.iter().any(|w| w == wiki) {
+            state.credit.retain(|w, _| eligible.contains(w));
+            let mut total = 0;
+            for w in &eligible {
+                let weight = i64::from(self.weight(w));
+                *state.credit.entry(w.clone()).or_default() += weight;
+                total += weight;
+            }
+            *state.credit.entry(wiki.to_string()).or_default() -= total;
+        }
+        if let Some(queued) = state.queued.get_mut(wiki) {
+            *queued = queued.saturating_sub(1);
+        }
+        *state.running.entry(wiki.to_string()).or_default() += 1;
+    }
+
+    /// Whether there are pages ready, but all of their wikis are at their cap.
+    pub async fn all_at_cap(&self) -> bool {
+        let state = self.state.lock().await;
+        state.queued.values().any(|queued| *queued > 0) && self.eligible(&state).is_empty()
+    }
+
+    /// Records that a page of `wiki` has finished.
+    pub async fn finish(&self, wiki: &str) {
+        let mut state = self.state.lock().await;
+        if let Some(running) = state.running.get_mut(wiki) {
+            *running = running.saturating_sub(1);
+            if *running == 0 {
+                state.running.remove(wiki);
+            }
+        }
+        self.finished.notify_one();
+    }
+
+    /// Returns once a page has finished, e.g. to try again after
+    /// [`AllWikisAtCap`]. A page that finished since the last call counts.
+    pub async fn wait_for_finish(&self) {
+        self.finished.notified().await;
+    }
+
+    /// Queue depth and running pages of every wiki with either, by name.
+    pub async fn queues(&self) -> Vec<WikiQueue> {
+        let state = self.state.lock().await;
+        let mut wikis: Vec<&String> = state.queued.keys().chain(state.running.keys()).collect();
+        wikis.sort();
+        wikis.dedup();
+        wikis
+            .into_iter()
+            .map(|wiki| WikiQueue {
+                wiki: wiki.clone(),
+                queued: state.queued.get(wiki).copied().unwrap_or_default(),
+                running: state.running.get(wiki).copied().unwrap_or_default(),
+                weight: self.weight(wiki),
+                max_running: self.max_running(wiki),
+            })
+            .filter(|q| q.queued > 0 || q.running > 0)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    async fn scheduler(settings: Value, queued: &[(&str, usize)]) -> FairScheduler {
+        let scheduler = FairScheduler::new(FairSchedulingSettings::from_json(&settings), None);
+        scheduler
+            .set_queue_depths(
+                queued
+                    .iter()
+                    .map(|(wiki, n)| (wiki.to_string(), *n))
+                    .collect(),
+            )
+            .await;
+        scheduler
+    }
+
+    /// Starts `n` pages, always from the first candidate, and returns their wikis.
+    async fn run(scheduler: &FairScheduler, n: usize) -> Vec<String> {
+        let mut picked = vec![];
+        for _ in 0..n {
+            let wiki = scheduler.candidates().await[0].clone();
+            scheduler.start(&wiki).await;
+            picked.push(wiki);
+        }
+        picked
+    }
+
+    #[test]
+    fn test_settings_from_json() {
+        let s = FairSchedulingSettings::from_json(&json!({
+            "default_weight": 0,
+            "weights": {"enwiki": 3, "dewiki": 0, "frwiki": "x"},
+            "max_running_per_wiki": 4,
+            "max_running": {"enwiki": 8},
+            "refresh_sec": 0,
+            "min_age_sec": 0
+        }));
+        assert_eq!(s.default_weight, 1);
+        assert_eq!(s.weights, HashMap::from([("enwiki".to_string(), 3)]));
+        assert_eq!(s.max_running_per_wiki, Some(4));
+        assert_eq!(s.max_running, HashMap::from([("enwiki".to_string(), 8)]));
+        assert_eq!(s.refresh_sec, 60);
+        assert_eq!(s.min_age_sec, 0);
+        assert_eq!(
+            FairSchedulingSettings::from_json(&Value::Null),
+            FairSchedulingSettings::default()
+        );
+    }
+
+    #[test]
+    fn test_max_running_defaults_to_api_limit() {
+        let settings = FairSchedulingSettings::from_json(&json!({"max_running": {"enwiki": 8}}));
+        let scheduler = FairScheduler::new(settings, Some(3));
+        assert_eq!(scheduler.max_running("enwiki"), Some(8));
+        assert_eq!(scheduler.max_running("dewiki"), Some(3));
+        let scheduler = FairScheduler::new(FairSchedulingSettings::default(), None);
+        assert_eq!(scheduler.max_running("dewiki"), None);
+    }
+
+    #[tokio::test]
+    async fn test_round_robin_does_not_starve_small_wikis() {
+        let scheduler = scheduler(json!({}), &[("big", 50_000), ("small", 2)]).await;
+        assert_eq!(
+            run(&scheduler, 5).await,
+            ["big", "small", "big", "small", "big"]
+        );
+        // Small is out of pages now
+        assert_eq!(scheduler.candidates().await, ["big"]);
+    }
+
+    #[tokio::test]
+    async fn test_weights() {
+        let scheduler = scheduler(json!({"weights": {"a": 2}}), &[("a", 100), ("b", 100)]).await;
+        let picked = run(&scheduler, 6).await;
+        assert_eq!(picked.iter().filter(|w| *w == "a").count(), 4);
+        // Smooth: the heavier wiki's turns are spread out
+        assert_eq!(picked, ["a", "b", "a", "a", "b", "a"]);
+    }
+
+    #[tokio::test]
+    async fn test_cap_and_finish() {
+        let scheduler = scheduler(
+            json!({"max_running_per_wiki": 1, "max_running": {"big": 2}}),
+            &[("big", 100), ("small", 100)],
+        )
+        .await;
+        assert_eq!(run(&scheduler, 3).await, ["big", "small", "big"]);
+        assert!(scheduler.candidates().await.is_empty());
+        scheduler.finish("small").await;
+        assert_eq!(scheduler.candidates().await, ["small"]);
+    }
+
+    #[tokio::test]
+    async fn test_all_at_cap_and_wait_for_finish() {
+        let scheduler = scheduler(json!({"max_running_per_wiki": 1}), &[("a", 10)]).await;
+        assert!(!scheduler.all_at_cap().await);
+        scheduler.start("a").await;
+        assert!(scheduler.all_at_cap().await);
+        scheduler.finish("a").await;
+        assert!(!scheduler.all_at_cap().await);
+        // The finish above is not lost on a later wait
+        tokio::time::timeout(Duration::from_secs(1), scheduler.wait_for_finish())
+            .await
+            .unwrap();
+        // Nothing ready is not the same as all at cap
+        scheduler.mark_empty("a").await;
+        assert!(!scheduler.all_at_cap().await);
+    }
+
+    #[tokio::test]
+    async fn test_priority_pages_count_as_running() {
+        let scheduler = scheduler(json!({"max_running_per_wiki": 1}), &[("a", 10)]).await;
+        scheduler.start("a").await;
+        assert!(scheduler.candidates().await.is_empty());
+        assert_eq!(scheduler.queues().await[0].running, 1);
+    }
+
+    #[tokio::test]
+    async fn test_mark_empty_and_refresh() {
+        let scheduler = scheduler(json!({}), &[("a", 10), ("b", 10)]).await;
+        assert!(!scheduler.needs_refresh().await);
+        scheduler.mark_empty("a").await;
+        assert_eq!(scheduler.candidates().await, ["b"]);
+        assert!(
+            FairScheduler::new(FairSchedulingSettings::default(), None)
+                .needs_refresh()
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_queues() {
+        let scheduler = scheduler(json!({"weights": {"b": 5}}), &[("a", 3), ("b", 0)]).await;
+        scheduler.start("b").await;
+        assert_eq!(
+            scheduler.queues().await,
+            vec![
+                WikiQueue {
+                    wiki: "a".to_string(),
+                    queued: 3,
+                    running: 0,
+                    weight: 1,
+                    max_running: None,
+                },
+                WikiQueue {
+                    wiki: "b".to_string(),
+                    queued: 0,
+                    running: 1,
+                    weight: 5,
+                    max_running: None,
+                },
+            ]
+        );
+    }
+}
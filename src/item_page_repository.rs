@@ -0,0 +1,117 @@
+//! Data-access layer for the `page_items` table: which entities each
+//! Listeria page lists.
+//!
+//! ```sql
+//! CREATE TABLE `page_items` (
+//!   `pagestatus_id` INT UNSIGNED NOT NULL,
+//!   `item` VARCHAR(32) NOT NULL,
+//!   PRIMARY KEY (`pagestatus_id`,`item`),
+//!   KEY `item` (`item`)
+//! );
+//! ```
+//!
+//! Rows are keyed by `pagestatus.id`, so a page's entry goes away with its
+//! queue row; lookups join back to `pagestatus` and `wikis` for the names.
+
+use crate::database_pool::DatabasePool;
+use anyhow::Result;
+use mysql_async::{TxOpts, from_row, prelude::*};
+
+#[derive(Debug, Clone)]
+pub struct ItemPageRepository {
+    pool: DatabasePool,
+}
+
+impl ItemPageRepository {
+    #[must_use]
+    pub const fn new(pool: DatabasePool) -> Self {
+        Self { pool }
+    }
+
+    /// Replaces the items listed on a page, in one transaction so lookups
+    /// never see the page without its items. Unknown pages are ignored.
+    pub async fn set_page_items(&self, wiki: &str, page: &str, items: &[String]) -> Result<()> {
+        self.pool
+            .with_timeout("set_page_items", || async {
+                let mut conn = self.pool.get_conn().await?;
+                let pagestatus_id = conn
+                    .exec_iter(
+                        "SELECT pagestatus.id FROM pagestatus,wikis WHERE wikis.id=pagestatus.wiki AND wikis.name=? AND pagestatus.page=?",
+                        (wiki, page),
+                    )
+                    .await?
+                    .map_and_drop(from_row::<u64>)
+                    .await?
+                    .into_iter()
+                    .next();
+                let Some(pagestatus_id) = pagestatus_id else {
+                    return Ok(());
+                };
+                let mut tx = conn.start_transaction(TxOpts::default()).await?;
+                tx.exec_drop(
+                    "DELETE FROM `page_items` WHERE `pagestatus_id`=?",
+                    (pagestatus_id,),
+                )
+                .await?;
+                for chunk in items.chunks(10000) {
+                    let element = format!("({pagestatus_id},?)");
+                    let placeholders = std::iter::repeat_n(element.as_str(), chunk.len())
+                        .collect::<Vec<_>>()
+                        .join(",");
+                    let sql = format!(
+                        "INSERT IGNORE INTO `page_items` (`pagestatus_id`,`item`) VALUES {placeholders}"
+                    );
+                    tx.exec_drop(sql, chunk.to_vec()).await?;
+                }
+                tx.commit().await?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// `(wiki, page)` of every page listing one of `items`.
+    pub async fn get_pages_for_items(&self, items: &[String]) -> Result<Vec<(String, String)>> {
+        let mut ret = vec![];
+        for chunk in items.chunks(1000) {
+            let rows = self
+                .pool
+                .with_timeout("get_pages_for_items", || async {
+                    let placeholders = std::iter::repeat_n("?", chunk.len())
+                        .collect::<Vec<_>>()
+                        .join(",");
+                    let sql = format!(
+                        "SELECT DISTINCT wikis.name,pagestatus.page
+                        FROM page_items,pagestatus,wikis
+                        WHERE page_items.pagestatus_id=pagestatus.id AND wikis.id=pagestatus.wiki
+                        AND page_items.item IN ({placeholders})"
+                    );
+                    Ok(self
+                        .pool
+                        .get_conn()
+                        .await?
+                        .exec_iter(sql, chunk.to_vec())
+                        .await?
+                        .map_and_drop(from_row::<(String, String)>)
+                        .await?)
+                })
+                .await?;
+            ret.extend(rows);
+        }
+        ret.sort();
+        ret.dedup();
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Like the other repositories, the SQL needs a live MySQL server; this
+    /// only guards that the type can be embedded in `MysqlStorage`.
+    #[test]
+    fn test_repository_is_clone_and_debug() {
+        fn assert_clone_debug<T: Clone + std::fmt::Debug>() {}
+        assert_clone_debug::<ItemPageRepository>();
+    }
+}
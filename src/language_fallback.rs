@@ -0,0 +1,255 @@
+//! MediaWiki-style language fallback chains.
+//!
+//! Labels, descriptions, aliases, monolingual texts and bot messages are all
+//! looked up along a chain of languages: the requested language, its
+//! explicit fallbacks (`de-ch` → `de`, `zh-hant` → `zh-tw`/`zh-hk`/`zh`, …),
+//! the language-neutral `mul`, then a final list of widely-covered
+//! languages. The built-in table mirrors the MediaWiki `$fallback` settings
+//! for the variants that matter most for labels; the `language_fallbacks`
+//! config key can add or replace entries.
+
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Languages tried after a language's own fallbacks and `mul`.
+const DEFAULT_FINAL_LANGUAGES: &[&str] = &["en", "de", "fr", "es", "it", "el", "nl"];
+
+/// Built-in explicit fallbacks, following MediaWiki's `MessagesXx.php`.
+const BUILTIN_FALLBACKS: &[(&str, &[&str])] = &[
+    ("als", &["gsw", "de"]),
+    ("bar", &["de"]),
+    ("be-tarask", &["be-x-old", "be"]),
+    ("be-x-old", &["be-tarask", "be"]),
+    ("de-at", &["de"]),
+    ("de-ch", &["de"]),
+    ("de-formal", &["de"]),
+    ("en-ca", &["en"]),
+    ("en-gb", &["en"]),
+    ("gsw", &["de"]),
+    ("ksh", &["de"]),
+    ("lb", &["de"]),
+    ("nds", &["de"]),
+    ("nds-nl", &["nds", "nl"]),
+    ("pt", &["pt-br"]),
+    ("pt-br", &["pt"]),
+    ("sr-ec", &["sr-cyrl", "sr"]),
+    ("sr-el", &["sr-latn", "sr"]),
+    ("zh", &["zh-hans"]),
+    ("zh-cn", &["zh-hans", "zh"]),
+    ("zh-hans", &["zh-cn", "zh"]),
+    ("zh-hant", &["zh-tw", "zh-hk", "zh", "zh-hans"]),
+    ("zh-hk", &["zh-hant", "zh-tw", "zh"]),
+    ("zh-tw", &["zh-hant", "zh-hk", "zh"]),
+];
+
+/// Language-neutral labels (Wikidata's `mul`) rank after all real fallbacks.
+const MUL: &str = "mul";
+
+#[derive(Debug, Clone)]
+pub struct LanguageFallbacks {
+    /// Per-language overrides from config; replace the built-in entry.
+    custom: HashMap<String, Vec<String>>,
+    final_languages: Vec<String>,
+}
+
+impl Default for LanguageFallbacks {
+    fn default() -> Self {
+        Self {
+            custom: HashMap::new(),
+            final_languages: DEFAULT_FINAL_LANGUAGES
+                .iter()
+                .map(|s| (*s).to_string())
+                .collect(),
+        }
+    }
+}
+
+impl LanguageFallbacks {
+    /// Parses the `language_fallbacks` config object:
+    /// `{"de-ch": ["de"], "*": ["en", "fr"]}`. The `"*"` entry replaces the
+    /// final language list. Malformed entries are skipped with a warning.
+    #[must_use]
+    pub fn from_json(j: &Value) -> Self {
+        let mut ret = Self::default();
+        let Some(o) = j.as_object() else {
+            if !j.is_null() {
+                log::warn!("Ignoring non-object language_fallbacks config: {j}");
+            }
+            return ret;
+        };
+        for (language, v) in o {
+            let Some(arr) = v.as_array() else {
+                log::warn!("Ignoring language_fallbacks.{language}, expected an array: {v}");
+                continue;
+            };
+            let chain: Vec<String> = arr
+                .iter()
+                .filter_map(|x| x.as_str())
+                .map(|s| s.trim().to_lowercase())
+                .filter(|s| !s.is_empty())
+                .collect();
+            if language == "*" {
+                ret.final_languages = chain;
+            } else {
+                ret.custom.insert(language.to_lowercase(), chain);
+            }
+        }
+        ret
+    }
+
+    /// Explicit fallbacks for a single language, without recursion.
+    fn explicit(&self, language: &str) -> Option<Vec<String>> {
+        if let Some(custom) = self.custom.get(language) {
+            return Some(custom.clone());
+        }
+        BUILTIN_FALLBACKS
+            .iter()
+            .find(|(l, _)| *l == language)
+            .map(|(_, fb)| fb.iter().map(|s| (*s).to_string()).collect())
+    }
+
+    /// The full lookup chain for `language`: the language itself, its
+    /// fallbacks (expanded transitively, or by stripping subtags when none
+    /// are configured), `mul`, then the final language list. No duplicates.
+    #[must_use]
+    pub fn chain(&self, language: &str) -> Vec<String> {
+        self.chain_with_default(language, None)
+    }
+
+    /// Like [`Self::chain`], trying the wiki's `default_language` right
+    /// after the requested language's own fallbacks.
+    #[must_use]
+    pub fn chain_with_default(
+        &self,
+        language: &str,
+        default_language: Option<&str>,
+    ) -> Vec<String> {
+        let mut chain = vec![language.to_lowercase()];
+        let mut pos = 0;
+        while let Some(current) = chain.get(pos).cloned() {
+            let next = match self.explicit(&current) {
+                Some(fallbacks) => fallbacks,
+                None => current
+                    .rsplit_once('-')
+                    .map(|(base, _)| vec![base.to_string()])
+                    .unwrap_or_default(),
+            };
+            for lang in next {
+                if !chain.contains(&lang) {
+                    chain.push(lang);
+                }
+            }
+            pos += 1;
+        }
+        let tail = default_language
+            .into_iter()
+            .chain(std::iter::once(MUL))
+            .chain(self.final_languages.iter().map(String::as_str));
+        for lang in tail {
+            if !chain.iter().any(|l| l == lang) {
+                chain.push(lang.to_string());
+            }
+        }
+        chain
+    }
+
+    /// Wraps `text` in a `lang=` span when it was taken from a fallback
+    /// language rather than `requested`, so editors can spot values that
+    /// still need translating. `mul` values are language-neutral and never
+    /// marked.
+    #[must_use]
+    pub fn mark(text: &str, value_language: &str, requested: &str) -> String {
+        if value_language == requested || value_language == MUL || text.is_empty() {
+            return text.to_string();
+        }
+        format!(
+            "<span lang=\"{value_language}\" class=\"listeria-language-fallback\">{text}</span>"
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // ── chain ──────────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_chain_plain_language() {
+        assert_eq!(
+            LanguageFallbacks::default().chain("en"),
+            vec!["en", "mul", "de", "fr", "es", "it", "el", "nl"]
+        );
+    }
+
+    #[test]
+    fn test_chain_de_ch() {
+        let chain = LanguageFallbacks::default().chain("de-ch");
+        assert_eq!(&chain[..3], &["de-ch", "de", "mul"]);
+        assert_eq!(chain.iter().filter(|l| *l == "de").count(), 1);
+    }
+
+    #[test]
+    fn test_chain_zh_hant() {
+        let chain = LanguageFallbacks::default().chain("zh-hant");
+        assert_eq!(
+            &chain[..6],
+            &["zh-hant", "zh-tw", "zh-hk", "zh", "zh-hans", "zh-cn"]
+        );
+    }
+
+    #[test]
+    fn test_chain_sr_el_and_be_tarask() {
+        let fb = LanguageFallbacks::default();
+        assert_eq!(&fb.chain("sr-el")[..3], &["sr-el", "sr-latn", "sr"]);
+        assert_eq!(
+            &fb.chain("be-tarask")[..3],
+            &["be-tarask", "be-x-old", "be"]
+        );
+    }
+
+    #[test]
+    fn test_chain_strips_unknown_subtags() {
+        let chain = LanguageFallbacks::default().chain("fr-x-foo");
+        assert_eq!(&chain[..4], &["fr-x-foo", "fr-x", "fr", "mul"]);
+    }
+
+    #[test]
+    fn test_chain_with_default_language() {
+        let chain = LanguageFallbacks::default().chain_with_default("gsw", Some("fr"));
+        assert_eq!(&chain[..4], &["gsw", "de", "fr", "mul"]);
+    }
+
+    // ── config ─────────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_from_json_custom_and_final() {
+        let fb = LanguageFallbacks::from_json(&json!({
+            "de-ch": ["fr", "de"],
+            "*": ["en"],
+            "bad": "de"
+        }));
+        assert_eq!(fb.chain("de-ch"), vec!["de-ch", "fr", "de", "mul", "en"]);
+        assert_eq!(fb.chain("bad"), vec!["bad", "mul", "en"]);
+    }
+
+    #[test]
+    fn test_from_json_null_is_default() {
+        let fb = LanguageFallbacks::from_json(&Value::Null);
+        assert_eq!(fb.chain("nl")[0], "nl");
+        assert!(fb.chain("nl").contains(&"en".to_string()));
+    }
+
+    // ── mark ───────────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_mark() {
+        assert_eq!(LanguageFallbacks::mark("Bern", "de-ch", "de-ch"), "Bern");
+        assert_eq!(LanguageFallbacks::mark("Bern", "mul", "de-ch"), "Bern");
+        assert_eq!(
+            LanguageFallbacks::mark("Bern", "de", "de-ch"),
+            "<span lang=\"de\" class=\"listeria-language-fallback\">Bern</span>"
+        );
+    }
+}
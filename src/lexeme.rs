@@ -0,0 +1,237 @@
+//! Lexemes (`L…`) with their forms (`L…-F…`) and senses (`L…-S…`).
+//!
+//! Lexemes have no labels or sitelinks; what a list shows for them comes
+//! from the lemmas, form representations and sense glosses, which are read
+//! straight from the `wbgetentities` JSON.
+
+use serde_json::Value;
+
+/// Whether `id` is a lexeme, form or sense ID (`L1`, `L1-F2`, `L1-S3`).
+#[must_use]
+pub fn is_lexeme_id(id: &str) -> bool {
+    lexeme_base_id(id).is_some()
+}
+
+/// The lexeme part of a lexeme, form or sense ID: `L1-F2` → `L1`.
+#[must_use]
+pub fn lexeme_base_id(id: &str) -> Option<&str> {
+    let (base, sub) = match id.split_once('-') {
+        Some((base, sub)) => (base, Some(sub)),
+        None => (id, None),
+    };
+    if !is_prefixed_number(base, 'L') {
+        return None;
+    }
+    match sub {
+        Some(sub) if !is_prefixed_number(sub, 'F') && !is_prefixed_number(sub, 'S') => None,
+        _ => Some(base),
+    }
+}
+
+fn is_prefixed_number(s: &str, prefix: char) -> bool {
+    s.strip_prefix(prefix)
+        .is_some_and(|digits| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// `(language, value)` pairs of a `{"en": {"language": "en", "value": "…"}}`
+/// term map.
+fn terms_from_json(j: &Value) -> Vec<(String, String)> {
+    let Some(o) = j.as_object() else {
+        return vec![];
+    };
+    o.iter()
+        .filter_map(|(language, term)| {
+            let value = term["value"].as_str()?;
+            Some((language.to_owned(), value.to_string()))
+        })
+        .collect()
+}
+
+/// The first term along `chain`, with its language; failing that, the first
+/// term in any language.
+#[must_use]
+pub fn term_in_chain<'a>(
+    terms: &'a [(String, String)],
+    chain: &[String],
+) -> Option<(&'a str, &'a str)> {
+    chain
+        .iter()
+        .find_map(|language| terms.iter().find(|(l, _)| l == language))
+        .or_else(|| terms.first())
+        .map(|(language, value)| (value.as_str(), language.as_str()))
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LexemeForm {
+    pub id: String,
+    pub representations: Vec<(String, String)>,
+    /// Item IDs, e.g. "plural".
+    pub grammatical_features: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LexemeSense {
+    pub id: String,
+    pub glosses: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Lexeme {
+    pub id: String,
+    pub lemmas: Vec<(String, String)>,
+    /// Item ID, e.g. "noun".
+    pub lexical_category: Option<String>,
+    /// Item ID of the language.
+    pub language: Option<String>,
+    pub forms: Vec<LexemeForm>,
+    pub senses: Vec<LexemeSense>,
+}
+
+impl Lexeme {
+    /// Parses one entity of a `wbgetentities` response; `None` unless it is
+    /// a lexeme.
+    #[must_use]
+    pub fn from_json(j: &Value) -> Option<Self> {
+        if j["type"].as_str() != Some("lexeme") {
+            return None;
+        }
+        let id = j["id"].as_str()?.to_string();
+        let forms = j["forms"]
+            .as_array()
+            .map(|forms| {
+                forms
+                    .iter()
+                    .filter_map(|form| {
+                        Some(LexemeForm {
+                            id: form["id"].as_str()?.to_string(),
+                            representations: terms_from_json(&form["representations"]),
+                            grammatical_features: form["grammaticalFeatures"]
+                                .as_array()
+                                .map(|features| {
+                                    features
+                                        .iter()
+                                        .filter_map(|f| f.as_str().map(str::to_string))
+                                        .collect()
+                                })
+                                .unwrap_or_default(),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let senses = j["senses"]
+            .as_array()
+            .map(|senses| {
+                senses
+                    .iter()
+                    .filter_map(|sense| {
+                        Some(LexemeSense {
+                            id: sense["id"].as_str()?.to_string(),
+                            glosses: terms_from_json(&sense["glosses"]),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Some(Self {
+            id,
+            lemmas: terms_from_json(&j["lemmas"]),
+            lexical_category: j["lexicalCategory"].as_str().map(str::to_string),
+            language: j["language"].as_str().map(str::to_string),
+            forms,
+            senses,
+        })
+    }
+
+    #[must_use]
+    pub fn form(&self, id: &str) -> Option<&LexemeForm> {
+        self.forms.iter().find(|form| form.id == id)
+    }
+
+    #[must_use]
+    pub fn sense(&self, id: &str) -> Option<&LexemeSense> {
+        self.senses.iter().find(|sense| sense.id == id)
+    }
+
+    /// The lemma along `chain`, with its language.
+    #[must_use]
+    pub fn lemma<'a>(&'a self, chain: &[String]) -> Option<(&'a str, &'a str)> {
+        term_in_chain(&self.lemmas, chain)
+    }
+
+    /// What to show for `id` (this lexeme, or one of its forms or senses):
+    /// the lemma, the form's representation, or the sense's gloss.
+    #[must_use]
+    pub fn label<'a>(&'a self, id: &str, chain: &[String]) -> Option<(&'a str, &'a str)> {
+        if id == self.id {
+            return self.lemma(chain);
+        }
+        if let Some(form) = self.form(id) {
+            return term_in_chain(&form.representations, chain);
+        }
+        term_in_chain(&self.sense(id)?.glosses, chain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn lexeme_json() -> Value {
+        json!({
+            "type": "lexeme",
+            "id": "L7",
+            "lemmas": {"en": {"language": "en", "value": "cat"}},
+            "lexicalCategory": "Q1084",
+            "language": "Q1860",
+            "claims": {},
+            "forms": [
+                {"id": "L7-F1", "representations": {"en": {"language": "en", "value": "cat"}},
+                 "grammaticalFeatures": ["Q110786"], "claims": {}},
+                {"id": "L7-F2", "representations": {"en": {"language": "en", "value": "cats"}},
+                 "grammaticalFeatures": ["Q146786"], "claims": {}}
+            ],
+            "senses": [
+                {"id": "L7-S1", "glosses": {
+                    "en": {"language": "en", "value": "domesticated feline"},
+                    "de": {"language": "de", "value": "Hauskatze"}
+                }, "claims": {}}
+            ]
+        })
+    }
+
+    #[test]
+    fn test_lexeme_ids() {
+        assert_eq!(lexeme_base_id("L7"), Some("L7"));
+        assert_eq!(lexeme_base_id("L7-F12"), Some("L7"));
+        assert_eq!(lexeme_base_id("L7-S1"), Some("L7"));
+        for id in ["Q7", "L", "L7-", "L7-X1", "L7-F", "l7", "L7-F1-S1"] {
+            assert!(!is_lexeme_id(id), "{id}");
+        }
+    }
+
+    #[test]
+    fn test_lexeme_from_json() {
+        let lexeme = Lexeme::from_json(&lexeme_json()).unwrap();
+        assert_eq!(lexeme.id, "L7");
+        assert_eq!(lexeme.lexical_category.as_deref(), Some("Q1084"));
+        assert_eq!(lexeme.language.as_deref(), Some("Q1860"));
+        assert_eq!(lexeme.forms.len(), 2);
+        assert_eq!(
+            lexeme.form("L7-F2").unwrap().grammatical_features,
+            ["Q146786"]
+        );
+        assert!(Lexeme::from_json(&json!({"type": "item", "id": "Q7"})).is_none());
+    }
+
+    #[test]
+    fn test_lexeme_labels() {
+        let lexeme = Lexeme::from_json(&lexeme_json()).unwrap();
+        let de = vec!["de".to_string()];
+        assert_eq!(lexeme.label("L7", &de), Some(("cat", "en")));
+        assert_eq!(lexeme.label("L7-F2", &de), Some(("cats", "en")));
+        assert_eq!(lexeme.label("L7-S1", &de), Some(("Hauskatze", "de")));
+        assert_eq!(lexeme.label("L7-S9", &de), None);
+    }
+}
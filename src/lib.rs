@@ -53,11 +53,19 @@
 extern crate serde_json;
 
 pub mod circuit_breaker;
+pub mod citation_templates;
 pub mod column;
 pub mod column_type;
 pub mod configuration;
 pub mod database_pool;
+pub mod date_format;
+pub mod diagnostics;
 pub mod entity_container_wrapper;
+pub mod fair_scheduler;
+pub mod item_page_repository;
+pub mod language_fallback;
+pub mod lexeme;
+pub mod list_pattern;
 pub mod list_processor;
 pub mod listeria_bot;
 pub mod listeria_bot_single;
@@ -67,13 +75,17 @@ pub mod listeria_error;
 pub mod listeria_list;
 pub mod listeria_page;
 pub mod main_commands;
+pub mod mediainfo;
+pub mod messages;
 pub mod my_entity;
 pub mod page_element;
 pub mod page_operations;
 pub mod page_params;
 pub mod page_to_process;
 pub mod pagestatus_repository;
+pub mod param_validation;
 pub mod profiling_service;
+pub mod recent_changes;
 pub mod reference;
 pub mod render_context;
 pub mod render_tabbed_data;
@@ -84,8 +96,10 @@ pub mod result_cell_part;
 pub mod result_generator;
 pub mod result_row;
 pub mod retry;
+pub mod shutdown;
 pub mod sparql_results;
 pub mod status_server;
+pub mod storage;
 pub mod template;
 pub mod template_params;
 pub mod wiki;
@@ -0,0 +1,268 @@
+//! The `pattern=` parameter of `format=list`: one row's line, with `$key`
+//! or `${key}` standing for the cell of the column with that key (see
+//! [`crate::column_type::ColumnType::as_key`]), e.g. `$label ($p569–$p570)`.
+//! A column spec works as well, so `$description` is the `desc` column.
+//!
+//! Empty cells should not leave empty brackets or dangling separators
+//! behind, so a bracketed group whose cells are all empty is dropped, and a
+//! separator (like `, ` or ` – `) only shows between two values.
+
+use crate::column_type::ColumnType;
+
+/// Text made of these (and spaces) only is a separator.
+const SEPARATORS: &[char] = &[',', ';', ':', '–', '—', '-', '/', '·'];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Text(String),
+    Key(String),
+    /// `(…)`: dropped if all keys in it are empty.
+    Group(Vec<Token>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListPattern {
+    tokens: Vec<Token>,
+}
+
+impl ListPattern {
+    #[must_use]
+    pub fn new(pattern: &str) -> Self {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut pos = 0;
+        Self {
+            tokens: Self::parse(&chars, &mut pos, false),
+        }
+    }
+
+    /// Parses up to the end, or up to the closing `)` of a group.
+    fn parse(chars: &[char], pos: &mut usize, in_group: bool) -> Vec<Token> {
+        let mut tokens = vec![];
+        let mut text = String::new();
+        while *pos < chars.len() {
+            let c = chars[*pos];
+            *pos += 1;
+            match c {
+                ')' if in_group => break,
+                '(' => {
+                    Self::push_text(&mut tokens, &mut text);
+                    let inner = Self::parse(chars, pos, true);
+                    tokens.push(Token::Group(inner));
+                }
+                '$' => match Self::parse_key(chars, pos) {
+                    Some(key) => {
+                        Self::push_text(&mut tokens, &mut text);
+                        tokens.push(Token::Key(key));
+                    }
+                    None => text.push(c),
+                },
+                _ => text.push(c),
+            }
+        }
+        Self::push_text(&mut tokens, &mut text);
+        tokens
+    }
+
+    /// `key` or `{key}` after a `$`; `None` (and nothing consumed) if there
+    /// is none.
+    fn parse_key(chars: &[char], pos: &mut usize) -> Option<String> {
+        if chars.get(*pos) == Some(&'{') {
+            let len = chars[*pos + 1..].iter().position(|c| *c == '}')?;
+            let key: String = chars[*pos + 1..*pos + 1 + len].iter().collect();
+            *pos += len + 2;
+            return Some(key.trim().to_lowercase())
+                .filter(|k| !k.is_empty())
+                .map(Self::column_key);
+        }
+        let len = chars[*pos..]
+            .iter()
+            .take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+            .count();
+        if len == 0 {
+            return None;
+        }
+        let key: String = chars[*pos..*pos + len].iter().collect();
+        *pos += len;
+        Some(Self::column_key(key.to_lowercase()))
+    }
+
+    /// The key of the column `key` is a spec for, like `desc` for
+    /// `description`; other keys are kept.
+    fn column_key(key: String) -> String {
+        match ColumnType::new(&key) {
+            ColumnType::Unknown => key,
+            column_type => column_type.as_key(),
+        }
+    }
+
+    fn push_text(tokens: &mut Vec<Token>, text: &mut String) {
+        if !text.is_empty() {
+            tokens.push(Token::Text(std::mem::take(text)));
+        }
+    }
+
+    /// The (lowercase) keys the pattern uses, in order.
+    #[must_use]
+    pub fn keys(&self) -> Vec<&str> {
+        fn collect<'a>(tokens: &'a [Token], keys: &mut Vec<&'a str>) {
+            for token in tokens {
+                match token {
+                    Token::Text(_) => {}
+                    Token::Key(key) => keys.push(key),
+                    Token::Group(inner) => collect(inner, keys),
+                }
+            }
+        }
+        let mut keys = vec![];
+        collect(&self.tokens, &mut keys);
+        keys
+    }
+
+    /// The line for one row; `value` gives the cell for a (lowercase) key,
+    /// `None` or empty if there is none.
+    pub fn render<'a>(&self, value: impl Fn(&str) -> Option<&'a str>) -> String {
+        let (line, _) = Self::render_tokens(&self.tokens, &value);
+        line.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    fn is_separator(text: &str) -> bool {
+        text.chars()
+            .all(|c| c.is_whitespace() || SEPARATORS.contains(&c))
+    }
+
+    /// The filled-in tokens, and whether any key in them had a value.
+    ///
+    /// A separator is only written between two things that are: it waits
+    /// until the next value, and is dropped if none follows or nothing came
+    /// before. Of several separators in a row (around empty cells), the
+    /// first one is used.
+    fn render_tokens<'a>(
+        tokens: &[Token],
+        value: &impl Fn(&str) -> Option<&'a str>,
+    ) -> (String, bool) {
+        let mut ret = String::new();
+        let mut any_value = false;
+        let mut pending_separator: Option<&str> = None;
+        for token in tokens {
+            let content = match token {
+                Token::Text(text) if Self::is_separator(text) => {
+                    if !ret.is_empty() && pending_separator.is_none() {
+                        pending_separator = Some(text);
+                    }
+                    continue;
+                }
+                Token::Text(text) => text.to_string(),
+                Token::Key(key) => match value(key).map(str::trim).filter(|v| !v.is_empty()) {
+                    Some(v) => {
+                        any_value = true;
+                        v.to_string()
+                    }
+                    None => continue,
+                },
+                Token::Group(inner) => {
+                    let (text, has_value) = Self::render_tokens(inner, value);
+                    let has_keys = inner.iter().any(|t| !matches!(t, Token::Text(_)));
+                    if has_keys && !has_value {
+                        continue;
+                    }
+                    any_value |= has_value;
+                    format!("({})", text.trim())
+                }
+            };
+            if let Some(separator) = pending_separator.take() {
+                ret += separator;
+            }
+            ret += &content;
+        }
+        (ret, any_value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn render(pattern: &str, values: &[(&str, &str)]) -> String {
+        let values: HashMap<&str, &str> = values.iter().copied().collect();
+        ListPattern::new(pattern).render(|key| values.get(key).copied())
+    }
+
+    #[test]
+    fn test_render_fills_in_keys() {
+        assert_eq!(
+            render(
+                "$label ($P569–$P570)",
+                &[
+                    ("label", "[[Ada Lovelace]]"),
+                    ("p569", "1815"),
+                    ("p570", "1852")
+                ]
+            ),
+            "[[Ada Lovelace]] (1815–1852)"
+        );
+        assert_eq!(
+            render(
+                "${sitelink/enwiki}: $p31_p580",
+                &[("sitelink/enwiki", "x"), ("p31_p580", "y")]
+            ),
+            "x: y"
+        );
+    }
+
+    #[test]
+    fn test_column_specs_as_keys() {
+        let values = [("label", "Ada"), ("desc", "mathematician")];
+        assert_eq!(
+            render("$label, $description", &values),
+            "Ada, mathematician"
+        );
+        assert_eq!(
+            render("$label, ${Description}", &values),
+            "Ada, mathematician"
+        );
+        assert_eq!(render("$label, $desc", &values), "Ada, mathematician");
+        assert_eq!(
+            ListPattern::new("$description $P31 ${P31/P580} $name").keys(),
+            vec!["desc", "p31", "p31_p580", "name"]
+        );
+    }
+
+    #[test]
+    fn test_render_drops_empty_groups_and_separators() {
+        assert_eq!(
+            render("$label ($p569–$p570), $description", &[("label", "Ada")]),
+            "Ada"
+        );
+        assert_eq!(
+            render(
+                "$label ($p569–$p570)",
+                &[("label", "Ada"), ("p570", "1852")]
+            ),
+            "Ada (1852)"
+        );
+        assert_eq!(
+            render(
+                "$p31, $p17, $p131 – $description",
+                &[("p31", "A"), ("p131", "C")]
+            ),
+            "A, C"
+        );
+        assert_eq!(render("– $label", &[("label", "Ada")]), "Ada");
+        // Brackets without keys are plain text
+        assert_eq!(
+            render("$label (person)", &[("label", "Ada")]),
+            "Ada (person)"
+        );
+        assert_eq!(render("$label", &[]), "");
+    }
+
+    #[test]
+    fn test_dollar_without_key_is_text() {
+        assert_eq!(
+            render("$label costs 5 $", &[("label", "Tea")]),
+            "Tea costs 5 $"
+        );
+        assert_eq!(render("${unclosed", &[]), "${unclosed");
+    }
+}
@@ -32,19 +32,28 @@ mod tests {
     use super::ListProcessor;
     use crate::listeria_list::ListeriaList;
     use crate::page_params::PageParams;
+    use crate::reference::Reference;
+    use crate::result_cell::ResultCell;
+    use crate::result_cell_part::{PartWithReference, ResultCellPart};
+    use crate::result_row::ResultRow;
     use crate::template::Template;
     use std::sync::Arc;
+    use wikimisc::wikibase::Snak;
 
     async fn create_test_list() -> ListeriaList {
+        create_test_list_with(
+            "{{Wikidata list|columns=item|sparql=SELECT ?item WHERE { ?item wdt:P31 wd:Q5 }}}",
+        )
+        .await
+    }
+
+    async fn create_test_list_with(template_text: &str) -> ListeriaList {
         let api = crate::test_utils::cached_api("https://www.wikidata.org/w/api.php").await;
         let config = crate::test_utils::cached_config().await;
         let page_params = PageParams::new(config, api, "Test:Page".to_string())
             .await
             .unwrap();
         let page_params = Arc::new(page_params);
-
-        let template_text =
-            "{{Wikidata list|columns=item|sparql=SELECT ?item WHERE { ?item wdt:P31 wd:Q5 }}}";
         let template = Template::new_from_params(template_text).unwrap();
 
         ListeriaList::new(template, page_params).await.unwrap()
@@ -90,6 +99,114 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    /// A cell with `part`, citing `urls`.
+    fn cell_citing(part: ResultCellPart, urls: &[&str]) -> ResultCell {
+        let references: Vec<Reference> = urls
+            .iter()
+            .filter_map(|url| Reference::new_from_snaks(&[Snak::new_string("P854", url)], "en"))
+            .collect();
+        let part = PartWithReference::new(part, Some(references));
+        serde_json::from_value(serde_json::json!({
+            "parts": [part],
+            "wdedit_class": null,
+            "deduplicate_parts": true
+        }))
+        .unwrap()
+    }
+
+    fn text_citing(urls: &[&str]) -> ResultCell {
+        cell_citing(ResultCellPart::Text("x".to_string()), urls)
+    }
+
+    /// Where each reference is defined, by URL.
+    fn first_uses(list: &ListeriaList) -> Vec<(String, (usize, usize))> {
+        list.reference_definitions()
+            .iter()
+            .map(|(id, wikitext)| (wikitext.clone(), list.reference_ids()[id]))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_process_reference_ids_first_use_carries_definition() {
+        let mut list = create_test_list().await;
+        let mut row1 = ResultRow::new("Q1");
+        row1.set_cells(vec![text_citing(&["https://a.example"])]);
+        let mut row2 = ResultRow::new("Q2");
+        row2.set_cells(vec![text_citing(&[
+            "https://b.example",
+            "https://a.example",
+        ])]);
+        *list.results_mut() = vec![row1, row2];
+
+        ListProcessor::process_reference_ids(&mut list).await;
+        let definitions: Vec<&str> = list
+            .reference_definitions()
+            .iter()
+            .map(|(_, wikitext)| wikitext.as_str())
+            .collect();
+        assert_eq!(definitions, vec!["https://a.example", "https://b.example"]);
+        let first_uses: Vec<(usize, usize)> = list
+            .reference_definitions()
+            .iter()
+            .map(|(id, _)| list.reference_ids()[id])
+            .collect();
+        assert_eq!(first_uses, vec![(0, 0), (1, 0)]);
+    }
+
+    #[tokio::test]
+    async fn test_process_reference_ids_gallery_skips_hidden_cells() {
+        let mut list = create_test_list_with(
+            "{{Wikidata list|columns=number,P18,label|format=gallery|sparql=SELECT ?item WHERE { ?item wdt:P31 wd:Q5 }}}",
+        )
+        .await;
+        let image = |urls: &[&str]| cell_citing(ResultCellPart::File("A.jpg".to_string()), urls);
+        // No image: the row is not in the gallery
+        let mut row1 = ResultRow::new("Q1");
+        row1.set_cells(vec![
+            text_citing(&[]),
+            text_citing(&[]),
+            text_citing(&["https://b.example"]),
+        ]);
+        // The number and image cells are not in the caption
+        let mut row2 = ResultRow::new("Q2");
+        row2.set_cells(vec![
+            text_citing(&["https://a.example"]),
+            image(&["https://a.example"]),
+            text_citing(&["https://b.example", "https://a.example"]),
+        ]);
+        *list.results_mut() = vec![row1, row2];
+
+        ListProcessor::process_reference_ids(&mut list).await;
+        assert_eq!(
+            first_uses(&list),
+            vec![
+                ("https://b.example".to_string(), (1, 2)),
+                ("https://a.example".to_string(), (1, 2)),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_process_reference_ids_pattern_skips_unused_columns() {
+        let mut list = create_test_list_with(
+            "{{Wikidata list|columns=item,P569,label|format=list|pattern=$label|sparql=SELECT ?item WHERE { ?item wdt:P31 wd:Q5 }}}",
+        )
+        .await;
+        let mut row = ResultRow::new("Q1");
+        row.set_cells(vec![
+            text_citing(&["https://a.example"]),
+            text_citing(&["https://b.example"]),
+            text_citing(&["https://a.example"]),
+        ]);
+        *list.results_mut() = vec![row];
+
+        ListProcessor::process_reference_ids(&mut list).await;
+        assert_eq!(
+            first_uses(&list),
+            vec![("https://a.example".to_string(), (0, 2))]
+        );
+    }
+
     #[tokio::test]
     async fn test_fix_local_links_with_empty_results() {
         let mut list = create_test_list().await;
@@ -256,7 +373,7 @@ mod tests {
     #[test]
     fn test_create_section_mappings_misc_always_appended() {
         let valid = vec!["human".to_string(), "state".to_string()];
-        let (name2id, id2name, misc_id) = ListProcessor::create_section_mappings(valid);
+        let (name2id, id2name, misc_id) = ListProcessor::create_section_mappings(valid, "Misc");
         assert!(name2id.contains_key("Misc"));
         assert!(id2name.values().any(|v| v == "Misc"));
         assert_eq!(misc_id, 2);
@@ -265,17 +382,26 @@ mod tests {
 
     #[test]
     fn test_create_section_mappings_empty_input() {
-        let (name2id, id2name, misc_id) = ListProcessor::create_section_mappings(vec![]);
+        let (name2id, id2name, misc_id) = ListProcessor::create_section_mappings(vec![], "Misc");
         assert_eq!(name2id.len(), 1);
         assert_eq!(id2name.len(), 1);
         assert_eq!(misc_id, 0);
         assert_eq!(name2id["Misc"], 0);
     }
 
+    #[test]
+    fn test_create_section_mappings_uses_given_misc_name() {
+        let valid = vec!["Mensch".to_string()];
+        let (name2id, id2name, misc_id) = ListProcessor::create_section_mappings(valid, "Sonstige");
+        assert_eq!(id2name[&misc_id], "Sonstige");
+        assert_eq!(name2id["Sonstige"], misc_id);
+        assert!(!name2id.contains_key("Misc"));
+    }
+
     #[test]
     fn test_create_section_mappings_bidirectional_consistency() {
         let valid = vec!["alpha".to_string(), "beta".to_string(), "gamma".to_string()];
-        let (name2id, id2name, _misc_id) = ListProcessor::create_section_mappings(valid);
+        let (name2id, id2name, _misc_id) = ListProcessor::create_section_mappings(valid, "Misc");
         for (name, id) in &name2id {
             assert_eq!(&id2name[id], name);
         }
@@ -287,7 +413,7 @@ mod tests {
     #[test]
     fn test_create_section_mappings_ids_are_unique() {
         let valid = vec!["a".to_string(), "b".to_string(), "c".to_string()];
-        let (name2id, _id2name, _misc_id) = ListProcessor::create_section_mappings(valid);
+        let (name2id, _id2name, _misc_id) = ListProcessor::create_section_mappings(valid, "Misc");
         let mut ids: Vec<usize> = name2id.values().cloned().collect();
         ids.sort();
         ids.dedup();
@@ -301,7 +427,7 @@ mod tests {
         use crate::result_row::ResultRow;
 
         let valid = vec!["alpha".to_string(), "beta".to_string()];
-        let (name2id, id2name, misc_id) = ListProcessor::create_section_mappings(valid);
+        let (name2id, id2name, misc_id) = ListProcessor::create_section_mappings(valid, "Misc");
 
         let mut rows = [
             ResultRow::new("Q1"),
@@ -331,7 +457,7 @@ mod tests {
         use crate::result_row::ResultRow;
 
         let valid = vec!["human".to_string()];
-        let (name2id, _id2name, misc_id) = ListProcessor::create_section_mappings(valid);
+        let (name2id, _id2name, misc_id) = ListProcessor::create_section_mappings(valid, "Misc");
 
         let mut row = ResultRow::new("Q99");
         let section_id = name2id.get("unknown").copied().unwrap_or(misc_id);
@@ -344,7 +470,7 @@ mod tests {
         use crate::result_row::ResultRow;
 
         let valid = vec!["human".to_string()];
-        let (name2id, _id2name, misc_id) = ListProcessor::create_section_mappings(valid);
+        let (name2id, _id2name, misc_id) = ListProcessor::create_section_mappings(valid, "Misc");
         let section_names: Vec<String> = vec![];
         let mut rows: Vec<ResultRow> = vec![];
 
@@ -624,7 +750,7 @@ mod tests {
         ];
 
         let valid = vec!["alpha".to_string(), "beta".to_string()];
-        let (name2id, id2name, misc_id) = ListProcessor::create_section_mappings(valid);
+        let (name2id, id2name, misc_id) = ListProcessor::create_section_mappings(valid, "Misc");
         *list.section_id_to_name_mut() = id2name;
 
         let section_names = vec![
@@ -659,7 +785,7 @@ mod tests {
         ];
 
         let valid = vec!["alpha".to_string()];
-        let (name2id, _id2name, misc_id) = ListProcessor::create_section_mappings(valid);
+        let (name2id, _id2name, misc_id) = ListProcessor::create_section_mappings(valid, "Misc");
 
         let section_names = vec!["alpha".to_string()];
         let result =
@@ -679,7 +805,7 @@ mod tests {
         ];
 
         let valid = vec!["alpha".to_string()];
-        let (name2id, id2name, misc_id) = ListProcessor::create_section_mappings(valid);
+        let (name2id, id2name, misc_id) = ListProcessor::create_section_mappings(valid, "Misc");
         *list.section_id_to_name_mut() = id2name;
 
         let section_names = vec![
@@ -726,7 +852,7 @@ mod tests {
         let section_count = ListProcessor::build_section_count(&section_names);
         let valid_section_names = ListProcessor::build_valid_section_names(section_count, 1);
         let (name2id, id2name, misc_id) =
-            ListProcessor::create_section_mappings(valid_section_names);
+            ListProcessor::create_section_mappings(valid_section_names, "Misc");
         *list.section_id_to_name_mut() = id2name;
 
         ListProcessor::assign_row_section_ids(&mut list, section_names, name2id, misc_id).unwrap();
@@ -774,7 +900,7 @@ mod tests {
         assert!(valid_section_names.contains(&"Atari".to_string()));
 
         let (name2id, id2name, misc_id) =
-            ListProcessor::create_section_mappings(valid_section_names);
+            ListProcessor::create_section_mappings(valid_section_names, "Misc");
         *list.section_id_to_name_mut() = id2name;
 
         ListProcessor::assign_row_section_ids(&mut list, section_names, name2id, misc_id).unwrap();
@@ -1,5 +1,6 @@
 //! Redlink, local-link, and link-fixing processing.
 
+use crate::entity_container_wrapper::EntityContainerWrapper;
 use crate::listeria_list::ListeriaList;
 use crate::result_cell::ResultCell;
 use crate::result_cell_part::{LinkTarget, ResultCellPart};
@@ -27,26 +28,53 @@ impl super::ListProcessor {
         Ok(())
     }
 
+    /// Keeps only rows whose item has no local article. With
+    /// `redirects=sections_missing`, an article that is a redirect to a
+    /// section of another one counts as missing.
     pub async fn process_redlinks_only(list: &mut ListeriaList) -> Result<()> {
         if *list.get_links_type() != LinksType::RedOnly {
             return Ok(());
         }
-        let keep_flags = Self::find_keep_flags(list).await;
+        let sitelinks = Self::find_local_sitelinks(list).await;
+        if list.template_params().redirects().sections_missing() {
+            let mut titles: Vec<String> = sitelinks.iter().flatten().flatten().cloned().collect();
+            titles.sort();
+            titles.dedup();
+            Self::cache_local_page_existence(list, titles).await;
+        }
+        let keep_flags = sitelinks
+            .iter()
+            .map(|sitelink| match sitelink {
+                Some(Some(title)) => list
+                    .local_page(title)
+                    .is_some_and(|page| page.redirects_to_section()),
+                Some(None) => true,
+                None => false,
+            })
+            .collect();
         Self::set_keep_flags(list, keep_flags);
         list.results_mut().retain(|r| r.keep());
         Ok(())
     }
 
+    /// Looks up the pages that links may point to: labels for `links=red`
+    /// and `red_only`, and with `redirects=resolve` also the local pages of
+    /// items, so that redirects can be resolved.
     pub async fn process_redlinks(list: &mut ListeriaList) -> Result<()> {
-        if *list.get_links_type() != LinksType::RedOnly && *list.get_links_type() != LinksType::Red
-        {
-            return Ok(());
+        let mut titles = Vec::new();
+        if matches!(list.get_links_type(), LinksType::Red | LinksType::RedOnly) {
+            let ids = Self::collect_entity_ids_from_results(list);
+            titles = Self::get_labels_for_entity_ids(list, ids).await;
+        }
+        if list.template_params().redirects().resolve() {
+            titles.extend(Self::collect_local_link_pages(list));
+        }
+        titles.retain(|title| list.local_page(title).is_none());
+        titles.sort();
+        titles.dedup();
+        if !titles.is_empty() {
+            Self::cache_local_page_existence(list, titles).await;
         }
-
-        let ids = Self::collect_entity_ids_from_results(list);
-        let labels = Self::get_labels_for_entity_ids(list, ids).await;
-        Self::cache_local_page_existence(list, labels).await;
-
         Ok(())
     }
 
@@ -96,18 +124,42 @@ impl super::ListProcessor {
         ids
     }
 
+    /// Local pages that items were linked to via their sitelinks.
+    fn collect_local_link_pages(list: &ListeriaList) -> Vec<String> {
+        let mut pages = Vec::new();
+        for row in list.results().iter() {
+            for cell in row.cells() {
+                for part in cell.parts() {
+                    let subparts = match part.part() {
+                        ResultCellPart::SnakList(v) => v.iter().map(|p| p.part()).collect(),
+                        other => vec![other],
+                    };
+                    for subpart in subparts {
+                        if let ResultCellPart::LocalLink(link_info) = subpart
+                            && link_info.item.is_some()
+                        {
+                            pages.push(link_info.page.to_owned());
+                        }
+                    }
+                }
+            }
+        }
+        pages
+    }
+
     async fn get_labels_for_entity_ids(list: &mut ListeriaList, ids: Vec<String>) -> Vec<String> {
         let ecw = list.ecw().clone();
-        let language: Arc<str> = list.language().into();
+        let chain: Arc<[String]> = list.language_chain(list.language()).into();
         let futures: Vec<_> = ids
             .into_iter()
             .map(|id| {
                 let ecw = ecw.clone();
-                let language = Arc::clone(&language);
+                let chain = Arc::clone(&chain);
                 async move {
-                    ecw.get_entity(&id)
-                        .await
-                        .and_then(|e| e.label_in_locale(&language).map(|l| l.to_string()))
+                    ecw.get_entity(&id).await.and_then(|e| {
+                        EntityContainerWrapper::label_in_chain(&e, &chain)
+                            .map(|(l, _lang)| l.to_string())
+                    })
                 }
             })
             .collect();
@@ -133,12 +185,14 @@ impl super::ListProcessor {
         let stream =
             futures::stream::iter(futures).buffer_unordered(MAX_CONCURRENT_REDLINKS_REQUESTS);
         let results = stream.collect::<Vec<_>>().await;
-        for (title, page_exists) in results.into_iter().flatten() {
-            list.local_page_cache_mut().insert(title, page_exists);
+        for (title, status) in results.into_iter().flatten() {
+            list.local_page_cache_mut().insert(title, status);
         }
     }
 
-    async fn find_keep_flags(list: &mut ListeriaList) -> Vec<bool> {
+    /// For each row, the title of its item's local article, if any; `None`
+    /// if the item did not load.
+    async fn find_local_sitelinks(list: &mut ListeriaList) -> Vec<Option<Option<String>>> {
         let wiki: Arc<str> = list.wiki().into();
         let ecw = list.ecw().clone();
 
@@ -150,12 +204,12 @@ impl super::ListProcessor {
                 let wiki = Arc::clone(&wiki);
                 let entity_id = row.entity_id().to_string();
                 async move {
-                    ecw.get_entity(&entity_id).await.is_some_and(|entity| {
-                        entity
-                            .sitelinks()
-                            .as_ref()
-                            .map_or_else(|| true, |sl| !sl.iter().any(|s| *s.site() == *wiki))
-                    })
+                    let entity = ecw.get_entity(&entity_id).await?;
+                    Some(entity.sitelinks().as_ref().and_then(|sl| {
+                        sl.iter()
+                            .find(|s| *s.site() == *wiki)
+                            .map(|s| s.title().to_string())
+                    }))
                 }
             })
             .collect();
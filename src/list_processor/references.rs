@@ -1,8 +1,10 @@
-//! Reference item loading (stated-in sources).
+//! Reference item loading (stated-in sources) and de-duplication.
 
 use crate::listeria_list::ListeriaList;
 use crate::result_cell_part::PartWithReference;
 use anyhow::Result;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
 
 impl super::ListProcessor {
     pub async fn process_reference_items(list: &mut ListeriaList) -> Result<()> {
@@ -41,4 +43,35 @@ impl super::ListProcessor {
             }
         }
     }
+
+    /// Assigns each distinct reference to the first cell (in result order)
+    /// that cites it and is part of the output (see
+    /// [`ResultRow::emitted_columns`](crate::result_row::ResultRow::emitted_columns)).
+    /// That cell carries the full `<ref>` definition, all other uses become
+    /// `<ref name=… />`. Must run after sorting and sectioning, as it records
+    /// row indices.
+    pub async fn process_reference_ids(list: &mut ListeriaList) {
+        let mut reference_ids = HashMap::new();
+        let mut definitions = Vec::new();
+        for (rownum, row) in list.results().iter().enumerate() {
+            for colnum in row.emitted_columns(&*list) {
+                let Some(cell) = row.cells().get(colnum) else {
+                    continue;
+                };
+                let references = cell
+                    .parts()
+                    .iter()
+                    .filter_map(|part| part.references().as_ref())
+                    .flatten();
+                for reference in references {
+                    let (wikitext, id) = reference.compute_wikitext(&*list).await;
+                    if let Entry::Vacant(e) = reference_ids.entry(id.clone()) {
+                        e.insert((rownum, colnum));
+                        definitions.push((id, wikitext));
+                    }
+                }
+            }
+        }
+        list.set_references(reference_ids, definitions);
+    }
 }
@@ -25,17 +25,20 @@ impl super::ListProcessor {
         Ok(())
     }
 
+    /// Keeps only the first file of each cell, except in columns with the
+    /// `all` image option.
     pub fn process_excess_files(list: &mut ListeriaList) -> Result<()> {
+        let keep_all_columns: Vec<usize> = (0..list.columns().len())
+            .filter(|colnum| list.image_options(*colnum).all)
+            .collect();
         for row in list.results_mut().iter_mut() {
-            row.remove_excess_files();
+            row.remove_excess_files(&keep_all_columns);
         }
         Ok(())
     }
 
     fn check_this_wiki_for_shadow_images(list: &ListeriaList) -> bool {
-        list.page_params()
-            .config()
-            .check_for_shadow_images(list.wiki())
+        list.page_params().settings().shadow_images_check
     }
 
     async fn fetch_file_info(
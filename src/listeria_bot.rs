@@ -3,7 +3,8 @@
 use std::sync::Arc;
 
 use crate::{
-    configuration::Configuration, page_to_process::PageToProcess, wiki_page_result::WikiPageResult,
+    configuration::Configuration, page_to_process::PageToProcess, storage::Storage,
+    wiki_page_result::WikiPageResult,
 };
 use anyhow::Result;
 
@@ -23,6 +24,9 @@ pub trait ListeriaBot {
     /// circuit breaker — at bot startup we want those to become eligible
     /// for retry rather than staying invisible to the dispatcher.
     async fn clear_deferred(&self) -> Result<()>;
+    /// Makes DEFERRED rows eligible again once the circuit breaker that
+    /// deferred them has closed, instead of waiting for the next startup.
+    async fn recover_deferred(&self) -> Result<()>;
     async fn set_runtime(&self, pagestatus_id: u64, seconds: u64) -> Result<()>;
     async fn run_single_bot(&self, page: PageToProcess) -> Result<WikiPageResult>;
 
@@ -35,3 +39,33 @@ pub trait ListeriaBot {
     /// Returns a page to be processed.
     async fn prepare_next_single_page(&self) -> Result<PageToProcess>;
 }
+
+/// Turns the DEFERRED pages behind circuit breakers that closed again since
+/// the last call back into FAIL. A SPARQL or entity-loading breaker may have
+/// deferred pages of any wiki, so all of them are recovered then.
+///
+/// A breaker only closes after a request succeeds, and deferred pages make
+/// no requests, so an open breaker that is due for its half-open probe gets
+/// one of its deferred pages released to be that probe.
+pub async fn recover_deferred(config: &Configuration, storage: &impl Storage) -> Result<()> {
+    let (wikis, shared) = config.take_probe_circuits();
+    if shared {
+        log::info!("A shared circuit breaker is half-open, releasing a deferred page as a probe");
+        storage.release_deferred_probe(None).await?;
+    }
+    for wiki in &wikis {
+        log::info!("Circuit breaker for {wiki} is half-open, releasing a deferred page as a probe");
+        storage.release_deferred_probe(Some(wiki)).await?;
+    }
+
+    let (wikis, shared) = config.take_recovered_circuits();
+    if shared {
+        log::info!("A shared circuit breaker closed, recovering all deferred pages");
+        return storage.recover_deferred(None).await;
+    }
+    for wiki in &wikis {
+        log::info!("Circuit breaker for {wiki} closed, recovering its deferred pages");
+        storage.recover_deferred(Some(wiki)).await?;
+    }
+    Ok(())
+}
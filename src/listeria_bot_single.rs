@@ -1,18 +1,27 @@
 //! Single-wiki bot implementation for dedicated wiki installations.
+//!
+//! Queue state, runtimes and last edits go to the configured storage —
+//! usually a `storage_file`, so a restarted bot picks up where it left off.
 
 use crate::configuration::Configuration;
-use crate::listeria_bot::ListeriaBot;
+use crate::listeria_bot::{self, ListeriaBot};
 use crate::listeria_bot_wiki::ListeriaBotWiki;
+use crate::listeria_error::{ErrorCode, ListeriaError};
 use crate::page_to_process::PageToProcess;
+use crate::storage::{FileStorage, IGNORE_STATUS, Storage, StorageBackend, storage_timestamp};
 use crate::wiki_page_result::WikiPageResult;
 use anyhow::{Result, anyhow};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
+/// The name the single wiki is known by, in storage and configuration.
+const WIKI: &str = "wiki";
+
 #[derive(Debug, Clone)]
 pub struct ListeriaBotSingle {
     config: Arc<Configuration>,
+    storage: StorageBackend,
     ticontinue: Arc<Mutex<Option<String>>>,
     page_cache: Arc<Mutex<Vec<PageToProcess>>>,
     template_inclusion_end_reached: Arc<Mutex<bool>>,
@@ -27,8 +36,14 @@ impl ListeriaBot for ListeriaBotSingle {
     }
 
     async fn new_from_config(config: Arc<Configuration>) -> Result<Self> {
+        let storage = config
+            .storage()
+            .cloned()
+            .unwrap_or_else(|_| StorageBackend::File(FileStorage::in_memory()));
+        storage.add_wikis(&[WIKI.to_string()]).await?;
         Ok(Self {
             config,
+            storage,
             ticontinue: Arc::new(Mutex::new(None)),
             page_cache: Arc::new(Mutex::new(Vec::new())),
             template_inclusion_end_reached: Arc::new(Mutex::new(false)),
@@ -40,29 +55,25 @@ impl ListeriaBot for ListeriaBotSingle {
         &self.config
     }
     async fn reset_running(&self) -> Result<()> {
-        // No need
-        Ok(())
+        self.storage.reset_running().await
     }
     async fn clear_deleted(&self) -> Result<()> {
-        // No need
-        Ok(())
+        self.storage.clear_deleted().await
     }
     async fn clear_deferred(&self) -> Result<()> {
-        // No DB-backed queue in single-wiki mode; nothing to clear.
-        Ok(())
+        self.storage.clear_deferred().await
     }
-    async fn set_runtime(&self, _pagestatus_id: u64, _seconds: u64) -> Result<()> {
-        // No need
-        Ok(())
+    async fn recover_deferred(&self) -> Result<()> {
+        listeria_bot::recover_deferred(&self.config, &self.storage).await
+    }
+    async fn set_runtime(&self, pagestatus_id: u64, seconds: u64) -> Result<()> {
+        self.storage.set_runtime(pagestatus_id, seconds).await
     }
     async fn run_single_bot(&self, page: PageToProcess) -> Result<WikiPageResult> {
         let bot = match self.create_bot_for_wiki(page.wiki()).await {
             Some(bot) => bot.to_owned(),
             None => {
-                return Err(anyhow!(
-                    "ListeriaBot::run_single_bot: No such wiki '{}'",
-                    page.wiki()
-                ));
+                return Err(ListeriaError::NoSuchWiki(page.wiki().to_string()).into());
             }
         };
         if !self.config().quiet() {
@@ -70,6 +81,18 @@ impl ListeriaBot for ListeriaBotSingle {
         }
         let mut wpr = bot.process_page(page.title()).await;
         wpr.standardize_message();
+        self.storage
+            .update_page_status_with_code(
+                page.title(),
+                page.wiki(),
+                wpr.result(),
+                wpr.message(),
+                wpr.code().map(ErrorCode::as_str),
+            )
+            .await?;
+        if wpr.edited() {
+            self.storage.set_last_edit(page.title(), page.wiki()).await?;
+        }
         Ok(wpr)
     }
 
@@ -85,6 +108,10 @@ impl ListeriaBot for ListeriaBotSingle {
 
     /// Returns a page to be processed.
     async fn prepare_next_single_page(&self) -> Result<PageToProcess> {
+        if let Some(page) = self.storage.find_priority_page(&[], IGNORE_STATUS).await? {
+            self.mark_running(&page).await?;
+            return Ok(page);
+        }
         if self.page_cache_is_empty().await {
             if *self.template_inclusion_end_reached.lock().await {
                 // Throw an error but only once, so the caller knows the list has been processed
@@ -97,11 +124,31 @@ impl ListeriaBot for ListeriaBotSingle {
             Some(page) => page,
             None => return Err(anyhow!("No pages available")),
         };
+        self.mark_running(&ret).await?;
         Ok(ret)
     }
 }
 
 impl ListeriaBotSingle {
+    /// Marks a page as FAIL in storage, for runs that ended before
+    /// `run_single_bot` could record their outcome.
+    pub async fn mark_page_failed(&self, wiki: &str, page: &str, message: &str) -> Result<()> {
+        self.storage
+            .update_page_status(page, wiki, "FAIL", message)
+            .await
+    }
+
+    /// Puts a page that was not processed back to the status it had, for a
+    /// shutdown that stopped it.
+    pub async fn release_page(&self, page: &PageToProcess) -> Result<()> {
+        self.storage.release_running_page(page).await
+    }
+
+    /// Writes out any storage data not written yet.
+    pub async fn flush(&self) -> Result<()> {
+        self.storage.flush().await
+    }
+
     async fn create_bot_for_wiki(&self, wiki: &str) -> Option<Arc<ListeriaBotWiki>> {
         let mut the_bot = self.the_bot.lock().await;
         if let Some(bot) = &*the_bot {
@@ -114,6 +161,12 @@ impl ListeriaBotSingle {
         Some(bot)
     }
 
+    async fn mark_running(&self, page: &PageToProcess) -> Result<()> {
+        self.storage
+            .update_page_status(page.title(), page.wiki(), "RUNNING", "PREPARING")
+            .await
+    }
+
     async fn page_cache_is_empty(&self) -> bool {
         self.page_cache.lock().await.is_empty()
     }
@@ -121,9 +174,29 @@ impl ListeriaBotSingle {
     fn get_start_template(&self) -> Result<String> {
         Ok(self
             .config
-            .get_local_template_title_start("wiki")?
+            .get_local_template_title_start(WIKI)?
             .to_string())
     }
+    /// Adds `titles` to the stored queue and returns them as a page cache,
+    /// least recently processed last, since the cache is consumed with `pop`.
+    /// Pages waiting for a retry after a failure are left out; priority
+    /// pages are picked up regardless.
+    async fn queue_pages(&self, titles: &[String]) -> Result<Vec<PageToProcess>> {
+        let wiki_id = self.storage.get_wiki_id(WIKI).await?;
+        self.storage.add_pages_for_wiki(wiki_id, titles).await?;
+        let now = storage_timestamp();
+        let mut pages: Vec<_> = self
+            .storage
+            .get_page_statuses(WIKI)
+            .await?
+            .into_iter()
+            .filter(|p| titles.contains(&p.page))
+            .filter(|p| p.next_attempt.as_ref().is_none_or(|t| *t <= now))
+            .collect();
+        pages.sort_by(|a, b| b.timestamp.cmp(&a.timestamp).then(b.id.cmp(&a.id)));
+        Ok(pages.iter().map(|p| p.to_page_to_process()).collect())
+    }
+
     async fn load_more_pages(&self) -> Result<()> {
         let api = self.config.get_default_wbapi()?;
         let mut params: HashMap<String, String> = [
@@ -151,19 +224,11 @@ impl ListeriaBotSingle {
             let pages = template["transcludedin"]
                 .as_array()
                 .ok_or(anyhow!("'transcludedin' not an array in API response"))?;
-            let pages: Vec<PageToProcess> = pages
+            let titles: Vec<String> = pages
                 .iter()
-                .filter_map(|page| {
-                    let parts = (
-                        page["pageid"].as_u64()?,
-                        page["title"].as_str()?.to_string(),
-                        "dummy".to_string(),
-                        "wiki".to_string(),
-                    );
-                    Some(PageToProcess::from_parts(parts))
-                })
+                .filter_map(|page| Some(page["title"].as_str()?.to_string()))
                 .collect();
-            *(self.page_cache.lock().await) = pages;
+            *(self.page_cache.lock().await) = self.queue_pages(&titles).await?;
         }
         if let Some(s) = result["continue"]["ticontinue"].as_str() {
             *ticontinue_lock = Some(s.to_string());
@@ -2,7 +2,9 @@
 
 use crate::ApiArc;
 use crate::configuration::Configuration;
+use crate::listeria_error::ListeriaError;
 use crate::listeria_page::ListeriaPage;
+use crate::storage::Storage;
 use crate::wiki_apis::WikiApis;
 use crate::wiki_page_result::WikiPageResult;
 use std::sync::Arc;
@@ -78,6 +80,9 @@ impl ListeriaBotWiki {
             .await
         {
             Ok(p) => p,
+            Err(e) if ListeriaError::from_anyhow(&e).is_some() => {
+                return WikiPageResult::from_error(&self.wiki, page, &e);
+            }
             Err(e) => {
                 return WikiPageResult::new(
                     &self.wiki,
@@ -90,10 +95,45 @@ impl ListeriaBotWiki {
         if let Err(wpr) = listeria_page.run().await {
             return wpr;
         }
-        let _ = match listeria_page.update_source_page().await {
+        let edited = match listeria_page.update_source_page().await {
             Ok(x) => x,
             Err(wpr) => return wpr,
         };
-        WikiPageResult::new(&self.wiki, page, "OK", "".to_string())
+        let list_errors = listeria_page.list_errors();
+        if !list_errors.is_empty() {
+            let mut wpr = WikiPageResult::fail(&self.wiki, page, &list_errors.join("; "));
+            wpr.set_edited(edited);
+            return wpr;
+        }
+        self.index_items(page, &listeria_page).await;
+        let mut notes = vec![];
+        let redirected_items = listeria_page.redirected_items();
+        if redirected_items > 0 {
+            notes.push(format!(
+                "{redirected_items} redirected item(s) in query results"
+            ));
+        }
+        notes.extend(listeria_page.param_warnings());
+        let mut wpr = WikiPageResult::new(&self.wiki, page, "OK", notes.join("; "));
+        wpr.set_edited(edited);
+        wpr
+    }
+
+    /// Stores which items the page lists, for item lookups and the
+    /// recent-changes watcher. A failure only costs the page its item-edit
+    /// refreshes, so it is logged rather than failing the page.
+    async fn index_items(&self, page: &str, listeria_page: &ListeriaPage) {
+        if !self.config.item_page_index() {
+            return;
+        }
+        let Ok(storage) = self.config.storage() else {
+            return;
+        };
+        if let Err(e) = storage
+            .set_page_items(&self.wiki, page, &listeria_page.entity_ids())
+            .await
+        {
+            log::warn!("Could not index items of {}:{page}: {e}", self.wiki);
+        }
     }
 }
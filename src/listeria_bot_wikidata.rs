@@ -1,15 +1,16 @@
 //! Multi-wiki bot that processes Listeria templates across Wikimedia wikis.
 
 use crate::configuration::Configuration;
-use crate::listeria_bot::ListeriaBot;
+use crate::fair_scheduler::{AllWikisAtCap, FairScheduler};
+use crate::listeria_bot::{self, ListeriaBot};
 use crate::listeria_bot_wiki::ListeriaBotWiki;
+use crate::listeria_error::{ErrorCode, ListeriaError};
 use crate::page_to_process::PageToProcess;
-use crate::pagestatus_repository::PageStatusRepository;
+use crate::storage::{IGNORE_STATUS, Storage, StorageBackend, storage_timestamp_before};
 use crate::wiki_apis::WikiApis;
 use crate::wiki_page_result::WikiPageResult;
 use anyhow::{Result, anyhow};
 use dashmap::DashMap;
-use dashmap::DashSet;
 use log::info;
 use std::sync::Arc;
 
@@ -18,24 +19,37 @@ pub struct ListeriaBotWikidata {
     config: Arc<Configuration>,
     wiki_apis: Arc<WikiApis>,
     bot_per_wiki: DashMap<String, ListeriaBotWiki>,
-    running: DashSet<u64>,
-    pagestatus: PageStatusRepository,
+    /// Running pages, by pagestatus ID.
+    running: DashMap<u64, PageToProcess>,
+    scheduler: Arc<FairScheduler>,
+    storage: StorageBackend,
 }
 
 impl ListeriaBotWikidata {
     pub async fn clear_log_table(&self) -> Result<()> {
-        use mysql_async::prelude::Queryable;
-        let pool = self.config.pool()?;
-        pool.with_timeout("clear_log_table", || async {
-            let sql = "TRUNCATE `list_log`";
-            pool.get_conn().await?.exec_iter(sql, ()).await?;
-            Ok(())
-        })
-        .await
+        self.storage.clear_profile_log().await
+    }
+
+    /// The per-wiki API pool, shared with the recent-changes watcher.
+    pub fn wiki_apis(&self) -> Arc<WikiApis> {
+        Arc::clone(&self.wiki_apis)
+    }
+
+    /// The per-wiki scheduler, for the status server's queue table.
+    pub fn scheduler(&self) -> Arc<FairScheduler> {
+        Arc::clone(&self.scheduler)
+    }
+
+    fn running_ids(&self) -> Vec<u64> {
+        self.running.iter().map(|entry| *entry.key()).collect()
     }
 
     fn running_ids_string(&self) -> String {
-        let mut parts: Vec<String> = self.running.iter().map(|id| id.to_string()).collect();
+        let mut parts: Vec<String> = self
+            .running
+            .iter()
+            .map(|entry| entry.key().to_string())
+            .collect();
         if parts.is_empty() {
             "0".to_string()
         } else {
@@ -58,14 +72,19 @@ impl ListeriaBot for ListeriaBotWikidata {
             .await?;
         let config = Arc::new((*config).clone().with_wikis(wikis));
         let wiki_apis = WikiApis::new(config.clone()).await?;
-        let pagestatus = PageStatusRepository::new(config.pool()?.as_ref().clone());
+        let storage = config.storage()?.clone();
+        let scheduler = FairScheduler::new(
+            config.fair_scheduling().clone(),
+            *config.get_max_mw_apis_per_wiki(),
+        );
 
         Ok(Self {
             config: config.clone(),
             wiki_apis: Arc::new(wiki_apis),
             bot_per_wiki: DashMap::new(),
-            running: DashSet::new(),
-            pagestatus,
+            running: DashMap::new(),
+            scheduler: Arc::new(scheduler),
+            storage,
         })
     }
 
@@ -74,20 +93,26 @@ impl ListeriaBot for ListeriaBotWikidata {
     }
 
     async fn reset_running(&self) -> Result<()> {
-        self.pagestatus.reset_running().await
+        self.storage.reset_running().await
     }
 
     async fn clear_deleted(&self) -> Result<()> {
-        self.pagestatus.clear_deleted().await
+        self.storage.clear_deleted().await
     }
 
     async fn clear_deferred(&self) -> Result<()> {
-        self.pagestatus.clear_deferred().await
+        self.storage.clear_deferred().await
+    }
+
+    async fn recover_deferred(&self) -> Result<()> {
+        listeria_bot::recover_deferred(&self.config, &self.storage).await
     }
 
     /// Removes a pagestatus ID from the running list.
     async fn release_running(&self, pagestatus_id: u64) {
-        self.running.remove(&pagestatus_id);
+        if let Some((_, page)) = self.running.remove(&pagestatus_id) {
+            self.scheduler.finish(page.wiki()).await;
+        }
     }
 
     /// Returns how many pages are currently running.
@@ -97,78 +122,133 @@ impl ListeriaBot for ListeriaBotWikidata {
 
     /// Returns the next page to be processed.
     async fn prepare_next_single_page(&self) -> Result<PageToProcess> {
-        let ids = self.running_ids_string();
-        info!(target: "lock", "Getting next page, without {ids}");
+        info!(target: "lock", "Getting next page, without {}", self.running_ids_string());
         // DEFERRED rows are pages whose processing hit an open circuit
         // breaker; they are cleared at bot startup, so during steady state
-        // we want the dispatcher to leave them alone.
-        const IGNORE_STATUS: &str = "'RUNNING','DELETED','TRANSLATION','DEFERRED'";
+        // we want the dispatcher to leave them alone (see IGNORE_STATUS).
+        // Failed rows wait for their backoff unless they are priority pages.
+        let ids = self.running_ids();
 
-        if let Some(page) = self.pagestatus.find_priority_page(&ids, IGNORE_STATUS).await? {
+        if let Some(page) = self.storage.find_priority_page(&ids, IGNORE_STATUS).await? {
             info!(target: "lock", "Found a priority page: {:?}", &page);
-            self.pagestatus
-                .update_page_status(page.title(), page.wiki(), "RUNNING", "PREPARING")
-                .await?;
-            self.running.insert(page.id());
-            return Ok(page);
+            return self.start_page(page).await;
         }
 
-        let page = self
-            .pagestatus
-            .find_oldest_page(&ids, IGNORE_STATUS)
-            .await?
-            .ok_or_else(|| anyhow!("prepare_next_single_page: no page available"))?;
-
-        info!(target: "lock", "Found a page: {:?}", &page);
-        self.pagestatus
-            .update_page_status(page.title(), page.wiki(), "RUNNING", "PREPARING")
-            .await?;
-        self.running.insert(page.id());
-        Ok(page)
+        // Other pages are taken wiki by wiki, see FairScheduler
+        let updated_before = storage_timestamp_before(self.scheduler.settings().min_age());
+        if self.scheduler.needs_refresh().await {
+            let depths = self
+                .storage
+                .get_queue_depths(IGNORE_STATUS, &updated_before)
+                .await?;
+            self.scheduler.set_queue_depths(depths).await;
+        }
+        let candidates = self.scheduler.candidates().await;
+        if candidates.is_empty() && self.scheduler.all_at_cap().await {
+            return Err(AllWikisAtCap.into());
+        }
+        for wiki in candidates {
+            match self
+                .storage
+                .find_oldest_page_for_wiki(&wiki, &ids, IGNORE_STATUS, &updated_before)
+                .await?
+            {
+                Some(page) => {
+                    info!(target: "lock", "Found a page: {:?}", &page);
+                    return self.start_page(page).await;
+                }
+                None => self.scheduler.mark_empty(&wiki).await,
+            }
+        }
+        Err(anyhow!("prepare_next_single_page: no page available"))
     }
 
     async fn set_runtime(&self, pagestatus_id: u64, seconds: u64) -> Result<()> {
-        self.pagestatus.set_runtime(pagestatus_id, seconds).await
+        self.storage.set_runtime(pagestatus_id, seconds).await
     }
 
     async fn run_single_bot(&self, page: PageToProcess) -> Result<WikiPageResult> {
         let bot = match self.create_bot_for_wiki(page.wiki()).await {
             Some(bot) => bot.to_owned(),
             None => {
-                self.pagestatus
-                    .update_page_status(
+                let error = ListeriaError::NoSuchWiki(page.wiki().to_string());
+                self.storage
+                    .update_page_status_with_code(
                         page.title(),
                         page.wiki(),
-                        "FAIL",
-                        &format!("No such wiki: {}", page.wiki()),
+                        error.code().status(),
+                        &error.to_string(),
+                        Some(error.code().as_str()),
                     )
                     .await?;
-                return Err(anyhow!(
-                    "ListeriaBot::run_single_bot: No such wiki '{}'",
-                    page.wiki()
-                ));
+                return Err(error.into());
             }
         };
         let mut wpr = bot.process_page(page.title()).await;
         wpr.standardize_message();
-        self.pagestatus
-            .update_page_status(wpr.page(), wpr.wiki(), wpr.result(), wpr.message())
+        self.storage
+            .update_page_status_with_code(
+                wpr.page(),
+                wpr.wiki(),
+                wpr.result(),
+                wpr.message(),
+                wpr.code().map(ErrorCode::as_str),
+            )
             .await?;
+        if wpr.edited() {
+            self.storage.set_last_edit(wpr.page(), wpr.wiki()).await?;
+        }
         Ok(wpr)
     }
 }
 
 impl ListeriaBotWikidata {
+    /// Marks a page RUNNING and counts it against its wiki's share.
+    async fn start_page(&self, page: PageToProcess) -> Result<PageToProcess> {
+        self.storage
+            .update_page_status(page.title(), page.wiki(), "RUNNING", "PREPARING")
+            .await?;
+        self.running.insert(page.id(), page.clone());
+        self.scheduler.start(page.wiki()).await;
+        Ok(page)
+    }
+
     /// Marks a page as FAIL in the pagestatus queue. Used by the dispatcher
     /// when an outer wall-clock timeout aborts `run_single_bot` before its
     /// own status update can run — without this, the row would stay RUNNING
     /// until the next `reset_running` on bot restart.
     pub async fn mark_page_failed(&self, wiki: &str, page: &str, message: &str) -> Result<()> {
-        self.pagestatus
+        self.storage
             .update_page_status(page, wiki, "FAIL", message)
             .await
     }
 
+    /// Puts the pages that are still marked running back to the status they
+    /// had, for a shutdown after their tasks were stopped.
+    pub async fn release_all_running(&self) -> Result<()> {
+        let pages: Vec<PageToProcess> = self
+            .running
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect();
+        for page in pages {
+            info!(
+                "Releasing {}:{} back to {}",
+                page.wiki(),
+                page.title(),
+                page.status()
+            );
+            self.storage.release_running_page(&page).await?;
+            self.release_running(page.id()).await;
+        }
+        Ok(())
+    }
+
+    /// Writes out any storage data not written yet.
+    pub async fn flush(&self) -> Result<()> {
+        self.storage.flush().await
+    }
+
     async fn create_bot_for_wiki(&self, wiki: &str) -> Option<ListeriaBotWiki> {
         if let Some(bot) = self.bot_per_wiki.get(wiki) {
             let new_bot = bot.to_owned();
@@ -196,10 +276,13 @@ mod tests {
     #[test]
     fn test_running_ids_string_empty() {
         // Can't build a full ListeriaBotWikidata without a DB, so test the
-        // logic directly via a local DashSet.
-        let running: DashSet<u64> = DashSet::new();
+        // logic directly via a local DashMap.
+        let running: DashMap<u64, PageToProcess> = DashMap::new();
         let ids = {
-            let mut parts: Vec<String> = running.iter().map(|id| id.to_string()).collect();
+            let mut parts: Vec<String> = running
+                .iter()
+                .map(|entry| entry.key().to_string())
+                .collect();
             if parts.is_empty() {
                 "0".to_string()
             } else {
@@ -212,11 +295,14 @@ mod tests {
 
     #[test]
     fn test_running_ids_string_sorted() {
-        let running: DashSet<u64> = DashSet::new();
-        running.insert(3);
-        running.insert(1);
-        running.insert(2);
-        let mut parts: Vec<String> = running.iter().map(|id| id.to_string()).collect();
+        let running: DashMap<u64, PageToProcess> = DashMap::new();
+        running.insert(3, PageToProcess::default());
+        running.insert(1, PageToProcess::default());
+        running.insert(2, PageToProcess::default());
+        let mut parts: Vec<String> = running
+            .iter()
+            .map(|entry| entry.key().to_string())
+            .collect();
         parts.sort_unstable();
         let ids = parts.join(",");
         assert_eq!(ids, "1,2,3");
@@ -1,10 +1,13 @@
 //! Typed error variants for the Listeria pipeline.
 //!
-//! These errors appear in the SPARQL and entity-loading layers.
-//! They implement `std::error::Error` via `thiserror` and convert
-//! into `anyhow::Error` automatically, so existing `?` call-sites
-//! keep working unchanged.
+//! These errors cover page loading and editing, the SPARQL query, entity
+//! loading, rendering and configuration. They implement `std::error::Error`
+//! via `thiserror` and convert into `anyhow::Error` automatically, so
+//! existing `?` call-sites keep working unchanged; [`ListeriaError::code`]
+//! gives the stable [`ErrorCode`] that ends up in `pagestatus`, and with it
+//! whether another run can succeed.
 
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -12,6 +15,12 @@ pub enum ListeriaError {
     #[error("No wikibase setup configured for '{0}'")]
     SparqlNoConfig(String),
 
+    #[error("No SPARQL endpoint for '{wikibase}'; set '{setting}' in the configuration")]
+    SparqlNoEndpoint {
+        wikibase: String,
+        setting: &'static str,
+    },
+
     #[error("SPARQL query must include the ?item variable — do not rename it")]
     SparqlNoItemVariable,
 
@@ -23,11 +32,299 @@ pub enum ListeriaError {
 
     #[error("SPARQL circuit open — endpoint '{0}' is temporarily blocked after repeated failures")]
     SparqlCircuitOpen(String),
+
+    #[error("MW API circuit open for {0}")]
+    MwApiCircuitOpen(String),
+
+    #[error("entity-loading circuit open")]
+    EntityCircuitOpen,
+
+    #[error("Wiki says this page is missing")]
+    PageMissing,
+
+    #[error("Wiki says this page has an invalid title")]
+    InvalidTitle,
+
+    /// The wiki's message, which names the page this one translates.
+    #[error("{0}")]
+    TranslationPage(String),
+
+    #[error(
+        "{{{{Wikidata list end}}}} is missing; refusing to update to prevent list duplication (#108)"
+    )]
+    MissingEndTemplate,
+
+    #[error("Namespace {namespace} not allowed for edit on {wiki}")]
+    NamespaceNotAllowed { namespace: i64, wiki: String },
+
+    #[error("Could not load page: {0}")]
+    PageLoad(String),
+
+    #[error("Edit conflict: the page was changed while the list was updated")]
+    EditConflict,
+
+    #[error("The page is protected: {0}")]
+    ProtectedPage(String),
+
+    #[error("The edit was stopped by a filter: {0}")]
+    AbuseFilter(String),
+
+    #[error("The bot is blocked: {0}")]
+    Blocked(String),
+
+    #[error("Error while saving ({code}): {info}")]
+    EditFailed { code: String, info: String },
+
+    #[error("Could not load entities: {0}")]
+    EntityLoad(String),
+
+    #[error("Could not render the list: {0}")]
+    Render(String),
+
+    #[error("No such wiki: {0}")]
+    NoSuchWiki(String),
+
+    #[error("Configuration error: {0}")]
+    Configuration(String),
+}
+
+impl ListeriaError {
+    #[must_use]
+    pub const fn code(&self) -> ErrorCode {
+        match self {
+            Self::SparqlNoConfig(_) | Self::SparqlNoEndpoint { .. } => ErrorCode::SparqlNoConfig,
+            Self::SparqlNoItemVariable => ErrorCode::SparqlNoItemVariable,
+            Self::MissingSparqlParam => ErrorCode::MissingSparqlParam,
+            Self::NoItemsToShow => ErrorCode::NoItemsToShow,
+            Self::SparqlCircuitOpen(_) | Self::MwApiCircuitOpen(_) | Self::EntityCircuitOpen => {
+                ErrorCode::CircuitOpen
+            }
+            Self::PageMissing => ErrorCode::PageMissing,
+            Self::InvalidTitle => ErrorCode::InvalidTitle,
+            Self::TranslationPage(_) => ErrorCode::TranslationPage,
+            Self::MissingEndTemplate => ErrorCode::MissingEndTemplate,
+            Self::NamespaceNotAllowed { .. } => ErrorCode::NamespaceNotAllowed,
+            Self::PageLoad(_) => ErrorCode::PageLoad,
+            Self::EditConflict => ErrorCode::EditConflict,
+            Self::ProtectedPage(_) => ErrorCode::ProtectedPage,
+            Self::AbuseFilter(_) => ErrorCode::AbuseFilter,
+            Self::Blocked(_) => ErrorCode::Blocked,
+            Self::EditFailed { .. } => ErrorCode::EditFailed,
+            Self::EntityLoad(_) => ErrorCode::EntityLoad,
+            Self::Render(_) => ErrorCode::Render,
+            Self::NoSuchWiki(_) => ErrorCode::NoSuchWiki,
+            Self::Configuration(_) => ErrorCode::Configuration,
+        }
+    }
+
+    /// The typed error behind `e`, if there is one.
+    #[must_use]
+    pub fn from_anyhow(e: &anyhow::Error) -> Option<&Self> {
+        e.downcast_ref::<Self>()
+    }
+
+    /// The error for an `error` object of a failed `action=edit`.
+    #[must_use]
+    pub fn from_edit_error(error: &serde_json::Value) -> Self {
+        let code = error["code"].as_str().unwrap_or_default();
+        let info = error["info"]
+            .as_str()
+            .unwrap_or("Error while saving")
+            .to_string();
+        match code {
+            "editconflict" => Self::EditConflict,
+            "protectedpage"
+            | "cascadeprotected"
+            | "protectednamespace"
+            | "protectednamespace-interface"
+            | "customcssjsprotected" => Self::ProtectedPage(info),
+            c if c.starts_with("abusefilter") || c == "spamblacklist" => Self::AbuseFilter(info),
+            "blocked" | "autoblocked" | "globalblocking-blockedtext" => Self::Blocked(info),
+            "tpt-target-page" => Self::TranslationPage(info),
+            _ => Self::EditFailed {
+                code: code.to_string(),
+                info,
+            },
+        }
+    }
+}
+
+/// What to do with a page after an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryDecision {
+    /// Try again after a backoff of minutes to an hour (see
+    /// [`ErrorCode::backoff_base`]), doubling with every failure in a row.
+    Retry,
+    /// An upstream is blocked, or the bot is blocked on the wiki: the page is
+    /// DEFERRED and tried again as soon as a circuit breaker closes, or when
+    /// the bot restarts.
+    Defer,
+    /// Another run will likely fail the same way until someone edits the
+    /// page or the configuration: the page is BROKEN and retried after a
+    /// day, doubling up to 7 days. An edit retries it at once.
+    Permanent,
+}
+
+/// A stable, storable code for each kind of error. Codes that
+/// `WikiPageResult::standardize_message` derives from untyped error
+/// messages are included, so that every failed page can carry one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    SparqlNoConfig,
+    SparqlNoItemVariable,
+    MissingSparqlParam,
+    NoItemsToShow,
+    SparqlTimeout,
+    SparqlError,
+    CircuitOpen,
+    PageMissing,
+    InvalidTitle,
+    TranslationPage,
+    MissingEndTemplate,
+    NamespaceNotAllowed,
+    PageLoad,
+    WikiTimeout,
+    ConnectionReset,
+    EditConflict,
+    ProtectedPage,
+    AbuseFilter,
+    Blocked,
+    EditFailed,
+    EntityLoad,
+    Render,
+    NoSuchWiki,
+    Configuration,
+}
+
+impl ErrorCode {
+    const ALL: [Self; 24] = [
+        Self::SparqlNoConfig,
+        Self::SparqlNoItemVariable,
+        Self::MissingSparqlParam,
+        Self::NoItemsToShow,
+        Self::SparqlTimeout,
+        Self::SparqlError,
+        Self::CircuitOpen,
+        Self::PageMissing,
+        Self::InvalidTitle,
+        Self::TranslationPage,
+        Self::MissingEndTemplate,
+        Self::NamespaceNotAllowed,
+        Self::PageLoad,
+        Self::WikiTimeout,
+        Self::ConnectionReset,
+        Self::EditConflict,
+        Self::ProtectedPage,
+        Self::AbuseFilter,
+        Self::Blocked,
+        Self::EditFailed,
+        Self::EntityLoad,
+        Self::Render,
+        Self::NoSuchWiki,
+        Self::Configuration,
+    ];
+
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::SparqlNoConfig => "SPARQL_NO_CONFIG",
+            Self::SparqlNoItemVariable => "SPARQL_NO_ITEM_VARIABLE",
+            Self::MissingSparqlParam => "MISSING_SPARQL_PARAM",
+            Self::NoItemsToShow => "NO_ITEMS",
+            Self::SparqlTimeout => "SPARQL_TIMEOUT",
+            Self::SparqlError => "SPARQL_ERROR",
+            Self::CircuitOpen => "CIRCUIT_OPEN",
+            Self::PageMissing => "PAGE_MISSING",
+            Self::InvalidTitle => "INVALID_TITLE",
+            Self::TranslationPage => "TRANSLATION_PAGE",
+            Self::MissingEndTemplate => "MISSING_END_TEMPLATE",
+            Self::NamespaceNotAllowed => "NAMESPACE_NOT_ALLOWED",
+            Self::PageLoad => "PAGE_LOAD",
+            Self::WikiTimeout => "WIKI_TIMEOUT",
+            Self::ConnectionReset => "104_RESET_BY_PEER",
+            Self::EditConflict => "EDIT_CONFLICT",
+            Self::ProtectedPage => "PROTECTED_PAGE",
+            Self::AbuseFilter => "ABUSE_FILTER",
+            Self::Blocked => "BLOCKED",
+            Self::EditFailed => "EDIT_FAILED",
+            Self::EntityLoad => "ENTITY_LOAD",
+            Self::Render => "RENDER",
+            Self::NoSuchWiki => "NO_SUCH_WIKI",
+            Self::Configuration => "CONFIGURATION",
+        }
+    }
+
+    /// The code stored as `s`, as written by [`Self::as_str`].
+    #[must_use]
+    pub fn from_code(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|code| code.as_str() == s)
+    }
+
+    #[must_use]
+    pub const fn retry(self) -> RetryDecision {
+        match self {
+            Self::CircuitOpen | Self::Blocked => RetryDecision::Defer,
+            Self::SparqlNoConfig
+            | Self::SparqlNoItemVariable
+            | Self::MissingSparqlParam
+            | Self::PageMissing
+            | Self::InvalidTitle
+            | Self::TranslationPage
+            | Self::MissingEndTemplate
+            | Self::NamespaceNotAllowed
+            | Self::ProtectedPage
+            | Self::AbuseFilter => RetryDecision::Permanent,
+            Self::NoItemsToShow
+            | Self::SparqlTimeout
+            | Self::SparqlError
+            | Self::PageLoad
+            | Self::WikiTimeout
+            | Self::ConnectionReset
+            | Self::EditConflict
+            | Self::EditFailed
+            | Self::EntityLoad
+            | Self::Render
+            | Self::NoSuchWiki
+            | Self::Configuration => RetryDecision::Retry,
+        }
+    }
+
+    /// How long to wait before retrying a page after its first failure
+    /// with this code; the wait doubles with every further failure in a
+    /// row. `None` for errors that defer the page instead.
+    #[must_use]
+    pub const fn backoff_base(self) -> Option<Duration> {
+        match self.retry() {
+            RetryDecision::Defer => None,
+            RetryDecision::Permanent => Some(Duration::from_secs(24 * 3600)),
+            RetryDecision::Retry => match self {
+                Self::EditConflict => Some(Duration::from_secs(5 * 60)),
+                Self::NoSuchWiki | Self::Configuration => Some(Duration::from_secs(3600)),
+                _ => Some(Duration::from_secs(15 * 60)),
+            },
+        }
+    }
+
+    /// The `pagestatus` status for a page that failed with this code.
+    #[must_use]
+    pub const fn status(self) -> &'static str {
+        match self {
+            Self::PageMissing => "DELETED",
+            Self::InvalidTitle => "INVALID",
+            Self::TranslationPage => "TRANSLATION",
+            _ => match self.retry() {
+                RetryDecision::Retry => "FAIL",
+                RetryDecision::Defer => "DEFERRED",
+                RetryDecision::Permanent => crate::storage::BROKEN_STATUS,
+            },
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde_json::json;
 
     #[test]
     fn test_sparql_no_config_message() {
@@ -35,6 +332,16 @@ mod tests {
         assert_eq!(e.to_string(), "No wikibase setup configured for 'enwiki'");
     }
 
+    #[test]
+    fn test_sparql_no_endpoint_names_setting() {
+        let e = ListeriaError::SparqlNoEndpoint {
+            wikibase: "commons".to_string(),
+            setting: "commons_query_endpoint",
+        };
+        assert!(e.to_string().contains("'commons_query_endpoint'"));
+        assert_eq!(e.code(), ErrorCode::SparqlNoConfig);
+    }
+
     #[test]
     fn test_sparql_no_item_variable_message_contains_item() {
         let e = ListeriaError::SparqlNoItemVariable;
@@ -57,5 +364,69 @@ mod tests {
     fn test_error_converts_to_anyhow() {
         let e: anyhow::Error = ListeriaError::SparqlNoItemVariable.into();
         assert!(e.to_string().contains("?item"));
+        assert_eq!(
+            ListeriaError::from_anyhow(&e).map(ListeriaError::code),
+            Some(ErrorCode::SparqlNoItemVariable)
+        );
+        assert!(ListeriaError::from_anyhow(&anyhow::anyhow!("untyped")).is_none());
+    }
+
+    #[test]
+    fn test_missing_end_template_message() {
+        assert!(
+            ListeriaError::MissingEndTemplate
+                .to_string()
+                .starts_with("{{Wikidata list end}} is missing")
+        );
+    }
+
+    // --- error codes ---
+
+    #[test]
+    fn test_error_codes_round_trip() {
+        for code in ErrorCode::ALL {
+            assert_eq!(ErrorCode::from_code(code.as_str()), Some(code));
+        }
+        assert_eq!(ErrorCode::from_code("NOPE"), None);
+    }
+
+    #[test]
+    fn test_error_code_status() {
+        assert_eq!(ErrorCode::EditConflict.status(), "FAIL");
+        assert_eq!(ErrorCode::CircuitOpen.status(), "DEFERRED");
+        assert_eq!(ErrorCode::PageMissing.status(), "DELETED");
+        assert_eq!(ErrorCode::ProtectedPage.status(), "BROKEN");
+        assert_eq!(ErrorCode::CircuitOpen.backoff_base(), None);
+        assert_eq!(ErrorCode::Blocked.status(), "DEFERRED");
+        assert_eq!(ErrorCode::Blocked.backoff_base(), None);
+        assert!(ErrorCode::ProtectedPage.backoff_base() > ErrorCode::WikiTimeout.backoff_base());
+        assert_eq!(
+            ErrorCode::MissingSparqlParam.retry(),
+            RetryDecision::Permanent
+        );
+    }
+
+    #[test]
+    fn test_from_edit_error() {
+        let error = |code: &str| json!({"code": code, "info": "Some info"});
+        assert_eq!(
+            ListeriaError::from_edit_error(&error("editconflict")).code(),
+            ErrorCode::EditConflict
+        );
+        assert_eq!(
+            ListeriaError::from_edit_error(&error("protectedpage")).code(),
+            ErrorCode::ProtectedPage
+        );
+        assert_eq!(
+            ListeriaError::from_edit_error(&error("abusefilter-disallowed")).code(),
+            ErrorCode::AbuseFilter
+        );
+        assert_eq!(
+            ListeriaError::from_edit_error(&error("blocked")).code(),
+            ErrorCode::Blocked
+        );
+        let e = ListeriaError::from_edit_error(&error("readonly"));
+        assert_eq!(e.code(), ErrorCode::EditFailed);
+        assert_eq!(e.to_string(), "Error while saving (readonly): Some info");
     }
 }
@@ -4,23 +4,28 @@
 
 use crate::column::Column;
 use crate::column_type::ColumnType;
+use crate::date_format::DateFormat;
+use crate::diagnostics::Diagnostic;
 use crate::entity_container_wrapper::{EntityContainerWrapper, EntityEntry};
+use crate::language_fallback::LanguageFallbacks;
 use crate::list_processor::ListProcessor;
 use crate::listeria_error::ListeriaError;
+use crate::messages::{Message, Messages};
 use crate::my_entity::MyEntity;
 use crate::page_params::PageParams;
+use crate::param_validation::{self, ParamWarning};
 use crate::profiling_service::ProfilingService;
 use crate::result_cell_part::ResultCellPart;
 use crate::result_generator::ResultGenerator;
 use crate::result_row::ResultRow;
-use crate::sparql_results::SparqlResults;
+use crate::sparql_results::{self, SparqlResults};
 use crate::template::Template;
+use crate::template_params::ImageOptions;
 use crate::template_params::LinksType;
 use crate::template_params::ReferencesParameter;
 use crate::template_params::SectionType;
 use crate::template_params::SortMode;
 use crate::template_params::TemplateParams;
-use crate::wiki::Wiki;
 use anyhow::{Result, anyhow};
 use serde_json::Value;
 use std::collections::HashMap;
@@ -47,12 +52,62 @@ pub struct ProcessingState {
     pub shadow_files: HashSet<String>,
     /// Per-page memoised "does this local page exist?" lookups, used by the
     /// redlink-aware link rendering paths.
-    pub local_page_cache: HashMap<String, bool>,
+    pub local_page_cache: HashMap<String, LocalPage>,
     /// Section-id → display-name map populated during the sections stage.
     pub section_id_to_name: HashMap<usize, String>,
-    /// Set of reference IDs already emitted on the page, used to deduplicate
-    /// `<ref name="...">` definitions in the output wikitext.
-    pub reference_ids: HashSet<String>,
+    /// Reference ID → the (row, column) of the cell carrying its full
+    /// `<ref name="...">` definition; all other uses are emitted as
+    /// `<ref name="..." />`. Populated after sorting and sectioning.
+    pub reference_ids: HashMap<String, (usize, usize)>,
+    /// `(reference ID, wikitext)` in order of first use, for the
+    /// `<references group=... />` block.
+    pub reference_definitions: Vec<(String, String)>,
+    /// How many IDs in the query results redirect to another entity,
+    /// e.g. because the item was merged.
+    pub redirected_items: usize,
+    /// How many IDs in the query results could not be loaded.
+    pub unloaded_items: usize,
+    /// Template parameters that are unknown or have values that are not
+    /// understood.
+    pub param_warnings: Vec<ParamWarning>,
+}
+
+/// What the wiki says about a local page title.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocalPageStatus {
+    Missing,
+    Exists,
+    /// The page exists, and is a disambiguation page.
+    Disambiguation,
+}
+
+impl LocalPageStatus {
+    /// From one page of a `prop=pageprops&ppprop=disambiguation` query.
+    fn from_page_json(page: &Value) -> Self {
+        if page["missing"].as_str().is_some() {
+            Self::Missing
+        } else if page["pageprops"]["disambiguation"].is_null() {
+            Self::Exists
+        } else {
+            Self::Disambiguation
+        }
+    }
+}
+
+/// A looked-up local page title.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalPage {
+    /// Of the redirect target, if the title is a redirect.
+    pub status: LocalPageStatus,
+    /// Where the title redirects to: `Target`, or `Target#Section`.
+    pub redirect: Option<String>,
+}
+
+impl LocalPage {
+    #[must_use]
+    pub fn redirects_to_section(&self) -> bool {
+        self.redirect.as_ref().is_some_and(|r| r.contains('#'))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -66,6 +121,8 @@ pub struct ListeriaList {
     state: ProcessingState,
     wb_api: Arc<Api>,
     language: String,
+    /// Messages in `language`, see [`PageParams::messages_for`].
+    messages: Arc<Messages>,
     profiler: ProfilingService,
 }
 
@@ -80,35 +137,37 @@ impl ListeriaList {
             page_params.page(),
             page_params.config().profiling(),
         );
+        let mut ecw =
+            EntityContainerWrapper::new(page_params.config().max_concurrent_entry_queries())
+                .await?
+                .with_circuit_breaker(
+                    page_params
+                        .config()
+                        .mw_api_circuit_breaker(crate::configuration::MW_API_ENTITIES_KEY),
+                );
+        if let Some(api) = page_params
+            .config()
+            .get_wbapi(crate::configuration::MEDIAINFO_API_KEY)
+        {
+            ecw = ecw.with_mediainfo_api(api.clone());
+        }
         Ok(Self {
             page_params: page_params.clone(),
             template,
             columns: Vec::new(),
             params: TemplateParams::new(),
             sparql_table: Arc::new(SparqlTableVec::new()),
-            ecw: EntityContainerWrapper::new(
-                page_params.config().max_concurrent_entry_queries(),
-            )
-            .await?
-            .with_circuit_breaker(
-                page_params
-                    .config()
-                    .mw_api_circuit_breaker(crate::configuration::MW_API_ENTITIES_KEY),
-            ),
+            ecw,
             state: ProcessingState::default(),
             wb_api,
             language: page_params.language().to_string(),
+            messages: page_params.messages_for(page_params.language()).await,
             profiler,
         })
     }
 
     pub fn do_get_regions(&self) -> bool {
-        let wiki = self.wiki();
-        self.page_params()
-            .config()
-            .location_regions()
-            .iter()
-            .any(|r| r == wiki)
+        self.page_params().settings().location_regions
     }
 
     pub fn process_regions_get_entity_ids(&self) -> HashSet<String> {
@@ -133,7 +192,9 @@ impl ListeriaList {
     /// Main processing pipeline: parses template, runs SPARQL query, and generates results.
     pub async fn process(&mut self) -> Result<()> {
         self.profile("START list::process").await;
+        self.expand_template_values().await;
         self.process_template()?;
+        self.messages = self.page_params.messages_for(&self.language).await;
         self.profile("AFTER list::process process_template").await;
         self.run_query().await?;
         self.profile("AFTER list::process run_query").await;
@@ -159,10 +220,84 @@ impl ListeriaList {
         &mut self.state.results
     }
 
+    pub const fn redirected_items(&self) -> usize {
+        self.state.redirected_items
+    }
+
+    pub const fn param_warnings(&self) -> &Vec<ParamWarning> {
+        &self.state.param_warnings
+    }
+
+    pub const fn set_redirected_items(&mut self, redirected_items: usize) {
+        self.state.redirected_items = redirected_items;
+    }
+
     pub const fn columns(&self) -> &Vec<Column> {
         &self.columns
     }
 
+    /// Whether problems with the list are shown above it.
+    pub fn show_diagnostics(&self) -> bool {
+        self.params
+            .diagnostics()
+            .unwrap_or(self.page_params.settings().inline_diagnostics)
+    }
+
+    /// Problems with a list that was processed: parameters and columns that
+    /// are not understood or without effect, results cut off by the query's
+    /// `LIMIT`, and items that could not be loaded.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut ret: Vec<Diagnostic> = self
+            .state
+            .param_warnings
+            .iter()
+            .cloned()
+            .map(Diagnostic::Parameter)
+            .collect();
+        let has_param = |name: &str| self.template.get_value(name).is_some();
+        let ignored = [
+            (
+                "pattern",
+                "format=list",
+                self.params.format().list_marker().is_none(),
+            ),
+            (
+                "row_template_params",
+                "row_template",
+                self.params.row_template().is_none(),
+            ),
+            (
+                "disambiguation",
+                "links=red",
+                !matches!(self.params.links(), LinksType::Red | LinksType::RedOnly),
+            ),
+            (
+                "references_group",
+                "references=all",
+                *self.params.references() == ReferencesParameter::None,
+            ),
+        ];
+        ret.extend(
+            ignored
+                .into_iter()
+                .filter(|(name, _, ignored)| *ignored && has_param(name))
+                .map(|(name, requires, _)| Diagnostic::IgnoredParameter { name, requires }),
+        );
+        if let Some(limit) = self
+            .template
+            .get_value("sparql")
+            .and_then(|sparql| sparql_results::query_limit(&sparql))
+            && limit > 0
+            && self.sparql_table.len() >= limit
+        {
+            ret.push(Diagnostic::TruncatedResults(limit));
+        }
+        if self.state.unloaded_items > 0 {
+            ret.push(Diagnostic::UnloadedItems(self.state.unloaded_items));
+        }
+        ret
+    }
+
     pub const fn shadow_files(&self) -> &HashSet<String> {
         &self.state.shadow_files
     }
@@ -171,7 +306,7 @@ impl ListeriaList {
         &mut self.state.shadow_files
     }
 
-    pub const fn local_page_cache_mut(&mut self) -> &mut HashMap<String, bool> {
+    pub const fn local_page_cache_mut(&mut self) -> &mut HashMap<String, LocalPage> {
         &mut self.state.local_page_cache
     }
 
@@ -191,10 +326,23 @@ impl ListeriaList {
         &self.wb_api
     }
 
-    pub const fn reference_ids(&self) -> &HashSet<String> {
+    pub const fn reference_ids(&self) -> &HashMap<String, (usize, usize)> {
         &self.state.reference_ids
     }
 
+    pub fn reference_definitions(&self) -> &[(String, String)] {
+        &self.state.reference_definitions
+    }
+
+    pub fn set_references(
+        &mut self,
+        reference_ids: HashMap<String, (usize, usize)>,
+        reference_definitions: Vec<(String, String)>,
+    ) {
+        self.state.reference_ids = reference_ids;
+        self.state.reference_definitions = reference_definitions;
+    }
+
     pub fn sparql_table(&self) -> &SparqlTableVec {
         &self.sparql_table
     }
@@ -207,16 +355,34 @@ impl ListeriaList {
         self.page_params.local_file_namespace_prefix()
     }
 
-    pub fn get_wiki(&self) -> Option<Wiki> {
-        let wiki = self.page_params.wiki();
-        self.page_params.config().get_wiki(wiki).cloned()
-    }
-
     pub fn section_name(&self, id: usize) -> Option<&str> {
         self.state.section_id_to_name.get(&id).map(|s| s.as_str())
     }
 
+    /// With `expand_template_params`, expands the templates and magic words
+    /// in parameter values (see [`Template::keys_to_expand`]). A value that
+    /// cannot be expanded is kept as it is.
+    async fn expand_template_values(&mut self) {
+        if !self.page_params.settings().expand_template_params {
+            return;
+        }
+        for key in self.template.keys_to_expand() {
+            let Some(value) = self.template.params().get(&key) else {
+                continue;
+            };
+            match self.page_params.expand_templates(value).await {
+                Ok(expanded) => self.template.set_value(&key, expanded),
+                Err(e) => log::warn!(
+                    "Could not expand {key} on {}:{}: {e}",
+                    self.page_params.wiki(),
+                    self.page_params.page()
+                ),
+            }
+        }
+    }
+
     pub fn process_template(&mut self) -> Result<()> {
+        self.state.param_warnings = param_validation::validate(&self.template);
         match self.template.get_value("columns") {
             Some(columns) => {
                 columns
@@ -240,11 +406,15 @@ impl ListeriaList {
         }
 
         let wikibase = self.params.wikibase();
-        self.wb_api = match self
-            .page_params
-            .config()
-            .get_wbapi(&wikibase.to_lowercase())
-        {
+        // Commons' statements use Wikidata's items and properties; the
+        // MediaInfo entities themselves come from the MediaInfo API
+        let entity_wikibase =
+            if wikibase.eq_ignore_ascii_case(crate::configuration::MEDIAINFO_API_KEY) {
+                self.page_params.config().get_default_api().to_string()
+            } else {
+                wikibase.to_lowercase()
+            };
+        self.wb_api = match self.page_params.config().get_wbapi(&entity_wikibase) {
             Some(api) => api.clone(),
             None => return Err(ListeriaError::SparqlNoConfig(wikibase.to_string()).into()),
         };
@@ -256,10 +426,12 @@ impl ListeriaList {
         &self.language
     }
 
-    pub async fn cache_local_pages_exist(&self, pages: &[String]) -> Vec<(String, bool)> {
+    pub async fn cache_local_pages_exist(&self, pages: &[String]) -> Vec<(String, LocalPage)> {
         let params: HashMap<String, String> = [
             ("action", "query"),
-            ("prop", ""),
+            ("prop", "pageprops"),
+            ("ppprop", "disambiguation"),
+            ("redirects", "1"),
             ("titles", pages.join("|").as_str()),
         ]
         .into_iter()
@@ -269,37 +441,63 @@ impl ListeriaList {
         let Ok(result) = self.page_params.mw_api().get_query_api_json(&params).await else {
             return Vec::new();
         };
-
-        // Only the set of known titles is needed (the "from" side of any
-        // normalization entry is never read), so store them in a HashSet.
-        let mut known_titles: HashSet<&str> = pages.iter().map(String::as_str).collect();
-        if let Some(query_normalized) = result["query"]["normalized"].as_array() {
-            for n in query_normalized {
-                if let Some(to) = n["to"].as_str() {
-                    known_titles.insert(to);
-                }
-            }
-        }
-
-        result["query"]["pages"]
+        Self::local_pages_from_query(pages, &result)
+    }
+
+    /// The requested `pages` in a `redirects=1` query result, by normalised
+    /// title. A redirect gets the status of its target.
+    fn local_pages_from_query(pages: &[String], result: &Value) -> Vec<(String, LocalPage)> {
+        let query = &result["query"];
+        let normalized: HashMap<&str, &str> = query["normalized"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|n| Some((n["from"].as_str()?, n["to"].as_str()?)))
+            .collect();
+        let redirects: HashMap<&str, String> = query["redirects"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|r| {
+                let to = r["to"].as_str()?;
+                let target = match r["tofragment"].as_str() {
+                    Some(fragment) => format!("{to}#{fragment}"),
+                    None => to.to_string(),
+                };
+                Some((r["from"].as_str()?, target))
+            })
+            .collect();
+        let statuses: HashMap<&str, LocalPageStatus> = query["pages"]
             .as_object()
-            .map(|obj| {
-                obj.iter()
-                    .filter_map(|(_k, v)| {
-                        v["title"].as_str().and_then(|title| {
-                            known_titles.contains(title).then(|| {
-                                let page_exists = v["missing"].as_str().is_none();
-                                (title.to_string(), page_exists)
-                            })
-                        })
-                    })
-                    .collect()
+            .into_iter()
+            .flatten()
+            .filter_map(|(_k, v)| Some((v["title"].as_str()?, LocalPageStatus::from_page_json(v))))
+            .collect();
+
+        let mut ret: Vec<(String, LocalPage)> = pages
+            .iter()
+            .filter_map(|page| {
+                let title = normalized.get(page.as_str()).copied().unwrap_or(page);
+                let redirect = redirects.get(title).cloned();
+                let target = redirect
+                    .as_deref()
+                    .map_or(title, |r| r.split_once('#').map_or(r, |(target, _)| target));
+                let status = *statuses.get(target)?;
+                Some((title.to_string(), LocalPage { status, redirect }))
             })
-            .unwrap_or_default()
+            .collect();
+        ret.sort_by(|a, b| a.0.cmp(&b.0));
+        ret.dedup_by(|a, b| a.0 == b.0);
+        ret
     }
 
     pub fn local_page_exists(&self, page: &str) -> bool {
-        *self.state.local_page_cache.get(page).unwrap_or(&false)
+        self.local_page(page)
+            .is_some_and(|local_page| local_page.status != LocalPageStatus::Missing)
+    }
+
+    pub fn local_page(&self, page: &str) -> Option<&LocalPage> {
+        self.state.local_page_cache.get(page)
     }
 
     /// Test-only helper that uses the first-letter (case-insensitive)
@@ -330,8 +528,8 @@ impl ListeriaList {
         let entity_id = entity_id.unwrap_or_default();
         let label = label.unwrap_or_else(|| entity_id.clone());
         self.page_params
-            .config()
-            .get_location_template(self.page_params.wiki())
+            .settings()
+            .location_template
             .replace("$LAT$", &Self::format_coordinate(lat))
             .replace("$LON$", &Self::format_coordinate(lon))
             .replace("$ITEM$", &entity_id)
@@ -340,7 +538,7 @@ impl ListeriaList {
     }
 
     pub fn thumbnail_size(&self) -> u64 {
-        let default = self.page_params.config().default_thumbnail_size();
+        let default = self.page_params.settings().thumbnail_size;
         self.template
             .get_value("thumb")
             .and_then(|s| s.parse::<u64>().ok())
@@ -375,7 +573,7 @@ impl ListeriaList {
     ///   (`EntityContainerWrapper::use_local_links`); with no entities loaded
     ///   every row is silently dropped.
     /// - `LinksType::RedOnly` filters rows via the entity's sitelinks in
-    ///   `ListProcessor::find_keep_flags`; same silent-empty failure mode.
+    ///   `ListProcessor::find_local_sitelinks`; same silent-empty failure mode.
     ///
     /// `Red`, `Text` and `Reasonator` only consult the entity cache when an
     /// `Entity` part has `try_localize=true`, which Item/Field/Number columns
@@ -401,6 +599,13 @@ impl ListeriaList {
             return Err(ListeriaError::NoItemsToShow.into());
         }
         self.ecw.load_entities(&self.wb_api, &ids).await?;
+        let mut unloaded_items = 0;
+        for id in &ids {
+            if self.ecw.get_entity(id).await.is_none() && self.ecw.get_lexeme(id).is_none() {
+                unloaded_items += 1;
+            }
+        }
+        self.state.unloaded_items = unloaded_items;
 
         self.label_columns().await;
 
@@ -462,7 +667,7 @@ impl ListeriaList {
         self.gather_and_load_items().await?;
         self.profile("AFTER list::process_results gather_and_load_items")
             .await;
-        let flags = *self.page_params.config().feature_flags();
+        let flags = self.page_params.settings().feature_flags;
         if flags.enable_autodesc {
             ListProcessor::fill_autodesc(self).await?;
             self.profile("AFTER list::process_results fill_autodesc")
@@ -505,6 +710,11 @@ impl ListeriaList {
         ListProcessor::fix_local_links(self)?;
         self.profile("AFTER list::process_results fix_local_links")
             .await;
+        if flags.enable_references {
+            ListProcessor::process_reference_ids(self).await;
+            self.profile("AFTER list::process_results process_reference_ids")
+                .await;
+        }
         self.profile("END list::process_results").await;
         Ok(())
     }
@@ -644,17 +854,41 @@ impl ListeriaList {
     }
 
     pub async fn get_label_with_fallback(&self, entity_id: &str) -> String {
-        self.ecw
-            .get_entity_label_with_fallback(entity_id, self.language())
+        self.get_label_with_fallback_lang(entity_id, self.language())
             .await
     }
 
     pub async fn get_label_with_fallback_lang(&self, entity_id: &str, language: &str) -> String {
         self.ecw
-            .get_entity_label_with_fallback(entity_id, language)
+            .get_entity_label_with_fallback(entity_id, &self.language_chain(language))
             .await
     }
 
+    /// Fallback chain for `language`, trying the wiki's default language
+    /// right after the language's own fallbacks.
+    pub fn language_chain(&self, language: &str) -> Vec<String> {
+        let default_language = &self.page_params.settings().default_language;
+        self.page_params
+            .config()
+            .language_fallbacks()
+            .chain_with_default(language, Some(default_language))
+    }
+
+    /// Marks `text` as taken from `value_language` when that is not the
+    /// `requested` language, unless the wiki disabled fallback marking.
+    pub fn mark_language_fallback(
+        &self,
+        text: &str,
+        value_language: &str,
+        requested: &str,
+    ) -> String {
+        if self.page_params.settings().mark_language_fallbacks {
+            LanguageFallbacks::mark(text, value_language, requested)
+        } else {
+            text.to_string()
+        }
+    }
+
     pub fn is_main_wikibase_wiki(&self) -> bool {
         if self.page_params.config().is_single_wiki() {
             true
@@ -673,6 +907,21 @@ impl ListeriaList {
         if entity_id.starts_with('p') || entity_id.starts_with('P') {
             return format!("{prefix}Property:{entity_id}");
         }
+        if crate::mediainfo::is_mediainfo_id(entity_id) {
+            return if self.page_params.wiki() == "commonswiki" {
+                format!("Special:EntityPage/{entity_id}")
+            } else {
+                format!(":c:Special:EntityPage/{entity_id}")
+            };
+        }
+        // Forms and senses are sections of their lexeme's page
+        if let Some(lexeme_id) = crate::lexeme::lexeme_base_id(entity_id) {
+            return if lexeme_id == entity_id {
+                format!("{prefix}Lexeme:{entity_id}")
+            } else {
+                format!("{prefix}Lexeme:{lexeme_id}#{entity_id}")
+            };
+        }
         format!("{prefix}{entity_id}")
     }
 
@@ -705,7 +954,7 @@ impl ListeriaList {
             .map(|&x| x.clone())
             .collect();
 
-        if self.page_params.config().prefer_preferred() {
+        if self.page_params.settings().prefer_preferred {
             let has_preferred = ret.iter().any(|x| *x.rank() == StatementRank::Preferred);
             if has_preferred {
                 ret.retain(|x| *x.rank() == StatementRank::Preferred);
@@ -733,8 +982,34 @@ impl ListeriaList {
             .await
     }
 
+    /// Formats a bot-generated string in the list's language.
+    pub fn message(&self, message: Message, args: &[&str]) -> String {
+        self.messages.format(message, &self.language, args)
+    }
+
     pub fn default_language(&self) -> String {
-        self.page_params.config().default_language().to_string()
+        self.page_params.settings().default_language.clone()
+    }
+
+    /// `date_format=` for the column (or the whole list), else the wiki default.
+    pub fn date_format(&self, colnum: usize) -> DateFormat {
+        let column = self.columns.get(colnum).map(Column::obj);
+        self.params
+            .date_format()
+            .for_column(column)
+            .unwrap_or(self.page_params.settings().date_format)
+    }
+
+    /// `images=` options for the column (and the whole list).
+    pub fn image_options(&self, colnum: usize) -> ImageOptions {
+        let column = self.columns.get(colnum).map(Column::obj);
+        self.params.images().for_column(column)
+    }
+
+    pub fn annotate_julian_dates(&self) -> bool {
+        self.params
+            .julian()
+            .unwrap_or(self.page_params.settings().annotate_julian_dates)
     }
 
     pub const fn template_params(&self) -> &TemplateParams {
@@ -755,6 +1030,22 @@ impl crate::render_context::RenderContext for ListeriaList {
         ListeriaList::default_language(self)
     }
 
+    fn language_chain(&self, language: &str) -> Vec<String> {
+        ListeriaList::language_chain(self, language)
+    }
+
+    fn date_format(&self, colnum: usize) -> DateFormat {
+        ListeriaList::date_format(self, colnum)
+    }
+
+    fn annotate_julian_dates(&self) -> bool {
+        ListeriaList::annotate_julian_dates(self)
+    }
+
+    fn mark_language_fallback(&self, text: &str, value_language: &str, requested: &str) -> String {
+        ListeriaList::mark_language_fallback(self, text, value_language, requested)
+    }
+
     fn page_title(&self) -> &str {
         ListeriaList::page_title(self)
     }
@@ -793,6 +1084,10 @@ impl crate::render_context::RenderContext for ListeriaList {
         ListeriaList::thumbnail_size(self)
     }
 
+    fn image_options(&self, colnum: usize) -> ImageOptions {
+        ListeriaList::image_options(self, colnum)
+    }
+
     fn local_file_namespace_prefix(&self) -> &str {
         ListeriaList::local_file_namespace_prefix(self)
     }
@@ -805,18 +1100,26 @@ impl crate::render_context::RenderContext for ListeriaList {
         ListeriaList::results(self)
     }
 
-    fn reference_ids(&self) -> &std::collections::HashSet<String> {
+    fn reference_ids(&self) -> &HashMap<String, (usize, usize)> {
         ListeriaList::reference_ids(self)
     }
 
-    fn get_wiki(&self) -> Option<crate::wiki::Wiki> {
-        ListeriaList::get_wiki(self)
+    fn reference_definitions(&self) -> &[(String, String)] {
+        ListeriaList::reference_definitions(self)
+    }
+
+    fn citation_templates(&self) -> &crate::citation_templates::CitationTemplates {
+        &self.page_params.settings().citation_templates
     }
 
     fn get_item_wiki_target(&self, entity_id: &str) -> String {
         ListeriaList::get_item_wiki_target(self, entity_id)
     }
 
+    fn local_page(&self, page: &str) -> Option<&LocalPage> {
+        ListeriaList::local_page(self, page)
+    }
+
     fn get_location_template(
         &self,
         lat: f64,
@@ -868,6 +1171,10 @@ impl crate::render_context::RenderContext for ListeriaList {
         ListeriaList::section_name(self, id)
     }
 
+    fn message(&self, message: crate::messages::Message, args: &[&str]) -> String {
+        ListeriaList::message(self, message, args)
+    }
+
     async fn get_entity(
         &self,
         entity_id: &str,
@@ -921,6 +1228,75 @@ mod tests {
         assert_eq!(ListeriaList::normalize_page_title(""), "");
     }
 
+    // --- LocalPageStatus ---
+
+    #[test]
+    fn test_local_page_status_from_page_json() {
+        let status = |j| LocalPageStatus::from_page_json(&j);
+        assert_eq!(
+            status(serde_json::json!({"ns": 0, "title": "Foo", "missing": ""})),
+            LocalPageStatus::Missing
+        );
+        assert_eq!(
+            status(serde_json::json!({"pageid": 1, "ns": 0, "title": "Foo"})),
+            LocalPageStatus::Exists
+        );
+        assert_eq!(
+            status(serde_json::json!({
+                "pageid": 2, "ns": 0, "title": "Mercury",
+                "pageprops": {"disambiguation": ""}
+            })),
+            LocalPageStatus::Disambiguation
+        );
+    }
+
+    #[test]
+    fn test_local_pages_from_query_resolves_redirects() {
+        let result = serde_json::json!({"query": {
+            "normalized": [{"from": "mercury", "to": "Mercury"}],
+            "redirects": [
+                {"from": "Mercury", "to": "Mercury (disambiguation)"},
+                {"from": "Old name", "to": "Town", "tofragment": "History"}
+            ],
+            "pages": {
+                "-1": {"ns": 0, "title": "Nowhere", "missing": ""},
+                "1": {"pageid": 1, "ns": 0, "title": "Mercury (disambiguation)",
+                      "pageprops": {"disambiguation": ""}},
+                "2": {"pageid": 2, "ns": 0, "title": "Town"}
+            }
+        }});
+        let pages = ["mercury", "Old name", "Nowhere"].map(str::to_string);
+        let local_pages = ListeriaList::local_pages_from_query(&pages, &result);
+        assert_eq!(
+            local_pages,
+            vec![
+                (
+                    "Mercury".to_string(),
+                    LocalPage {
+                        status: LocalPageStatus::Disambiguation,
+                        redirect: Some("Mercury (disambiguation)".to_string()),
+                    }
+                ),
+                (
+                    "Nowhere".to_string(),
+                    LocalPage {
+                        status: LocalPageStatus::Missing,
+                        redirect: None,
+                    }
+                ),
+                (
+                    "Old name".to_string(),
+                    LocalPage {
+                        status: LocalPageStatus::Exists,
+                        redirect: Some("Town#History".to_string()),
+                    }
+                ),
+            ]
+        );
+        assert!(local_pages[2].1.redirects_to_section());
+        assert!(!local_pages[0].1.redirects_to_section());
+    }
+
     #[test]
     fn test_normalize_page_title_two_chars() {
         assert_eq!(ListeriaList::normalize_page_title("ab"), "Ab");
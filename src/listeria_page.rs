@@ -6,7 +6,7 @@ use futures::future::try_join_all;
 use std::sync::Arc;
 
 use crate::{
-    ApiArc, configuration::Configuration, page_element::PageElement,
+    ApiArc, configuration::Configuration, listeria_error::ListeriaError, page_element::PageElement,
     page_operations::PageOperations, page_params::PageParams, render_wikitext::RendererWikitext,
     renderer::Renderer, wiki_page_result::WikiPageResult,
 };
@@ -71,36 +71,76 @@ impl ListeriaPage {
         let title = wikimisc::mediawiki::title::Title::new_from_full(self.page_params.page(), api);
         if self
             .page_params
-            .config()
-            .can_edit_namespace(self.page_params.wiki(), title.namespace_id())
+            .settings()
+            .can_edit_namespace(title.namespace_id())
         {
             Ok(())
         } else {
-            Err(anyhow!(
-                "Namespace {} not allowed for edit on {}",
-                title.namespace_id(),
-                self.page_params.wiki()
-            ))
+            Err(ListeriaError::NamespaceNotAllowed {
+                namespace: title.namespace_id(),
+                wiki: self.page_params.wiki().to_string(),
+            }
+            .into())
         }
     }
 
     pub async fn run(&mut self) -> Result<(), WikiPageResult> {
-        self.check_namespace()
-            .map_err(|e| self.fail(&e.to_string()))?;
+        self.check_namespace().map_err(|e| self.fail(&e))?;
         self.elements = PageOperations::load_page(self).await?;
 
         let mut promises = Vec::new();
         for element in &mut self.elements {
             promises.push(element.process());
         }
-        let _ = try_join_all(promises)
-            .await
-            .map_err(|e| self.fail(&e.to_string()))?;
+        let _ = try_join_all(promises).await.map_err(|e| self.fail(&e))?;
         Ok(())
     }
 
-    fn fail(&self, message: &str) -> WikiPageResult {
-        WikiPageResult::fail(self.wiki(), self.page_params.page(), message)
+    /// IDs of the entities listed on the page, sorted and without duplicates.
+    #[must_use]
+    pub fn entity_ids(&self) -> Vec<String> {
+        let mut ret: Vec<String> = self
+            .elements
+            .iter()
+            .flat_map(PageElement::entity_ids)
+            .collect();
+        ret.sort();
+        ret.dedup();
+        ret
+    }
+
+    /// How many query result IDs on the page are redirects to other
+    /// entities; editors may want to update their queries.
+    #[must_use]
+    pub fn redirected_items(&self) -> usize {
+        self.elements
+            .iter()
+            .map(PageElement::redirected_items)
+            .sum()
+    }
+
+    /// Warnings about the template parameters of all lists on the page.
+    #[must_use]
+    pub fn param_warnings(&self) -> Vec<String> {
+        self.elements
+            .iter()
+            .flat_map(PageElement::param_warnings)
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    /// Errors of lists that failed but were shown on the page with their
+    /// last good version (see `diagnostics=`).
+    #[must_use]
+    pub fn list_errors(&self) -> Vec<String> {
+        self.elements
+            .iter()
+            .filter_map(|element| element.error().map(str::to_string))
+            .collect()
+    }
+
+    fn fail(&self, error: &anyhow::Error) -> WikiPageResult {
+        WikiPageResult::from_error(self.wiki(), self.page_params.page(), error)
     }
 
     pub async fn load_page_as(&self, mode: &str) -> Result<String, WikiPageResult> {
@@ -164,7 +204,10 @@ impl ListeriaPage {
         let new_wikitext = renderer
             .get_new_wikitext(&old_wikitext, self)
             .await
-            .map_err(|e| self.fail(&e.to_string()))?; // Safe
+            .map_err(|e| match ListeriaError::from_anyhow(&e) {
+                Some(_) => self.fail(&e),
+                None => self.fail(&ListeriaError::Render(e.to_string()).into()),
+            })?; // Safe
         if let Some(new_wikitext) = new_wikitext
             && old_wikitext != new_wikitext
         {
@@ -175,7 +218,7 @@ impl ListeriaPage {
                 basetimestamp.as_deref(),
             )
             .await
-            .map_err(|e| self.fail(&e.to_string()))?;
+            .map_err(|e| self.fail(&e))?;
             edited = true;
         }
 
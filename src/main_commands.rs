@@ -1,6 +1,10 @@
 //! CLI command implementations for the bot's operation modes.
 
+use crate::fair_scheduler::AllWikisAtCap;
+use crate::recent_changes::RecentChangesWatcher;
+use crate::shutdown::Shutdown;
 use crate::status_server::{AppState, StatusServer};
+use crate::storage::{Storage, normalize_item_ids};
 use crate::wiki_page_result::WikiPageResult;
 use crate::{
     configuration::Configuration, entity_container_wrapper::EntityContainerWrapper,
@@ -11,13 +15,17 @@ use anyhow::{Result, anyhow};
 use std::collections::HashMap;
 use std::fs::read_to_string;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tokio::sync::{RwLock, Semaphore};
+use tokio::task::JoinSet;
 use tracing::Instrument;
 use wikimisc::{seppuku::Seppuku, wikibase::EntityTrait};
 
 const MAX_INACTIVITY_BEFORE_SEPPUKU_SEC: u64 = 300;
 
+/// Pause of the multi-wiki dispatcher when no page can be started.
+const NO_PAGE_DELAY: Duration = Duration::from_secs(1);
+
 #[derive(Debug, Clone)]
 pub struct MainCommands {
     pub config: Arc<Configuration>,
@@ -33,6 +41,9 @@ impl MainCommands {
         let mw_api = Arc::new(mw_api);
         let mut page = ListeriaPage::new(self.config.clone(), mw_api, page_title.into()).await?;
         page.run().await.map_err(|e| anyhow!("{e:?}"))?;
+        for warning in page.param_warnings() {
+            log::warn!("{page_title}: {warning}");
+        }
 
         Ok(
             match page
@@ -103,31 +114,96 @@ impl MainCommands {
         Ok(())
     }
 
+    /// Prints the pages listing any of `items`, one `wiki<TAB>page` per line.
+    pub async fn pages_for_items(&self, items: &[String]) -> Result<()> {
+        let items = normalize_item_ids(items);
+        for (wiki, page) in self.config.storage()?.get_pages_for_items(&items).await? {
+            println!("{wiki}\t{page}");
+        }
+        Ok(())
+    }
+
+    /// Moves the pages listing any of `items` to the front of the queue, e.g.
+    /// after those items were edited or merged (pass both IDs of a merge).
+    pub async fn prioritize_items(&self, items: &[String]) -> Result<()> {
+        let items = normalize_item_ids(items);
+        let pages = self
+            .config
+            .storage()?
+            .prioritize_pages_for_items(&items)
+            .await?;
+        for (wiki, page) in &pages {
+            println!("{wiki}\t{page}");
+        }
+        log::info!("Prioritized {} page(s)", pages.len());
+        Ok(())
+    }
+
     pub async fn run_wikidata_bot(&self) -> Result<()> {
+        self.config.storage()?.check_schema().await?;
         let config = Arc::new((*self.config).clone());
         let bot = ListeriaBotWikidata::new_from_config(config).await?;
+        if let Some(port) = self.config.status_server_port() {
+            let state = AppState {
+                pages: Arc::new(RwLock::new(HashMap::new())),
+                started: Instant::now(),
+                wiki_page_pattern: None,
+                storage: self.config.storage().ok().cloned(),
+                scheduler: Some(bot.scheduler()),
+            };
+            tokio::spawn(async move {
+                if let Err(e) = StatusServer::run(port, state).await {
+                    log::error!("Status server error: {e}");
+                }
+            });
+        }
         let max_threads = bot.config().max_threads();
         log::info!("Starting {max_threads} bots");
         let _ = bot.reset_running().await;
         let _ = bot.clear_deleted().await;
         let _ = bot.clear_deferred().await;
         let _ = bot.clear_log_table().await;
+        if bot.config().recent_changes().enabled {
+            let config = Arc::new(bot.config().clone());
+            let watcher = RecentChangesWatcher::new(config.clone(), config.storage()?.clone());
+            tokio::spawn(watcher.run_multi(bot.wiki_apis()));
+        }
 
         let bot = Arc::new(bot);
         static THREADS_SEMAPHORE: Semaphore = Semaphore::const_new(0);
         THREADS_SEMAPHORE.add_permits(max_threads);
+        let shutdown = Shutdown::on_signal();
+        let scheduler = bot.scheduler();
+        let mut tasks = JoinSet::new();
         let seppuku = Seppuku::new(MAX_INACTIVITY_BEFORE_SEPPUKU_SEC);
         seppuku.arm();
-        loop {
+        while !shutdown.is_requested() {
+            while tasks.try_join_next().is_some() {}
+            if let Err(e) = bot.recover_deferred().await {
+                log::warn!("Could not recover deferred pages: {e}");
+            }
             let page = match bot.prepare_next_single_page().await {
                 Ok(page) => page,
+                Err(e) if e.downcast_ref::<AllWikisAtCap>().is_some() => {
+                    log::debug!("{e}, waiting for a page to finish");
+                    tokio::select! {
+                        () = scheduler.wait_for_finish() => {}
+                        () = shutdown.requested() => {}
+                    }
+                    continue;
+                }
                 Err(e) => {
                     log::warn!("Trying to get next page to process: {e}");
+                    tokio::time::sleep(NO_PAGE_DELAY).await;
                     continue;
                 }
             };
 
-            let permit = THREADS_SEMAPHORE.acquire().await?;
+            // The page is released with the others if no thread frees up
+            let permit = tokio::select! {
+                permit = THREADS_SEMAPHORE.acquire() => permit?,
+                () = shutdown.requested() => break,
+            };
             log::info!(
                 "Starting new bot, {} running, {} available",
                 max_threads - THREADS_SEMAPHORE.available_permits(),
@@ -149,7 +225,7 @@ impl MainCommands {
             let pagestatus_id = page.id();
             let page_title = page.title().to_string();
             let page_wiki = page.wiki().to_string();
-            tokio::spawn(
+            tasks.spawn(
                 async move {
                     let start_time = Instant::now();
                     match tokio::time::timeout(page_timeout, bot.run_single_bot(page)).await {
@@ -177,6 +253,34 @@ impl MainCommands {
                 .instrument(span),
             );
         }
+
+        seppuku.disarm();
+        Self::drain_wikidata_bot(&bot, tasks).await
+    }
+
+    /// Gives the running pages the shutdown grace period to finish, then
+    /// stops the rest and puts them back into the queue.
+    async fn drain_wikidata_bot(bot: &ListeriaBotWikidata, mut tasks: JoinSet<()>) -> Result<()> {
+        let grace = bot.config().shutdown_grace();
+        log::info!(
+            "Waiting up to {}s for {} running page(s)",
+            grace.as_secs(),
+            tasks.len()
+        );
+        if tokio::time::timeout(grace, async { while tasks.join_next().await.is_some() {} })
+            .await
+            .is_err()
+        {
+            log::warn!(
+                "Stopping {} page(s) that did not finish in time",
+                tasks.len()
+            );
+            tasks.shutdown().await;
+        }
+        bot.release_all_running().await?;
+        bot.flush().await?;
+        log::info!("Shut down");
+        Ok(())
     }
 
     pub async fn run_single_wiki_bot(&self, once: bool) -> Result<()> {
@@ -184,6 +288,8 @@ impl MainCommands {
             pages: Arc::new(RwLock::new(HashMap::new())),
             started: Instant::now(),
             wiki_page_pattern: self.config.wiki_page_pattern(),
+            storage: self.config.storage().ok().cloned(),
+            scheduler: None,
         };
         if let Some(port) = self.config.status_server_port() {
             let state_clone = state.clone();
@@ -195,9 +301,26 @@ impl MainCommands {
         }
         let config = Arc::new((*self.config).clone());
         let bot = ListeriaBotSingle::new_from_config(config).await?;
+        if let Ok(storage) = self.config.storage() {
+            storage.check_schema().await?;
+        }
+        let _ = bot.reset_running().await;
+        let _ = bot.clear_deleted().await;
+        let _ = bot.clear_deferred().await;
+        if self.config.recent_changes().enabled
+            && let (Ok(storage), Ok(api)) =
+                (self.config.storage(), self.config.get_default_wbapi())
+        {
+            let watcher = RecentChangesWatcher::new(self.config.clone(), storage.clone());
+            tokio::spawn(watcher.run_single("wiki", api.clone()));
+        }
+        let shutdown = Shutdown::on_signal();
         let seppuku = Seppuku::new(MAX_INACTIVITY_BEFORE_SEPPUKU_SEC);
         seppuku.arm();
-        loop {
+        while !shutdown.is_requested() {
+            if let Err(e) = bot.recover_deferred().await {
+                log::warn!("Could not recover deferred pages: {e}");
+            }
             let page = match bot.prepare_next_single_page().await {
                 Ok(page) => page,
                 Err(_error) => {
@@ -205,7 +328,7 @@ impl MainCommands {
                         if !bot.config().quiet() {
                             log::info!("All pages processed");
                         }
-                        return Ok(());
+                        break;
                     }
                     if !bot.config().quiet() {
                         log::info!("All pages processed, restarting from beginning");
@@ -224,12 +347,19 @@ impl MainCommands {
                 page = %page.title(),
             );
             let page_timeout = bot.config().page_timeout();
-            let mut result = match tokio::time::timeout(
+            let run = tokio::time::timeout(
                 page_timeout,
                 bot.run_single_bot(page.clone()).instrument(span),
-            )
-            .await
-            {
+            );
+            let outcome = tokio::select! {
+                outcome = run => outcome,
+                () = shutdown.grace_expired(bot.config().shutdown_grace()) => {
+                    log::warn!("Stopping {}, which did not finish in time", page.title());
+                    bot.release_page(&page).await?;
+                    break;
+                }
+            };
+            let mut result = match outcome {
                 Ok(Ok(result)) => result,
                 Ok(Err(e)) => WikiPageResult::new("wiki", page.title(), "Error", e.to_string()),
                 Err(_) => WikiPageResult::new(
@@ -244,6 +374,16 @@ impl MainCommands {
             };
             let end_time = Instant::now();
             let diff = end_time - start_time;
+            if result.result() == "Error" {
+                // run_single_bot did not get to record the outcome
+                if let Err(e) = bot
+                    .mark_page_failed(page.wiki(), page.title(), result.message())
+                    .await
+                {
+                    log::error!("Failed to mark page FAIL: {e}");
+                }
+            }
+            let _ = bot.set_runtime(page.id(), diff.as_secs()).await;
             result.set_runtime(diff);
             result.set_completed(Instant::now());
             state
@@ -253,10 +393,16 @@ impl MainCommands {
                 .insert(page.title().to_string(), result);
             if let Some(seconds) = bot.config().delay_after_page_check_sec() {
                 seppuku.disarm();
-                tokio::time::sleep(std::time::Duration::from_secs(seconds)).await;
+                tokio::select! {
+                    () = tokio::time::sleep(Duration::from_secs(seconds)) => {}
+                    () = shutdown.requested() => {}
+                }
                 seppuku.arm();
             }
         }
+        seppuku.disarm();
+        bot.flush().await?;
+        Ok(())
     }
 }
 
@@ -572,6 +718,14 @@ mod tests {
         assert!(Arc::ptr_eq(&main_commands.config, &config));
     }
 
+    #[tokio::test]
+    async fn test_item_commands_need_storage() {
+        let main_commands = create_main_commands(Arc::new(Configuration::default()));
+        let items = ["Q42".to_string()];
+        assert!(main_commands.pages_for_items(&items).await.is_err());
+        assert!(main_commands.prioritize_items(&items).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_mock_server_setup() {
         // Basic test to ensure wiremock is working
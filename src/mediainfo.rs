@@ -0,0 +1,174 @@
+//! Commons structured data: MediaInfo entities (`M…`), one per file.
+//!
+//! MediaInfo JSON differs from item JSON in three ways: statements are under
+//! `statements` rather than `claims`, snaks carry no `datatype` (the
+//! properties live on Wikidata), and the captions take the place of labels.
+//! [`entity_json`] rewrites it into item shape so that the entity model, and
+//! with it every property column, can use it unchanged.
+
+use serde_json::{Map, Value, json};
+
+/// Whether `id` is a MediaInfo ID (`M123`).
+#[must_use]
+pub fn is_mediainfo_id(id: &str) -> bool {
+    id.strip_prefix('M')
+        .is_some_and(|digits| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// The snak datatype implied by a datavalue type, for snaks without one.
+fn datatype_for_value_type(value_type: &str) -> Option<&'static str> {
+    Some(match value_type {
+        "wikibase-entityid" => "wikibase-item",
+        "string" => "string",
+        "time" => "time",
+        "quantity" => "quantity",
+        "monolingualtext" => "monolingualtext",
+        "globecoordinate" => "globe-coordinate",
+        _ => return None,
+    })
+}
+
+fn add_datatype(snak: &mut Value) {
+    if snak.get("datatype").is_some() {
+        return;
+    }
+    let datatype = snak["datavalue"]["type"]
+        .as_str()
+        .and_then(datatype_for_value_type)
+        // somevalue/novalue snaks have no datavalue to go by
+        .unwrap_or("string");
+    if let Some(o) = snak.as_object_mut() {
+        o.insert("datatype".to_string(), json!(datatype));
+    }
+}
+
+/// Applies [`add_datatype`] to every snak in a `{"P1": [snak, …]}` map.
+fn add_datatypes_to_snak_map(snaks: &mut Value) {
+    if let Some(o) = snaks.as_object_mut() {
+        o.values_mut()
+            .filter_map(Value::as_array_mut)
+            .flatten()
+            .for_each(add_datatype);
+    }
+}
+
+fn add_datatypes_to_statement(statement: &mut Value) {
+    add_datatype(&mut statement["mainsnak"]);
+    add_datatypes_to_snak_map(&mut statement["qualifiers"]);
+    if let Some(references) = statement["references"].as_array_mut() {
+        for reference in references {
+            add_datatypes_to_snak_map(&mut reference["snaks"]);
+        }
+    }
+}
+
+/// MediaInfo JSON from `wbgetentities`, rewritten as item JSON; `None` for
+/// anything else, including missing files.
+#[must_use]
+pub fn entity_json(j: &Value) -> Option<Value> {
+    if j["type"].as_str() != Some("mediainfo") {
+        return None;
+    }
+    let id = j["id"].as_str().filter(|id| is_mediainfo_id(id))?;
+    // Files without structured data have `"statements": []`
+    let mut claims = match &j["statements"] {
+        Value::Object(o) => Value::Object(o.clone()),
+        _ => Value::Object(Map::new()),
+    };
+    if let Some(o) = claims.as_object_mut() {
+        o.values_mut()
+            .filter_map(Value::as_array_mut)
+            .flatten()
+            .for_each(add_datatypes_to_statement);
+    }
+    let labels = match &j["labels"] {
+        Value::Object(o) => Value::Object(o.clone()),
+        _ => Value::Object(Map::new()),
+    };
+    Some(json!({
+        "type": "item",
+        "id": id,
+        "labels": labels,
+        "descriptions": {},
+        "aliases": {},
+        "claims": claims,
+        "sitelinks": {}
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mediainfo_json() -> Value {
+        json!({
+            "type": "mediainfo",
+            "id": "M42",
+            "title": "File:Example.jpg",
+            "labels": {"en": {"language": "en", "value": "An example"}},
+            "statements": {
+                "P180": [{
+                    "mainsnak": {
+                        "snaktype": "value",
+                        "property": "P180",
+                        "datavalue": {
+                            "value": {"entity-type": "item", "numeric-id": 146, "id": "Q146"},
+                            "type": "wikibase-entityid"
+                        }
+                    },
+                    "type": "statement",
+                    "id": "M42$1",
+                    "rank": "normal",
+                    "qualifiers": {"P2677": [{
+                        "snaktype": "value",
+                        "property": "P2677",
+                        "datavalue": {"value": "1", "type": "string"}
+                    }]}
+                }],
+                "P170": [{
+                    "mainsnak": {"snaktype": "somevalue", "property": "P170"},
+                    "type": "statement",
+                    "id": "M42$2",
+                    "rank": "normal"
+                }]
+            }
+        })
+    }
+
+    #[test]
+    fn test_is_mediainfo_id() {
+        assert!(is_mediainfo_id("M42"));
+        for id in ["M", "Q42", "m42", "M4x", "L1-F1"] {
+            assert!(!is_mediainfo_id(id), "{id}");
+        }
+    }
+
+    #[test]
+    fn test_entity_json_is_item_shaped() {
+        let j = entity_json(&mediainfo_json()).unwrap();
+        assert_eq!(j["type"], "item");
+        assert_eq!(j["id"], "M42");
+        assert_eq!(j["labels"]["en"]["value"], "An example");
+        assert_eq!(
+            j["claims"]["P180"][0]["mainsnak"]["datatype"],
+            "wikibase-item"
+        );
+        assert_eq!(
+            j["claims"]["P180"][0]["qualifiers"]["P2677"][0]["datatype"],
+            "string"
+        );
+        assert_eq!(j["claims"]["P170"][0]["mainsnak"]["datatype"], "string");
+    }
+
+    #[test]
+    fn test_entity_json_without_statements() {
+        let j = entity_json(&json!({
+            "type": "mediainfo", "id": "M43", "labels": [], "statements": []
+        }))
+        .unwrap();
+        assert_eq!(j["claims"], json!({}));
+        assert_eq!(j["labels"], json!({}));
+        assert!(entity_json(&json!({"id": "M44", "missing": ""})).is_none());
+        assert!(entity_json(&json!({"type": "item", "id": "Q1"})).is_none());
+    }
+}
@@ -0,0 +1,573 @@
+//! Localised strings for text the bot itself writes into wiki pages.
+//!
+//! Everything Listeria generates outside the list data (shadow-file notice,
+//! summary line, the catch-all section heading, the edit summary, …) is
+//! looked up here by page language. For each language in the fallback chain
+//! (see [`crate::language_fallback`]) the lookup consults, in order:
+//!
+//! 1. on-wiki overrides from `MediaWiki:Listeria-<key>` pages (opt-in via
+//!    `load_onwiki_messages`, see [`Messages::load_onwiki`]; kept for an
+//!    hour per wiki and language by [`OnwikiMessages`]),
+//! 2. the `messages` map in the JSON config (`{"de": {"misc": "Sonstiges"}}`),
+//! 3. the built-in translations below.
+//!
+//! If no language in the chain has a text, the English default is used, so a
+//! lookup never fails.
+
+use crate::language_fallback::LanguageFallbacks;
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use wikimisc::mediawiki::api::Api;
+
+/// How long the on-wiki overrides of a wiki are used before they are loaded
+/// again.
+const ONWIKI_MESSAGES_TTL: Duration = Duration::from_secs(3600);
+
+/// A bot-generated string that can be translated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Message {
+    /// Heading for the list of local files hidden because they shadow Commons files.
+    ShadowFilesNotice,
+    /// Cell text for "no value" / "unknown value" snaks.
+    NoValue,
+    /// `summary=ITEMNUMBER` line; `$1` is the row count, `$2` the summary label.
+    SummaryLine,
+    /// Default summary label when the template sets no `summary_label=`.
+    SummaryItems,
+    /// Section heading for rows that fit no other section.
+    MiscSection,
+    /// Edit summary used when saving a page.
+    EditSummary,
+    /// Annotation for dates given in the Julian calendar.
+    JulianCalendar,
+    /// Tooltip on a link that leads to a disambiguation page.
+    DisambiguationLink,
+    /// First line of the box listing problems with a list.
+    DiagnosticsHeading,
+    /// The list could not be updated; `$1` is the error.
+    DiagnosticsFailed,
+    /// `$1` is the column spec.
+    DiagnosticsUnknownColumn,
+    /// `$1` is the parameter, `$2` what it needs to have an effect.
+    DiagnosticsIgnoredParameter,
+    /// `$1` is the number of items that could not be loaded.
+    DiagnosticsUnloadedItems,
+    /// `$1` describes the parameter and what is wrong with it.
+    DiagnosticsParameter,
+    /// `$1` is the `LIMIT` of the query, which the results reached.
+    DiagnosticsTruncatedResults,
+}
+
+impl Message {
+    pub const ALL: [Self; 15] = [
+        Self::ShadowFilesNotice,
+        Self::NoValue,
+        Self::SummaryLine,
+        Self::SummaryItems,
+        Self::MiscSection,
+        Self::EditSummary,
+        Self::JulianCalendar,
+        Self::DisambiguationLink,
+        Self::DiagnosticsHeading,
+        Self::DiagnosticsFailed,
+        Self::DiagnosticsUnknownColumn,
+        Self::DiagnosticsIgnoredParameter,
+        Self::DiagnosticsUnloadedItems,
+        Self::DiagnosticsParameter,
+        Self::DiagnosticsTruncatedResults,
+    ];
+
+    /// Key used in the config map; the on-wiki page is `MediaWiki:Listeria-<key>`.
+    #[must_use]
+    pub const fn key(self) -> &'static str {
+        match self {
+            Self::ShadowFilesNotice => "shadow-files",
+            Self::NoValue => "no-value",
+            Self::SummaryLine => "summary",
+            Self::SummaryItems => "summary-items",
+            Self::MiscSection => "misc",
+            Self::EditSummary => "edit-summary",
+            Self::JulianCalendar => "julian",
+            Self::DisambiguationLink => "disambiguation",
+            Self::DiagnosticsHeading => "diagnostics",
+            Self::DiagnosticsFailed => "diagnostics-failed",
+            Self::DiagnosticsUnknownColumn => "diagnostics-unknown-column",
+            Self::DiagnosticsIgnoredParameter => "diagnostics-ignored-parameter",
+            Self::DiagnosticsUnloadedItems => "diagnostics-unloaded-items",
+            Self::DiagnosticsParameter => "diagnostics-parameter",
+            Self::DiagnosticsTruncatedResults => "diagnostics-truncated-results",
+        }
+    }
+
+    #[must_use]
+    pub fn from_key(key: &str) -> Option<Self> {
+        let key = key.to_lowercase();
+        let key = key.strip_prefix("listeria-").unwrap_or(&key);
+        Self::ALL.into_iter().find(|m| m.key() == key)
+    }
+
+    /// The English text, used when nothing better is available.
+    #[must_use]
+    pub fn default_text(self) -> &'static str {
+        self.builtin("en").unwrap_or_default()
+    }
+
+    fn builtin(self, language: &str) -> Option<&'static str> {
+        let text = match (self, language) {
+            (Self::ShadowFilesNotice, "en") => {
+                "The following local image(s) are not shown in the above list, because they shadow a Commons image of the same name, and might be non-free:"
+            }
+            (Self::ShadowFilesNotice, "de") => {
+                "Die folgenden lokalen Bilder werden in der obigen Liste nicht angezeigt, da sie ein gleichnamiges Bild auf Commons überdecken und möglicherweise nicht frei sind:"
+            }
+            (Self::ShadowFilesNotice, "fr") => {
+                "Les images locales suivantes ne sont pas affichées dans la liste ci-dessus, car elles masquent une image de Commons portant le même nom et pourraient ne pas être libres :"
+            }
+            (Self::ShadowFilesNotice, "es") => {
+                "Las siguientes imágenes locales no se muestran en la lista anterior porque ocultan una imagen de Commons con el mismo nombre y podrían no ser libres:"
+            }
+            (Self::ShadowFilesNotice, "it") => {
+                "Le seguenti immagini locali non sono mostrate nell'elenco qui sopra perché oscurano un'immagine di Commons con lo stesso nome e potrebbero non essere libere:"
+            }
+            (Self::ShadowFilesNotice, "nl") => {
+                "De volgende lokale afbeelding(en) worden niet in de bovenstaande lijst getoond, omdat ze een Commons-afbeelding met dezelfde naam overschaduwen en mogelijk niet vrij zijn:"
+            }
+            (Self::NoValue, "en") => "No/unknown value",
+            (Self::NoValue, "de") => "Kein/unbekannter Wert",
+            (Self::NoValue, "fr") => "Valeur absente ou inconnue",
+            (Self::NoValue, "es") => "Sin valor o valor desconocido",
+            (Self::NoValue, "it") => "Valore assente o sconosciuto",
+            (Self::NoValue, "nl") => "Geen/onbekende waarde",
+            (Self::SummaryLine, "en") => "&sum; $1 $2.",
+            (Self::SummaryItems, "en") => "items",
+            (Self::SummaryItems, "de") => "Einträge",
+            (Self::SummaryItems, "fr") => "éléments",
+            (Self::SummaryItems, "es") => "elementos",
+            (Self::SummaryItems, "it") => "elementi",
+            (Self::SummaryItems, "nl") => "items",
+            (Self::MiscSection, "en") => "Misc",
+            (Self::MiscSection, "de") => "Sonstige",
+            (Self::MiscSection, "fr") => "Divers",
+            (Self::MiscSection, "es") => "Otros",
+            (Self::MiscSection, "it") => "Altro",
+            (Self::MiscSection, "nl") => "Overig",
+            (Self::EditSummary, "en") => "Wikidata list updated [V2]",
+            (Self::EditSummary, "de") => "Wikidata-Liste aktualisiert [V2]",
+            (Self::EditSummary, "fr") => "Liste Wikidata mise à jour [V2]",
+            (Self::EditSummary, "es") => "Lista de Wikidata actualizada [V2]",
+            (Self::EditSummary, "it") => "Lista Wikidata aggiornata [V2]",
+            (Self::EditSummary, "nl") => "Wikidata-lijst bijgewerkt [V2]",
+            (Self::JulianCalendar, "en") => "Julian",
+            (Self::JulianCalendar, "de") => "julianisch",
+            (Self::JulianCalendar, "fr") => "julien",
+            (Self::JulianCalendar, "es") => "juliano",
+            (Self::JulianCalendar, "it") => "giuliano",
+            (Self::JulianCalendar, "nl") => "juliaans",
+            (Self::DisambiguationLink, "en") => "This link leads to a disambiguation page",
+            (Self::DisambiguationLink, "de") => "Dieser Link führt auf eine Begriffsklärungsseite",
+            (Self::DisambiguationLink, "fr") => "Ce lien mène vers une page d'homonymie",
+            (Self::DisambiguationLink, "es") => "Este enlace lleva a una página de desambiguación",
+            (Self::DisambiguationLink, "it") => {
+                "Questo collegamento porta a una pagina di disambiguazione"
+            }
+            (Self::DisambiguationLink, "nl") => "Deze link verwijst naar een doorverwijspagina",
+            (Self::DiagnosticsHeading, "en") => "Listeria found problems with this list:",
+            (Self::DiagnosticsHeading, "de") => "Listeria hat Probleme mit dieser Liste gefunden:",
+            (Self::DiagnosticsHeading, "fr") => {
+                "Listeria a trouvé des problèmes dans cette liste :"
+            }
+            (Self::DiagnosticsHeading, "es") => "Listeria encontró problemas en esta lista:",
+            (Self::DiagnosticsHeading, "it") => "Listeria ha trovato problemi in questo elenco:",
+            (Self::DiagnosticsHeading, "nl") => "Listeria heeft problemen met deze lijst gevonden:",
+            (Self::DiagnosticsFailed, "en") => {
+                "The list could not be updated ($1). It shows the result of the last successful update."
+            }
+            (Self::DiagnosticsFailed, "de") => {
+                "Die Liste konnte nicht aktualisiert werden ($1). Sie zeigt das Ergebnis der letzten erfolgreichen Aktualisierung."
+            }
+            (Self::DiagnosticsFailed, "fr") => {
+                "La liste n'a pas pu être mise à jour ($1). Elle affiche le résultat de la dernière mise à jour réussie."
+            }
+            (Self::DiagnosticsFailed, "es") => {
+                "No se pudo actualizar la lista ($1). Muestra el resultado de la última actualización correcta."
+            }
+            (Self::DiagnosticsFailed, "it") => {
+                "Non è stato possibile aggiornare l'elenco ($1). Mostra il risultato dell'ultimo aggiornamento riuscito."
+            }
+            (Self::DiagnosticsFailed, "nl") => {
+                "De lijst kon niet worden bijgewerkt ($1). Ze toont het resultaat van de laatste geslaagde update."
+            }
+            (Self::DiagnosticsUnknownColumn, "en") => "Unknown column: $1",
+            (Self::DiagnosticsUnknownColumn, "de") => "Unbekannte Spalte: $1",
+            (Self::DiagnosticsUnknownColumn, "fr") => "Colonne inconnue : $1",
+            (Self::DiagnosticsUnknownColumn, "es") => "Columna desconocida: $1",
+            (Self::DiagnosticsUnknownColumn, "it") => "Colonna sconosciuta: $1",
+            (Self::DiagnosticsUnknownColumn, "nl") => "Onbekende kolom: $1",
+            (Self::DiagnosticsIgnoredParameter, "en") => "The parameter $1 is ignored without $2.",
+            (Self::DiagnosticsIgnoredParameter, "de") => "Der Parameter $1 wird ohne $2 ignoriert.",
+            (Self::DiagnosticsIgnoredParameter, "fr") => "Le paramètre $1 est ignoré sans $2.",
+            (Self::DiagnosticsIgnoredParameter, "es") => "El parámetro $1 se ignora sin $2.",
+            (Self::DiagnosticsIgnoredParameter, "it") => "Il parametro $1 viene ignorato senza $2.",
+            (Self::DiagnosticsIgnoredParameter, "nl") => {
+                "De parameter $1 wordt genegeerd zonder $2."
+            }
+            (Self::DiagnosticsUnloadedItems, "en") => {
+                "$1 item(s) could not be loaded; their rows may be missing or incomplete."
+            }
+            (Self::DiagnosticsUnloadedItems, "de") => {
+                "$1 Objekt(e) konnten nicht geladen werden; ihre Zeilen fehlen womöglich oder sind unvollständig."
+            }
+            (Self::DiagnosticsUnloadedItems, "fr") => {
+                "$1 élément(s) n'ont pas pu être chargés ; leurs lignes peuvent manquer ou être incomplètes."
+            }
+            (Self::DiagnosticsUnloadedItems, "es") => {
+                "No se pudieron cargar $1 elemento(s); sus filas pueden faltar o estar incompletas."
+            }
+            (Self::DiagnosticsUnloadedItems, "it") => {
+                "Non è stato possibile caricare $1 elemento/i; le loro righe potrebbero mancare o essere incomplete."
+            }
+            (Self::DiagnosticsUnloadedItems, "nl") => {
+                "$1 item(s) konden niet worden geladen; hun rijen kunnen ontbreken of onvolledig zijn."
+            }
+            (Self::DiagnosticsParameter, "en") => "Template parameter: $1",
+            (Self::DiagnosticsParameter, "de") => "Vorlagenparameter: $1",
+            (Self::DiagnosticsParameter, "fr") => "Paramètre du modèle : $1",
+            (Self::DiagnosticsParameter, "es") => "Parámetro de la plantilla: $1",
+            (Self::DiagnosticsParameter, "it") => "Parametro del template: $1",
+            (Self::DiagnosticsParameter, "nl") => "Sjabloonparameter: $1",
+            (Self::DiagnosticsTruncatedResults, "en") => {
+                "The query returned as many results as its LIMIT $1 allows; the list may be incomplete."
+            }
+            (Self::DiagnosticsTruncatedResults, "de") => {
+                "Die Abfrage lieferte so viele Ergebnisse, wie ihr LIMIT $1 erlaubt; die Liste ist womöglich unvollständig."
+            }
+            (Self::DiagnosticsTruncatedResults, "fr") => {
+                "La requête a renvoyé autant de résultats que son LIMIT $1 le permet ; la liste est peut-être incomplète."
+            }
+            (Self::DiagnosticsTruncatedResults, "es") => {
+                "La consulta devolvió tantos resultados como permite su LIMIT $1; la lista puede estar incompleta."
+            }
+            (Self::DiagnosticsTruncatedResults, "it") => {
+                "La query ha restituito tanti risultati quanti ne consente il suo LIMIT $1; l'elenco potrebbe essere incompleto."
+            }
+            (Self::DiagnosticsTruncatedResults, "nl") => {
+                "De query gaf zoveel resultaten als de LIMIT $1 toestaat; de lijst is mogelijk onvolledig."
+            }
+            _ => return None,
+        };
+        Some(text)
+    }
+}
+
+/// Message overrides by language, layered over the built-in translations.
+#[derive(Debug, Clone, Default)]
+pub struct Messages {
+    /// Overrides loaded from `MediaWiki:Listeria-*` pages; consulted first.
+    onwiki: HashMap<String, HashMap<Message, String>>,
+    /// Overrides from the `messages` config map.
+    configured: HashMap<String, HashMap<Message, String>>,
+    fallbacks: LanguageFallbacks,
+}
+
+impl Messages {
+    /// Parses the `messages` config object: `{ "<lang>": { "<key>": "<text>" } }`.
+    /// Unknown keys and non-string values are skipped with a warning.
+    #[must_use]
+    pub fn from_json(j: &Value) -> Self {
+        let mut ret = Self::default();
+        let Some(languages) = j.as_object() else {
+            return ret;
+        };
+        for (language, entries) in languages {
+            let Some(entries) = entries.as_object() else {
+                log::warn!("Ignoring non-object messages.{language} config block");
+                continue;
+            };
+            for (key, text) in entries {
+                match (Message::from_key(key), text.as_str()) {
+                    (Some(message), Some(text)) => {
+                        ret.configured
+                            .entry(language.to_string())
+                            .or_default()
+                            .insert(message, text.to_string());
+                    }
+                    _ => log::warn!("Ignoring messages.{language}.{key} config entry"),
+                }
+            }
+        }
+        ret
+    }
+
+    /// Loads `MediaWiki:Listeria-*` overrides for `language` from the wiki.
+    ///
+    /// Uses `meta=allmessages`, which only returns text for messages that exist
+    /// on the wiki (Listeria messages are not part of MediaWiki core, so any hit
+    /// is a local override).
+    pub async fn load_onwiki(&mut self, api: &Api, language: &str) -> Result<()> {
+        let names: Vec<String> = Message::ALL
+            .iter()
+            .map(|m| format!("listeria-{}", m.key()))
+            .collect();
+        let params: HashMap<String, String> = [
+            ("action", "query"),
+            ("meta", "allmessages"),
+            ("ammessages", &names.join("|")),
+            ("amlang", language),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let result = api.get_query_api_json(&params).await?;
+        self.add_onwiki_from_json(&result, language);
+        Ok(())
+    }
+
+    fn add_onwiki_from_json(&mut self, result: &Value, language: &str) {
+        let Some(messages) = result["query"]["allmessages"].as_array() else {
+            return;
+        };
+        for m in messages {
+            if m.get("missing").is_some() {
+                continue;
+            }
+            let text = m["*"].as_str().or_else(|| m["content"].as_str());
+            if let (Some(message), Some(text)) =
+                (m["name"].as_str().and_then(Message::from_key), text)
+            {
+                self.onwiki
+                    .entry(language.to_string())
+                    .or_default()
+                    .insert(message, text.to_string());
+            }
+        }
+    }
+
+    /// Uses `fallbacks` instead of the built-in chains when resolving languages.
+    #[must_use]
+    pub fn with_fallbacks(mut self, fallbacks: LanguageFallbacks) -> Self {
+        self.fallbacks = fallbacks;
+        self
+    }
+
+    /// Returns the text for `message` in `language`, walking the fallback chain.
+    #[must_use]
+    pub fn get(&self, message: Message, language: &str) -> String {
+        for lang in self.fallbacks.chain(language) {
+            for layer in [&self.onwiki, &self.configured] {
+                if let Some(text) = layer.get(&lang).and_then(|m| m.get(&message)) {
+                    return text.to_owned();
+                }
+            }
+            if let Some(text) = message.builtin(&lang) {
+                return text.to_string();
+            }
+        }
+        message.default_text().to_string()
+    }
+
+    /// Like [`Messages::get`], replacing `$1`, `$2`, … with `args`.
+    #[must_use]
+    pub fn format(&self, message: Message, language: &str, args: &[&str]) -> String {
+        let mut text = self.get(message, language);
+        // Replace from the highest index down so `$1` never clobbers `$10`
+        for (num, arg) in args.iter().enumerate().rev() {
+            text = text.replace(&format!("${}", num + 1), arg);
+        }
+        text
+    }
+}
+
+/// [`Messages`] with the on-wiki overrides of each wiki and language, so
+/// they are loaded once per TTL rather than for every page. Cheap to share:
+/// pages get an `Arc` of the cached catalogue.
+#[derive(Debug)]
+pub struct OnwikiMessages {
+    ttl: Duration,
+    loaded: Mutex<HashMap<(String, String), (Instant, Arc<Messages>)>>,
+}
+
+impl Default for OnwikiMessages {
+    fn default() -> Self {
+        Self::new(ONWIKI_MESSAGES_TTL)
+    }
+}
+
+impl OnwikiMessages {
+    #[must_use]
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            loaded: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// `base` with the on-wiki overrides of `wiki` in `language`, loaded if
+    /// they are not cached or older than the TTL. If they cannot be loaded,
+    /// `base` alone is used and loading is tried again for the next page.
+    pub async fn get(
+        &self,
+        base: &Messages,
+        api: &Api,
+        wiki: &str,
+        language: &str,
+    ) -> Arc<Messages> {
+        if let Some(messages) = self.cached(wiki, language).await {
+            return messages;
+        }
+        let mut messages = base.clone();
+        if let Err(e) = messages.load_onwiki(api, language).await {
+            log::warn!("Could not load on-wiki messages for {wiki}: {e}");
+            return Arc::new(messages);
+        }
+        let messages = Arc::new(messages);
+        self.insert(wiki, language, messages.clone()).await;
+        messages
+    }
+
+    async fn cached(&self, wiki: &str, language: &str) -> Option<Arc<Messages>> {
+        let loaded = self.loaded.lock().await;
+        let (at, messages) = loaded.get(&(wiki.to_string(), language.to_string()))?;
+        (at.elapsed() < self.ttl).then(|| messages.clone())
+    }
+
+    async fn insert(&self, wiki: &str, language: &str, messages: Arc<Messages>) {
+        self.loaded.lock().await.insert(
+            (wiki.to_string(), language.to_string()),
+            (Instant::now(), messages),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_message_has_english_text() {
+        for message in Message::ALL {
+            assert!(!message.default_text().is_empty(), "{}", message.key());
+        }
+    }
+
+    #[test]
+    fn test_from_key_roundtrip_and_prefix() {
+        for message in Message::ALL {
+            assert_eq!(Message::from_key(message.key()), Some(message));
+        }
+        assert_eq!(Message::from_key("Listeria-misc"), Some(Message::MiscSection));
+        assert_eq!(Message::from_key("unknown"), None);
+    }
+
+    #[test]
+    fn test_get_builtin_language() {
+        let messages = Messages::default();
+        assert_eq!(messages.get(Message::MiscSection, "de"), "Sonstige");
+        assert_eq!(messages.get(Message::MiscSection, "en"), "Misc");
+    }
+
+    #[test]
+    fn test_get_falls_back_to_base_language() {
+        let messages = Messages::default();
+        assert_eq!(messages.get(Message::MiscSection, "de-ch"), "Sonstige");
+    }
+
+    #[test]
+    fn test_get_falls_back_to_english() {
+        let messages = Messages::default();
+        assert_eq!(messages.get(Message::NoValue, "xyz"), "No/unknown value");
+        // Summary line has no translations; every language uses the English pattern
+        assert_eq!(messages.get(Message::SummaryLine, "de"), "&sum; $1 $2.");
+    }
+
+    #[test]
+    fn test_configured_overrides_builtin() {
+        let messages = Messages::from_json(&serde_json::json!({
+            "de": { "misc": "Weitere", "bogus": "x", "no-value": 3 },
+            "sv": "not an object"
+        }));
+        assert_eq!(messages.get(Message::MiscSection, "de"), "Weitere");
+        assert_eq!(messages.get(Message::NoValue, "de"), "Kein/unbekannter Wert");
+        assert_eq!(messages.get(Message::MiscSection, "sv"), "Misc");
+    }
+
+    #[test]
+    fn test_configured_language_beats_builtin_base_language() {
+        let messages = Messages::from_json(&serde_json::json!({
+            "de-ch": { "misc": "Übrige" }
+        }));
+        assert_eq!(messages.get(Message::MiscSection, "de-ch"), "Übrige");
+        assert_eq!(messages.get(Message::MiscSection, "de"), "Sonstige");
+    }
+
+    #[test]
+    fn test_onwiki_overrides_configured() {
+        let mut messages = Messages::from_json(&serde_json::json!({
+            "fr": { "misc": "Autres" }
+        }));
+        messages.add_onwiki_from_json(
+            &serde_json::json!({"query": {"allmessages": [
+                {"name": "listeria-misc", "*": "Divers (local)"},
+                {"name": "listeria-no-value", "missing": ""}
+            ]}}),
+            "fr",
+        );
+        assert_eq!(messages.get(Message::MiscSection, "fr"), "Divers (local)");
+        assert_eq!(
+            messages.get(Message::NoValue, "fr"),
+            "Valeur absente ou inconnue"
+        );
+    }
+
+    #[test]
+    fn test_format_replaces_placeholders() {
+        let messages = Messages::default();
+        assert_eq!(
+            messages.format(Message::SummaryLine, "en", &["42", "items"]),
+            "&sum; 42 items."
+        );
+    }
+
+    #[test]
+    fn test_format_does_not_clobber_double_digit_placeholders() {
+        let messages = Messages::from_json(&serde_json::json!({
+            "en": { "summary": "$1/$10" }
+        }));
+        let args: Vec<String> = (1..=10).map(|i| format!("a{i}")).collect();
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        assert_eq!(
+            messages.format(Message::SummaryLine, "en", &args),
+            "a1/a10"
+        );
+    }
+
+    #[test]
+    fn test_get_follows_language_fallbacks() {
+        let messages = Messages::default();
+        let de = messages.get(Message::MiscSection, "de");
+        assert_eq!(messages.get(Message::MiscSection, "de-ch"), de);
+        assert_eq!(messages.get(Message::MiscSection, "gsw"), de);
+        assert_eq!(
+            messages.get(Message::MiscSection, "zh-hant"),
+            messages.get(Message::MiscSection, "en")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_onwiki_messages_cache_expires() {
+        let messages = Arc::new(Messages::default());
+        let cache = OnwikiMessages::default();
+        cache.insert("dewiki", "de", messages.clone()).await;
+        let cached = cache.cached("dewiki", "de").await.unwrap();
+        assert!(Arc::ptr_eq(&cached, &messages));
+        assert!(cache.cached("dewiki", "en").await.is_none());
+        assert!(cache.cached("enwiki", "de").await.is_none());
+
+        let cache = OnwikiMessages::new(Duration::ZERO);
+        cache.insert("dewiki", "de", messages).await;
+        assert!(cache.cached("dewiki", "de").await.is_none());
+    }
+}
@@ -1,7 +1,9 @@
 //! Wiki page parsing to extract Listeria template blocks.
 
+use crate::diagnostics::{self, Diagnostic};
 use crate::listeria_list::ListeriaList;
 use crate::listeria_page::ListeriaPage;
+use crate::param_validation::ParamWarning;
 use crate::render_wikitext::RendererWikitext;
 use crate::renderer::Renderer;
 use crate::template::Template;
@@ -18,6 +20,8 @@ pub struct PageElement {
     after: String,
     list: ListeriaList,
     is_just_text: bool,
+    /// Why the list failed, if it did and shows diagnostics.
+    error: Option<String>,
 }
 
 impl PageElement {
@@ -94,6 +98,7 @@ impl PageElement {
             },
             list: ListeriaList::new(template, page.page_params()).await.ok()?,
             is_just_text: false,
+            error: None,
         })
     }
 
@@ -136,6 +141,7 @@ impl PageElement {
             after: String::new(),
             list: ListeriaList::new(template, page.page_params()).await?,
             is_just_text: true,
+            error: None,
         })
     }
 
@@ -147,8 +153,24 @@ impl PageElement {
         if self.is_just_text {
             return Ok(String::new());
         }
+        let message = |message, args: &[&str]| self.list.message(message, args);
+        if let Some(error) = &self.error {
+            let diagnostics = [Diagnostic::Failed(error.to_owned())];
+            let old_inside = diagnostics::strip_box(self._inside.trim_start_matches('\n'));
+            return Ok(format!(
+                "{}\n{old_inside}",
+                diagnostics::render_box(&diagnostics, message)
+            ));
+        }
         let mut renderer = RendererWikitext::new();
-        renderer.render(&self.list).await
+        let wikitext = renderer.render(&self.list).await?;
+        if !self.list.show_diagnostics() {
+            return Ok(wikitext);
+        }
+        match diagnostics::render_box(&self.list.diagnostics(), message) {
+            diagnostics_box if diagnostics_box.is_empty() => Ok(wikitext),
+            diagnostics_box => Ok(format!("{diagnostics_box}\n{wikitext}")),
+        }
     }
 
     pub async fn as_wikitext(&mut self) -> Result<String> {
@@ -162,11 +184,57 @@ impl PageElement {
         ))
     }
 
+    /// Processes the list. If that fails and the list shows diagnostics,
+    /// the error is kept for [`Self::new_inside`] rather than returned, so
+    /// the page still gets the error box.
     pub async fn process(&mut self) -> Result<()> {
         if self.is_just_text {
             return Ok(());
         }
-        self.list.process().await
+        match self.list.process().await {
+            Err(e) if self.list.show_diagnostics() && !self.template_end.is_empty() => {
+                self.error = Some(e.to_string());
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Why the list failed, if it did and the error went into the page.
+    #[must_use]
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// IDs of the entities listed in this element, one per row.
+    #[must_use]
+    pub fn entity_ids(&self) -> Vec<String> {
+        if self.is_just_text {
+            return vec![];
+        }
+        self.list
+            .results()
+            .iter()
+            .map(|row| row.entity_id().to_string())
+            .collect()
+    }
+
+    /// How many query result IDs of this element are redirects.
+    #[must_use]
+    pub const fn redirected_items(&self) -> usize {
+        if self.is_just_text {
+            return 0;
+        }
+        self.list.redirected_items()
+    }
+
+    /// Warnings about the list's template parameters.
+    #[must_use]
+    pub fn param_warnings(&self) -> &[ParamWarning] {
+        if self.is_just_text {
+            return &[];
+        }
+        self.list.param_warnings()
     }
 
     #[must_use]
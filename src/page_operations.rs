@@ -1,9 +1,11 @@
 use crate::circuit_breaker::with_breaker;
+use crate::listeria_error::ListeriaError;
 use crate::listeria_page::ListeriaPage;
+use crate::messages::Message;
 use crate::page_element::PageElement;
 use crate::retry::retry_with_backoff;
 use crate::wiki_page_result::WikiPageResult;
-use anyhow::{Result, anyhow};
+use anyhow::Result;
 use std::collections::HashMap;
 use std::time::Duration;
 
@@ -43,12 +45,7 @@ impl PageOperations {
             ret.push(element);
         }
         if ret.iter().any(|e| e.is_missing_end_template()) {
-            return Err(WikiPageResult::new(
-                page.page_params().wiki(),
-                page.page_params().page(),
-                "FAIL",
-                "{{Wikidata list end}} is missing; refusing to update to prevent list duplication (#108)".to_string(),
-            ));
+            return Err(Self::fail_with(page, ListeriaError::MissingEndTemplate));
         }
 
         Ok(ret)
@@ -82,7 +79,7 @@ impl PageOperations {
         let breaker = page.page_params().config().mw_api_circuit_breaker(&wiki);
         let result = with_breaker(
             &breaker,
-            || anyhow!("MW API circuit open for {wiki}"),
+            || ListeriaError::MwApiCircuitOpen(wiki.clone()).into(),
             || async {
                 retry_with_backoff(
                     "load_page_as",
@@ -100,38 +97,21 @@ impl PageOperations {
             },
         )
         .await
-        .map_err(|e| Self::fail(page, &e.to_string()))?;
+        .map_err(|e| WikiPageResult::from_error(page.wiki(), page.page_params().page(), &e))?;
         if let Some(error) = result["error"]["code"].as_str() {
-            match error {
-                "missingtitle" => {
-                    return Err(WikiPageResult::new(
-                        page.page_params().wiki(),
-                        page.page_params().page(),
-                        "DELETED",
-                        "Wiki says this page is missing".to_string(),
-                    ));
-                }
-                "invalid" => {
-                    return Err(WikiPageResult::new(
-                        page.page_params().wiki(),
-                        page.page_params().page(),
-                        "INVALID",
-                        "Wiki says this page has an invalid title".to_string(),
-                    ));
-                }
-                other => {
-                    return Err(WikiPageResult::new(
-                        page.page_params().wiki(),
-                        page.page_params().page(),
-                        "FAIL",
-                        other.to_string(),
-                    ));
-                }
-            }
+            let error = match error {
+                "missingtitle" => ListeriaError::PageMissing,
+                "invalid" => ListeriaError::InvalidTitle,
+                other => ListeriaError::PageLoad(other.to_string()),
+            };
+            return Err(Self::fail_with(page, error));
         };
         match result["parse"][mode]["*"].as_str() {
             Some(ret) => Ok(ret.to_string()),
-            None => Err(Self::fail(page, &format!("No parse tree for {mode}"))),
+            None => Err(Self::fail_with(
+                page,
+                ListeriaError::PageLoad(format!("No parse tree for {mode}")),
+            )),
         }
     }
 
@@ -155,15 +135,16 @@ impl PageOperations {
         // count against the same wiki budget that the edit POST uses.
         let token = with_breaker(
             &breaker,
-            || anyhow!("MW API circuit open for {wiki}"),
+            || ListeriaError::MwApiCircuitOpen(wiki.clone()).into(),
             || async { Self::get_edit_token_with_retries(&mut api).await },
         )
         .await?;
+        let summary = page_params.message(Message::EditSummary, page_params.language(), &[]);
         let mut params: HashMap<String, String> = vec![
             ("action", "edit"),
             ("title", title),
             ("text", wikitext),
-            ("summary", "Wikidata list updated [V2]"),
+            ("summary", &summary),
             ("token", &token),
             ("bot", "1"),
         ]
@@ -180,7 +161,7 @@ impl PageOperations {
         }
         let j = with_breaker(
             &breaker,
-            || anyhow!("MW API circuit open for {wiki}"),
+            || ListeriaError::MwApiCircuitOpen(wiki.clone()).into(),
             || async {
                 retry_with_backoff(
                     "save_wikitext_to_page",
@@ -193,11 +174,8 @@ impl PageOperations {
             },
         )
         .await?;
-        match j["error"].as_object() {
-            Some(o) => {
-                let msg = o["info"].as_str().unwrap_or("Error while saving");
-                Err(anyhow!("{msg}"))
-            }
+        match j.get("error") {
+            Some(error) => Err(ListeriaError::from_edit_error(error).into()),
             None => Ok(()),
         }
     }
@@ -288,7 +266,7 @@ impl PageOperations {
         let breaker = page.page_params().config().mw_api_circuit_breaker(&wiki);
         let _ = with_breaker(
             &breaker,
-            || anyhow!("MW API circuit open for {wiki}"),
+            || ListeriaError::MwApiCircuitOpen(wiki.clone()).into(),
             || async {
                 retry_with_backoff(
                     "purge_page",
@@ -312,6 +290,15 @@ impl PageOperations {
     fn fail(page: &ListeriaPage, message: &str) -> WikiPageResult {
         WikiPageResult::fail(page.wiki(), page.page_params().page(), message)
     }
+
+    fn fail_with(page: &ListeriaPage, error: ListeriaError) -> WikiPageResult {
+        WikiPageResult::failed_with(
+            page.wiki(),
+            page.page_params().page(),
+            error.code(),
+            error.to_string(),
+        )
+    }
 }
 
 #[cfg(test)]
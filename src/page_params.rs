@@ -1,7 +1,13 @@
 //! Page-level parameter handling and API resolution.
 
-use crate::{ApiArc, configuration::Configuration};
-use anyhow::Result;
+use crate::{
+    ApiArc,
+    configuration::{Configuration, WikiSettings},
+    listeria_error::ListeriaError,
+    messages::{Message, Messages},
+};
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
 use std::sync::Arc;
 use wikimisc::mediawiki::api::Api;
 
@@ -17,6 +23,8 @@ pub struct PageParams {
     simulated_sparql_results: Option<String>,
     simulated_autodesc: Option<Vec<String>>,
     config: Arc<Configuration>,
+    settings: WikiSettings,
+    messages: Arc<Messages>,
     local_file_namespace_prefix: String,
 }
 
@@ -26,14 +34,34 @@ impl PageParams {
         let wiki_name = if config.is_single_wiki() {
             "wiki".to_string()
         } else {
-            api.get_site_info_string("general", "wikiid")?.to_string()
+            api.get_site_info_string("general", "wikiid")
+                .map_err(|e| ListeriaError::Configuration(e.to_string()))?
+                .to_string()
+        };
+        let settings = config.wiki_settings(&wiki_name);
+        let language = api
+            .get_site_info_string("general", "lang")
+            .map_err(|e| ListeriaError::Configuration(e.to_string()))?
+            .to_string();
+        let messages = if settings.load_onwiki_messages {
+            config
+                .onwiki_messages()
+                .get(config.messages(), &api, &wiki_name, &language)
+                .await
+        } else {
+            config.messages().clone()
         };
         let ret = Self {
+            settings,
+            messages,
             wiki: wiki_name,
             page,
-            language: api.get_site_info_string("general", "lang")?.to_string(),
+            language,
             mw_api: mw_api.clone(),
-            wb_api: config.get_default_wbapi()?.clone(),
+            wb_api: config
+                .get_default_wbapi()
+                .map_err(|e| ListeriaError::Configuration(e.to_string()))?
+                .clone(),
             simulate: false,
             simulated_text: None,
             simulated_sparql_results: None,
@@ -71,6 +99,35 @@ impl PageParams {
         &self.config
     }
 
+    /// Effective settings for this page's wiki, with any `wikis.<dbname>`
+    /// overrides already applied.
+    pub const fn settings(&self) -> &WikiSettings {
+        &self.settings
+    }
+
+    /// Translations for bot-generated strings, including on-wiki overrides.
+    pub fn messages(&self) -> &Messages {
+        &self.messages
+    }
+
+    /// Formats a bot-generated string in `language` (see [`Messages::format`]).
+    pub fn message(&self, message: Message, language: &str, args: &[&str]) -> String {
+        self.messages.format(message, language, args)
+    }
+
+    /// The messages for a list in `language`: those of the page, or for a
+    /// language other than the wiki's, with the on-wiki overrides in that
+    /// language (cached per wiki and language).
+    pub async fn messages_for(&self, language: &str) -> Arc<Messages> {
+        if language == self.language || !self.settings.load_onwiki_messages {
+            return self.messages.clone();
+        }
+        self.config
+            .onwiki_messages()
+            .get(self.config.messages(), &self.mw_api, &self.wiki, language)
+            .await
+    }
+
     pub const fn mw_api(&self) -> &ApiArc {
         &self.mw_api
     }
@@ -91,6 +148,33 @@ impl PageParams {
         &self.simulated_autodesc
     }
 
+    /// `text` with its templates and magic words expanded by the wiki, as
+    /// they would be on this page; unchanged if the wiki returns nothing.
+    pub async fn expand_templates(&self, text: &str) -> Result<String> {
+        let params: HashMap<String, String> = [
+            ("action", "expandtemplates"),
+            ("title", self.page()),
+            ("prop", "wikitext"),
+            ("text", text),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        // The underlying reqwest client is already configured with
+        // `api_timeout`, but a future refactor that swaps clients would
+        // silently regress that bound. Wrap the call in a defensive outer
+        // timeout so a hung MW API can never block the list pipeline
+        // indefinitely.
+        let timeout = self.config.api_timeout();
+        let j = tokio::time::timeout(timeout, self.mw_api.get_query_api_json(&params))
+            .await
+            .map_err(|_| anyhow!("expandtemplates timed out after {}s", timeout.as_secs()))??;
+        Ok(j["expandtemplates"]["wikitext"]
+            .as_str()
+            .unwrap_or(text)
+            .to_string())
+    }
+
     pub fn set_simulation(
         &mut self,
         text: Option<String>,
@@ -122,6 +206,31 @@ mod tests {
         assert!(!pp.simulate());
     }
 
+    #[tokio::test]
+    async fn test_settings_resolved_for_page_wiki() {
+        let pp = make_page_params().await;
+        let expected = pp.config().wiki_settings(pp.wiki());
+        assert_eq!(pp.settings().thumbnail_size, expected.thumbnail_size);
+        assert_eq!(pp.settings().location_template, expected.location_template);
+    }
+
+    #[tokio::test]
+    async fn test_message_formats_in_requested_language() {
+        let pp = make_page_params().await;
+        assert_eq!(
+            pp.message(Message::SummaryLine, "en", &["3", "items"]),
+            "&sum; 3 items."
+        );
+        assert_eq!(pp.message(Message::MiscSection, "de", &[]), "Sonstige");
+    }
+
+    #[tokio::test]
+    async fn test_messages_for_page_language_are_the_pages() {
+        let pp = make_page_params().await;
+        let messages = pp.messages_for(pp.language()).await;
+        assert!(Arc::ptr_eq(&messages, &pp.messages));
+    }
+
     #[tokio::test]
     async fn test_set_simulation_enables_flag() {
         let mut pp = make_page_params().await;
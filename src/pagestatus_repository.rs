@@ -3,12 +3,80 @@
 //! All SQL that touches these tables lives here. Callers deal only with
 //! typed Rust values; the SQL strings are invisible to the rest of the
 //! codebase.
+//!
+//! The code of the last error (see [`ErrorCode`](crate::listeria_error::ErrorCode))
+//! is kept next to the message:
+//!
+//! ```sql
+//! ALTER TABLE `pagestatus` ADD COLUMN `error_code` VARCHAR(32) DEFAULT NULL;
+//! ```
+//!
+//! Failed pages are retried after a backoff (see [`Backoff`]):
+//!
+//! ```sql
+//! ALTER TABLE `pagestatus`
+//!   ADD COLUMN `fail_count` INT UNSIGNED NOT NULL DEFAULT 0,
+//!   ADD COLUMN `next_attempt` VARCHAR(14) DEFAULT NULL;
+//! ```
+//!
+//! The time of the bot's last edit to a page is kept as well:
+//!
+//! ```sql
+//! ALTER TABLE `pagestatus` ADD COLUMN `last_edit` VARCHAR(14) DEFAULT NULL;
+//! ```
+//!
+//! [`PageStatusRepository::check_schema`] refuses to start the bot while any
+//! of these columns is missing, and names the `ALTER TABLE` to run.
 
 use crate::database_pool::DatabasePool;
 use crate::page_to_process::PageToProcess;
-use anyhow::Result;
+use crate::storage::{Backoff, MAX_BACKOFF, PageStatus, RELEASED_MESSAGE, storage_timestamp};
+use anyhow::{Result, anyhow};
 use chrono::{DateTime, Utc};
-use mysql_async::{from_row, params, prelude::*};
+use mysql_async::{Params, from_row, params, prelude::*};
+use std::collections::HashMap;
+
+/// The columns [`PageStatusRepository::get_page_statuses`] and friends read,
+/// in [`PageStatusRow`] order.
+const PAGE_STATUS_COLUMNS: &str = "pagestatus.id,wikis.name,pagestatus.page,pagestatus.status,
+    pagestatus.message,pagestatus.timestamp,pagestatus.priority,pagestatus.last_runtime_sec,
+    pagestatus.error_code,pagestatus.fail_count,pagestatus.next_attempt,pagestatus.last_edit";
+
+type PageStatusRow = (
+    u64,
+    String,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    Option<i64>,
+    Option<u64>,
+    Option<String>,
+    Option<u32>,
+    Option<String>,
+    Option<String>,
+);
+
+/// Columns added to `pagestatus` after it was first created, with what adds
+/// them; see the module docs.
+const ADDED_COLUMNS: &[(&str, &str)] = &[
+    (
+        "error_code",
+        "ADD COLUMN `error_code` VARCHAR(32) DEFAULT NULL",
+    ),
+    (
+        "fail_count",
+        "ADD COLUMN `fail_count` INT UNSIGNED NOT NULL DEFAULT 0",
+    ),
+    (
+        "next_attempt",
+        "ADD COLUMN `next_attempt` VARCHAR(14) DEFAULT NULL",
+    ),
+    (
+        "last_edit",
+        "ADD COLUMN `last_edit` VARCHAR(14) DEFAULT NULL",
+    ),
+];
 
 #[derive(Debug, Clone)]
 pub struct PageStatusRepository {
@@ -20,6 +88,46 @@ impl PageStatusRepository {
         Self { pool }
     }
 
+    /// Fails if `pagestatus` lacks a column this version uses, with the
+    /// `ALTER TABLE` that adds the missing ones; called at startup.
+    pub async fn check_schema(&self) -> Result<()> {
+        let columns = self
+            .pool
+            .with_timeout("check_schema", || async {
+                let sql = "SELECT `COLUMN_NAME` FROM `information_schema`.`COLUMNS` \
+                           WHERE `TABLE_SCHEMA`=DATABASE() AND `TABLE_NAME`='pagestatus'";
+                let columns = self
+                    .pool
+                    .get_conn()
+                    .await?
+                    .exec_iter(sql, ())
+                    .await?
+                    .map_and_drop(from_row::<String>)
+                    .await?;
+                Ok(columns)
+            })
+            .await?;
+        match Self::missing_columns(&columns) {
+            Some(alter) => Err(anyhow!(
+                "The pagestatus table is missing columns this version needs; run:\n{alter}"
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// The `ALTER TABLE` adding the [`ADDED_COLUMNS`] not in `columns`, if any.
+    fn missing_columns(columns: &[String]) -> Option<String> {
+        let missing: Vec<&str> = ADDED_COLUMNS
+            .iter()
+            .filter(|(name, _)| !columns.iter().any(|c| c.eq_ignore_ascii_case(name)))
+            .map(|(_, add)| *add)
+            .collect();
+        if missing.is_empty() {
+            return None;
+        }
+        Some(format!("ALTER TABLE `pagestatus` {};", missing.join(", ")))
+    }
+
     /// Marks every RUNNING row as FAIL with a "bot restarted" message.
     ///
     /// The FAIL message embeds the row's pre-reset `timestamp` (the moment
@@ -66,6 +174,45 @@ impl PageStatusRepository {
             .await
     }
 
+    /// Resets the DEFERRED rows of `wiki` (or all of them) back to FAIL,
+    /// after a circuit breaker closed again.
+    pub async fn recover_deferred(&self, wiki: Option<&str>) -> Result<()> {
+        self.pool
+            .with_timeout("recover_deferred", || async {
+                let sql = "UPDATE `pagestatus` SET `status`='FAIL', \
+                           `message`='cleared from DEFERRED after the circuit closed' \
+                           WHERE `status`='DEFERRED' \
+                           AND (:wiki IS NULL OR `wiki`=(SELECT id FROM `wikis` WHERE `name`=:wiki))";
+                self.pool
+                    .get_conn()
+                    .await?
+                    .exec_drop(sql, params! { wiki })
+                    .await?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// Turns the longest-waiting DEFERRED row of `wiki` (or of all wikis)
+    /// back into FAIL, to run as the probe of an open circuit breaker.
+    pub async fn release_deferred_probe(&self, wiki: Option<&str>) -> Result<()> {
+        self.pool
+            .with_timeout("release_deferred_probe", || async {
+                let sql = "UPDATE `pagestatus` SET `status`='FAIL', \
+                           `message`='released from DEFERRED as a circuit breaker probe' \
+                           WHERE `status`='DEFERRED' \
+                           AND (:wiki IS NULL OR `wiki`=(SELECT id FROM `wikis` WHERE `name`=:wiki)) \
+                           ORDER BY `timestamp` LIMIT 1";
+                self.pool
+                    .get_conn()
+                    .await?
+                    .exec_drop(sql, params! { wiki })
+                    .await?;
+                Ok(())
+            })
+            .await
+    }
+
     /// Resets all DEFERRED rows back to FAIL so they become eligible for
     /// re-processing on the next dispatcher pass. Called at bot startup;
     /// pages that were deferred because a circuit was open are given a
@@ -86,6 +233,24 @@ impl PageStatusRepository {
             .await
     }
 
+    /// Sets a RUNNING page back to `status`, leaving its priority, error
+    /// code and backoff alone.
+    pub async fn release_running_page(&self, pagestatus_id: u64, status: &str) -> Result<()> {
+        self.pool
+            .with_timeout("release_running_page", || async {
+                let sql = "UPDATE `pagestatus` SET `status`=:status, `message`=:message
+                    WHERE `id`=:pagestatus_id AND `status`='RUNNING'";
+                let message = RELEASED_MESSAGE;
+                self.pool
+                    .get_conn()
+                    .await?
+                    .exec_drop(sql, params! {status, message, pagestatus_id})
+                    .await?;
+                Ok(())
+            })
+            .await
+    }
+
     /// Records how many seconds a page took to process.
     pub async fn set_runtime(&self, pagestatus_id: u64, seconds: u64) -> Result<()> {
         self.pool
@@ -107,12 +272,16 @@ impl PageStatusRepository {
     /// While a page is RUNNING the priority is preserved so the scheduler can
     /// still identify it as high-priority. For every other terminal status the
     /// priority is reset to 0 to prevent accumulation in the priority queue.
+    /// `error_code` replaces the stored code; `None` clears it. The failure
+    /// count and next attempt follow [`Backoff::for_status`]; the SQL
+    /// computes the same wait as [`crate::storage::backoff_delay`].
     pub async fn update_page_status(
         &self,
         page: &str,
         wiki: &str,
         status: &str,
         message: &str,
+        error_code: Option<&str>,
     ) -> Result<()> {
         self.pool
             .with_timeout("update_page_status", || async {
@@ -124,19 +293,35 @@ impl PageStatusRepository {
                     "timestamp" => timestamp,
                     "status" => status,
                     "message" => message.chars().take(200).collect::<String>(),
+                    "error_code" => error_code,
                 };
                 let priority = if status == "RUNNING" {
                     "`priority`"
                 } else {
                     "0"
                 };
+                // `next_attempt` is set before `fail_count` is incremented,
+                // so it sees the number of earlier failures in a row
+                let backoff = match Backoff::for_status(status, error_code) {
+                    Backoff::Keep => String::new(),
+                    Backoff::Reset => ",`fail_count`=0,`next_attempt`=NULL".to_string(),
+                    Backoff::Fail(base) => format!(
+                        ",`next_attempt`=DATE_FORMAT(UTC_TIMESTAMP() + INTERVAL \
+                        LEAST({} * POW(2, LEAST(`fail_count`, 30)), {}) SECOND, '%Y%m%d%H%i%s'),\
+                        `fail_count`=`fail_count`+1",
+                        base.as_secs(),
+                        MAX_BACKOFF.as_secs()
+                    ),
+                };
                 let sql = format!(
                     "UPDATE `pagestatus` SET
                     `status`=:status,
                     `message`=:message,
+                    `error_code`=:error_code,
                     `timestamp`=:timestamp,
                     `bot_version`=2,
                     `priority`={priority}
+                    {backoff}
                     WHERE `wiki`=(SELECT id FROM `wikis` WHERE `name`=:wiki) AND `page`=:page"
                 );
                 self.pool
@@ -151,6 +336,51 @@ impl PageStatusRepository {
             .await
     }
 
+    /// Records that the bot has just edited a page.
+    pub async fn set_last_edit(&self, page: &str, wiki: &str) -> Result<()> {
+        self.pool
+            .with_timeout("set_last_edit", || async {
+                let sql = "UPDATE `pagestatus` SET `last_edit`=:last_edit
+                    WHERE `wiki`=(SELECT id FROM `wikis` WHERE `name`=:wiki) AND `page`=:page";
+                let p = params! {
+                    "wiki" => wiki,
+                    "page" => page,
+                    "last_edit" => storage_timestamp(),
+                };
+                self.pool.get_conn().await?.exec_drop(sql, p).await?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// Sets the priority flag of the given pages of a wiki, in chunks of
+    /// 1 000 titles per statement.
+    pub async fn set_priority(&self, wiki: &str, pages: &[String]) -> Result<()> {
+        for chunk in pages.chunks(1000) {
+            self.pool
+                .with_timeout("set_priority", || async {
+                    let placeholders = std::iter::repeat_n("?", chunk.len())
+                        .collect::<Vec<_>>()
+                        .join(",");
+                    let sql = format!(
+                        "UPDATE `pagestatus` SET `priority`=1
+                        WHERE `wiki`=(SELECT id FROM `wikis` WHERE `name`=?) AND `page` IN ({placeholders})"
+                    );
+                    let mut params: Vec<String> = Vec::with_capacity(chunk.len() + 1);
+                    params.push(wiki.to_string());
+                    params.extend(chunk.iter().cloned());
+                    self.pool
+                        .get_conn()
+                        .await?
+                        .exec_drop(sql, params)
+                        .await?;
+                    Ok(())
+                })
+                .await?;
+        }
+        Ok(())
+    }
+
     /// Returns the highest-priority waiting page, if any.
     pub async fn find_priority_page(
         &self,
@@ -168,29 +398,159 @@ impl PageStatusRepository {
             ORDER BY pagestatus.timestamp
             LIMIT 1"
         );
-        Ok(self.get_page_for_sql(&sql).await)
+        Ok(self.get_page_for_sql(&sql, Params::Empty).await)
     }
 
-    /// Returns the oldest waiting page that is not in `ids` or `ignore_status`.
+    /// Returns the oldest waiting page that is not in `ids` or `ignore_status`,
+    /// and whose backoff is over.
     pub async fn find_oldest_page(
         &self,
         ids: &str,
         ignore_status: &str,
     ) -> Result<Option<PageToProcess>> {
+        let now = storage_timestamp();
+        let sql = format!(
+            "SELECT pagestatus.id,pagestatus.page,pagestatus.status,wikis.name AS wiki
+            FROM pagestatus,wikis
+            WHERE pagestatus.wiki=wikis.id
+            AND wikis.status='ACTIVE'
+            AND pagestatus.status NOT IN ({ignore_status})
+            AND pagestatus.id NOT IN ({ids})
+            AND (pagestatus.next_attempt IS NULL OR pagestatus.next_attempt<='{now}')
+            ORDER BY pagestatus.timestamp
+            LIMIT 1"
+        );
+        Ok(self.get_page_for_sql(&sql, Params::Empty).await)
+    }
+
+    /// Like [`Self::find_oldest_page`], restricted to one wiki, and to pages
+    /// last updated before `updated_before` unless they failed (or were
+    /// released from DEFERRED).
+    pub async fn find_oldest_page_for_wiki(
+        &self,
+        wiki: &str,
+        ids: &str,
+        ignore_status: &str,
+        updated_before: &str,
+    ) -> Result<Option<PageToProcess>> {
+        let now = storage_timestamp();
         let sql = format!(
             "SELECT pagestatus.id,pagestatus.page,pagestatus.status,wikis.name AS wiki
             FROM pagestatus,wikis
             WHERE pagestatus.wiki=wikis.id
+            AND wikis.name=:wiki
             AND wikis.status='ACTIVE'
             AND pagestatus.status NOT IN ({ignore_status})
             AND pagestatus.id NOT IN ({ids})
+            AND (pagestatus.next_attempt IS NULL OR pagestatus.next_attempt<='{now}')
+            AND (pagestatus.status='FAIL' OR pagestatus.fail_count>0 OR pagestatus.timestamp IS NULL OR pagestatus.timestamp<:updated_before)
             ORDER BY pagestatus.timestamp
             LIMIT 1"
         );
-        Ok(self.get_page_for_sql(&sql).await)
+        Ok(self
+            .get_page_for_sql(&sql, params! { wiki, updated_before })
+            .await)
+    }
+
+    /// Counts the pages [`Self::find_oldest_page_for_wiki`] could return, per
+    /// active wiki. Wikis without such pages are left out.
+    pub async fn get_queue_depths(
+        &self,
+        ignore_status: &str,
+        updated_before: &str,
+    ) -> Result<HashMap<String, usize>> {
+        let now = storage_timestamp();
+        let sql = format!(
+            "SELECT wikis.name,COUNT(*)
+            FROM pagestatus,wikis
+            WHERE pagestatus.wiki=wikis.id
+            AND wikis.status='ACTIVE'
+            AND pagestatus.status NOT IN ({ignore_status})
+            AND (pagestatus.next_attempt IS NULL OR pagestatus.next_attempt<='{now}')
+            AND (pagestatus.status='FAIL' OR pagestatus.fail_count>0 OR pagestatus.timestamp IS NULL OR pagestatus.timestamp<:updated_before)
+            GROUP BY wikis.name"
+        );
+        self.pool
+            .with_timeout("get_queue_depths", || async {
+                let rows = self
+                    .pool
+                    .get_conn()
+                    .await?
+                    .exec_iter(sql.as_str(), params! { updated_before })
+                    .await?
+                    .map_and_drop(from_row::<(String, u64)>)
+                    .await?;
+                Ok(rows
+                    .into_iter()
+                    .map(|(wiki, count)| (wiki, count as usize))
+                    .collect())
+            })
+            .await
+    }
+
+    /// Returns every queued page of a wiki.
+    pub async fn get_page_statuses(&self, wiki: &str) -> Result<Vec<PageStatus>> {
+        let sql = format!(
+            "SELECT {PAGE_STATUS_COLUMNS}
+            FROM pagestatus,wikis
+            WHERE wikis.id=pagestatus.wiki AND wikis.name=:wiki"
+        );
+        self.get_page_statuses_for_sql("get_page_statuses", &sql, params! { wiki })
+            .await
+    }
+
+    /// Returns up to `limit` pages that failed last time, soonest retry first.
+    pub async fn get_backoff_pages(&self, limit: usize) -> Result<Vec<PageStatus>> {
+        let sql = format!(
+            "SELECT {PAGE_STATUS_COLUMNS}
+            FROM pagestatus,wikis
+            WHERE wikis.id=pagestatus.wiki AND pagestatus.fail_count>0
+            ORDER BY pagestatus.next_attempt
+            LIMIT :limit"
+        );
+        let limit = limit as u64;
+        self.get_page_statuses_for_sql("get_backoff_pages", &sql, params! { limit })
+            .await
     }
 
-    async fn get_page_for_sql(&self, sql: &str) -> Option<PageToProcess> {
+    async fn get_page_statuses_for_sql(
+        &self,
+        label: &str,
+        sql: &str,
+        params: mysql_async::Params,
+    ) -> Result<Vec<PageStatus>> {
+        self.pool
+            .with_timeout(label, || async {
+                let rows = self
+                    .pool
+                    .get_conn()
+                    .await?
+                    .exec_iter(sql, params.clone())
+                    .await?
+                    .map_and_drop(from_row::<PageStatusRow>)
+                    .await?;
+                Ok(rows
+                    .into_iter()
+                    .map(|r| PageStatus {
+                        id: r.0,
+                        wiki: r.1,
+                        page: r.2,
+                        status: r.3,
+                        message: r.4.unwrap_or_default(),
+                        timestamp: r.5.unwrap_or_default(),
+                        priority: r.6.unwrap_or_default() > 0,
+                        last_runtime_sec: r.7,
+                        last_edit: r.11,
+                        error_code: r.8,
+                        fail_count: r.9.unwrap_or_default(),
+                        next_attempt: r.10,
+                    })
+                    .collect())
+            })
+            .await
+    }
+
+    async fn get_page_for_sql(&self, sql: &str, params: Params) -> Option<PageToProcess> {
         // The Option-returning signature drops the error context; wrap in a
         // timeout-aware closure so a wedged query at least gets logged before
         // being converted to None.
@@ -201,7 +561,7 @@ impl PageStatusRepository {
                     .pool
                     .get_conn()
                     .await?
-                    .exec_iter(sql, ())
+                    .exec_iter(sql, params.clone())
                     .await?
                     .map_and_drop(PageToProcess::from_row)
                     .await?;
@@ -249,4 +609,27 @@ mod tests {
             assert_eq!(fragment, "0", "status={status} should reset priority");
         }
     }
+
+    #[test]
+    fn test_missing_columns() {
+        use super::PageStatusRepository;
+        let mut columns: Vec<String> = [
+            "id",
+            "wiki",
+            "page",
+            "error_code",
+            "NEXT_ATTEMPT",
+            "last_edit",
+        ]
+        .map(String::from)
+        .to_vec();
+        assert_eq!(
+            PageStatusRepository::missing_columns(&columns).as_deref(),
+            Some(
+                "ALTER TABLE `pagestatus` ADD COLUMN `fail_count` INT UNSIGNED NOT NULL DEFAULT 0;"
+            )
+        );
+        columns.push("fail_count".to_string());
+        assert_eq!(PageStatusRepository::missing_columns(&columns), None);
+    }
 }
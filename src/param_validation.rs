@@ -0,0 +1,329 @@
+//! Checks a list's template parameters for what the parsers in
+//! [`crate::template_params`] would silently ignore: misspelt parameter
+//! names (`sort_oder`), values that fall back to a default (`links=blue`,
+//! `sort=P31x`) and unknown columns. Each warning comes with a "did you
+//! mean" suggestion where a known name or value is close enough.
+
+use crate::column_type::ColumnType;
+use crate::template::Template;
+use crate::template_params::{PARAMETERS, ParamValues};
+use regex::Regex;
+use std::fmt;
+use std::sync::LazyLock;
+
+/// Column names that are not properties or variables.
+const COLUMN_KEYWORDS: &[&str] = &[
+    "number",
+    "label",
+    "description",
+    "item",
+    "qid",
+    "lemma",
+    "lexical_category",
+    "lexeme_language",
+    "forms",
+    "senses",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamWarning {
+    UnknownParameter {
+        name: String,
+        suggestion: Option<String>,
+    },
+    /// A value the parameter does not understand; `expected` lists the
+    /// accepted ones, if there are few.
+    InvalidValue {
+        name: String,
+        value: String,
+        suggestion: Option<String>,
+        expected: Option<Vec<&'static str>>,
+    },
+    UnknownColumn {
+        spec: String,
+        suggestion: Option<String>,
+    },
+}
+
+impl fmt::Display for ParamWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let suggestion = match self {
+            Self::UnknownParameter { name, suggestion } => {
+                write!(f, "unknown parameter '{name}'")?;
+                suggestion
+            }
+            Self::InvalidValue {
+                name,
+                value,
+                suggestion,
+                expected,
+            } => {
+                write!(f, "invalid value '{value}' for '{name}'")?;
+                if let (None, Some(expected)) = (suggestion, expected) {
+                    write!(f, " (expected one of: {})", expected.join(", "))?;
+                }
+                suggestion
+            }
+            Self::UnknownColumn { spec, suggestion } => {
+                write!(f, "unknown column '{spec}'")?;
+                suggestion
+            }
+        };
+        match suggestion {
+            Some(suggestion) => write!(f, " (did you mean '{suggestion}'?)"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// All warnings for the parameters of `template`, sorted by parameter name.
+#[must_use]
+pub fn validate(template: &Template) -> Vec<ParamWarning> {
+    let mut params: Vec<(&String, &String)> = template.params().iter().collect();
+    params.sort();
+    let mut ret = vec![];
+    for (name, value) in params {
+        let Some((known, values)) = PARAMETERS
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name))
+        else {
+            let candidates = PARAMETERS.iter().map(|(known, _)| *known);
+            ret.push(ParamWarning::UnknownParameter {
+                name: name.to_owned(),
+                suggestion: closest(&name.to_lowercase(), candidates).map(str::to_string),
+            });
+            continue;
+        };
+        if *known == "columns" {
+            ret.extend(validate_columns(value));
+        } else {
+            ret.extend(validate_value(name, value.trim(), *values));
+        }
+    }
+    ret
+}
+
+fn validate_value(name: &str, value: &str, values: ParamValues) -> Vec<ParamWarning> {
+    static RE_PROP: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"^[Pp]\d+$").expect("RE_PROP does not parse"));
+    static RE_SECTION: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"^([Pp]?\d+|@.+)$").expect("RE_SECTION does not parse"));
+    static RE_SPARQL: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"^\?\S+$").expect("RE_SPARQL does not parse"));
+    if value.is_empty() {
+        return vec![];
+    }
+    let lower = value.to_lowercase();
+    let invalid = |suggestion: Option<String>, expected: Option<Vec<&'static str>>| {
+        ParamWarning::InvalidValue {
+            name: name.to_string(),
+            value: value.to_string(),
+            suggestion,
+            expected,
+        }
+    };
+    match values {
+        ParamValues::Any => vec![],
+        ParamValues::OneOf(known) => {
+            let known = known();
+            if known.contains(&lower.as_str()) {
+                return vec![];
+            }
+            vec![invalid(
+                closest(&lower, known.iter().copied()).map(str::to_string),
+                Some(known),
+            )]
+        }
+        ParamValues::Options(known) => {
+            let known = known();
+            value
+                .split([',', ';', ' '])
+                .map(str::trim)
+                .filter(|option| !option.is_empty())
+                .filter(|option| !known.contains(&option.to_lowercase().as_str()))
+                .map(|option| ParamWarning::InvalidValue {
+                    name: name.to_string(),
+                    value: option.to_string(),
+                    suggestion: closest(&option.to_lowercase(), known.iter().copied())
+                        .map(str::to_string),
+                    expected: Some(known.clone()),
+                })
+                .collect()
+        }
+        ParamValues::Number if value.parse::<u64>().is_ok() => vec![],
+        ParamValues::Number => vec![invalid(None, None)],
+        ParamValues::Sort
+            if ["label", "family_name"].contains(&lower.as_str())
+                || RE_PROP.is_match(value)
+                || RE_SPARQL.is_match(value) =>
+        {
+            vec![]
+        }
+        ParamValues::Sort => vec![invalid(
+            property_prefix(value)
+                .or_else(|| closest(&lower, ["label", "family_name"]).map(str::to_string)),
+            None,
+        )],
+        ParamValues::Section if RE_SECTION.is_match(value) => vec![],
+        ParamValues::Section => vec![invalid(property_prefix(value), None)],
+    }
+}
+
+/// Warnings for the column specs of `columns=`; the part after a `:` is
+/// the column label.
+fn validate_columns(columns: &str) -> Vec<ParamWarning> {
+    columns
+        .split(',')
+        .map(|column| {
+            column
+                .split_once(':')
+                .map_or(column, |(spec, _)| spec)
+                .trim()
+        })
+        .filter(|spec| !spec.is_empty())
+        .filter(|spec| ColumnType::new(spec) == ColumnType::Unknown)
+        .map(|spec| ParamWarning::UnknownColumn {
+            spec: spec.to_string(),
+            suggestion: property_prefix(spec).or_else(|| {
+                closest(&spec.to_lowercase(), COLUMN_KEYWORDS.iter().copied()).map(str::to_string)
+            }),
+        })
+        .collect()
+}
+
+/// The property at the start of a value with trailing junk: `P31x` → `P31`.
+fn property_prefix(value: &str) -> Option<String> {
+    let digits: String = value
+        .strip_prefix(['P', 'p'])?
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    (!digits.is_empty()).then(|| format!("P{digits}"))
+}
+
+/// The candidate closest to `s`, if it is close enough to be a likely typo.
+fn closest<'a>(s: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(s, candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= 2 && *distance * 3 <= candidate.len())
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance, by characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, left) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, right) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if left == *right {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn warnings(params: &str) -> Vec<String> {
+        let template = Template::new_from_params(params).unwrap();
+        validate(&template)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("sort_oder", "sort_order"), 1);
+        assert_eq!(edit_distance("colums", "columns"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("same", "same"), 0);
+    }
+
+    #[test]
+    fn test_valid_parameters() {
+        assert!(
+            warnings(
+                "sparql=SELECT ?item {}|columns=number,label:Name,P31,?x|sort=P569|links=Red_only\
+                 |sort_order=DESC|section=31|redirects=resolve, sections_missing|summary="
+            )
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_names_and_values_ignore_case() {
+        assert!(
+            warnings(
+                "Sort=P31|SORT_ORDER=Desc|Sort_Values=dates|row_template_params=item, raw\
+                 |disambiguation=plain|Diagnostics=YES"
+            )
+            .is_empty()
+        );
+        assert_eq!(
+            warnings("Links=Locl"),
+            ["invalid value 'Locl' for 'Links' (did you mean 'local'?)"]
+        );
+    }
+
+    #[test]
+    fn test_unknown_parameters() {
+        assert_eq!(
+            warnings("sort_oder=desc|colums=label|foo=bar"),
+            [
+                "unknown parameter 'colums' (did you mean 'columns'?)",
+                "unknown parameter 'foo'",
+                "unknown parameter 'sort_oder' (did you mean 'sort_order'?)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        assert_eq!(
+            warnings("links=blue"),
+            [
+                "invalid value 'blue' for 'links' (expected one of: all, local, red, red_only, text, reasonator)"
+            ]
+        );
+        assert_eq!(
+            warnings("links=redonly"),
+            ["invalid value 'redonly' for 'links' (did you mean 'red_only'?)"]
+        );
+        assert_eq!(
+            warnings("sort=P31x"),
+            ["invalid value 'P31x' for 'sort' (did you mean 'P31'?)"]
+        );
+        assert_eq!(
+            warnings("redirects=resolve,sectons_missing"),
+            ["invalid value 'sectons_missing' for 'redirects' (did you mean 'sections_missing'?)"]
+        );
+        assert_eq!(
+            warnings("thumb=200px"),
+            ["invalid value '200px' for 'thumb'"]
+        );
+    }
+
+    #[test]
+    fn test_unknown_columns() {
+        assert_eq!(
+            warnings("columns=labl, P31x:type, foo"),
+            [
+                "unknown column 'labl' (did you mean 'label'?)",
+                "unknown column 'P31x' (did you mean 'P31'?)",
+                "unknown column 'foo'",
+            ]
+        );
+    }
+}
@@ -1,16 +1,18 @@
-//! Per-page profiling: optional timing data written to the `list_log` table.
+//! Per-page profiling: optional timing data written to the configured
+//! storage (the `list_log` table, or the single-wiki storage file).
 //!
 //! Separating this concern from `ListeriaList` keeps the core pipeline free
 //! of direct database I/O and makes the profiling lifecycle independently
 //! testable.
 
 use crate::configuration::Configuration;
+use crate::storage::Storage;
 use chrono::{DateTime, Utc};
 use std::sync::Arc;
 
-/// Writes per-stage timing rows to `list_log` when profiling is enabled.
+/// Writes per-stage timing rows to storage when profiling is enabled.
 ///
-/// Holds an `Arc<Configuration>` (for DB pool access), the wiki/page identity,
+/// Holds an `Arc<Configuration>` (for storage access), the wiki/page identity,
 /// and the wall-clock timestamp of the previous `profile()` call so it can
 /// compute inter-stage deltas.
 #[derive(Debug, Clone)]
@@ -52,20 +54,10 @@ impl ProfilingService {
     }
 
     async fn log2db(&self, ms: i64, timestamp: &str, msg: &str) -> anyhow::Result<()> {
-        use mysql_async::prelude::Queryable;
-        use mysql_async::params;
-        let pool = self.config.pool()?;
-        let wiki = self.wiki.as_str();
-        let page = self.page.as_str();
-        pool.with_timeout("log2db", || async {
-            let sql = "REPLACE INTO list_log (wiki, page, timestamp, diff_ms, message) VALUES (:wiki, :page, :timestamp, :ms, :msg)";
-            pool.get_conn()
-                .await?
-                .exec_drop(sql, params! {wiki, page, timestamp, ms, msg})
-                .await?;
-            Ok(())
-        })
-        .await
+        self.config
+            .storage()?
+            .log_profile(&self.wiki, &self.page, timestamp, ms, msg)
+            .await
     }
 }
 
@@ -94,8 +86,8 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_profile_advances_timestamp_when_enabled_but_no_pool() {
-        // With no DB pool the log2db call fails silently; the timestamp still
+    async fn test_profile_advances_timestamp_when_enabled_but_no_storage() {
+        // With no storage the log2db call fails silently; the timestamp still
         // advances and the function does not panic.
         let config = Arc::new(crate::configuration::Configuration::default());
         let mut svc = ProfilingService::new(config, "testwiki", "Test Page", true);
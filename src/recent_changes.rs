@@ -0,0 +1,622 @@
+//! Recent-changes watcher: bumps the priority of pages that need a refresh.
+//!
+//! Pages are otherwise processed by age, so a list whose template was just
+//! edited can wait days for its turn. The watcher polls `list=recentchanges`
+//! and sets the priority flag of:
+//!
+//! - queued pages whose Listeria start template changed (the list's
+//!   parameters; edits elsewhere on the page, including the bot's own list
+//!   updates, are ignored)
+//! - with `watch_items`, pages listing an item that was edited on Wikidata,
+//!   looked up in the item→page index that is written after each run
+//!
+//! Each poll continues from the newest change seen by the previous one, so
+//! no change is lost between polls; the first poll of a run looks one
+//! interval back. A poll makes at most [`MAX_REQUESTS_PER_POLL`] requests;
+//! after a burst of changes, the next poll continues where it stopped.
+//! Item changes are filtered by the API to edits of entity pages.
+
+use crate::ApiArc;
+use crate::configuration::Configuration;
+use crate::storage::{Storage, StorageBackend};
+use crate::wiki_apis::WikiApis;
+use anyhow::{Result, anyhow};
+use chrono::{SecondsFormat, Utc};
+use regex::{Regex, RegexBuilder};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use wikimisc::mediawiki::api::Api;
+
+/// Cursor key for item edits, kept apart from the wiki names.
+const ITEMS_CURSOR: &str = "#items";
+
+/// Revisions fetched per `prop=revisions` request (the API maximum).
+const REVISIONS_PER_REQUEST: usize = 50;
+
+/// `list=recentchanges` requests per wiki and poll, of 500 changes each.
+const MAX_REQUESTS_PER_POLL: usize = 10;
+
+/// Namespaces of items, properties and lexemes, both on Wikidata (0, 120,
+/// 146) and on a default Wikibase (120, 122).
+const ITEM_NAMESPACES: &str = "0|120|122|146";
+
+/// An entity page title, with or without its namespace (`Q42`, `Item:Q42`).
+static ITEM_TITLE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(?:Item:|Property:|Lexeme:)?([QPL]\d+)$").expect("valid regex"));
+
+/// The `recent_changes` config block.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecentChangesSettings {
+    /// Run the watcher alongside the bot.
+    pub enabled: bool,
+    /// Seconds between polls.
+    pub interval_sec: u64,
+    /// Also bump pages listing items edited on Wikidata. Turns on writing
+    /// the item→page index.
+    pub watch_items: bool,
+}
+
+impl Default for RecentChangesSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_sec: 60,
+            watch_items: false,
+        }
+    }
+}
+
+impl RecentChangesSettings {
+    #[must_use]
+    pub fn from_json(j: &Value) -> Self {
+        let defaults = Self::default();
+        Self {
+            enabled: j["enabled"].as_bool().unwrap_or(defaults.enabled),
+            interval_sec: j["interval_sec"]
+                .as_u64()
+                .filter(|sec| *sec > 0)
+                .unwrap_or(defaults.interval_sec),
+            watch_items: j["watch_items"].as_bool().unwrap_or(defaults.watch_items),
+        }
+    }
+
+    #[must_use]
+    pub const fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_sec)
+    }
+}
+
+/// One entry of `list=recentchanges`.
+#[derive(Debug, Clone, PartialEq)]
+struct Change {
+    title: String,
+    revid: u64,
+    old_revid: u64,
+    timestamp: String,
+}
+
+impl Change {
+    fn from_json(j: &Value) -> Option<Self> {
+        Some(Self {
+            title: j["title"].as_str()?.to_string(),
+            revid: j["revid"].as_u64().unwrap_or_default(),
+            old_revid: j["old_revid"].as_u64().unwrap_or_default(),
+            timestamp: j["timestamp"].as_str()?.to_string(),
+        })
+    }
+}
+
+/// Where the next poll of a wiki starts.
+#[derive(Debug, Clone, PartialEq)]
+struct Cursor {
+    /// `rcstart` timestamp.
+    start: String,
+    /// `rccontinue` of a poll that stopped at [`MAX_REQUESTS_PER_POLL`].
+    rccontinue: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct RecentChangesWatcher {
+    config: Arc<Configuration>,
+    storage: StorageBackend,
+    /// Where to continue from, per wiki (and [`ITEMS_CURSOR`]).
+    cursors: Mutex<HashMap<String, Cursor>>,
+}
+
+impl RecentChangesWatcher {
+    #[must_use]
+    pub fn new(config: Arc<Configuration>, storage: StorageBackend) -> Self {
+        Self {
+            config,
+            storage,
+            cursors: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Single-wiki mode: the wiki and its items live behind the same API.
+    pub async fn run_single(self, wiki: &str, api: ApiArc) {
+        let settings = *self.config.recent_changes();
+        loop {
+            tokio::time::sleep(settings.interval()).await;
+            if let Err(e) = self.check_wiki(wiki, &api).await {
+                log::warn!("Recent changes on {wiki}: {e}");
+            }
+            if settings.watch_items
+                && let Err(e) = self.check_items(&api).await
+            {
+                log::warn!("Recent item changes: {e}");
+            }
+        }
+    }
+
+    /// Multi-wiki mode: every active wiki, and items on the default API.
+    pub async fn run_multi(self, wiki_apis: Arc<WikiApis>) {
+        let settings = *self.config.recent_changes();
+        loop {
+            tokio::time::sleep(settings.interval()).await;
+            let wikis = match self.storage.get_all_wikis().await {
+                Ok(wikis) => wikis,
+                Err(e) => {
+                    log::warn!("Recent changes: could not load wikis: {e}");
+                    continue;
+                }
+            };
+            for wiki in wikis.values().filter(|w| w.is_active()) {
+                let result = match wiki_apis.acquire_wiki_api(wiki.name()).await {
+                    Ok(handle) => self.check_wiki(wiki.name(), handle.api()).await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    log::warn!("Recent changes on {}: {e}", wiki.name());
+                }
+            }
+            if settings.watch_items {
+                let result = match self.config.get_default_wbapi() {
+                    Ok(api) => self.check_items(api).await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    log::warn!("Recent item changes: {e}");
+                }
+            }
+        }
+    }
+
+    /// Bumps queued pages of `wiki` whose start template changed since the
+    /// last poll. Returns how many pages were bumped.
+    pub async fn check_wiki(&self, wiki: &str, api: &Api) -> Result<usize> {
+        let changes = self
+            .recent_changes(wiki, api, &[("rcprop", "title|ids|timestamp")])
+            .await?;
+        if changes.is_empty() {
+            return Ok(0);
+        }
+        let queued: HashSet<String> = self
+            .storage
+            .get_pages_for_wiki(wiki)
+            .await?
+            .into_iter()
+            .collect();
+        let changes: Vec<Change> = changes
+            .into_iter()
+            .filter(|c| queued.contains(&c.title))
+            .collect();
+        if changes.is_empty() {
+            return Ok(0);
+        }
+
+        let template = self.config.get_local_template_title_start(wiki)?;
+        let revids: Vec<u64> = changes
+            .iter()
+            .flat_map(|c| [c.old_revid, c.revid])
+            .filter(|revid| *revid > 0)
+            .collect();
+        let texts = Self::revision_texts(api, &revids).await?;
+        let templates_of = |revid: u64| {
+            texts
+                .get(&revid)
+                .map(|text| start_templates(text, &template))
+                .unwrap_or_default()
+        };
+
+        let mut bump: Vec<String> = vec![];
+        for change in &changes {
+            // A new page has no old revision; it is bumped as a change
+            if change.old_revid == 0 || templates_of(change.old_revid) != templates_of(change.revid)
+            {
+                bump.push(change.title.clone());
+            }
+        }
+        bump.sort();
+        bump.dedup();
+        self.storage.set_priority(wiki, &bump).await?;
+        if !bump.is_empty() {
+            log::info!("Recent changes: bumped {} page(s) on {wiki}", bump.len());
+        }
+        Ok(bump.len())
+    }
+
+    /// Bumps pages listing items edited since the last poll. Returns how
+    /// many pages were bumped.
+    pub async fn check_items(&self, api: &Api) -> Result<usize> {
+        let filter = [
+            ("rcprop", "title|timestamp"),
+            ("rcnamespace", ITEM_NAMESPACES),
+        ];
+        let changes = self.recent_changes(ITEMS_CURSOR, api, &filter).await?;
+        let mut items: Vec<String> = changes
+            .iter()
+            .filter_map(|c| ITEM_TITLE_RE.captures(&c.title))
+            .map(|caps| caps[1].to_string())
+            .collect();
+        items.sort();
+        items.dedup();
+        if items.is_empty() {
+            return Ok(0);
+        }
+        let bumped = self.storage.prioritize_pages_for_items(&items).await?.len();
+        if bumped > 0 {
+            log::info!("Recent changes: bumped {bumped} page(s) listing edited items");
+        }
+        Ok(bumped)
+    }
+
+    /// Edits and page creations since the cursor for `key`, oldest first,
+    /// with the `filter` parameters (at least `rcprop`); advances the cursor
+    /// past them.
+    async fn recent_changes(
+        &self,
+        key: &str,
+        api: &Api,
+        filter: &[(&str, &str)],
+    ) -> Result<Vec<Change>> {
+        let cursor = match self.cursors.lock().await.get(key) {
+            Some(cursor) => cursor.clone(),
+            None => {
+                let interval = chrono::Duration::from_std(self.config.recent_changes().interval())?;
+                Cursor {
+                    start: (Utc::now() - interval).to_rfc3339_opts(SecondsFormat::Secs, true),
+                    rccontinue: None,
+                }
+            }
+        };
+        let mut params: HashMap<String, String> = [
+            ("action", "query"),
+            ("list", "recentchanges"),
+            ("rctype", "edit|new"),
+            ("rcdir", "newer"),
+            ("rclimit", "500"),
+            ("rcstart", cursor.start.as_str()),
+        ]
+        .iter()
+        .chain(filter)
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        if let Some(rccontinue) = &cursor.rccontinue {
+            params.insert("rccontinue".to_string(), rccontinue.clone());
+        }
+
+        let mut changes = vec![];
+        let mut rccontinue = None;
+        for _ in 0..MAX_REQUESTS_PER_POLL {
+            let result = api.get_query_api_json(&params).await?;
+            let rc = result["query"]["recentchanges"]
+                .as_array()
+                .ok_or_else(|| anyhow!("'query.recentchanges' not an array in API response"))?;
+            changes.extend(rc.iter().filter_map(Change::from_json));
+            rccontinue = result["continue"]["rccontinue"]
+                .as_str()
+                .map(ToString::to_string);
+            match &rccontinue {
+                Some(rccontinue) => {
+                    params.insert("rccontinue".to_string(), rccontinue.clone());
+                }
+                None => break,
+            }
+        }
+
+        let next = match rccontinue {
+            // Stopped early; the next poll picks up from here
+            Some(rccontinue) => Cursor {
+                start: cursor.start,
+                rccontinue: Some(rccontinue),
+            },
+            None => Cursor {
+                start: changes
+                    .iter()
+                    .map(|c| &c.timestamp)
+                    .max()
+                    .cloned()
+                    .unwrap_or(cursor.start),
+                rccontinue: None,
+            },
+        };
+        self.cursors.lock().await.insert(key.to_string(), next);
+        Ok(changes)
+    }
+
+    /// Wikitext of each revision in `revids`.
+    async fn revision_texts(api: &Api, revids: &[u64]) -> Result<HashMap<u64, String>> {
+        let mut ret = HashMap::new();
+        for chunk in revids.chunks(REVISIONS_PER_REQUEST) {
+            let revids = chunk
+                .iter()
+                .map(|revid| revid.to_string())
+                .collect::<Vec<_>>()
+                .join("|");
+            let params: HashMap<String, String> = [
+                ("action", "query"),
+                ("prop", "revisions"),
+                ("rvprop", "ids|content"),
+                ("rvslots", "main"),
+                ("revids", revids.as_str()),
+            ]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+            let result = api.get_query_api_json(&params).await?;
+            let pages: Vec<&Value> = match &result["query"]["pages"] {
+                Value::Object(o) => o.values().collect(),
+                Value::Array(a) => a.iter().collect(),
+                _ => continue,
+            };
+            for rev in pages
+                .iter()
+                .filter_map(|page| page["revisions"].as_array())
+                .flatten()
+            {
+                let main = &rev["slots"]["main"];
+                let text = main["content"].as_str().or_else(|| main["*"].as_str());
+                if let (Some(revid), Some(text)) = (rev["revid"].as_u64(), text) {
+                    ret.insert(revid, text.to_string());
+                }
+            }
+        }
+        Ok(ret)
+    }
+}
+
+/// The text of every call of `template` in `text`, with the template name
+/// normalised, so two revisions compare equal if their lists are set up the
+/// same way.
+fn start_templates(text: &str, template: &str) -> Vec<String> {
+    let name = regex::escape(template).replace(' ', "[ _]");
+    let Ok(re) = RegexBuilder::new(&format!(r"\{{\{{\s*(?:[^{{}}|:]+:)?{name}\s*(\||\}}\}})"))
+        .case_insensitive(true)
+        .build()
+    else {
+        return vec![];
+    };
+    let mut ret = vec![];
+    for m in re.captures_iter(text) {
+        let Some(whole) = m.get(0) else { continue };
+        let params_start = whole.end() - m[1].len();
+        let rest = &text[params_start..];
+        let mut depth = 2_usize;
+        let mut end = None;
+        for (pos, c) in rest.char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        // `pos` is the second brace of the closing `}}`
+                        end = Some(pos - 1);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let params = match end {
+            Some(end) => &rest[..end],
+            None => rest,
+        };
+        ret.push(params.trim().to_string());
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::FileStorage;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    // ── start_templates ────────────────────────────────────────────────────
+
+    #[test]
+    fn test_start_templates() {
+        let text = "intro\n{{Wikidata list|sparql=SELECT {{{1}}}|columns=label}}\nold list\n{{Wikidata list end}}";
+        assert_eq!(
+            start_templates(text, "Wikidata list"),
+            vec!["|sparql=SELECT {{{1}}}|columns=label".to_string()]
+        );
+        // Name variants and a namespace prefix match; the end template does not
+        assert_eq!(
+            start_templates("{{template:wikidata_list|a=1}}", "Wikidata list"),
+            vec!["|a=1".to_string()]
+        );
+        assert!(start_templates("{{Wikidata list end}}", "Wikidata list").is_empty());
+    }
+
+    #[test]
+    fn test_settings_from_json() {
+        let s = RecentChangesSettings::from_json(&json!({"enabled": true, "interval_sec": 0}));
+        assert!(s.enabled);
+        assert_eq!(s.interval_sec, 60);
+        assert!(!s.watch_items);
+        assert_eq!(
+            RecentChangesSettings::from_json(&Value::Null),
+            RecentChangesSettings::default()
+        );
+    }
+
+    // ── mock recentchanges API ─────────────────────────────────────────────
+
+    async fn mock_api(server: &MockServer, recentchanges: Value, revisions: Value) -> Api {
+        Mock::given(method("GET"))
+            .and(path("/w/api.php"))
+            .and(query_param("meta", "siteinfo"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "query": { "general": { "sitename": "Test Wiki" } }
+            })))
+            .mount(server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/w/api.php"))
+            .and(query_param("list", "recentchanges"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "query": { "recentchanges": recentchanges }
+            })))
+            .mount(server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/w/api.php"))
+            .and(query_param("prop", "revisions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "query": { "pages": revisions }
+            })))
+            .mount(server)
+            .await;
+        Api::new(&format!("{}/w/api.php", server.uri()))
+            .await
+            .unwrap()
+    }
+
+    fn revision(revid: u64, text: &str) -> Value {
+        json!({ "revid": revid, "slots": { "main": { "content": text } } })
+    }
+
+    async fn watcher_with_pages(pages: &[&str]) -> (RecentChangesWatcher, StorageBackend) {
+        let config = Configuration::new_from_json(json!({
+            "template_start": "Wikidata list",
+            "template_end": "Wikidata list end",
+            "default_api": "wiki",
+        }))
+        .await
+        .unwrap();
+        let storage = StorageBackend::File(FileStorage::in_memory());
+        storage.add_wikis(&["wiki".to_string()]).await.unwrap();
+        let pages: Vec<String> = pages.iter().map(|p| p.to_string()).collect();
+        storage.add_pages_for_wiki(1, &pages).await.unwrap();
+        let watcher = RecentChangesWatcher::new(Arc::new(config), storage.clone());
+        (watcher, storage)
+    }
+
+    #[tokio::test]
+    async fn test_check_wiki_bumps_changed_start_templates() {
+        let server = MockServer::start().await;
+        let api = mock_api(
+            &server,
+            json!([
+                { "title": "Params", "revid": 11, "old_revid": 10, "timestamp": "2024-01-01T00:00:01Z" },
+                { "title": "List only", "revid": 21, "old_revid": 20, "timestamp": "2024-01-01T00:00:02Z" },
+                { "title": "Not queued", "revid": 31, "old_revid": 30, "timestamp": "2024-01-01T00:00:03Z" },
+            ]),
+            json!([
+                { "revisions": [
+                    revision(10, "{{Wikidata list|columns=label}}\nA\n{{Wikidata list end}}"),
+                    revision(11, "{{Wikidata list|columns=label,P18}}\nA\n{{Wikidata list end}}"),
+                ] },
+                { "revisions": [
+                    revision(20, "{{Wikidata list|columns=label}}\nA\n{{Wikidata list end}}"),
+                    revision(21, "{{Wikidata list|columns=label}}\nA\nB\n{{Wikidata list end}}"),
+                ] },
+            ]),
+        )
+        .await;
+        let (watcher, storage) = watcher_with_pages(&["Params", "List only"]).await;
+        assert_eq!(watcher.check_wiki("wiki", &api).await.unwrap(), 1);
+        let priority = storage.find_priority_page(&[], &[]).await.unwrap().unwrap();
+        assert_eq!(priority.title(), "Params");
+        // The cursor moved on to the newest change
+        assert_eq!(
+            watcher.cursors.lock().await.get("wiki").unwrap().start,
+            "2024-01-01T00:00:03Z"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_poll_is_capped_and_continues() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/w/api.php"))
+            .and(query_param("meta", "siteinfo"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "query": { "general": { "sitename": "Test Wiki" } }
+            })))
+            .mount(&server)
+            .await;
+        // An endless stream of item edits, filtered to entity namespaces
+        Mock::given(method("GET"))
+            .and(path("/w/api.php"))
+            .and(query_param("list", "recentchanges"))
+            .and(query_param("rcnamespace", ITEM_NAMESPACES))
+            .and(query_param("rcprop", "title|timestamp"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "continue": { "rccontinue": "20240101000002|7" },
+                "query": { "recentchanges": [
+                    { "title": "Q42", "timestamp": "2024-01-01T00:00:01Z" },
+                ] }
+            })))
+            .mount(&server)
+            .await;
+        let api = Api::new(&format!("{}/w/api.php", server.uri()))
+            .await
+            .unwrap();
+        let (watcher, _storage) = watcher_with_pages(&[]).await;
+        watcher.check_items(&api).await.unwrap();
+        let requests = server.received_requests().await.unwrap();
+        let polls = requests
+            .iter()
+            .filter(|r| r.url.query().is_some_and(|q| q.contains("recentchanges")))
+            .count();
+        assert_eq!(polls, MAX_REQUESTS_PER_POLL);
+        let cursor = watcher.cursors.lock().await.get(ITEMS_CURSOR).cloned();
+        assert_eq!(
+            cursor.unwrap().rccontinue.as_deref(),
+            Some("20240101000002|7")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_items_bumps_pages_listing_edited_items() {
+        let server = MockServer::start().await;
+        let api = mock_api(
+            &server,
+            json!([
+                { "title": "Q42", "revid": 2, "old_revid": 1, "timestamp": "2024-01-01T00:00:01Z" },
+                { "title": "Item:Q5", "revid": 4, "old_revid": 3, "timestamp": "2024-01-01T00:00:02Z" },
+                { "title": "Talk:Q1", "revid": 6, "old_revid": 5, "timestamp": "2024-01-01T00:00:03Z" },
+            ]),
+            json!([]),
+        )
+        .await;
+        let (watcher, storage) = watcher_with_pages(&["A", "B", "C"]).await;
+        storage
+            .set_page_items("wiki", "A", &["Q42".to_string()])
+            .await
+            .unwrap();
+        storage
+            .set_page_items("wiki", "B", &["Q5".to_string(), "Q42".to_string()])
+            .await
+            .unwrap();
+        storage
+            .set_page_items("wiki", "C", &["Q1".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(watcher.check_items(&api).await.unwrap(), 2);
+        let pages = storage.get_page_statuses("wiki").await.unwrap();
+        let bumped: Vec<&str> = pages
+            .iter()
+            .filter(|p| p.priority)
+            .map(|p| p.page.as_str())
+            .collect();
+        assert_eq!(bumped, vec!["A", "B"]);
+    }
+}
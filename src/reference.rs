@@ -1,6 +1,10 @@
 //! Wikidata reference handling and formatting.
 
+use crate::citation_templates::{CitationField, CitationTemplate, ReferenceShape};
+use crate::date_format::DateFormat;
+use crate::language_fallback::LanguageFallbacks;
 use crate::render_context::RenderContext;
+use crate::result_cell_part::ResultCellPart;
 use serde::{Deserialize, Serialize};
 use wikimisc::wikibase::Snak;
 use wikimisc::wikibase::Value;
@@ -11,6 +15,23 @@ pub struct Reference {
     title: Option<String>,
     date: Option<String>,
     stated_in: Option<String>, // Item
+    /// Author items (P50)
+    #[serde(default)]
+    authors: Vec<String>,
+    /// Author name strings (P2093)
+    #[serde(default)]
+    author_names: Vec<String>,
+    #[serde(default)]
+    publication_date: Option<String>,
+    #[serde(default)]
+    page: Option<String>,
+    #[serde(default)]
+    volume: Option<String>,
+    #[serde(default)]
+    issue: Option<String>,
+    /// The Wikimedia project this was imported from (P143)
+    #[serde(default)]
+    imported_from: Option<String>,
 }
 
 impl PartialEq for Reference {
@@ -19,6 +40,13 @@ impl PartialEq for Reference {
             && self.title == other.title
             && self.date == other.date
             && self.stated_in == other.stated_in
+            && self.authors == other.authors
+            && self.author_names == other.author_names
+            && self.publication_date == other.publication_date
+            && self.page == other.page
+            && self.volume == other.volume
+            && self.issue == other.issue
+            && self.imported_from == other.imported_from
     }
 }
 
@@ -34,8 +62,15 @@ impl Reference {
             match snak.property() {
                 "P854" => Self::extract_reference_url(snak, &mut ret),
                 "P1476" => Self::extract_title(snak, language, &mut ret),
-                "P813" => Self::extract_timestamp(snak, &mut ret),
+                "P813" => ret.date = Self::extract_date(snak, language),
+                "P577" => ret.publication_date = Self::extract_date(snak, language),
                 "P248" => Self::extract_stated_in(snak, &mut ret),
+                "P50" => ret.authors.extend(Self::extract_item(snak)),
+                "P2093" => ret.author_names.extend(Self::extract_string(snak)),
+                "P304" => ret.page = Self::extract_string(snak),
+                "P478" => ret.volume = Self::extract_string(snak),
+                "P433" => ret.issue = Self::extract_string(snak),
+                "P143" => ret.imported_from = Self::extract_item(snak),
                 _ => {}
             }
         }
@@ -48,65 +83,115 @@ impl Reference {
         &self.stated_in
     }
 
+    /// Whether the reference was imported from a Wikimedia project.
+    #[must_use]
+    pub const fn is_imported(&self) -> bool {
+        self.imported_from.is_some()
+    }
+
     /// Returns true if the reference is empty
     const fn is_empty(&self) -> bool {
         self.url.is_none() && self.stated_in.is_none()
     }
 
-    /// Returns the reference as a wikitext string.
-    pub async fn as_reference(&self, list: &impl RenderContext) -> String {
-        let (wikitext, md5) = self.compute_wikitext(list).await;
-        let has_md5 = list.reference_ids().get(&md5).is_some();
-        if has_md5 {
-            format!("<ref name=\"ref_{md5}\" />")
+    /// Which citation template fits this reference; `None` for a bare URL.
+    fn shape(&self) -> Option<ReferenceShape> {
+        let has_details = !self.authors.is_empty()
+            || !self.author_names.is_empty()
+            || self.publication_date.is_some()
+            || self.page.is_some()
+            || self.volume.is_some()
+            || self.issue.is_some();
+        if self.url.is_some() && self.title.is_some() {
+            Some(ReferenceShape::Web)
+        } else if self.stated_in.is_some() && has_details {
+            Some(ReferenceShape::Publication)
+        } else if self.url.is_some() {
+            None
         } else {
+            Some(ReferenceShape::Item)
+        }
+    }
+
+    /// Returns the reference as a wikitext string for the cell at
+    /// `rownum`/`colnum`. Only the cell recorded in `reference_ids` carries
+    /// the full definition; with `references_group=`, all definitions are in
+    /// the block after the list and every use is a short `<ref name=… />`.
+    pub async fn as_reference(&self, list: &impl RenderContext, rownum: usize, colnum: usize) -> String {
+        let (wikitext, md5) = self.compute_wikitext(list).await;
+        let group = list.template_params().references_group();
+        let group_attr = group.map(|g| format!(" group=\"{g}\"")).unwrap_or_default();
+        let is_definition = group.is_none()
+            && list
+                .reference_ids()
+                .get(&md5)
+                .is_none_or(|first_use| *first_use == (rownum, colnum));
+        if is_definition {
             format!("<ref name=\"ref_{md5}\">{wikitext}</ref>")
+        } else {
+            format!("<ref name=\"ref_{md5}\"{group_attr} />")
         }
     }
 
     /// Computes the wikitext and a blake3 hash for this reference.
     /// Returns `(wikitext, hash)`. Results are not cached; callers run this once per render.
-    async fn compute_wikitext(&self, list: &impl RenderContext) -> (String, String) {
-        let mut s = String::new();
+    pub(crate) async fn compute_wikitext(&self, list: &impl RenderContext) -> (String, String) {
+        let template = self
+            .shape()
+            .and_then(|shape| list.citation_templates().get(shape));
 
-        let (use_invoke, use_cite_web) = match list.get_wiki() {
-            Some(wiki) => (wiki.use_invoke(), wiki.use_cite_web()),
-            None => (true, true), // Fallback for unknown wiki, should be fixed manually in DB
-        };
-
-        if use_cite_web && self.title.is_some() && self.url.is_some() {
-            s = self.render_cite_web(use_invoke, list).await;
+        let s = if let Some(template) = template {
+            template.render(&self.citation_values(template, list).await)
         } else if let Some(url) = &self.url {
-            s += url;
+            url.to_owned()
         } else if let Some(q) = &self.stated_in {
-            s += &list.get_item_link_with_fallback(q).await;
-        }
+            list.get_item_link_with_fallback(q).await
+        } else {
+            String::new()
+        };
 
         let hash = blake3::hash(s.as_bytes()).to_hex().to_string();
         (s, hash)
     }
 
-    async fn render_cite_web(&self, use_invoke: bool, list: &impl RenderContext) -> String {
-        let template = if use_invoke {
-            "{{#invoke:cite|web"
-        } else {
-            "{{cite web"
+    /// The values for `template`. Item lookups are only done for fields the
+    /// template actually takes.
+    async fn citation_values(
+        &self,
+        template: &CitationTemplate,
+        list: &impl RenderContext,
+    ) -> Vec<(CitationField, String)> {
+        let mut ret = vec![];
+        let mut push = |field, value: &Option<String>| {
+            if let Some(value) = value {
+                ret.push((field, value.to_owned()));
+            }
         };
-        let mut ret = format!(
-            "{template}|url={}|title={}",
-            self.url.as_deref().unwrap_or(""),
-            self.title.as_deref().unwrap_or("")
-        );
-        if let Some(stated_in) = &self.stated_in {
-            ret += &format!(
-                "|website={}",
-                list.get_item_link_with_fallback(stated_in).await
-            );
+        push(CitationField::Item, &self.stated_in);
+        push(CitationField::Url, &self.url);
+        push(CitationField::Title, &self.title);
+        push(CitationField::Date, &self.publication_date);
+        push(CitationField::Volume, &self.volume);
+        push(CitationField::Issue, &self.issue);
+        push(CitationField::Page, &self.page);
+        push(CitationField::AccessDate, &self.date);
+        if template.uses(CitationField::Website)
+            && let Some(stated_in) = &self.stated_in
+        {
+            let website = list.get_item_link_with_fallback(stated_in).await;
+            ret.push((CitationField::Website, website));
         }
-        if let Some(date) = &self.date {
-            ret += &format!("|access-date={}", &date);
+        if template.uses(CitationField::Author) {
+            let mut authors = Vec::with_capacity(self.authors.len() + self.author_names.len());
+            for author in &self.authors {
+                authors.push(
+                    list.get_label_with_fallback_lang(author, list.language())
+                        .await,
+                );
+            }
+            authors.extend(self.author_names.iter().cloned());
+            ret.push((CitationField::Author, authors.join(", ")));
         }
-        ret += "}}";
         ret
     }
 
@@ -120,95 +205,34 @@ impl Reference {
         }
     }
 
-    /// Extracts the timestamp from a snak
-    fn extract_timestamp(snak: &Snak, ret: &mut Reference) {
+    /// Extracts a date from a snak, formatted for `language`
+    fn extract_date(snak: &Snak, language: &str) -> Option<String> {
         if let Some(dv) = snak.data_value()
             && let Value::Time(tv) = dv.value()
-            && let Some(pos_t) = tv.time().find('T')
+            && let Some(time) = ResultCellPart::reduce_time(tv)
         {
-            let (date, _) = tv.time().split_at(pos_t);
-            let date = date.replace('+', "");
-            let year = Self::parse_year(&date);
-
-            ret.date = Some(Self::format_date_by_precision(date, year, *tv.precision()));
+            let languages = LanguageFallbacks::default().chain(language);
+            return Some(DateFormat::Iso.format(&time, &languages));
         }
+        None
     }
 
-    /// Parses just the year component from a string of the form `YYYY-MM-DD`
-    /// or `-YYYY-MM-DD` (negative years), tolerating year-only or year-month
-    /// inputs.
-    fn parse_year(date: &str) -> Option<i32> {
-        if let Some(rest) = date.strip_prefix('-') {
-            let year_part = rest.split_once('-').map_or(rest, |(y, _)| y);
-            format!("-{year_part}").parse::<i32>().ok()
-        } else {
-            date.split_once('-')
-                .map_or(date, |(y, _)| y)
-                .parse::<i32>()
-                .ok()
+    /// Extracts an item ID from a snak
+    fn extract_item(snak: &Snak) -> Option<String> {
+        match snak.data_value()?.value() {
+            Value::Entity(item) => Some(item.id().to_owned()),
+            _ => None,
         }
     }
 
-    /// Trims a `YYYY-MM-DD` style date to just its year component, preserving
-    /// any leading minus sign for negative years.
-    fn trim_to_year(date: &str) -> String {
-        if let Some(rest) = date.strip_prefix('-') {
-            match rest.find('-') {
-                Some(pos) => format!("-{}", &rest[..pos]),
-                None => date.to_string(),
-            }
-        } else {
-            match date.find('-') {
-                Some(pos) => date[..pos].to_string(),
-                None => date.to_string(),
-            }
+    /// Extracts a string value from a snak
+    fn extract_string(snak: &Snak) -> Option<String> {
+        match snak.data_value()?.value() {
+            Value::StringValue(s) => Some(s.to_owned()),
+            _ => None,
         }
     }
 
-    fn format_date_by_precision(date: String, year: Option<i32>, precision: u64) -> String {
-        match precision {
-            // Day precision or finer: keep full date.
-            p if p >= 11 => date,
-            // Month precision: drop the day.
-            10 => match date.rfind('-') {
-                Some(p) => date[..p].to_string(),
-                None => date,
-            },
-            // Year precision.
-            9 => Self::trim_to_year(&date),
-            // Decade precision: "1990s".
-            8 => year.map_or(date, |y| format!("{}0s", y / 10)),
-            // Century precision: "20th century".
-            7 => year.map_or(date, |y| {
-                let century = if y > 0 { (y - 1) / 100 + 1 } else { y / 100 - 1 };
-                format!("{} century", Self::ordinal_suffix(century))
-            }),
-            // Millennium precision.
-            6 => year.map_or(date, |y| {
-                let millennium = if y > 0 { (y - 1) / 1000 + 1 } else { y / 1000 - 1 };
-                format!("{} millennium", Self::ordinal_suffix(millennium))
-            }),
-            // Lower precisions: fall back to year only.
-            _ => Self::trim_to_year(&date),
-        }
-    }
-
-    /// Converts a number to its ordinal form (e.g., 1 -> "1st", 21 -> "21st")
-    fn ordinal_suffix(n: i32) -> String {
-        let abs_n = n.abs();
-        let suffix = if (abs_n % 100) >= 11 && (abs_n % 100) <= 13 {
-            "th"
-        } else {
-            match abs_n % 10 {
-                1 => "st",
-                2 => "nd",
-                3 => "rd",
-                _ => "th",
-            }
-        };
-        format!("{}{}", n, suffix)
-    }
-
     /// Extracts the title from a snak
     fn extract_title(snak: &Snak, language: &str, ret: &mut Reference) {
         // Title
@@ -284,12 +308,14 @@ mod tests {
             title: Some("Example".to_string()),
             date: Some("2025-01-01".to_string()),
             stated_in: Some("Q123".to_string()),
+            ..Default::default()
         };
         let ref2 = Reference {
             url: Some("https://example.com".to_string()),
             title: Some("Example".to_string()),
             date: Some("2025-01-01".to_string()),
             stated_in: Some("Q123".to_string()),
+            ..Default::default()
         };
         assert_eq!(ref1, ref2);
     }
@@ -367,49 +393,6 @@ mod tests {
         assert_eq!(reference.stated_in(), &None);
     }
 
-    #[test]
-    fn test_ordinal_suffix_basic() {
-        assert_eq!(Reference::ordinal_suffix(1), "1st");
-        assert_eq!(Reference::ordinal_suffix(2), "2nd");
-        assert_eq!(Reference::ordinal_suffix(3), "3rd");
-        assert_eq!(Reference::ordinal_suffix(4), "4th");
-        assert_eq!(Reference::ordinal_suffix(5), "5th");
-    }
-
-    #[test]
-    fn test_ordinal_suffix_teens() {
-        assert_eq!(Reference::ordinal_suffix(11), "11th");
-        assert_eq!(Reference::ordinal_suffix(12), "12th");
-        assert_eq!(Reference::ordinal_suffix(13), "13th");
-    }
-
-    #[test]
-    fn test_ordinal_suffix_twenties() {
-        assert_eq!(Reference::ordinal_suffix(21), "21st");
-        assert_eq!(Reference::ordinal_suffix(22), "22nd");
-        assert_eq!(Reference::ordinal_suffix(23), "23rd");
-        assert_eq!(Reference::ordinal_suffix(24), "24th");
-    }
-
-    #[test]
-    fn test_ordinal_suffix_hundreds() {
-        assert_eq!(Reference::ordinal_suffix(101), "101st");
-        assert_eq!(Reference::ordinal_suffix(111), "111th");
-        assert_eq!(Reference::ordinal_suffix(121), "121st");
-    }
-
-    #[test]
-    fn test_ordinal_suffix_negative() {
-        assert_eq!(Reference::ordinal_suffix(-1), "-1st");
-        assert_eq!(Reference::ordinal_suffix(-2), "-2nd");
-        assert_eq!(Reference::ordinal_suffix(-11), "-11th");
-    }
-
-    #[test]
-    fn test_ordinal_suffix_zero() {
-        assert_eq!(Reference::ordinal_suffix(0), "0th");
-    }
-
     // --- extract_reference_url ---
 
     #[test]
@@ -468,54 +451,42 @@ mod tests {
         assert_eq!(reference.title, None);
     }
 
-    // --- extract_timestamp ---
+    // --- extract_date ---
 
     #[test]
-    fn test_extract_timestamp_day_precision() {
+    fn test_extract_date_day_precision() {
         let snak = Snak::new_time("P813", "+2025-06-15T00:00:00Z", 11);
-        let mut reference = Reference::default();
-        Reference::extract_timestamp(&snak, &mut reference);
-        assert_eq!(reference.date, Some("2025-06-15".to_string()));
+        assert_eq!(Reference::extract_date(&snak, "en"), Some("2025-06-15".to_string()));
     }
 
     #[test]
-    fn test_extract_timestamp_month_precision() {
+    fn test_extract_date_month_precision() {
         let snak = Snak::new_time("P813", "+2025-06-15T00:00:00Z", 10);
-        let mut reference = Reference::default();
-        Reference::extract_timestamp(&snak, &mut reference);
-        assert_eq!(reference.date, Some("2025-06".to_string()));
+        assert_eq!(Reference::extract_date(&snak, "en"), Some("2025-06".to_string()));
     }
 
     #[test]
-    fn test_extract_timestamp_year_precision() {
+    fn test_extract_date_year_precision() {
         let snak = Snak::new_time("P813", "+2025-06-15T00:00:00Z", 9);
-        let mut reference = Reference::default();
-        Reference::extract_timestamp(&snak, &mut reference);
-        assert_eq!(reference.date, Some("2025".to_string()));
+        assert_eq!(Reference::extract_date(&snak, "en"), Some("2025".to_string()));
     }
 
     #[test]
-    fn test_extract_timestamp_decade_precision() {
+    fn test_extract_date_decade_precision() {
         let snak = Snak::new_time("P813", "+1990-01-01T00:00:00Z", 8);
-        let mut reference = Reference::default();
-        Reference::extract_timestamp(&snak, &mut reference);
-        assert_eq!(reference.date, Some("1990s".to_string()));
+        assert_eq!(Reference::extract_date(&snak, "en"), Some("1990s".to_string()));
     }
 
     #[test]
-    fn test_extract_timestamp_century_precision() {
+    fn test_extract_date_century_precision() {
         let snak = Snak::new_time("P813", "+1900-01-01T00:00:00Z", 7);
-        let mut reference = Reference::default();
-        Reference::extract_timestamp(&snak, &mut reference);
-        assert_eq!(reference.date, Some("19th century".to_string()));
+        assert_eq!(Reference::extract_date(&snak, "en"), Some("19th century".to_string()));
     }
 
     #[test]
-    fn test_extract_timestamp_millennium_precision() {
+    fn test_extract_date_millennium_precision() {
         let snak = Snak::new_time("P813", "+2000-01-01T00:00:00Z", 6);
-        let mut reference = Reference::default();
-        Reference::extract_timestamp(&snak, &mut reference);
-        assert_eq!(reference.date, Some("2nd millennium".to_string()));
+        assert_eq!(Reference::extract_date(&snak, "en"), Some("2nd millennium".to_string()));
     }
 
     // --- new_from_snaks ---
@@ -568,50 +539,46 @@ mod tests {
         assert_eq!(reference.title, Some("Test Title".to_string()));
     }
 
-    // --- extract_timestamp edge cases ---
+    // --- extract_date edge cases ---
 
     #[test]
-    fn test_extract_timestamp_precision_below_six_extracts_year() {
+    fn test_extract_date_precision_below_six_extracts_year() {
         // Precision 5 (or lower) hits the final else branch → year only
         let snak = Snak::new_time("P813", "+1234-06-15T00:00:00Z", 5);
-        let mut reference = Reference::default();
-        Reference::extract_timestamp(&snak, &mut reference);
-        assert_eq!(reference.date, Some("1234".to_string()));
+        assert_eq!(Reference::extract_date(&snak, "en"), Some("1234".to_string()));
     }
 
     #[test]
-    fn test_extract_timestamp_precision_zero_extracts_year() {
+    fn test_extract_date_precision_zero_extracts_year() {
         let snak = Snak::new_time("P813", "+2000-01-01T00:00:00Z", 0);
-        let mut reference = Reference::default();
-        Reference::extract_timestamp(&snak, &mut reference);
-        assert_eq!(reference.date, Some("2000".to_string()));
+        assert_eq!(Reference::extract_date(&snak, "en"), Some("2000".to_string()));
     }
 
     #[test]
-    fn test_extract_timestamp_negative_year_century() {
-        // Year -99 → century = -99/100 - 1 = -1, ordinal_suffix(-1) = "-1st"
+    fn test_extract_date_negative_year_century() {
+        // Year -99 is in the 1st century BCE
         let snak = Snak::new_time("P813", "-0099-01-01T00:00:00Z", 7);
-        let mut reference = Reference::default();
-        Reference::extract_timestamp(&snak, &mut reference);
-        assert_eq!(reference.date, Some("-1st century".to_string()));
+        assert_eq!(Reference::extract_date(&snak, "en"), Some("1st century BCE".to_string()));
     }
 
     #[test]
-    fn test_extract_timestamp_negative_year_millennium() {
-        // Year -999 → millennium = -999/1000 - 1 = -1, ordinal_suffix(-1) = "-1st"
+    fn test_extract_date_negative_year_millennium() {
+        // Year -999 is in the 1st millennium BCE
         let snak = Snak::new_time("P813", "-0999-01-01T00:00:00Z", 6);
-        let mut reference = Reference::default();
-        Reference::extract_timestamp(&snak, &mut reference);
-        assert_eq!(reference.date, Some("-1st millennium".to_string()));
+        assert_eq!(Reference::extract_date(&snak, "en"), Some("1st millennium BCE".to_string()));
+    }
+
+    #[test]
+    fn test_extract_date_localised_century() {
+        let snak = Snak::new_time("P813", "+1900-01-01T00:00:00Z", 7);
+        assert_eq!(Reference::extract_date(&snak, "de-ch"), Some("19. Jahrhundert".to_string()));
     }
 
     #[test]
-    fn test_extract_timestamp_decade_boundary() {
+    fn test_extract_date_decade_boundary() {
         // Year 1980 → 1980s (1980/10 = 198, 198*10 = 1980 → "1980s")
         let snak = Snak::new_time("P813", "+1980-06-01T00:00:00Z", 8);
-        let mut reference = Reference::default();
-        Reference::extract_timestamp(&snak, &mut reference);
-        assert_eq!(reference.date, Some("1980s".to_string()));
+        assert_eq!(Reference::extract_date(&snak, "en"), Some("1980s".to_string()));
     }
 
     // --- new_from_snaks edge cases ---
@@ -646,6 +613,73 @@ mod tests {
         assert_eq!(reference.date, Some("2024-06-01".to_string()));
     }
 
+    // --- publication details ---
+
+    #[test]
+    fn test_new_from_snaks_publication_details() {
+        let snaks = vec![
+            Snak::new_item("P248", "Q180445"),
+            Snak::new_item("P50", "Q42"),
+            Snak::new_string("P2093", "Jane Doe"),
+            Snak::new_time("P577", "+1979-10-12T00:00:00Z", 11),
+            Snak::new_string("P304", "12–14"),
+            Snak::new_string("P478", "3"),
+            Snak::new_string("P433", "7"),
+        ];
+        let reference = Reference::new_from_snaks(&snaks, "en").unwrap();
+        assert_eq!(reference.authors, vec!["Q42".to_string()]);
+        assert_eq!(reference.author_names, vec!["Jane Doe".to_string()]);
+        assert_eq!(reference.publication_date, Some("1979-10-12".to_string()));
+        assert_eq!(reference.page, Some("12–14".to_string()));
+        assert_eq!(reference.volume, Some("3".to_string()));
+        assert_eq!(reference.issue, Some("7".to_string()));
+        assert_eq!(reference.shape(), Some(ReferenceShape::Publication));
+        assert!(!reference.is_imported());
+    }
+
+    #[test]
+    fn test_new_from_snaks_imported_from() {
+        let snaks = vec![
+            Snak::new_item("P143", "Q328"),
+            Snak::new_string("P854", "https://en.wikipedia.org/wiki/Foo"),
+        ];
+        let reference = Reference::new_from_snaks(&snaks, "en").unwrap();
+        assert!(reference.is_imported());
+    }
+
+    #[test]
+    fn test_shape() {
+        let web = Reference {
+            url: Some("https://example.com".to_string()),
+            title: Some("Example".to_string()),
+            stated_in: Some("Q1".to_string()),
+            page: Some("5".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(web.shape(), Some(ReferenceShape::Web));
+        let bare_url = Reference {
+            url: Some("https://example.com".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(bare_url.shape(), None);
+        let item = Reference {
+            stated_in: Some("Q1".to_string()),
+            date: Some("2024-01-01".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(item.shape(), Some(ReferenceShape::Item));
+    }
+
+    #[test]
+    fn test_deserialize_without_publication_fields() {
+        let reference: Reference = serde_json::from_str(
+            r#"{"url":null,"title":null,"date":null,"stated_in":"Q42"}"#,
+        )
+        .unwrap();
+        assert_eq!(reference.stated_in(), &Some("Q42".to_string()));
+        assert!(reference.authors.is_empty());
+    }
+
     // --- serialization ---
 
     #[test]
@@ -655,6 +689,7 @@ mod tests {
             title: Some("Test".to_string()),
             date: Some("2025-01-01".to_string()),
             stated_in: Some("Q42".to_string()),
+            ..Default::default()
         };
         let json = serde_json::to_string(&reference).unwrap();
         let deserialized: Reference = serde_json::from_str(&json).unwrap();
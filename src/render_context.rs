@@ -4,13 +4,16 @@
 //! `Reference`) from the concrete `ListeriaList` type, making each
 //! renderer independently testable and easier to extend.
 
+use crate::citation_templates::CitationTemplates;
 use crate::column::Column;
+use crate::date_format::DateFormat;
 use crate::entity_container_wrapper::{EntityContainerWrapper, EntityEntry};
+use crate::listeria_list::LocalPage;
+use crate::messages::Message;
 use crate::my_entity::MyEntity;
 use crate::result_row::ResultRow;
-use crate::template_params::{LinksType, ReferencesParameter, TemplateParams};
-use crate::wiki::Wiki;
-use std::collections::HashSet;
+use crate::template_params::{ImageOptions, LinksType, ReferencesParameter, TemplateParams};
+use std::collections::{HashMap, HashSet};
 use wikimisc::wikibase::Statement;
 
 /// Methods from `ListeriaList` that the rendering layer needs.
@@ -23,6 +26,11 @@ pub trait RenderContext {
     // ── locale & identity ────────────────────────────────────────────────
     fn language(&self) -> &str;
     fn default_language(&self) -> String;
+    /// Language fallback chain for `language` (see [`crate::language_fallback`]).
+    fn language_chain(&self, language: &str) -> Vec<String>;
+    /// `text` wrapped in a `lang=` span if `value_language` is a fallback
+    /// for `requested`; unchanged otherwise.
+    fn mark_language_fallback(&self, text: &str, value_language: &str, requested: &str) -> String;
     fn page_title(&self) -> &str;
     fn wiki(&self) -> &str;
     fn is_main_wikibase_wiki(&self) -> bool;
@@ -39,16 +47,28 @@ pub trait RenderContext {
     fn template_params(&self) -> &TemplateParams;
     fn get_reference_parameter(&self) -> &ReferencesParameter;
     fn thumbnail_size(&self) -> u64;
+    /// `images=` options for column `colnum`.
+    fn image_options(&self, colnum: usize) -> ImageOptions;
     fn local_file_namespace_prefix(&self) -> &str;
+    /// Date format for time values in column `colnum`.
+    fn date_format(&self, colnum: usize) -> DateFormat;
+    fn annotate_julian_dates(&self) -> bool;
 
     // ── column / result access ────────────────────────────────────────────
     fn column(&self, colnum: usize) -> Option<&Column>;
     fn results(&self) -> &Vec<ResultRow>;
-    fn reference_ids(&self) -> &std::collections::HashSet<String>;
+    /// Reference ID → (row, column) of the cell holding its full definition.
+    fn reference_ids(&self) -> &HashMap<String, (usize, usize)>;
+    /// `(reference ID, wikitext)` in order of first use.
+    fn reference_definitions(&self) -> &[(String, String)];
 
     // ── wiki info ─────────────────────────────────────────────────────────
-    fn get_wiki(&self) -> Option<Wiki>;
+    /// The wiki's citation templates, by reference shape.
+    fn citation_templates(&self) -> &CitationTemplates;
     fn get_item_wiki_target(&self, entity_id: &str) -> String;
+    /// What `cache_local_pages_exist` found for a (normalised) local page
+    /// title; `None` if it was not looked up.
+    fn local_page(&self, page: &str) -> Option<&LocalPage>;
     fn get_location_template(
         &self,
         lat: f64,
@@ -71,6 +91,10 @@ pub trait RenderContext {
     fn get_row_template(&self) -> &Option<String>;
     fn section_name(&self, id: usize) -> Option<&str>;
 
+    // ── localisation ──────────────────────────────────────────────────────
+    /// Bot-generated string in the list's language, with `$1`… replaced by `args`.
+    fn message(&self, message: Message, args: &[&str]) -> String;
+
     // ── entity access (async) ─────────────────────────────────────────────
     async fn get_entity(&self, entity_id: &str) -> Option<EntityEntry>;
     async fn get_item_link_with_fallback(&self, entity_id: &str) -> String;
@@ -105,6 +129,22 @@ pub(crate) fn normalize_page_title(s: &str, case_sensitive: bool) -> String {
         .unwrap_or_default()
 }
 
+/// Escapes `&`, `<`, `>`, `"`, and `'` so text is safe to embed in HTML.
+pub(crate) fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            other => out.push(other),
+        }
+    }
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::normalize_page_title;
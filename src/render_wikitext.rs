@@ -2,8 +2,10 @@
 
 use crate::{
     listeria_page::ListeriaPage,
+    messages::Message,
     render_context::RenderContext,
     renderer::Renderer,
+    template_params::OutputFormat,
 };
 use anyhow::Result;
 use futures::future::join_all;
@@ -29,8 +31,10 @@ impl Renderer for RendererWikitext {
         for section_id in list.get_section_ids() {
             wt += &self.as_wikitext_section(list, section_id).await;
         }
+        wt += &Self::as_wikitext_references_block(list);
         if !list.shadow_files().is_empty() {
-            wt += "\n----\nThe following local image(s) are not shown in the above list, because they shadow a Commons image of the same name, and might be non-free:";
+            wt += "\n----\n";
+            wt += &list.message(Message::ShadowFilesNotice, &[]);
             let mut shadow_files: Vec<String> = list.shadow_files().iter().cloned().collect();
             shadow_files.sort(); // For prettier, consistent display
             for file in shadow_files {
@@ -39,8 +43,13 @@ impl Renderer for RendererWikitext {
         }
 
         if let Some("ITEMNUMBER") = list.summary().as_deref() {
-            let label = list.template_params().summary_label();
-            wt += format!("\n----\n&sum; {} {label}.", list.results().len()).as_str();
+            let label = match list.template_params().custom_summary_label() {
+                Some(label) => label.to_string(),
+                None => list.message(Message::SummaryItems, &[]),
+            };
+            let count = list.results().len().to_string();
+            wt += "\n----\n";
+            wt += &list.message(Message::SummaryLine, &[&count, &label]);
         }
 
         Ok(wt)
@@ -70,6 +79,15 @@ impl RendererWikitext {
             wt += &Self::render_header(name);
         }
 
+        if list.template_params().format() == OutputFormat::Gallery {
+            wt += &Self::as_wikitext_gallery(list, section_id).await;
+            return wt;
+        }
+        if let Some(marker) = list.template_params().format().list_marker() {
+            wt += &Self::as_wikitext_list(list, section_id, marker).await;
+            return wt;
+        }
+
         wt += &Self::as_wikitext_table_header(list);
 
         if !(list.skip_table()
@@ -90,6 +108,56 @@ impl RendererWikitext {
         wt
     }
 
+    /// `format=gallery`: the section's rows as a `<gallery>`, sized like the
+    /// first image's column. Rows without an image are left out.
+    async fn as_wikitext_gallery<C: RenderContext>(list: &C, section_id: usize) -> String {
+        let futures: Vec<_> = list
+            .results()
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| row.section() == section_id)
+            .map(|(global_idx, row)| row.as_gallery_line(list, global_idx))
+            .collect();
+        let lines: Vec<(usize, String)> = join_all(futures).await.into_iter().flatten().collect();
+        let Some((first_colnum, _)) = lines.first() else {
+            return String::new();
+        };
+        let size = list
+            .image_options(*first_colnum)
+            .size
+            .unwrap_or_else(|| list.thumbnail_size());
+        let mut wt = format!("<gallery widths=\"{size}px\" heights=\"{size}px\">\n");
+        for (_, line) in &lines {
+            wt += line;
+            wt += "\n";
+        }
+        wt += "</gallery>";
+        wt
+    }
+
+    /// `format=list` and `format=ol`: one list item per row. Rows that come
+    /// out empty are left out.
+    async fn as_wikitext_list<C: RenderContext>(
+        list: &C,
+        section_id: usize,
+        marker: &str,
+    ) -> String {
+        let futures: Vec<_> = list
+            .results()
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| row.section() == section_id)
+            .map(|(global_idx, row)| row.as_list_line(list, global_idx))
+            .collect();
+        join_all(futures)
+            .await
+            .into_iter()
+            .filter(|line| !line.is_empty())
+            .map(|line| format!("{marker} {line}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
     fn as_wikitext_table_header<C: RenderContext>(list: &C) -> String {
         let mut wt = String::new();
         match &list.header_template() {
@@ -116,6 +184,24 @@ impl RendererWikitext {
         wt
     }
 
+    /// With `references_group=`, the list's references are defined in a
+    /// `<references group=… />` block right after the list, so each cell
+    /// only carries a short `<ref name=… />`.
+    fn as_wikitext_references_block<C: RenderContext>(list: &C) -> String {
+        let Some(group) = list.template_params().references_group() else {
+            return String::new();
+        };
+        if list.reference_definitions().is_empty() {
+            return String::new();
+        }
+        let mut wt = format!("\n<references group=\"{group}\">\n");
+        for (id, wikitext) in list.reference_definitions() {
+            wt += &format!("<ref name=\"ref_{id}\">{wikitext}</ref>\n");
+        }
+        wt += "</references>";
+        wt
+    }
+
     fn render_header(name: &str) -> String {
         if name.trim().is_empty() {
             "\n\n\n".to_string()
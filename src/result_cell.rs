@@ -5,6 +5,7 @@ use crate::{
     entity_container_wrapper::EntityContainerWrapper,
     render_context::RenderContext,
     result_cell_part::{PartWithReference, ResultCellPart},
+    template_params::SortValuesParameter,
 };
 use futures::future::join_all;
 use serde::{Deserialize, Serialize};
@@ -42,17 +43,58 @@ impl ResultCell {
             Some(part_with_reference) => match part_with_reference.part() {
                 ResultCellPart::Entity(entity_info) => entity_info.id.clone(),
                 ResultCellPart::LocalLink(link_info) => link_info.page.clone(),
-                ResultCellPart::Time(_display, year) => year.to_string(),
+                ResultCellPart::Time(time) => time.year.to_string(),
                 ResultCellPart::File(s) | ResultCellPart::Uri(s) | ResultCellPart::Text(s) => {
                     s.clone()
                 }
                 ResultCellPart::ExternalId(ext_id_info) => ext_id_info.id.clone(),
+                ResultCellPart::LexemeForm(form) => form.representation.clone(),
                 _ => String::new(),
             },
             None => String::new(),
         }
     }
 
+    /// The cell's values as plain data, for row templates: item IDs, plain
+    /// numbers, ISO dates and `lat,lon`, separated by `; `. `None` if no
+    /// part has one.
+    #[must_use]
+    pub fn raw_value(&self) -> Option<String> {
+        let values: Vec<String> = self
+            .parts
+            .iter()
+            .filter_map(|part| Self::part_raw_value(part.part()))
+            .collect();
+        if values.is_empty() {
+            return None;
+        }
+        Some(Self::escape_sort_value(&values.join("; ")))
+    }
+
+    fn part_raw_value(part: &ResultCellPart) -> Option<String> {
+        Some(match part {
+            ResultCellPart::Entity(entity_info) => entity_info.id.clone(),
+            ResultCellPart::LocalLink(link_info) => link_info
+                .item
+                .clone()
+                .unwrap_or_else(|| link_info.page.clone()),
+            ResultCellPart::Time(time) => time.iso_date(),
+            ResultCellPart::Location(location) => {
+                format!("{},{}", location.latitude, location.longitude)
+            }
+            ResultCellPart::Quantity(amount, _) => amount.to_string(),
+            ResultCellPart::File(s)
+            | ResultCellPart::Uri(s)
+            | ResultCellPart::Text(s)
+            | ResultCellPart::EntitySchema(s) => s.clone(),
+            ResultCellPart::ExternalId(ext_id_info) => ext_id_info.id.clone(),
+            ResultCellPart::LexemeForm(form) => form.representation.clone(),
+            ResultCellPart::Number | ResultCellPart::SnakList(_) | ResultCellPart::AutoDesc(_) => {
+                return None;
+            }
+        })
+    }
+
     #[must_use]
     pub const fn parts(&self) -> &Vec<PartWithReference> {
         &self.parts
@@ -97,44 +139,153 @@ impl ResultCell {
     }
 
     pub async fn as_wikitext(&self, list: &impl RenderContext, rownum: usize, colnum: usize) -> String {
+        let content = self.content_as_wikitext(list, rownum, colnum).await;
+        let sort_value = if list.get_row_template().is_some() {
+            None
+        } else {
+            self.sort_value(list).await
+        };
+        self.get_cell_prefix(list, sort_value.as_deref()) + &content
+    }
+
+    /// The cell's parts as wikitext, without any table-cell attributes.
+    pub async fn content_as_wikitext(
+        &self,
+        list: &impl RenderContext,
+        rownum: usize,
+        colnum: usize,
+    ) -> String {
+        if let Some(gallery) = self.files_as_gallery(list, rownum, colnum).await {
+            return gallery;
+        }
+        self.parts_as_wikitext(list, rownum, colnum)
+            .await
+            .join("<br/>")
+    }
+
+    /// Each part as wikitext, de-duplicated if the cell asks for it.
+    pub async fn parts_as_wikitext(
+        &self,
+        list: &impl RenderContext,
+        rownum: usize,
+        colnum: usize,
+    ) -> Vec<String> {
         let futures: Vec<_> = self
             .parts
             .iter()
             .map(|part| part.as_wikitext(list, rownum, colnum))
             .collect();
-        let mut parts = join_all(futures).await;
+        let parts = join_all(futures).await;
         if self.deduplicate_parts {
-            parts = Self::do_deduplicate_parts(&parts);
+            Self::do_deduplicate_parts(&parts)
+        } else {
+            parts
         }
-        self.get_cell_prefix(list) + &parts.join("<br/>")
     }
 
-    fn get_cell_prefix(&self, list: &impl RenderContext) -> String {
+    /// The first file in the cell, if any.
+    pub fn first_file(&self) -> Option<&str> {
+        self.parts.iter().find_map(|part| match part.part() {
+            ResultCellPart::File(file) => Some(file.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Several files in a column with the `all` image option, as a small
+    /// `<gallery>`; `None` otherwise.
+    async fn files_as_gallery(
+        &self,
+        list: &impl RenderContext,
+        rownum: usize,
+        colnum: usize,
+    ) -> Option<String> {
+        let files: Vec<&str> = self
+            .parts
+            .iter()
+            .map(|part| match part.part() {
+                ResultCellPart::File(file) => Some(file.as_str()),
+                _ => None,
+            })
+            .collect::<Option<_>>()?;
+        let options = list.image_options(colnum);
+        if files.len() < 2 || !options.all {
+            return None;
+        }
+        let futures: Vec<_> = files
+            .iter()
+            .map(|file| ResultCellPart::file_caption(list, file, rownum, colnum))
+            .collect();
+        let captions = join_all(futures).await;
+        let size = options.size.unwrap_or_else(|| list.thumbnail_size());
+        let mut ret = format!("<gallery mode=\"packed\" heights=\"{size}px\">\n");
+        for (file, caption) in files.iter().zip(captions) {
+            ret += &format!("{}:{file}", list.local_file_namespace_prefix());
+            if let Some(caption) = caption {
+                ret += &format!("|{caption}");
+            }
+            ret += "\n";
+        }
+        ret += "</gallery>";
+        Some(ret)
+    }
+
+    /// The `data-sort-value` for this cell, taken from its first part, if the
+    /// list's `sort_values=` mode covers that kind of part. Dates sort at full
+    /// precision, quantities by amount, entities by their plain label.
+    pub async fn sort_value(&self, list: &impl RenderContext) -> Option<String> {
+        let mode = list.template_params().sort_values();
+        if mode == SortValuesParameter::None {
+            return None;
+        }
+        let value = match self.parts.first()?.part() {
+            ResultCellPart::Time(time) => time.sort_value().to_string(),
+            ResultCellPart::Quantity(amount, _) => amount.to_string(),
+            ResultCellPart::Entity(entity_info) if mode.entity_labels() => {
+                Self::entity_sort_label(list, &entity_info.id).await?
+            }
+            _ => return None,
+        };
+        Some(Self::escape_sort_value(&value))
+    }
+
+    async fn entity_sort_label(list: &impl RenderContext, id: &str) -> Option<String> {
+        let entity = list.get_entity(id).await?;
+        let chain = list.language_chain(list.language());
+        Some(EntityContainerWrapper::label_with_fallback_from_entity(
+            &entity, &chain, id,
+        ))
+    }
+
+    /// Sort values end up inside a quoted attribute or a template parameter.
+    fn escape_sort_value(value: &str) -> String {
+        value
+            .replace(['\n', '\r'], " ")
+            .replace('"', "&quot;")
+            .replace('|', "&#124;")
+    }
+
+    fn get_cell_prefix(&self, list: &impl RenderContext, sort_value: Option<&str>) -> String {
         // When a row template is in use, each cell becomes the value of a
         // template parameter (`| pNNN = value`), not a wikitable cell. Emitting
         // table-cell attributes like `data-sort-value="…" |` or `class='…' |`
         // would corrupt those parameter values (issue #83), so suppress them.
+        // Sort values can be passed as separate `<key>_sort` parameters instead.
         if list.get_row_template().is_some() {
             return " ".to_string();
         }
 
-        let time_sort_year = self.parts.first().and_then(|p| match p.part() {
-            ResultCellPart::Time(_, year) => Some(*year),
-            _ => None,
-        });
-
         let wdedit_class = if list.template_params().wdedit() && list.header_template().is_none() {
             self.wdedit_class.as_deref()
         } else {
             None
         };
 
-        match (wdedit_class, time_sort_year) {
-            (Some(class), Some(year)) => {
-                format!("class='{class}' data-sort-value=\"{year}\" | ")
+        match (wdedit_class, sort_value) {
+            (Some(class), Some(value)) => {
+                format!("class='{class}' data-sort-value=\"{value}\" | ")
             }
             (Some(class), None) => format!("class='{class}'| "),
-            (None, Some(year)) => format!(" data-sort-value=\"{year}\" | "),
+            (None, Some(value)) => format!(" data-sort-value=\"{value}\" | "),
             (None, None) => " ".to_string(),
         }
     }
@@ -154,7 +305,7 @@ impl ResultCell {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::result_cell_part::{EntityInfo, ExternalIdInfo, LinkTarget, LocalLinkInfo};
+    use crate::result_cell_part::{EntityInfo, ExternalIdInfo, LinkTarget, LocalLinkInfo, TimeInfo};
 
     fn make_cell(parts: Vec<ResultCellPart>) -> ResultCell {
         let pwrs: Vec<PartWithReference> = parts
@@ -192,7 +343,7 @@ mod tests {
 
     #[test]
     fn test_get_sortkey_time() {
-        let cell = make_cell(vec![ResultCellPart::Time("2024-01-15".to_string(), 2024)]);
+        let cell = make_cell(vec![ResultCellPart::Time(TimeInfo::new(2024, 1, 15, 11))]);
         assert_eq!(cell.get_sortkey(), "2024");
     }
 
@@ -286,6 +437,42 @@ mod tests {
         assert_eq!(result, vec!["x"]);
     }
 
+    // --- escape_sort_value ---
+
+    #[test]
+    fn test_escape_sort_value() {
+        assert_eq!(ResultCell::escape_sort_value("19550608"), "19550608");
+        assert_eq!(
+            ResultCell::escape_sort_value("a \"b\" | c\nd"),
+            "a &quot;b&quot; &#124; c d"
+        );
+    }
+
+    // --- raw_value ---
+
+    #[test]
+    fn test_raw_value() {
+        let cell = make_cell(vec![
+            ResultCellPart::LocalLink(
+                LocalLinkInfo::new(
+                    "Douglas Adams".to_string(),
+                    "Douglas Adams".to_string(),
+                    LinkTarget::Page,
+                )
+                .with_item("Q42"),
+            ),
+            ResultCellPart::Entity(EntityInfo::new("Q5".to_string(), true)),
+            ResultCellPart::Time(TimeInfo::new(-44, 3, 15, 10)),
+            ResultCellPart::Quantity(1.5, Some("Q11573".to_string())),
+            ResultCellPart::Text("a|b".to_string()),
+        ]);
+        assert_eq!(
+            cell.raw_value().as_deref(),
+            Some("Q42; Q5; -0044-03; 1.5; a&#124;b")
+        );
+        assert_eq!(make_cell(vec![ResultCellPart::Number]).raw_value(), None);
+    }
+
     // --- set_parts / parts ---
 
     #[test]
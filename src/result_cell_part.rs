@@ -4,7 +4,10 @@ mod from_snak;
 mod render;
 mod types;
 
-pub use types::{AutoDesc, EntityInfo, ExternalIdInfo, LinkTarget, LocalLinkInfo, LocationInfo};
+pub use types::{
+    AutoDesc, EntityInfo, ExternalIdInfo, LexemeFormInfo, LinkTarget, LocalLinkInfo, LocationInfo,
+    TimeInfo,
+};
 
 use crate::entity_container_wrapper::EntityContainerWrapper;
 use crate::reference::Reference;
@@ -48,7 +51,7 @@ impl PartWithReference {
         let wikitext_reference = if let Some(references) = &self.references {
             let futures: Vec<_> = references
                 .iter()
-                .map(|reference| reference.as_reference(list))
+                .map(|reference| reference.as_reference(list, rownum, colnum))
                 .collect();
             join_all(futures).await.join("")
         } else {
@@ -64,7 +67,7 @@ pub enum ResultCellPart {
     Entity(EntityInfo),
     EntitySchema(String),
     LocalLink(LocalLinkInfo),
-    Time(String, i32), // (display, sort_year)
+    Time(TimeInfo),
     Location(LocationInfo),
     File(String),
     Uri(String),
@@ -73,6 +76,7 @@ pub enum ResultCellPart {
     SnakList(Vec<PartWithReference>), // PP and PQP
     AutoDesc(AutoDesc),
     Quantity(f64, Option<String>), // (amount, unit_entity_id)
+    LexemeForm(LexemeFormInfo),
 }
 
 impl ResultCellPart {
@@ -5,11 +5,14 @@
 //! Pure data → data transforms with no rendering logic; rendering lives in
 //! the parent module's `as_wikitext_*` helpers.
 
-use super::{EntityInfo, ExternalIdInfo, LocationInfo, ResultCellPart};
-use era_date::{Era, Precision};
+use super::{EntityInfo, ExternalIdInfo, LocationInfo, ResultCellPart, TimeInfo};
+use crate::messages::Message;
 use wikimisc::sparql_value::SparqlValue;
 use wikimisc::wikibase::{Snak, SnakDataType, TimeValue, Value};
 
+/// Wikidata item for the proleptic Julian calendar model.
+const JULIAN_CALENDAR: &str = "Q1985786";
+
 impl ResultCellPart {
     pub fn from_sparql_value(v: &SparqlValue) -> Self {
         match v {
@@ -25,8 +28,14 @@ impl ResultCellPart {
     }
 
     pub fn from_snak(snak: &Snak) -> Self {
+        Self::from_snak_localized(snak, Message::NoValue.default_text())
+    }
+
+    /// Like [`Self::from_snak`], using `no_value` as the text for snaks
+    /// without a data value (and for unparseable dates).
+    pub fn from_snak_localized(snak: &Snak, no_value: &str) -> Self {
         let Some(dv) = &snak.data_value() else {
-            return ResultCellPart::Text("No/unknown value".to_string());
+            return ResultCellPart::Text(no_value.to_string());
         };
         match dv.value() {
             Value::Entity(v) => ResultCellPart::Entity(EntityInfo::new(v.id().to_string(), true)),
@@ -34,7 +43,7 @@ impl ResultCellPart {
             Value::Quantity(v) => {
                 ResultCellPart::Quantity(*v.amount(), Self::unit_entity_id_from_url(v.unit()))
             }
-            Value::Time(v) => Self::from_snak_time(v),
+            Value::Time(v) => Self::from_snak_time(v, no_value),
             Value::Coordinate(v) => {
                 ResultCellPart::Location(LocationInfo::new(*v.latitude(), *v.longitude(), None))
             }
@@ -56,10 +65,10 @@ impl ResultCellPart {
         }
     }
 
-    fn from_snak_time(v: &TimeValue) -> Self {
+    fn from_snak_time(v: &TimeValue, no_value: &str) -> Self {
         match ResultCellPart::reduce_time(v) {
-            Some((display, year)) => ResultCellPart::Time(display, year),
-            None => ResultCellPart::Text("No/unknown value".to_string()),
+            Some(time) => ResultCellPart::Time(time),
+            None => ResultCellPart::Text(no_value.to_string()),
         }
     }
 
@@ -89,8 +98,9 @@ impl ResultCellPart {
         year_str.parse::<i32>().ok()
     }
 
-    /// Returns `(display_string, sort_year)` for a Wikidata time value.
-    pub fn reduce_time(v: &TimeValue) -> Option<(String, i32)> {
+    /// Parses a Wikidata time value; `None` if the time string or precision
+    /// is malformed.
+    pub fn reduce_time(v: &TimeValue) -> Option<TimeInfo> {
         let s = v.time();
         // Parse format: +?(-?\d+)-(\d{1,2})-(\d{1,2})T...
         let s = s.strip_prefix('+').unwrap_or(s);
@@ -112,11 +122,14 @@ impl ResultCellPart {
         let (month_str, day_str) = rest.split_once('-')?;
         let month = month_str.parse::<u8>().ok()?;
         let day = day_str.parse::<u8>().ok()?;
-        let precision_val: u8 = (*v.precision()).try_into().ok()?;
-        let precision = Precision::try_from(precision_val).ok()?;
+        let precision: u8 = (*v.precision()).try_into().ok()?;
+        if precision > 14 {
+            return None;
+        }
 
-        let display = Era::new(year, month, day, precision).to_string();
-        Some((display, year))
+        let mut time = TimeInfo::new(year, month, day, precision);
+        time.julian = v.calendarmodel().ends_with(JULIAN_CALENDAR);
+        Some(time)
     }
 }
 
@@ -289,6 +302,16 @@ mod tests {
         assert_eq!(part, ResultCellPart::Text("No/unknown value".to_string()));
     }
 
+    #[test]
+    fn test_from_snak_localized_no_value() {
+        let snak = Snak::new_no_value("P31", SnakDataType::WikibaseItem);
+        let part = ResultCellPart::from_snak_localized(&snak, "Kein/unbekannter Wert");
+        assert_eq!(
+            part,
+            ResultCellPart::Text("Kein/unbekannter Wert".to_string())
+        );
+    }
+
     // --- unit_entity_id_from_url ---
 
     #[test]
@@ -320,9 +343,9 @@ mod tests {
         let snak = Snak::new_time("P569", "+1879-03-14T00:00:00Z", 11);
         let part = ResultCellPart::from_snak(&snak);
         match part {
-            ResultCellPart::Time(s, year) => {
-                assert_eq!(s, "1879-03-14");
-                assert_eq!(year, 1879);
+            ResultCellPart::Time(t) => {
+                assert_eq!(t.display(), "1879-03-14");
+                assert_eq!(t.year, 1879);
             }
             other => panic!("Expected Time, got {:?}", other),
         }
@@ -333,9 +356,9 @@ mod tests {
         let snak = Snak::new_time("P569", "+1955-06-08T00:00:00Z", 11);
         let part = ResultCellPart::from_snak(&snak);
         match part {
-            ResultCellPart::Time(s, year) => {
-                assert_eq!(s, "1955-06-08");
-                assert_eq!(year, 1955);
+            ResultCellPart::Time(t) => {
+                assert_eq!(t.display(), "1955-06-08");
+                assert_eq!(t.year, 1955);
             }
             other => panic!("Expected Time, got {:?}", other),
         }
@@ -343,7 +366,7 @@ mod tests {
 
     // Wikidata renders century-precision dates using the mathematical convention
     // (1st century = years 1-100, 19th century = years 1801-1900, etc.), which
-    // matches `era_date`. Issue #145 (comment 4434859636) reported that the bot
+    // matches `DateFormat`. Issue #145 (comment 4434859636) reported that the bot
     // displayed "20th century" for a Wikidata value that renders on-wiki as
     // "19th century"; verified against the MediaWiki `wbformatvalue` API.
 
@@ -354,9 +377,9 @@ mod tests {
         let snak = Snak::new_time("P569", "+1900-00-00T00:00:00Z", 7);
         let part = ResultCellPart::from_snak(&snak);
         match part {
-            ResultCellPart::Time(s, year) => {
-                assert_eq!(s, "19th century");
-                assert_eq!(year, 1900);
+            ResultCellPart::Time(t) => {
+                assert_eq!(t.display(), "19th century");
+                assert_eq!(t.year, 1900);
             }
             other => panic!("Expected Time, got {:?}", other),
         }
@@ -367,9 +390,9 @@ mod tests {
         let snak = Snak::new_time("P569", "+1901-00-00T00:00:00Z", 7);
         let part = ResultCellPart::from_snak(&snak);
         match part {
-            ResultCellPart::Time(s, year) => {
-                assert_eq!(s, "20th century");
-                assert_eq!(year, 1901);
+            ResultCellPart::Time(t) => {
+                assert_eq!(t.display(), "20th century");
+                assert_eq!(t.year, 1901);
             }
             other => panic!("Expected Time, got {:?}", other),
         }
@@ -380,9 +403,9 @@ mod tests {
         let snak = Snak::new_time("P569", "+2000-00-00T00:00:00Z", 7);
         let part = ResultCellPart::from_snak(&snak);
         match part {
-            ResultCellPart::Time(s, year) => {
-                assert_eq!(s, "20th century");
-                assert_eq!(year, 2000);
+            ResultCellPart::Time(t) => {
+                assert_eq!(t.display(), "20th century");
+                assert_eq!(t.year, 2000);
             }
             other => panic!("Expected Time, got {:?}", other),
         }
@@ -393,9 +416,9 @@ mod tests {
         let snak = Snak::new_time("P569", "+1801-00-00T00:00:00Z", 7);
         let part = ResultCellPart::from_snak(&snak);
         match part {
-            ResultCellPart::Time(s, year) => {
-                assert_eq!(s, "19th century");
-                assert_eq!(year, 1801);
+            ResultCellPart::Time(t) => {
+                assert_eq!(t.display(), "19th century");
+                assert_eq!(t.year, 1801);
             }
             other => panic!("Expected Time, got {:?}", other),
         }
@@ -406,9 +429,9 @@ mod tests {
         let snak = Snak::new_time("P569", "+1800-00-00T00:00:00Z", 7);
         let part = ResultCellPart::from_snak(&snak);
         match part {
-            ResultCellPart::Time(s, year) => {
-                assert_eq!(s, "18th century");
-                assert_eq!(year, 1800);
+            ResultCellPart::Time(t) => {
+                assert_eq!(t.display(), "18th century");
+                assert_eq!(t.year, 1800);
             }
             other => panic!("Expected Time, got {:?}", other),
         }
@@ -420,9 +443,9 @@ mod tests {
         let snak = Snak::new_time("P569", "+0033-00-00T00:00:00Z", 7);
         let part = ResultCellPart::from_snak(&snak);
         match part {
-            ResultCellPart::Time(s, year) => {
-                assert_eq!(s, "1st century");
-                assert_eq!(year, 33);
+            ResultCellPart::Time(t) => {
+                assert_eq!(t.display(), "1st century");
+                assert_eq!(t.year, 33);
             }
             other => panic!("Expected Time, got {:?}", other),
         }
@@ -434,7 +457,7 @@ mod tests {
         let snak = Snak::new_time("P569", "+1900-00-00T00:00:00Z", 8);
         let part = ResultCellPart::from_snak(&snak);
         match part {
-            ResultCellPart::Time(s, _year) => assert_eq!(s, "1900s"),
+            ResultCellPart::Time(t) => assert_eq!(t.display(), "1900s"),
             other => panic!("Expected Time, got {:?}", other),
         }
     }
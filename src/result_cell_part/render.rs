@@ -4,12 +4,18 @@
 //! `RenderContext`. Pure data transforms (Snak/SPARQL conversion) live in
 //! `from_snak.rs`; the data carriers themselves live in `types.rs`.
 
-use super::{LinkTarget, LocationInfo, PartWithReference, ResultCellPart};
+use super::{
+    LexemeFormInfo, LinkTarget, LocationInfo, PartWithReference, ResultCellPart, TimeInfo,
+};
 use crate::column_type::ColumnType;
-use crate::render_context::{normalize_page_title, RenderContext};
-use crate::template_params::LinksType;
+use crate::entity_container_wrapper::EntityContainerWrapper;
+use crate::lexeme;
+use crate::messages::Message;
+use crate::render_context::{escape_html, normalize_page_title, RenderContext};
+use crate::listeria_list::LocalPageStatus;
+use crate::template_params::{DisambiguationMode, LinksType};
 use futures::future::join_all;
-use wikimisc::wikibase::entity::EntityTrait;
+use wikimisc::wikibase::Value;
 
 impl ResultCellPart {
     fn tabbed_string_safe(s: String) -> String {
@@ -48,24 +54,144 @@ impl ResultCellPart {
             };
         }
 
+        if lexeme::is_lexeme_id(id) {
+            return Self::as_wikitext_lexeme(list, id).await;
+        }
+
         let entity_id_link = list.get_item_link_with_fallback(id).await;
         let Some(entity) = list.get_entity(id).await else {
             return entity_id_link;
         };
 
-        let use_language = entity
-            .label_in_locale(list.language())
-            .map_or_else(|| list.default_language(), |_| list.language().to_string());
-
-        let use_label = list.get_label_with_fallback_lang(id, &use_language).await;
+        let chain = list.language_chain(list.language());
+        let (label, label_language) = match EntityContainerWrapper::label_in_chain(&entity, &chain)
+        {
+            Some((label, lang)) => (label.to_string(), Some(lang)),
+            None => (
+                EntityContainerWrapper::label_with_fallback_from_entity(&entity, &chain, id),
+                None,
+            ),
+        };
         let target = list.get_item_wiki_target(id);
         let labeled_entity_link = if list.is_main_wikibase_wiki() {
-            format!("[[{target}|{use_label}]]")
+            format!("[[{target}|{label}]]")
+        } else {
+            format!("''[[{target}|{label}]]''")
+        };
+
+        let description = EntityContainerWrapper::description_in_chain(&entity, &chain)
+            .map(|(description, _lang)| description);
+
+        let links_type = list.get_links_type();
+        let link = Self::render_entity_link(list, label, id, labeled_entity_link, description);
+        // The mark goes around the whole link, never into its label. Red
+        // links are named after the label, so they are left as they are.
+        match label_language {
+            Some(lang) if !matches!(links_type, LinksType::Red | LinksType::RedOnly) => {
+                list.mark_language_fallback(&link, lang, list.language())
+            }
+            _ => link,
+        }
+    }
+
+    /// A label-derived red link that leads to a disambiguation page is kept,
+    /// flagged, or replaced according to the list's `disambiguation=`.
+    fn disambiguation_link(
+        list: &impl RenderContext,
+        link: String,
+        use_label: &str,
+        description: Option<&str>,
+        labeled_entity_link: String,
+    ) -> String {
+        let mode = list.template_params().disambiguation();
+        if mode == DisambiguationMode::Plain {
+            return link;
+        }
+        let page = normalize_page_title(use_label, list.is_case_sensitive_wiki());
+        if list.local_page(&page).map(|p| p.status) != Some(LocalPageStatus::Disambiguation) {
+            return link;
+        }
+        match (mode, description) {
+            (DisambiguationMode::Plain, _) => link,
+            (DisambiguationMode::Flag, _) => {
+                Self::flag_disambiguation(&link, &list.message(Message::DisambiguationLink, &[]))
+            }
+            (DisambiguationMode::Description, Some(description)) => {
+                format!("[[{use_label} ({description})|{use_label}]]")
+            }
+            (DisambiguationMode::Description, None) | (DisambiguationMode::Wikidata, _) => {
+                labeled_entity_link
+            }
+        }
+    }
+
+    /// Wraps a link to a disambiguation page; `title` is the on-wiki message,
+    /// which may contain quotes.
+    fn flag_disambiguation(link: &str, title: &str) -> String {
+        let title = escape_html(title);
+        format!("<span class=\"listeria-disambiguation\" title=\"{title}\">{link}</span>")
+    }
+
+    /// Lexemes and forms show their lemma or representation, senses their
+    /// gloss. On a Wiktionary they link to the entry for the lemma, elsewhere
+    /// to the lexeme on Wikidata.
+    async fn as_wikitext_lexeme(list: &impl RenderContext, id: &str) -> String {
+        let chain = list.language_chain(list.language());
+        let Some(lexeme) = list.ecw().get_lexeme(id) else {
+            return list.get_item_link_with_fallback(id).await;
+        };
+        let Some((label, lang)) = lexeme.label(id, &chain) else {
+            return list.get_item_link_with_fallback(id).await;
+        };
+        let link = if matches!(list.get_links_type(), LinksType::Text) {
+            label.to_string()
+        } else if list.wiki().ends_with("wiktionary")
+            && let Some((lemma, _)) = lexeme.lemma(&chain)
+        {
+            if lemma == label {
+                format!("[[{lemma}]]")
+            } else {
+                format!("[[{lemma}|{label}]]")
+            }
         } else {
-            format!("''[[{target}|{use_label}]]''")
+            let target = list.get_item_wiki_target(id);
+            if list.is_main_wikibase_wiki() {
+                format!("[[{target}|{label}]]")
+            } else {
+                format!("''[[{target}|{label}]]''")
+            }
         };
+        // Lemmas and representations are in the lexeme's own language; only
+        // a gloss can be a fallback. The mark goes around the whole link.
+        if lexeme.sense(id).is_some() {
+            list.mark_language_fallback(&link, lang, list.language())
+        } else {
+            link
+        }
+    }
 
-        Self::render_entity_link(list, use_label, id, labeled_entity_link)
+    /// `cats (plural)`, with the grammatical features' labels.
+    async fn as_wikitext_lexeme_form(list: &impl RenderContext, form: &LexemeFormInfo) -> String {
+        if form.grammatical_features.is_empty() {
+            return form.representation.clone();
+        }
+        let chain = list.language_chain(list.language());
+        let futures: Vec<_> = form
+            .grammatical_features
+            .iter()
+            .map(|feature| list.ecw().get_entity_label_with_fallback(feature, &chain))
+            .collect();
+        let features = join_all(futures).await.join(", ");
+        format!("{} ({features})", form.representation)
+    }
+
+    /// Where `title` redirects to, if the list asks for `redirects=resolve`.
+    fn redirect_target(list: &impl RenderContext, title: &str) -> Option<String> {
+        if !list.template_params().redirects().resolve() {
+            return None;
+        }
+        let page = normalize_page_title(title, list.is_case_sensitive_wiki());
+        list.local_page(&page)?.redirect.clone()
     }
 
     fn as_wikitext_local_link(
@@ -74,6 +200,8 @@ impl ResultCellPart {
         label: &str,
         link_target: &LinkTarget,
     ) -> String {
+        let redirect_target = Self::redirect_target(list, title);
+        let title = redirect_target.as_deref().unwrap_or(title);
         let start = if matches!(link_target, LinkTarget::Category) {
             "[[:"
         } else {
@@ -111,7 +239,7 @@ impl ResultCellPart {
             Some(id) => {
                 let l = list
                     .ecw()
-                    .get_entity_label_with_fallback(id, list.language())
+                    .get_entity_label_with_fallback(id, &list.language_chain(list.language()))
                     .await;
                 if l == *id { None } else { Some(l) }
             }
@@ -126,14 +254,79 @@ impl ResultCellPart {
         )
     }
 
-    fn as_wikitext_file(list: &impl RenderContext, file: &str) -> String {
-        let thumb = list.thumbnail_size();
-        format!(
+    fn as_wikitext_time(list: &impl RenderContext, time: &TimeInfo, colnum: usize) -> String {
+        let languages = list.language_chain(list.language());
+        let text = list.date_format(colnum).format(time, &languages);
+        // The calendar model only matters once months and days are shown
+        if time.julian && time.precision >= 10 && list.annotate_julian_dates() {
+            let note = list.message(Message::JulianCalendar, &[]);
+            format!("{text} <small>({note})</small>")
+        } else {
+            text
+        }
+    }
+
+    async fn as_wikitext_file(
+        list: &impl RenderContext,
+        file: &str,
+        rownum: usize,
+        colnum: usize,
+    ) -> String {
+        let thumb = list
+            .image_options(colnum)
+            .size
+            .unwrap_or_else(|| list.thumbnail_size());
+        let image = format!(
             "[[{}:{}|center|{}px]]",
             list.local_file_namespace_prefix(),
             file,
             thumb
-        )
+        );
+        match Self::file_caption(list, file, rownum, colnum).await {
+            Some(caption) => {
+                format!("{image}<div style=\"text-align:center\"><small>{caption}</small></div>")
+            }
+            None => image,
+        }
+    }
+
+    /// The `caption=` qualifier (see [`crate::template_params::ImageOptions`])
+    /// of the row's statement holding `file`, in the list language or a
+    /// fallback. Only property columns have statements to look at.
+    pub(crate) async fn file_caption(
+        list: &impl RenderContext,
+        file: &str,
+        rownum: usize,
+        colnum: usize,
+    ) -> Option<String> {
+        let caption_property = list.image_options(colnum).caption?;
+        let ColumnType::Property(property) = list.column(colnum)?.obj() else {
+            return None;
+        };
+        let entity_id = list.results().get(rownum)?.entity_id().to_string();
+        let entity = list.get_entity(&entity_id).await?;
+        let file = file.replace('_', " ");
+        let captions: Vec<(String, String)> = list
+            .get_filtered_claims(&entity, property)
+            .iter()
+            .filter(|statement| {
+                matches!(
+                    statement.main_snak().data_value().as_ref().map(|dv| dv.value()),
+                    Some(Value::StringValue(v)) if v.replace('_', " ") == file
+                )
+            })
+            .flat_map(|statement| statement.qualifiers().iter())
+            .filter(|snak| *snak.property() == caption_property)
+            .filter_map(|snak| match snak.data_value().as_ref()?.value() {
+                Value::MonoLingual(v) => Some((v.language().to_string(), v.text().to_string())),
+                _ => None,
+            })
+            .collect();
+        let chain = list.language_chain(list.language());
+        let (lang, caption) = chain
+            .iter()
+            .find_map(|language| captions.iter().find(|(l, _)| l == language))?;
+        Some(list.mark_language_fallback(caption, lang, list.language()))
     }
 
     async fn as_wikitext_external_id(
@@ -221,21 +414,27 @@ impl ResultCellPart {
                 // entity (the common case) see the same output as before.
                 let label = list
                     .ecw()
-                    .get_entity_label_with_fallback(id, list.language())
+                    .get_entity_label_with_fallback(id, &list.language_chain(list.language()))
                     .await;
                 format!("[[EntitySchema:{id}|{label}]]")
             }
-            ResultCellPart::LocalLink(link_info) => Self::as_wikitext_local_link(
-                list,
-                &link_info.page,
-                &link_info.label,
-                &link_info.target,
-            ),
-            ResultCellPart::Time(time, _year) => time.clone(),
+            ResultCellPart::LocalLink(link_info) => {
+                let link = Self::as_wikitext_local_link(
+                    list,
+                    &link_info.page,
+                    &link_info.label,
+                    &link_info.target,
+                );
+                match &link_info.label_language {
+                    Some(lang) => list.mark_language_fallback(&link, lang, list.language()),
+                    None => link,
+                }
+            }
+            ResultCellPart::Time(time) => Self::as_wikitext_time(list, time, colnum),
             ResultCellPart::Location(loc_info) => {
                 Self::as_wikitext_location(list, loc_info, rownum).await
             }
-            ResultCellPart::File(file) => Self::as_wikitext_file(list, file),
+            ResultCellPart::File(file) => Self::as_wikitext_file(list, file, rownum, colnum).await,
             ResultCellPart::Uri(url) => Self::uri_to_wikitext(url),
             ResultCellPart::ExternalId(ext_id_info) => {
                 Self::as_wikitext_external_id(list, &ext_id_info.property, &ext_id_info.id).await
@@ -246,6 +445,7 @@ impl ResultCellPart {
             ResultCellPart::Quantity(amount, unit_id) => {
                 self.as_wikitext_quantity(list, *amount, unit_id.as_deref()).await
             }
+            ResultCellPart::LexemeForm(form) => Self::as_wikitext_lexeme_form(list, form).await,
         }
     }
 
@@ -260,7 +460,7 @@ impl ResultCellPart {
             Some(uid) => {
                 let label = list
                     .ecw()
-                    .get_entity_label_with_fallback(uid, list.language())
+                    .get_entity_label_with_fallback(uid, &list.language_chain(list.language()))
                     .await;
                 format!("{amount_str} {label}")
             }
@@ -277,11 +477,15 @@ impl ResultCellPart {
         Self::tabbed_string_safe(self.as_wikitext(list, rownum, colnum).await)
     }
 
+    /// `use_label` is the plain label (usable as a page title); `display_label`
+    /// may carry a language-fallback marker. `description` is only used for
+    /// links to disambiguation pages.
     fn render_entity_link(
         list: &impl RenderContext,
         use_label: String,
         id: &str,
         labeled_entity_link: String,
+        description: Option<&str>,
     ) -> String {
         match list.get_links_type() {
             LinksType::Text => use_label,
@@ -290,11 +494,12 @@ impl ResultCellPart {
                 // a category inclusion; for everything else use a plain link.
                 // MediaWiki determines blue vs. red based on page existence;
                 // we no longer synthesise a "(Q-id)" page that rarely exists (#137).
-                if use_label.contains(':') {
-                    format!("[[:{}|]]", &use_label)
-                } else {
-                    format!("[[{}]]", &use_label)
-                }
+                let link = match Self::redirect_target(list, &use_label) {
+                    Some(target) => format!("[[:{target}|{use_label}]]"),
+                    None if use_label.contains(':') => format!("[[:{}|]]", &use_label),
+                    None => format!("[[{}]]", &use_label),
+                };
+                Self::disambiguation_link(list, link, &use_label, description, labeled_entity_link)
             }
             LinksType::Reasonator => {
                 format!("[https://reasonator.toolforge.org/?q={id} {use_label}]")
@@ -374,6 +579,17 @@ mod tests {
         assert_eq!(result.len() % 3, 0);
     }
 
+    #[test]
+    fn test_flag_disambiguation_escapes_title() {
+        assert_eq!(
+            ResultCellPart::flag_disambiguation(
+                "[[Mercury]]",
+                r#"Links to a "disambiguation" page"#
+            ),
+            "<span class=\"listeria-disambiguation\" title=\"Links to a &quot;disambiguation&quot; page\">[[Mercury]]</span>"
+        );
+    }
+
     // --- wikipedia_url_to_wikilink (#138) ---
 
     #[test]
@@ -5,6 +5,7 @@
 //! dedicated file makes the `ResultCellPart` enum easier to read and lets
 //! the supporting types own their own unit tests.
 
+use crate::date_format::DateFormat;
 use crate::my_entity::MyEntity;
 use serde::{Deserialize, Serialize};
 use wikimisc::wikibase::entity::EntityTrait;
@@ -33,6 +34,13 @@ pub struct LocalLinkInfo {
     pub page: String,
     pub label: String,
     pub target: LinkTarget,
+    /// The item this link stands for, if it replaced an item link.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item: Option<String>,
+    /// The language `label` was taken from, so the whole link can be marked
+    /// as a language fallback (see [`crate::language_fallback::LanguageFallbacks::mark`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label_language: Option<String>,
 }
 
 impl LocalLinkInfo {
@@ -42,8 +50,22 @@ impl LocalLinkInfo {
             page,
             label,
             target,
+            item: None,
+            label_language: None,
         }
     }
+
+    #[must_use]
+    pub fn with_label_language(mut self, language: &str) -> Self {
+        self.label_language = Some(language.to_string());
+        self
+    }
+
+    #[must_use]
+    pub fn with_item(mut self, item: &str) -> Self {
+        self.item = Some(item.to_string());
+        self
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -71,6 +93,68 @@ impl LocationInfo {
     }
 }
 
+/// A Wikidata time value, reduced to what rendering needs. The text is
+/// produced at render time, in the list's language and `date_format=`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimeInfo {
+    /// Negative for BCE; also the sort key.
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+    /// Wikidata precision: 11 = day, 10 = month, 9 = year, 8 = decade, …
+    pub precision: u8,
+    /// The value is given in the proleptic Julian calendar.
+    #[serde(default)]
+    pub julian: bool,
+}
+
+impl TimeInfo {
+    #[must_use]
+    pub const fn new(year: i32, month: u8, day: u8, precision: u8) -> Self {
+        Self {
+            year,
+            month,
+            day,
+            precision,
+            julian: false,
+        }
+    }
+
+    /// A numeric key ordering values chronologically at their precision:
+    /// `YYYYMMDD` as a signed number, with unknown month/day as `00`.
+    /// Works for BCE years too, since the year dominates.
+    #[must_use]
+    pub fn sort_value(&self) -> i64 {
+        let month = if self.precision >= 10 { self.month } else { 0 };
+        let day = if self.precision >= 11 { self.day } else { 0 };
+        i64::from(self.year) * 10_000 + i64::from(month) * 100 + i64::from(day)
+    }
+
+    /// The date as ISO 8601 at its precision (`1879-03-14`, `1879-03`,
+    /// `1879`), with a `-` for BCE years.
+    #[must_use]
+    pub fn iso_date(&self) -> String {
+        let mut ret = if self.year < 0 {
+            format!("-{:04}", self.year.unsigned_abs())
+        } else {
+            format!("{:04}", self.year)
+        };
+        if self.precision >= 10 {
+            ret += &format!("-{:02}", self.month);
+        }
+        if self.precision >= 11 {
+            ret += &format!("-{:02}", self.day);
+        }
+        ret
+    }
+
+    /// The default (numeric, English) text, for contexts without a list.
+    #[must_use]
+    pub fn display(&self) -> String {
+        DateFormat::Iso.format(self, &[])
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ExternalIdInfo {
     pub property: String,
@@ -84,6 +168,23 @@ impl ExternalIdInfo {
     }
 }
 
+/// One form of a lexeme: its representation and grammatical feature items.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LexemeFormInfo {
+    pub representation: String,
+    pub grammatical_features: Vec<String>,
+}
+
+impl LexemeFormInfo {
+    #[must_use]
+    pub const fn new(representation: String, grammatical_features: Vec<String>) -> Self {
+        Self {
+            representation,
+            grammatical_features,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AutoDesc {
     entity_id: String,
@@ -170,6 +271,9 @@ mod tests {
         assert_eq!(info.page, "Page_Title");
         assert_eq!(info.label, "Page Title");
         assert_eq!(info.target, LinkTarget::Page);
+        assert_eq!(info.item, None);
+        let info = info.with_item("Q42");
+        assert_eq!(info.item.as_deref(), Some("Q42"));
     }
 
     #[test]
@@ -197,6 +301,19 @@ mod tests {
         assert!(info.region.is_none());
     }
 
+    #[test]
+    fn test_time_info_sort_value() {
+        assert_eq!(TimeInfo::new(1955, 6, 8, 11).sort_value(), 19_550_608);
+        // Month and day beyond the precision are ignored
+        assert_eq!(TimeInfo::new(1850, 1, 1, 9).sort_value(), 18_500_000);
+        assert_eq!(TimeInfo::new(1850, 6, 1, 10).sort_value(), 18_500_600);
+        // BCE: later dates within a year still sort after earlier ones
+        let ides = TimeInfo::new(-44, 3, 15, 11).sort_value();
+        assert!(TimeInfo::new(-44, 1, 1, 11).sort_value() < ides);
+        assert!(TimeInfo::new(-45, 12, 31, 11).sort_value() < ides);
+        assert!(ides < TimeInfo::new(1, 1, 1, 11).sort_value());
+    }
+
     #[test]
     fn test_external_id_info_new() {
         let info = ExternalIdInfo::new("P213".to_string(), "0000-0001-2345-6789".to_string());
@@ -15,6 +15,11 @@ pub struct ResultGenerator;
 impl ResultGenerator {
     pub async fn generate_results(list: &mut ListeriaList) -> Result<()> {
         let mut tmp_results: Vec<ResultRow> = Vec::new();
+        let redirected_items = Self::get_ids_from_sparql_rows(list)?
+            .iter()
+            .filter(|id| list.ecw().resolve_id(id) != **id)
+            .count();
+        list.set_redirected_items(redirected_items);
         if list.template_params().one_row_per_item() {
             Self::generate_results_one_row_per_item(list, &mut tmp_results).await?;
         } else {
@@ -44,6 +49,7 @@ impl ResultGenerator {
                 // honour the fallible signature shared with the disk-spilling
                 // backend (see wikimisc::sparql_table::RowStorage).
                 tmp_table.push(row.to_owned())?;
+                let id = list.ecw().resolve_id(&id);
                 if let Some(x) = list.ecw().get_result_row(&id, &tmp_table, list).await {
                     tmp_results.push(x);
                 }
@@ -59,17 +65,26 @@ impl ResultGenerator {
         let var_index = Self::get_var_index(list)?;
         let sparql_row_ids: Vec<String> =
             Self::get_ids_from_sparql_rows(list)?.into_iter().collect();
+        // Rows of a redirect and of its target belong to the same item
         let mut id2rows: HashMap<String, Vec<usize>> = HashMap::new();
         for row_id in 0..list.sparql_table().len() {
             if let Some(SparqlValue::Entity(id)) =
                 list.sparql_table().get_row_col(row_id, var_index)
             {
-                id2rows.entry(id.to_string()).or_default().push(row_id);
+                id2rows
+                    .entry(list.ecw().resolve_id(&id))
+                    .or_default()
+                    .push(row_id);
             };
         }
+        let mut seen: HashSet<String> = HashSet::with_capacity(sparql_row_ids.len());
         for id in &sparql_row_ids {
-            let tmp_rows = Self::get_tmp_rows(list.sparql_table(), &id2rows, id)?;
-            if let Some(row) = list.ecw().get_result_row(id, &tmp_rows, list).await {
+            let id = list.ecw().resolve_id(id);
+            if !seen.insert(id.clone()) {
+                continue;
+            }
+            let tmp_rows = Self::get_tmp_rows(list.sparql_table(), &id2rows, &id)?;
+            if let Some(row) = list.ecw().get_result_row(&id, &tmp_rows, list).await {
                 tmp_results.push(row);
             }
         }
@@ -1,8 +1,9 @@
 //! Table rows containing cells with formatted data.
 
 use crate::{
-    column_type::ColumnType, render_context::RenderContext, result_cell::ResultCell,
-    result_cell_part::ResultCellPart,
+    column::Column, column_type::ColumnType, entity_container_wrapper::EntityContainerWrapper,
+    render_context::RenderContext, result_cell::ResultCell, result_cell_part::ResultCellPart,
+    template_params::OutputFormat,
 };
 use futures::future::join_all;
 use regex::Regex;
@@ -69,9 +70,13 @@ impl ResultRow {
         self.cells = cells;
     }
 
-    /// Remove all but the first part of each cell
-    pub fn remove_excess_files(&mut self) {
-        self.cells.iter_mut().for_each(|cell| {
+    /// Remove all but the first part of each cell holding files, except in
+    /// `keep_all_columns`
+    pub fn remove_excess_files(&mut self, keep_all_columns: &[usize]) {
+        self.cells.iter_mut().enumerate().for_each(|(colnum, cell)| {
+            if keep_all_columns.contains(&colnum) {
+                return;
+            }
             if let Some(part) = cell.parts().first() {
                 let has_files = matches!(*part.part(), ResultCellPart::File(_));
                 if has_files {
@@ -127,9 +132,10 @@ impl ResultRow {
         static RE_LAST_FIRST: LazyLock<Regex> = LazyLock::new(|| {
             Regex::new(r"^(?P<f>.+) (?P<l>\S+)$").expect("RE_LAST_FIRST does not parse")
         });
+        let chain = page.language_chain(page.language());
         match page.get_entity(&self.entity_id).await {
-            Some(entity) => match entity.label_in_locale(page.language()) {
-                Some(label) => {
+            Some(entity) => match EntityContainerWrapper::label_in_chain(&entity, &chain) {
+                Some((label, _lang)) => {
                     let ret = RE_SR_JR.replace_all(label, "");
                     let ret = RE_BRACES.replace_all(&ret, "");
                     let ret = RE_LAST_FIRST.replace_all(&ret, "$l, $f");
@@ -273,8 +279,19 @@ impl ResultRow {
         json!(ret)
     }
 
-    /// Get the cells as wikitext
-    fn cells_as_wikitext(list: &impl RenderContext, cells: &[String]) -> String {
+    /// Get the cells as row template parameters, named by column key (or,
+    /// with `row_template_params=labels`, by explicit column label). With
+    /// `sort_values=yes` or `all`, a cell's sort value, if any, follows it as
+    /// `<name>_sort`, since template parameters cannot carry
+    /// `data-sort-value` attributes; with `row_template_params=raw`, its
+    /// plain value follows as `<name>_raw`.
+    fn cells_as_wikitext(
+        list: &impl RenderContext,
+        cells: &[String],
+        sort_values: &[Option<String>],
+        raw_values: &[Option<String>],
+    ) -> String {
+        let options = list.template_params().row_template_params();
         cells
             .iter()
             .enumerate()
@@ -284,7 +301,17 @@ impl ResultRow {
                     if value.is_empty() {
                         None
                     } else {
-                        Some(format!("{} = {}", column.obj().as_key(), value))
+                        let key = Self::row_template_param_name(column, options.labels());
+                        let mut ret = format!("{key} = {value}");
+                        if let Some(Some(sort_value)) = sort_values.get(colnum) {
+                            ret += &format!("\n| {key}_sort = {sort_value}");
+                        }
+                        if options.raw()
+                            && let Some(Some(raw_value)) = raw_values.get(colnum)
+                        {
+                            ret += &format!("\n| {key}_raw = {raw_value}");
+                        }
+                        Some(ret)
                     }
                 }
                 _ => None,
@@ -293,6 +320,154 @@ impl ResultRow {
             .join("\n| ")
     }
 
+    /// The column's label if asked for and usable as a parameter name,
+    /// otherwise its key.
+    fn row_template_param_name(column: &Column, use_label: bool) -> String {
+        let label = column.label().trim();
+        if use_label
+            && column.has_label()
+            && !label.is_empty()
+            && !label.contains(['=', '|', '{', '}'])
+        {
+            label.to_string()
+        } else {
+            column.obj().as_key()
+        }
+    }
+
+    /// The reserved row template parameters, with `row_template_params=item`:
+    /// `_item`, and `_section` when the list has sections. The leading
+    /// underscore keeps them apart from column keys.
+    fn reserved_params_as_wikitext(&self, list: &impl RenderContext) -> String {
+        let mut ret = format!("_item = {}", self.entity_id);
+        if let Some(section) = list.section_name(self.section).filter(|s| !s.is_empty()) {
+            ret += &format!("\n| _section = {section}");
+        }
+        ret
+    }
+
+    /// The columns whose cells end up in the output of the list's format:
+    /// all of them in a table, the caption columns of a gallery line (none
+    /// if the row has no image), and the columns `pattern=` uses in a list.
+    /// A reference is defined in the first of these that cites it, so every
+    /// `<ref name=… />` has its definition on the page.
+    pub fn emitted_columns(&self, list: &impl RenderContext) -> Vec<usize> {
+        match list.template_params().format() {
+            OutputFormat::Table => (0..self.cells.len()).collect(),
+            OutputFormat::Gallery => self
+                .gallery_columns(list)
+                .map(|(_, columns)| columns)
+                .unwrap_or_default(),
+            OutputFormat::List | OutputFormat::OrderedList => self.list_columns(list),
+        }
+    }
+
+    fn is_number_column(list: &impl RenderContext, colnum: usize) -> bool {
+        matches!(
+            list.column(colnum).map(|column| column.obj()),
+            Some(ColumnType::Number)
+        )
+    }
+
+    /// The column of the row's first image, and the columns captioning it.
+    fn gallery_columns(&self, list: &impl RenderContext) -> Option<(usize, Vec<usize>)> {
+        let image_colnum = self
+            .cells
+            .iter()
+            .position(|cell| cell.first_file().is_some())?;
+        let caption_columns = (0..self.cells.len())
+            .filter(|colnum| *colnum != image_colnum && !Self::is_number_column(list, *colnum))
+            .collect();
+        Some((image_colnum, caption_columns))
+    }
+
+    /// The columns of a list line: those `pattern=` uses (the first column
+    /// with each key), or else all but the number column.
+    fn list_columns(&self, list: &impl RenderContext) -> Vec<usize> {
+        let Some(pattern) = list.template_params().pattern() else {
+            return (0..self.cells.len())
+                .filter(|colnum| !Self::is_number_column(list, *colnum))
+                .collect();
+        };
+        let mut ret: Vec<usize> = pattern
+            .keys()
+            .into_iter()
+            .filter_map(|key| {
+                (0..self.cells.len()).find(|colnum| {
+                    list.column(*colnum)
+                        .is_some_and(|column| column.obj().as_key() == key)
+                })
+            })
+            .collect();
+        ret.sort_unstable();
+        ret.dedup();
+        ret
+    }
+
+    /// The row as a `<gallery>` line: its first image, captioned with the
+    /// other cells, and the image's column. `None` if the row has no image.
+    pub async fn as_gallery_line(
+        &self,
+        list: &impl RenderContext,
+        rownum: usize,
+    ) -> Option<(usize, String)> {
+        let (image_colnum, caption_columns) = self.gallery_columns(list)?;
+        let file = self.cells[image_colnum].first_file()?;
+        let futures: Vec<_> = caption_columns
+            .into_iter()
+            .map(|colnum| self.cells[colnum].content_as_wikitext(list, rownum, colnum))
+            .collect();
+        // A gallery line ends at the first newline
+        let caption = join_all(futures)
+            .await
+            .iter()
+            .map(|cell| cell.replace('\n', " ").trim().to_string())
+            .filter(|cell| !cell.is_empty())
+            .collect::<Vec<_>>()
+            .join("<br/>");
+        let mut line = format!("{}:{file}", list.local_file_namespace_prefix());
+        if !caption.is_empty() {
+            line += &format!("|{caption}");
+        }
+        Some((image_colnum, line))
+    }
+
+    /// The row as one line of `format=list`: the `pattern=` filled in with
+    /// the cells, or else the non-empty cells separated by commas. Values
+    /// within a cell are separated by commas too.
+    pub async fn as_list_line(&self, list: &impl RenderContext, rownum: usize) -> String {
+        let futures: Vec<_> = self
+            .cells
+            .iter()
+            .enumerate()
+            .map(|(colnum, cell)| cell.parts_as_wikitext(list, rownum, colnum))
+            .collect();
+        let cells: Vec<(Option<&ColumnType>, String)> = join_all(futures)
+            .await
+            .into_iter()
+            .enumerate()
+            .map(|(colnum, parts)| {
+                let column = list.column(colnum).map(|column| column.obj());
+                (column, parts.join(", ").replace('\n', " "))
+            })
+            .collect();
+        match list.template_params().pattern() {
+            Some(pattern) => pattern.render(|key| {
+                cells
+                    .iter()
+                    .find(|(column, _)| column.is_some_and(|c| c.as_key() == key))
+                    .map(|(_, value)| value.as_str())
+            }),
+            None => cells
+                .iter()
+                .filter(|(column, _)| !matches!(column, Some(ColumnType::Number)))
+                .map(|(_, value)| value.trim())
+                .filter(|value| !value.is_empty())
+                .collect::<Vec<_>>()
+                .join(", "),
+        }
+    }
+
     /// Get the row as wikitext
     pub async fn as_wikitext(&self, list: &impl RenderContext, rownum: usize) -> String {
         let futures: Vec<_> = self
@@ -303,11 +478,25 @@ impl ResultRow {
             .collect();
         let cells = join_all(futures).await;
         match list.get_row_template() {
-            Some(t) => format!(
-                "{{{{{}\n| {}\n}}}}",
-                t,
-                Self::cells_as_wikitext(list, &cells)
-            ),
+            Some(t) => {
+                let sort_values = if list.template_params().sort_values().row_template() {
+                    let futures: Vec<_> =
+                        self.cells.iter().map(|cell| cell.sort_value(list)).collect();
+                    join_all(futures).await
+                } else {
+                    vec![]
+                };
+                let raw_values: Vec<_> = self.cells.iter().map(ResultCell::raw_value).collect();
+                let mut params = vec![];
+                if list.template_params().row_template_params().item() {
+                    params.push(self.reserved_params_as_wikitext(list));
+                }
+                let cells = Self::cells_as_wikitext(list, &cells, &sort_values, &raw_values);
+                if !cells.is_empty() {
+                    params.push(cells);
+                }
+                format!("{{{{{t}\n| {}\n}}}}", params.join("\n| "))
+            }
             None => "|".to_string() + &cells.join("\n|"),
         }
     }
@@ -560,7 +749,7 @@ mod tests {
             ResultCellPart::File("c.jpg".to_string()),
         ]);
         row.set_cells(vec![cell]);
-        row.remove_excess_files();
+        row.remove_excess_files(&[]);
         assert_eq!(row.cells()[0].parts().len(), 1);
         assert_eq!(
             row.cells()[0].parts()[0].part(),
@@ -576,16 +765,31 @@ mod tests {
             ResultCellPart::Text("world".to_string()),
         ]);
         row.set_cells(vec![cell]);
-        row.remove_excess_files();
+        row.remove_excess_files(&[]);
         assert_eq!(row.cells()[0].parts().len(), 2);
     }
 
+    #[test]
+    fn test_remove_excess_files_keeps_all_in_listed_columns() {
+        let mut row = ResultRow::new("Q1");
+        let files = || {
+            make_cell_with_parts(vec![
+                ResultCellPart::File("a.jpg".to_string()),
+                ResultCellPart::File("b.jpg".to_string()),
+            ])
+        };
+        row.set_cells(vec![files(), files()]);
+        row.remove_excess_files(&[1]);
+        assert_eq!(row.cells()[0].parts().len(), 1);
+        assert_eq!(row.cells()[1].parts().len(), 2);
+    }
+
     #[test]
     fn test_remove_excess_files_empty_cell() {
         let mut row = ResultRow::new("Q1");
         let cell = make_cell_with_parts(vec![]);
         row.set_cells(vec![cell]);
-        row.remove_excess_files();
+        row.remove_excess_files(&[]);
         assert!(row.cells()[0].parts().is_empty());
     }
 
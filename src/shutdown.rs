@@ -0,0 +1,117 @@
+//! Graceful shutdown on SIGTERM/SIGINT.
+//!
+//! A signal only sets a flag: the dispatch loops stop taking new pages,
+//! give the running ones `shutdown_grace_sec` to finish, put the rest back
+//! into the queue with the status they had, and flush storage before they
+//! return. Without this, every page that was running would be marked FAIL
+//! by `reset_running` at the next start.
+
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Cheap to clone; all clones see the same request.
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    requested: Arc<watch::Sender<bool>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self {
+            requested: Arc::new(watch::Sender::new(false)),
+        }
+    }
+}
+
+impl Shutdown {
+    /// A shutdown that is only requested through [`Self::request`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A shutdown that is also requested by SIGTERM or SIGINT.
+    #[must_use]
+    pub fn on_signal() -> Self {
+        let shutdown = Self::new();
+        let on_signal = shutdown.clone();
+        tokio::spawn(async move {
+            match Self::wait_for_signal().await {
+                Ok(()) => {
+                    log::warn!("Shutdown requested, no new pages will be started");
+                    on_signal.request();
+                }
+                Err(e) => log::error!("Cannot listen for shutdown signals: {e}"),
+            }
+        });
+        shutdown
+    }
+
+    #[cfg(unix)]
+    async fn wait_for_signal() -> std::io::Result<()> {
+        use tokio::signal::unix::{SignalKind, signal};
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            _ = terminate.recv() => Ok(()),
+            result = tokio::signal::ctrl_c() => result,
+        }
+    }
+
+    #[cfg(not(unix))]
+    async fn wait_for_signal() -> std::io::Result<()> {
+        tokio::signal::ctrl_c().await
+    }
+
+    pub fn request(&self) {
+        self.requested.send_replace(true);
+    }
+
+    #[must_use]
+    pub fn is_requested(&self) -> bool {
+        *self.requested.borrow()
+    }
+
+    /// Returns once a shutdown has been requested.
+    pub async fn requested(&self) {
+        let mut receiver = self.requested.subscribe();
+        // The sender lives in `self`, so this cannot fail
+        let _ = receiver.wait_for(|requested| *requested).await;
+    }
+
+    /// Returns `grace` after a shutdown has been requested (or after now,
+    /// if it already was).
+    pub async fn grace_expired(&self, grace: Duration) {
+        self.requested().await;
+        tokio::time::sleep(grace).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_request_is_seen_by_clones() {
+        let shutdown = Shutdown::new();
+        let clone = shutdown.clone();
+        assert!(!clone.is_requested());
+        let waiter = tokio::spawn(async move { clone.requested().await });
+        shutdown.request();
+        waiter.await.unwrap();
+        assert!(shutdown.is_requested());
+    }
+
+    #[tokio::test]
+    async fn test_grace_expired() {
+        let shutdown = Shutdown::new();
+        let grace = Duration::from_millis(10);
+        let not_requested =
+            tokio::time::timeout(Duration::from_millis(50), shutdown.grace_expired(grace));
+        assert!(not_requested.await.is_err());
+        shutdown.request();
+        let started = std::time::Instant::now();
+        shutdown.grace_expired(grace).await;
+        assert!(started.elapsed() >= grace);
+    }
+}
@@ -1,10 +1,11 @@
 //! SPARQL query execution with retry logic and rate limiting.
 
+use crate::configuration::MEDIAINFO_API_KEY;
 use crate::listeria_error::ListeriaError;
 use crate::page_params::PageParams;
 use crate::retry::retry_with_backoff;
 use anyhow::Result;
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{sync::Arc, time::Duration};
 use wikimisc::{
     mediawiki::api::Api, sparql_results::SparqlApiResult, sparql_table_vec::SparqlTableVec,
 };
@@ -86,7 +87,7 @@ impl SparqlResults {
         };
         // Acquire the per-endpoint permit so a slow endpoint can't starve
         // concurrent calls to a healthy one.
-        let query_api_url = self.get_sparql_endpoint(&api);
+        let query_api_url = self.get_sparql_endpoint(&api)?;
         let semaphore = self
             .page_params
             .config()
@@ -100,7 +101,7 @@ impl SparqlResults {
         wb_api_sparql: &Api,
         sparql: &str,
     ) -> Result<SparqlTableVec> {
-        let query_api_url = self.get_sparql_endpoint(wb_api_sparql);
+        let query_api_url = self.get_sparql_endpoint(wb_api_sparql)?;
         let circuit_breaker = self
             .page_params
             .config()
@@ -189,44 +190,36 @@ impl SparqlResults {
             // No template
             return Ok(());
         }
-        let api = self.page_params.mw_api();
-        let params: HashMap<String, String> = vec![
-            ("action", "expandtemplates"),
-            ("title", self.page_params.page()),
-            ("prop", "wikitext"),
-            ("text", sparql),
-        ]
-        .into_iter()
-        .map(|(k, v)| (k.to_string(), v.to_string()))
-        .collect();
-        // The underlying reqwest client is already configured with
-        // `api_timeout`, but a future refactor that swaps clients would
-        // silently regress that bound. Wrap the call in a defensive outer
-        // timeout so a hung MW API can never block the SPARQL pipeline
-        // indefinitely.
-        let timeout = self.page_params.config().api_timeout();
-        let j = tokio::time::timeout(timeout, api.get_query_api_json(&params))
-            .await
-            .map_err(|_| {
-                anyhow::anyhow!(
-                    "expandtemplates timed out after {}s",
-                    timeout.as_secs()
-                )
-            })??;
-        if let Some(s) = j["expandtemplates"]["wikitext"].as_str() {
-            *sparql = s.to_string();
-        }
+        *sparql = self.page_params.expand_templates(sparql).await?;
         Ok(())
     }
 
-    fn get_sparql_endpoint(&self, wb_api_sparql: &Api) -> String {
+    /// The configured endpoint, or the one the wikibase names in its site
+    /// info. Commons names none (and its query service needs a login), so
+    /// lists querying it need `commons_query_endpoint`.
+    fn get_sparql_endpoint(&self, wb_api_sparql: &Api) -> Result<String, ListeriaError> {
+        if self.wikibase_key == MEDIAINFO_API_KEY {
+            return self
+                .page_params
+                .config()
+                .commons_query_endpoint()
+                .map(str::to_string)
+                .ok_or_else(|| self.no_endpoint("commons_query_endpoint"));
+        }
         if let Some(endpoint) = &self.query_endpoint {
-            return endpoint.to_owned();
+            return Ok(endpoint.to_owned());
         }
         wb_api_sparql
             .get_site_info_string("general", "wikibase-sparql")
-            .unwrap_or("https://wcqs-beta.wmflabs.org/sparql")
-            .to_string()
+            .map(str::to_string)
+            .map_err(|_| self.no_endpoint("query_endpoint"))
+    }
+
+    fn no_endpoint(&self, setting: &'static str) -> ListeriaError {
+        ListeriaError::SparqlNoEndpoint {
+            wikibase: self.wikibase_key.clone(),
+            setting,
+        }
     }
 
     #[must_use]
@@ -235,10 +228,42 @@ impl SparqlResults {
     }
 }
 
+/// The `LIMIT` of the outermost query, which ends it (before an optional
+/// `OFFSET`). A `LIMIT` of a subquery is followed by its closing brace.
+#[must_use]
+pub fn query_limit(sparql: &str) -> Option<usize> {
+    let tokens: Vec<&str> = sparql.split_whitespace().collect();
+    let pos = tokens
+        .iter()
+        .rposition(|token| token.eq_ignore_ascii_case("limit"))?;
+    match &tokens[pos + 1..] {
+        [limit] => limit.parse().ok(),
+        [limit, offset, _] if offset.eq_ignore_ascii_case("offset") => limit.parse().ok(),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_query_limit() {
+        assert_eq!(
+            query_limit("SELECT ?item { ?item wdt:P31 wd:Q5 } LIMIT 50"),
+            Some(50)
+        );
+        assert_eq!(
+            query_limit("SELECT ?item { ?item wdt:P31 wd:Q5 }\nlimit 50 OFFSET 10"),
+            Some(50)
+        );
+        assert_eq!(query_limit("SELECT ?item { ?item wdt:P31 wd:Q5 }"), None);
+        assert_eq!(
+            query_limit("SELECT ?item { { SELECT ?item { ?item wdt:P31 wd:Q5 } LIMIT 50 } }"),
+            None
+        );
+    }
+
     #[test]
     fn test_sparql_result_parsing_with_item_variable() {
         // Test parsing SPARQL result with "item" variable
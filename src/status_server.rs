@@ -1,8 +1,17 @@
 //! Status server for displaying bot statistics and health information.
 
+use crate::fair_scheduler::{FairScheduler, WikiQueue};
+use crate::render_context::escape_html;
+use crate::storage::{PageStatus, Storage, StorageBackend, normalize_item_ids};
 use crate::wiki_page_result::WikiPageResult;
 use anyhow::Result;
-use axum::{Router, extract::State, response::Html, routing::get};
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::Html,
+    routing::get,
+};
 use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
 use std::{
     collections::HashMap,
@@ -14,21 +23,8 @@ use tokio::sync::RwLock;
 use tower_http::compression::CompressionLayer;
 use tower_http::services::ServeDir;
 
-/// Escapes `&`, `<`, `>`, `"`, and `'` so text is safe to embed in HTML.
-fn escape_html(s: &str) -> String {
-    let mut out = String::with_capacity(s.len());
-    for c in s.chars() {
-        match c {
-            '&' => out.push_str("&amp;"),
-            '<' => out.push_str("&lt;"),
-            '>' => out.push_str("&gt;"),
-            '"' => out.push_str("&quot;"),
-            '\'' => out.push_str("&#39;"),
-            other => out.push(other),
-        }
-    }
-    out
-}
+/// Rows shown in the retry backoff table.
+const MAX_BACKOFF_ROWS: usize = 100;
 
 /// Shared state for the HTTP status server.
 ///
@@ -41,6 +37,10 @@ pub struct AppState {
     pub pages: Arc<RwLock<HashMap<String, WikiPageResult>>>,
     pub started: Instant,
     pub wiki_page_pattern: Option<String>,
+    /// For item lookups; `/items/...` answers 404 without it.
+    pub storage: Option<StorageBackend>,
+    /// The multi-wiki bot's scheduler, for the queue table.
+    pub scheduler: Option<Arc<FairScheduler>>,
 }
 
 #[derive(Debug)]
@@ -126,24 +126,15 @@ impl StatusServer {
             html += "<p class='card-text'><table class='table table-striped'>";
             html += "<thead><tr><th>Page</th><th>Status</th><th>Message</th></tr></thead><tbody>";
             for (page, result) in problems {
-                let link = match wiki_page_pattern {
-                    Some(pattern) => {
-                        format!(
-                            "<a target=\"_blank\" href=\"{}\">{}</a>",
-                            pattern.replace(
-                                "$1",
-                                &utf8_percent_encode(&page.replace(' ', "_"), NON_ALPHANUMERIC)
-                                    .to_string()
-                            ),
-                            escape_html(page)
-                        )
-                    }
-                    None => escape_html(page),
+                let link = Self::page_link(page, wiki_page_pattern);
+                let status = match result.code() {
+                    Some(code) => format!("{} ({})", result.result(), code.as_str()),
+                    None => result.result().to_string(),
                 };
                 html += &format!(
                     "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
                     link,
-                    escape_html(result.result()),
+                    escape_html(&status),
                     escape_html(result.message())
                 );
             }
@@ -152,6 +143,76 @@ impl StatusServer {
         html
     }
 
+    fn page_link(page: &str, wiki_page_pattern: &Option<String>) -> String {
+        match wiki_page_pattern {
+            Some(pattern) => {
+                format!(
+                    "<a target=\"_blank\" href=\"{}\">{}</a>",
+                    pattern.replace(
+                        "$1",
+                        &utf8_percent_encode(&page.replace(' ', "_"), NON_ALPHANUMERIC).to_string()
+                    ),
+                    escape_html(page)
+                )
+            }
+            None => escape_html(page),
+        }
+    }
+
+    /// Pages that failed and wait for their next attempt (see
+    /// [`crate::storage::Backoff`]).
+    fn build_backoff_table(pages: &[PageStatus], wiki_page_pattern: &Option<String>) -> String {
+        let mut html = String::new();
+        if !pages.is_empty() {
+            html += r#"<div class="card"><div class="card-body"><h5 class="card-title">Retry backoff</h5>"#;
+            html += "<p class='card-text'><table class='table table-striped'>";
+            html += "<thead><tr><th>Page</th><th>Status</th><th>Failures</th><th>Next attempt</th></tr></thead><tbody>";
+            for page in pages {
+                let next_attempt = page.next_attempt.as_deref().map_or_else(String::new, |ts| {
+                    chrono::NaiveDateTime::parse_from_str(ts, "%Y%m%d%H%M%S").map_or_else(
+                        |_| ts.to_string(),
+                        |t| t.format("%Y-%m-%d %H:%M UTC").to_string(),
+                    )
+                });
+                html += &format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    Self::page_link(&page.page, wiki_page_pattern),
+                    escape_html(&page.status),
+                    page.fail_count,
+                    next_attempt
+                );
+            }
+            html += "</tbody></table></p></div></div>";
+        }
+        html
+    }
+
+    /// Pages ready and running per wiki (see [`FairScheduler`]).
+    fn build_queue_table(queues: &[WikiQueue]) -> String {
+        let mut html = String::new();
+        if !queues.is_empty() {
+            html +=
+                r#"<div class="card"><div class="card-body"><h5 class="card-title">Queues</h5>"#;
+            html += "<p class='card-text'><table class='table table-striped'>";
+            html += "<thead><tr><th>Wiki</th><th>Queued</th><th>Running</th><th>Weight</th><th>Max running</th></tr></thead><tbody>";
+            for queue in queues {
+                let max_running = queue
+                    .max_running
+                    .map_or_else(String::new, |cap| cap.to_string());
+                html += &format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    escape_html(&queue.wiki),
+                    queue.queued,
+                    queue.running,
+                    queue.weight,
+                    max_running
+                );
+            }
+            html += "</tbody></table></p></div></div>";
+        }
+        html
+    }
+
     async fn status_server_root(State(state): State<AppState>) -> Html<String> {
         let now = Instant::now();
         let mut statistics = ServerStatistics::from_state(&state, now);
@@ -176,23 +237,74 @@ impl StatusServer {
 
         let problems: Vec<_> = snapshot
             .iter()
-            .filter(|(_page, result)| result.result() != "OK")
+            // OK pages can carry notes, e.g. redirected items in the query
+            // or parameter warnings
+            .filter(|(_page, result)| result.result() != "OK" || !result.message().is_empty())
             .map(|(page, result)| (page.clone(), result.clone()))
             .collect();
 
         drop(snapshot);
 
+        let backoff = match &state.storage {
+            Some(storage) => storage
+                .get_backoff_pages(MAX_BACKOFF_ROWS)
+                .await
+                .unwrap_or_else(|e| {
+                    log::warn!("Could not load backoff state: {e}");
+                    vec![]
+                }),
+            None => vec![],
+        };
+
+        let queues = match &state.scheduler {
+            Some(scheduler) => scheduler.queues().await,
+            None => vec![],
+        };
+
         let mut html = Self::build_html_header();
         html += &Self::build_status_card(&statistics);
         html += &Self::build_statistics_table(&statistics.status_counts);
         html += &Self::build_problems_table(&problems, &state.wiki_page_pattern);
+        html += &Self::build_queue_table(&queues);
+        html += &Self::build_backoff_table(&backoff, &state.wiki_page_pattern);
         html += "</body></html>";
         Html(html)
     }
 
+    /// `/items/Q1,Q2`: the pages listing any of the given items, as JSON.
+    async fn pages_for_items(
+        State(state): State<AppState>,
+        Path(items): Path<String>,
+    ) -> (StatusCode, Json<serde_json::Value>) {
+        let Some(storage) = &state.storage else {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({"error": "No item index"})),
+            );
+        };
+        let items = normalize_item_ids(&[items]);
+        match storage.get_pages_for_items(&items).await {
+            Ok(pages) => {
+                let pages: Vec<_> = pages
+                    .into_iter()
+                    .map(|(wiki, page)| serde_json::json!({"wiki": wiki, "page": page}))
+                    .collect();
+                (
+                    StatusCode::OK,
+                    Json(serde_json::json!({"items": items, "pages": pages})),
+                )
+            }
+            Err(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": e.to_string()})),
+            ),
+        }
+    }
+
     pub async fn run(port: u16, state: AppState) -> Result<()> {
         let app = Router::new()
             .route("/", get(Self::status_server_root))
+            .route("/items/{items}", get(Self::pages_for_items))
             .nest_service("/html", ServeDir::new("html"))
             .layer(CompressionLayer::new())
             .with_state(state);
@@ -361,6 +473,59 @@ mod tests {
         assert!(html.contains("Something broke"));
     }
 
+    #[test]
+    fn test_build_backoff_table() {
+        assert!(StatusServer::build_backoff_table(&[], &None).is_empty());
+        let pages = vec![PageStatus {
+            page: "My page".to_string(),
+            status: "BROKEN".to_string(),
+            fail_count: 3,
+            next_attempt: Some("20261018123000".to_string()),
+            ..Default::default()
+        }];
+        let html = StatusServer::build_backoff_table(&pages, &None);
+        assert!(html.contains("Retry backoff"));
+        assert!(
+            html.contains("<td>My page</td><td>BROKEN</td><td>3</td><td>2026-10-18 12:30 UTC</td>")
+        );
+    }
+
+    #[test]
+    fn test_build_queue_table() {
+        assert!(StatusServer::build_queue_table(&[]).is_empty());
+        let queues = vec![
+            WikiQueue {
+                wiki: "enwiki".to_string(),
+                queued: 50_000,
+                running: 4,
+                weight: 2,
+                max_running: Some(4),
+            },
+            WikiQueue {
+                wiki: "<b>".to_string(),
+                queued: 3,
+                running: 0,
+                weight: 1,
+                max_running: None,
+            },
+        ];
+        let html = StatusServer::build_queue_table(&queues);
+        assert!(html.contains("Queues"));
+        assert!(html.contains("<td>enwiki</td><td>50000</td><td>4</td><td>2</td><td>4</td>"));
+        assert!(html.contains("<td>&lt;b&gt;</td><td>3</td><td>0</td><td>1</td><td></td>"));
+    }
+
+    #[test]
+    fn test_build_problems_table_shows_error_code() {
+        let error: anyhow::Error = crate::listeria_error::ListeriaError::EditConflict.into();
+        let problems = vec![(
+            "My page".to_string(),
+            WikiPageResult::from_error("enwiki", "My page", &error),
+        )];
+        let html = StatusServer::build_problems_table(&problems, &None);
+        assert!(html.contains("<td>FAIL (EDIT_CONFLICT)</td>"));
+    }
+
     #[test]
     fn test_build_problems_table_with_pattern_creates_link() {
         let problems = vec![(
@@ -429,6 +594,8 @@ mod tests {
             pages: Arc::new(RwLock::new(HashMap::new())),
             started,
             wiki_page_pattern: None,
+            storage: None,
+            scheduler: None,
         };
         let stats = ServerStatistics::from_state(&app_state, started);
         assert_eq!(stats.uptime_days, 0);
@@ -452,6 +619,8 @@ mod tests {
             pages: Arc::new(RwLock::new(HashMap::new())),
             started: Instant::now(),
             wiki_page_pattern: None,
+            storage: None,
+            scheduler: None,
         };
 
         let app = Router::new()
@@ -489,6 +658,8 @@ mod tests {
             pages: Arc::new(RwLock::new(pages)),
             started: Instant::now(),
             wiki_page_pattern: None,
+            storage: None,
+            scheduler: None,
         };
 
         let app = Router::new()
@@ -508,4 +679,134 @@ mod tests {
         assert!(body.contains("FAIL"));
         assert!(body.contains("timeout"));
     }
+
+    #[tokio::test]
+    async fn test_pages_for_items_json() {
+        use crate::storage::FileStorage;
+        use axum::body::Body;
+        use axum::http::Request;
+        use tower::ServiceExt;
+
+        let storage = StorageBackend::File(FileStorage::in_memory());
+        storage.add_wikis(&["wiki".to_string()]).await.unwrap();
+        let wiki_id = storage.get_wiki_id("wiki").await.unwrap();
+        storage
+            .add_pages_for_wiki(wiki_id, &["A".to_string()])
+            .await
+            .unwrap();
+        storage
+            .set_page_items("wiki", "A", &["Q42".to_string()])
+            .await
+            .unwrap();
+        let state = AppState {
+            pages: Arc::new(RwLock::new(HashMap::new())),
+            started: Instant::now(),
+            wiki_page_pattern: None,
+            storage: Some(storage),
+            scheduler: None,
+        };
+        let app = Router::new()
+            .route("/items/{items}", get(StatusServer::pages_for_items))
+            .with_state(state);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/items/q42,Q5")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body_bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let j: serde_json::Value = serde_json::from_slice(&body_bytes).unwrap();
+        assert_eq!(j["items"], serde_json::json!(["Q42", "Q5"]));
+        assert_eq!(
+            j["pages"],
+            serde_json::json!([{"wiki": "wiki", "page": "A"}])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pages_for_items_without_storage_is_404() {
+        use axum::body::Body;
+        use axum::http::Request;
+        use tower::ServiceExt;
+
+        let state = AppState {
+            pages: Arc::new(RwLock::new(HashMap::new())),
+            started: Instant::now(),
+            wiki_page_pattern: None,
+            storage: None,
+            scheduler: None,
+        };
+        let app = Router::new()
+            .route("/items/{items}", get(StatusServer::pages_for_items))
+            .with_state(state);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/items/Q42")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_status_server_root_lists_ok_pages_with_notes() {
+        use axum::body::Body;
+        use axum::http::Request;
+        use tower::ServiceExt;
+
+        let mut pages = HashMap::new();
+        pages.insert(
+            "Merged".to_string(),
+            WikiPageResult::new(
+                "wiki",
+                "Merged",
+                "OK",
+                "2 redirected item(s) in query results".to_string(),
+            ),
+        );
+        pages.insert(
+            "Typo".to_string(),
+            WikiPageResult::new(
+                "wiki",
+                "Typo",
+                "OK",
+                "unknown parameter 'sort_oder' (did you mean 'sort_order'?)".to_string(),
+            ),
+        );
+        pages.insert(
+            "Fine".to_string(),
+            WikiPageResult::new("wiki", "Fine", "OK", String::new()),
+        );
+        let state = AppState {
+            pages: Arc::new(RwLock::new(pages)),
+            started: Instant::now(),
+            wiki_page_pattern: None,
+            storage: None,
+            scheduler: None,
+        };
+        let app = Router::new()
+            .route("/", get(StatusServer::status_server_root))
+            .with_state(state);
+        let response = app
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body_bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = std::str::from_utf8(&body_bytes).unwrap();
+        assert!(body.contains("2 redirected item(s)"));
+        assert!(body.contains("did you mean &#39;sort_order&#39;?"));
+        assert!(!body.contains("<td>Fine</td>"));
+    }
 }
@@ -0,0 +1,628 @@
+//! Storage backends for the page queue, the wiki list and profiling data.
+//!
+//! The multi-wiki bot keeps its state in MySQL (`pagestatus`, `wikis` and
+//! `list_log`); the single-wiki bot usually runs without a database, next to
+//! a Wikibase Cloud instance. [`Storage`] is what both need from a backend:
+//!
+//! - [`MysqlStorage`] — the Toolforge tables, via [`PageStatusRepository`]
+//!   and [`WikiRepository`](crate::wiki_repository::WikiRepository)
+//! - [`FileStorage`]  — a single JSON file, or memory only
+//!
+//! [`StorageBackend`] wraps either so it can be held in `Configuration` and
+//! passed around without generics.
+//!
+//! [`PageStatusRepository`]: crate::pagestatus_repository::PageStatusRepository
+
+mod file_storage;
+mod mysql_storage;
+
+pub use file_storage::FileStorage;
+pub use mysql_storage::MysqlStorage;
+
+use crate::listeria_error::ErrorCode;
+use crate::page_to_process::PageToProcess;
+use crate::wiki::Wiki;
+use anyhow::Result;
+use chrono::{TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Queue statuses that must not be handed to a bot: already running, gone,
+/// a translation subpage, or parked behind an open circuit breaker.
+pub const IGNORE_STATUS: &[&str] = &["RUNNING", "DELETED", "TRANSLATION", "DEFERRED"];
+
+/// Status of a page that failed in a way another run cannot fix (see
+/// [`ErrorCode::retry`]); it is retried after a long backoff, or as soon as
+/// it becomes a priority page, e.g. after an edit.
+pub const BROKEN_STATUS: &str = "BROKEN";
+
+/// Message of a page released by [`Storage::release_running_page`].
+pub const RELEASED_MESSAGE: &str = "Bot shut down before the page was processed";
+
+/// The first retry of a page that failed without an error code waits this long.
+const DEFAULT_BACKOFF: Duration = Duration::from_secs(15 * 60);
+/// No retry waits longer than this.
+pub const MAX_BACKOFF: Duration = Duration::from_secs(7 * 24 * 3600);
+
+/// How a status change affects a page's failure count and next attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backoff {
+    /// Leave both alone: the page is running, or waits for a circuit breaker.
+    Keep,
+    /// The page is fine; forget earlier failures.
+    Reset,
+    /// Count a failure and wait [`backoff_delay`] from this base.
+    Fail(Duration),
+}
+
+impl Backoff {
+    #[must_use]
+    pub fn for_status(status: &str, error_code: Option<&str>) -> Self {
+        if matches!(status, "RUNNING" | "DEFERRED") {
+            return Self::Keep;
+        }
+        match error_code.and_then(ErrorCode::from_code) {
+            Some(code) => code.backoff_base().map_or(Self::Keep, Self::Fail),
+            None if status == "FAIL" => Self::Fail(DEFAULT_BACKOFF),
+            None => Self::Reset,
+        }
+    }
+}
+
+/// The wait after the `fail_count`th failure in a row: `base`, doubled for
+/// every failure before it, at most [`MAX_BACKOFF`].
+#[must_use]
+pub fn backoff_delay(base: Duration, fail_count: u32) -> Duration {
+    let factor = 2_u32.saturating_pow(fail_count.saturating_sub(1));
+    base.saturating_mul(factor).min(MAX_BACKOFF)
+}
+
+/// One row of the page queue, as stored.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PageStatus {
+    pub id: u64,
+    pub wiki: String,
+    pub page: String,
+    pub status: String,
+    pub message: String,
+    /// `YYYYMMDDHHMMSS` of the last status change; empty for new pages.
+    pub timestamp: String,
+    pub priority: bool,
+    pub last_runtime_sec: Option<u64>,
+    /// `YYYYMMDDHHMMSS` of the last edit the bot made to the page.
+    #[serde(default)]
+    pub last_edit: Option<String>,
+    /// [`ErrorCode`] of the last failure.
+    #[serde(default)]
+    pub error_code: Option<String>,
+    /// Failures in a row; see [`Backoff`].
+    #[serde(default)]
+    pub fail_count: u32,
+    /// `YYYYMMDDHHMMSS` before which the page is not picked from the queue
+    /// (unless it is a priority page).
+    #[serde(default)]
+    pub next_attempt: Option<String>,
+}
+
+impl PageStatus {
+    /// Whether the page's backoff is over at `now`, and, unless it failed
+    /// last time, it was last updated before `updated_before`. Pages
+    /// released from DEFERRED are FAIL without a failure count.
+    fn is_due(&self, now: &str, updated_before: &str) -> bool {
+        self.next_attempt.as_ref().is_none_or(|t| t.as_str() <= now)
+            && (self.status == "FAIL"
+                || self.fail_count > 0
+                || self.timestamp.as_str() < updated_before)
+    }
+
+    #[must_use]
+    pub fn to_page_to_process(&self) -> PageToProcess {
+        PageToProcess::from_parts((
+            self.id,
+            self.page.clone(),
+            self.status.clone(),
+            self.wiki.clone(),
+        ))
+    }
+}
+
+/// Current time in the `YYYYMMDDHHMMSS` format used by all stored timestamps.
+#[must_use]
+pub fn storage_timestamp() -> String {
+    Utc::now().format("%Y%m%d%H%M%S").to_string()
+}
+
+/// [`storage_timestamp`], `delay` from now.
+#[must_use]
+pub fn storage_timestamp_after(delay: Duration) -> String {
+    let delay = TimeDelta::from_std(delay).unwrap_or_default();
+    (Utc::now() + delay).format("%Y%m%d%H%M%S").to_string()
+}
+
+/// [`storage_timestamp`], `age` ago.
+#[must_use]
+pub fn storage_timestamp_before(age: Duration) -> String {
+    let age = TimeDelta::from_std(age).unwrap_or_default();
+    (Utc::now() - age).format("%Y%m%d%H%M%S").to_string()
+}
+
+/// Item IDs as the item index stores them, trimmed and uppercased. Each of
+/// `items` may hold several IDs separated by commas; empty ones are dropped.
+#[must_use]
+pub fn normalize_item_ids<S: AsRef<str>>(items: &[S]) -> Vec<String> {
+    items
+        .iter()
+        .flat_map(|item| item.as_ref().split(','))
+        .map(|item| item.trim().to_uppercase())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// What the bots need from a storage backend.
+#[allow(async_fn_in_trait)]
+pub trait Storage {
+    // ── page queue ────────────────────────────────────────────────────────
+    /// Fails if the storage lacks something this version needs; called at
+    /// startup, before anything else.
+    async fn check_schema(&self) -> Result<()>;
+    /// Marks every RUNNING page as FAIL; called at startup.
+    async fn reset_running(&self) -> Result<()>;
+    /// Removes all DELETED pages from the queue.
+    async fn clear_deleted(&self) -> Result<()>;
+    /// Turns DEFERRED pages back into FAIL so they are picked up again.
+    async fn clear_deferred(&self) -> Result<()>;
+    /// Like [`Self::clear_deferred`], for the pages of `wiki` (or all pages)
+    /// after a circuit breaker closed again.
+    async fn recover_deferred(&self, wiki: Option<&str>) -> Result<()>;
+    /// Turns the longest-waiting DEFERRED page of `wiki` (or of all wikis)
+    /// back into FAIL, as the probe of an open circuit breaker.
+    async fn release_deferred_probe(&self, wiki: Option<&str>) -> Result<()>;
+    /// Puts a page that is still RUNNING back to the status it was picked
+    /// up with, e.g. when the bot shuts down before processing it. The
+    /// priority flag and backoff are kept.
+    async fn release_running_page(&self, page: &PageToProcess) -> Result<()>;
+    /// Records how many seconds a page took to process.
+    async fn set_runtime(&self, pagestatus_id: u64, seconds: u64) -> Result<()>;
+    /// Sets a page's status and error code; every status except RUNNING
+    /// clears its priority. Failures and their backoff are counted as
+    /// [`Backoff::for_status`] says.
+    async fn update_page_status_with_code(
+        &self,
+        page: &str,
+        wiki: &str,
+        status: &str,
+        message: &str,
+        error_code: Option<&str>,
+    ) -> Result<()>;
+    /// Sets a page's status and clears its error code.
+    async fn update_page_status(
+        &self,
+        page: &str,
+        wiki: &str,
+        status: &str,
+        message: &str,
+    ) -> Result<()> {
+        self.update_page_status_with_code(page, wiki, status, message, None)
+            .await
+    }
+    /// Records that the bot has just edited a page.
+    async fn set_last_edit(&self, page: &str, wiki: &str) -> Result<()>;
+    /// The longest-waiting priority page not in `exclude_ids` or `ignore_status`.
+    async fn find_priority_page(
+        &self,
+        exclude_ids: &[u64],
+        ignore_status: &[&str],
+    ) -> Result<Option<PageToProcess>>;
+    /// The longest-waiting page not in `exclude_ids` or `ignore_status`,
+    /// and not waiting for its next attempt.
+    async fn find_oldest_page(
+        &self,
+        exclude_ids: &[u64],
+        ignore_status: &[&str],
+    ) -> Result<Option<PageToProcess>>;
+    /// Like [`Self::find_oldest_page`], restricted to one wiki, and to pages
+    /// last updated before `updated_before`. Failed pages, and pages released
+    /// from DEFERRED, only wait for their backoff.
+    async fn find_oldest_page_for_wiki(
+        &self,
+        wiki: &str,
+        exclude_ids: &[u64],
+        ignore_status: &[&str],
+        updated_before: &str,
+    ) -> Result<Option<PageToProcess>>;
+    /// How many pages [`Self::find_oldest_page_for_wiki`] could return, per
+    /// active wiki. Wikis without such pages are left out.
+    async fn get_queue_depths(
+        &self,
+        ignore_status: &[&str],
+        updated_before: &str,
+    ) -> Result<HashMap<String, usize>>;
+    /// All queued pages of a wiki.
+    async fn get_page_statuses(&self, wiki: &str) -> Result<Vec<PageStatus>>;
+    /// Up to `limit` pages that failed last time, of all wikis, by next attempt.
+    async fn get_backoff_pages(&self, limit: usize) -> Result<Vec<PageStatus>>;
+    /// Flags `pages` of `wiki` as priority pages.
+    async fn set_priority(&self, wiki: &str, pages: &[String]) -> Result<()>;
+
+    // ── wikis ─────────────────────────────────────────────────────────────
+    /// Every known wiki, keyed by name.
+    async fn get_all_wikis(&self) -> Result<HashMap<String, Wiki>>;
+    /// Adds wikis that are not known yet, as ACTIVE.
+    async fn add_wikis(&self, new_wikis: &[String]) -> Result<()>;
+    async fn get_wiki_id(&self, wiki: &str) -> Result<u64>;
+    /// Titles of all queued pages of a wiki.
+    async fn get_pages_for_wiki(&self, wiki: &str) -> Result<Vec<String>>;
+    /// Queues pages that are not queued yet, as WAITING.
+    async fn add_pages_for_wiki(&self, wiki_id: u64, new_pages: &[String]) -> Result<()>;
+
+    // ── item→page index ───────────────────────────────────────────────────
+    /// Replaces the entity IDs listed on a page.
+    async fn set_page_items(&self, wiki: &str, page: &str, items: &[String]) -> Result<()>;
+    /// `(wiki, page)` of every queued page listing one of `items`.
+    async fn get_pages_for_items(&self, items: &[String]) -> Result<Vec<(String, String)>>;
+    /// Flags every page listing one of `items` as a priority page, e.g.
+    /// after an edit to or a merge of those items; returns the pages.
+    async fn prioritize_pages_for_items(&self, items: &[String]) -> Result<Vec<(String, String)>> {
+        let pages = self.get_pages_for_items(items).await?;
+        let mut by_wiki: HashMap<&str, Vec<String>> = HashMap::new();
+        for (wiki, page) in &pages {
+            by_wiki.entry(wiki).or_default().push(page.to_owned());
+        }
+        for (wiki, pages) in &by_wiki {
+            self.set_priority(wiki, pages).await?;
+        }
+        Ok(pages)
+    }
+
+    // ── profiling ─────────────────────────────────────────────────────────
+    /// Stores one profiling checkpoint.
+    async fn log_profile(
+        &self,
+        wiki: &str,
+        page: &str,
+        timestamp: &str,
+        diff_ms: i64,
+        message: &str,
+    ) -> Result<()>;
+    /// Drops all profiling checkpoints.
+    async fn clear_profile_log(&self) -> Result<()>;
+    /// Writes out anything not stored yet; called before the bot exits.
+    async fn flush(&self) -> Result<()>;
+}
+
+/// Either storage backend; see the module docs.
+#[derive(Debug, Clone)]
+pub enum StorageBackend {
+    Mysql(MysqlStorage),
+    File(FileStorage),
+}
+
+impl Storage for StorageBackend {
+    async fn check_schema(&self) -> Result<()> {
+        match self {
+            Self::Mysql(s) => s.check_schema().await,
+            Self::File(s) => s.check_schema().await,
+        }
+    }
+
+    async fn reset_running(&self) -> Result<()> {
+        match self {
+            Self::Mysql(s) => s.reset_running().await,
+            Self::File(s) => s.reset_running().await,
+        }
+    }
+
+    async fn clear_deleted(&self) -> Result<()> {
+        match self {
+            Self::Mysql(s) => s.clear_deleted().await,
+            Self::File(s) => s.clear_deleted().await,
+        }
+    }
+
+    async fn clear_deferred(&self) -> Result<()> {
+        match self {
+            Self::Mysql(s) => s.clear_deferred().await,
+            Self::File(s) => s.clear_deferred().await,
+        }
+    }
+
+    async fn recover_deferred(&self, wiki: Option<&str>) -> Result<()> {
+        match self {
+            Self::Mysql(s) => s.recover_deferred(wiki).await,
+            Self::File(s) => s.recover_deferred(wiki).await,
+        }
+    }
+
+    async fn release_deferred_probe(&self, wiki: Option<&str>) -> Result<()> {
+        match self {
+            Self::Mysql(s) => s.release_deferred_probe(wiki).await,
+            Self::File(s) => s.release_deferred_probe(wiki).await,
+        }
+    }
+
+    async fn release_running_page(&self, page: &PageToProcess) -> Result<()> {
+        match self {
+            Self::Mysql(s) => s.release_running_page(page).await,
+            Self::File(s) => s.release_running_page(page).await,
+        }
+    }
+
+    async fn set_runtime(&self, pagestatus_id: u64, seconds: u64) -> Result<()> {
+        match self {
+            Self::Mysql(s) => s.set_runtime(pagestatus_id, seconds).await,
+            Self::File(s) => s.set_runtime(pagestatus_id, seconds).await,
+        }
+    }
+
+    async fn update_page_status_with_code(
+        &self,
+        page: &str,
+        wiki: &str,
+        status: &str,
+        message: &str,
+        error_code: Option<&str>,
+    ) -> Result<()> {
+        match self {
+            Self::Mysql(s) => {
+                s.update_page_status_with_code(page, wiki, status, message, error_code)
+                    .await
+            }
+            Self::File(s) => {
+                s.update_page_status_with_code(page, wiki, status, message, error_code)
+                    .await
+            }
+        }
+    }
+
+    async fn set_last_edit(&self, page: &str, wiki: &str) -> Result<()> {
+        match self {
+            Self::Mysql(s) => s.set_last_edit(page, wiki).await,
+            Self::File(s) => s.set_last_edit(page, wiki).await,
+        }
+    }
+
+    async fn find_priority_page(
+        &self,
+        exclude_ids: &[u64],
+        ignore_status: &[&str],
+    ) -> Result<Option<PageToProcess>> {
+        match self {
+            Self::Mysql(s) => s.find_priority_page(exclude_ids, ignore_status).await,
+            Self::File(s) => s.find_priority_page(exclude_ids, ignore_status).await,
+        }
+    }
+
+    async fn find_oldest_page(
+        &self,
+        exclude_ids: &[u64],
+        ignore_status: &[&str],
+    ) -> Result<Option<PageToProcess>> {
+        match self {
+            Self::Mysql(s) => s.find_oldest_page(exclude_ids, ignore_status).await,
+            Self::File(s) => s.find_oldest_page(exclude_ids, ignore_status).await,
+        }
+    }
+
+    async fn find_oldest_page_for_wiki(
+        &self,
+        wiki: &str,
+        exclude_ids: &[u64],
+        ignore_status: &[&str],
+        updated_before: &str,
+    ) -> Result<Option<PageToProcess>> {
+        match self {
+            Self::Mysql(s) => {
+                s.find_oldest_page_for_wiki(wiki, exclude_ids, ignore_status, updated_before)
+                    .await
+            }
+            Self::File(s) => {
+                s.find_oldest_page_for_wiki(wiki, exclude_ids, ignore_status, updated_before)
+                    .await
+            }
+        }
+    }
+
+    async fn get_queue_depths(
+        &self,
+        ignore_status: &[&str],
+        updated_before: &str,
+    ) -> Result<HashMap<String, usize>> {
+        match self {
+            Self::Mysql(s) => s.get_queue_depths(ignore_status, updated_before).await,
+            Self::File(s) => s.get_queue_depths(ignore_status, updated_before).await,
+        }
+    }
+
+    async fn get_page_statuses(&self, wiki: &str) -> Result<Vec<PageStatus>> {
+        match self {
+            Self::Mysql(s) => s.get_page_statuses(wiki).await,
+            Self::File(s) => s.get_page_statuses(wiki).await,
+        }
+    }
+
+    async fn get_backoff_pages(&self, limit: usize) -> Result<Vec<PageStatus>> {
+        match self {
+            Self::Mysql(s) => s.get_backoff_pages(limit).await,
+            Self::File(s) => s.get_backoff_pages(limit).await,
+        }
+    }
+
+    async fn set_priority(&self, wiki: &str, pages: &[String]) -> Result<()> {
+        match self {
+            Self::Mysql(s) => s.set_priority(wiki, pages).await,
+            Self::File(s) => s.set_priority(wiki, pages).await,
+        }
+    }
+
+    async fn get_all_wikis(&self) -> Result<HashMap<String, Wiki>> {
+        match self {
+            Self::Mysql(s) => s.get_all_wikis().await,
+            Self::File(s) => s.get_all_wikis().await,
+        }
+    }
+
+    async fn add_wikis(&self, new_wikis: &[String]) -> Result<()> {
+        match self {
+            Self::Mysql(s) => s.add_wikis(new_wikis).await,
+            Self::File(s) => s.add_wikis(new_wikis).await,
+        }
+    }
+
+    async fn get_wiki_id(&self, wiki: &str) -> Result<u64> {
+        match self {
+            Self::Mysql(s) => s.get_wiki_id(wiki).await,
+            Self::File(s) => s.get_wiki_id(wiki).await,
+        }
+    }
+
+    async fn get_pages_for_wiki(&self, wiki: &str) -> Result<Vec<String>> {
+        match self {
+            Self::Mysql(s) => s.get_pages_for_wiki(wiki).await,
+            Self::File(s) => s.get_pages_for_wiki(wiki).await,
+        }
+    }
+
+    async fn add_pages_for_wiki(&self, wiki_id: u64, new_pages: &[String]) -> Result<()> {
+        match self {
+            Self::Mysql(s) => s.add_pages_for_wiki(wiki_id, new_pages).await,
+            Self::File(s) => s.add_pages_for_wiki(wiki_id, new_pages).await,
+        }
+    }
+
+    async fn set_page_items(&self, wiki: &str, page: &str, items: &[String]) -> Result<()> {
+        match self {
+            Self::Mysql(s) => s.set_page_items(wiki, page, items).await,
+            Self::File(s) => s.set_page_items(wiki, page, items).await,
+        }
+    }
+
+    async fn get_pages_for_items(&self, items: &[String]) -> Result<Vec<(String, String)>> {
+        match self {
+            Self::Mysql(s) => s.get_pages_for_items(items).await,
+            Self::File(s) => s.get_pages_for_items(items).await,
+        }
+    }
+
+    async fn log_profile(
+        &self,
+        wiki: &str,
+        page: &str,
+        timestamp: &str,
+        diff_ms: i64,
+        message: &str,
+    ) -> Result<()> {
+        match self {
+            Self::Mysql(s) => s.log_profile(wiki, page, timestamp, diff_ms, message).await,
+            Self::File(s) => s.log_profile(wiki, page, timestamp, diff_ms, message).await,
+        }
+    }
+
+    async fn clear_profile_log(&self) -> Result<()> {
+        match self {
+            Self::Mysql(s) => s.clear_profile_log().await,
+            Self::File(s) => s.clear_profile_log().await,
+        }
+    }
+
+    async fn flush(&self) -> Result<()> {
+        match self {
+            Self::Mysql(s) => s.flush().await,
+            Self::File(s) => s.flush().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_status_to_page_to_process() {
+        let ps = PageStatus {
+            id: 7,
+            wiki: "wiki".to_string(),
+            page: "Foo".to_string(),
+            status: "WAITING".to_string(),
+            ..Default::default()
+        };
+        let p = ps.to_page_to_process();
+        assert_eq!(p.id(), 7);
+        assert_eq!(p.title(), "Foo");
+        assert_eq!(p.status(), "WAITING");
+        assert_eq!(p.wiki(), "wiki");
+    }
+
+    #[test]
+    fn test_backoff_for_status() {
+        assert_eq!(Backoff::for_status("RUNNING", None), Backoff::Keep);
+        assert_eq!(
+            Backoff::for_status("DEFERRED", Some("CIRCUIT_OPEN")),
+            Backoff::Keep
+        );
+        assert_eq!(Backoff::for_status("OK", None), Backoff::Reset);
+        assert_eq!(
+            Backoff::for_status("FAIL", None),
+            Backoff::Fail(DEFAULT_BACKOFF)
+        );
+        assert_eq!(
+            Backoff::for_status("BROKEN", Some("PROTECTED_PAGE")),
+            Backoff::Fail(Duration::from_secs(24 * 3600))
+        );
+    }
+
+    #[test]
+    fn test_backoff_delay_doubles_up_to_max() {
+        let base = Duration::from_secs(60);
+        assert_eq!(backoff_delay(base, 1), base);
+        assert_eq!(backoff_delay(base, 2), base * 2);
+        assert_eq!(backoff_delay(base, 4), base * 8);
+        assert_eq!(backoff_delay(base, 40), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_storage_timestamp_format() {
+        let ts = storage_timestamp();
+        assert_eq!(ts.len(), 14);
+        assert!(ts.chars().all(|c| c.is_ascii_digit()));
+    }
+
+    #[test]
+    fn test_normalize_item_ids() {
+        assert_eq!(
+            normalize_item_ids(&[" q42, Q5,", "m7"]),
+            ["Q42", "Q5", "M7"]
+        );
+        assert!(normalize_item_ids::<&str>(&[]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_prioritize_pages_for_items() {
+        let storage = StorageBackend::File(FileStorage::in_memory());
+        storage.add_wikis(&["wiki".to_string()]).await.unwrap();
+        let wiki_id = storage.get_wiki_id("wiki").await.unwrap();
+        let pages = ["A".to_string(), "B".to_string()];
+        storage.add_pages_for_wiki(wiki_id, &pages).await.unwrap();
+        storage
+            .set_page_items("wiki", "A", &["Q42".to_string()])
+            .await
+            .unwrap();
+        storage
+            .set_page_items("wiki", "B", &["Q5".to_string()])
+            .await
+            .unwrap();
+
+        // A merge of Q42 into Q5 touches both
+        let merged = ["Q42".to_string(), "Q5".to_string()];
+        let bumped = storage.prioritize_pages_for_items(&merged).await.unwrap();
+        assert_eq!(bumped.len(), 2);
+        let statuses = storage.get_page_statuses("wiki").await.unwrap();
+        assert!(statuses.iter().all(|ps| ps.priority));
+
+        let none = storage
+            .prioritize_pages_for_items(&["Q1".to_string()])
+            .await
+            .unwrap();
+        assert!(none.is_empty());
+    }
+}
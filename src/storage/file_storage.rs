@@ -0,0 +1,936 @@
+//! [`Storage`] in a single JSON file, for installations without MySQL.
+//!
+//! The whole state is held in memory and written back after every change,
+//! to a temporary file that then replaces the old one, so a crash never
+//! leaves a half-written file behind. That is plenty for the few hundred
+//! list pages of a typical Wikibase Cloud wiki. Profiling checkpoints are
+//! the exception: they come many per page, so they are written with the
+//! next change, or by [`Storage::flush`] at shutdown. Without a path the
+//! store is memory-only and starts empty on every run.
+
+use super::{
+    Backoff, PageStatus, RELEASED_MESSAGE, Storage, backoff_delay, storage_timestamp,
+    storage_timestamp_after,
+};
+use crate::page_to_process::PageToProcess;
+use crate::wiki::Wiki;
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Profiling checkpoints beyond this many are dropped, oldest first.
+const MAX_PROFILE_ENTRIES: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct WikiRecord {
+    id: u64,
+    name: String,
+    status: String,
+    timestamp: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ProfileEntry {
+    wiki: String,
+    page: String,
+    timestamp: String,
+    diff_ms: i64,
+    message: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct FileState {
+    #[serde(default)]
+    last_wiki_id: u64,
+    #[serde(default)]
+    last_page_id: u64,
+    #[serde(default)]
+    wikis: Vec<WikiRecord>,
+    #[serde(default)]
+    pages: Vec<PageStatus>,
+    #[serde(default)]
+    profile_log: Vec<ProfileEntry>,
+    /// Entity IDs listed on each page, by page ID.
+    #[serde(default)]
+    page_items: HashMap<u64, Vec<String>>,
+}
+
+impl FileState {
+    fn page_mut(&mut self, page: &str, wiki: &str) -> Option<&mut PageStatus> {
+        self.pages
+            .iter_mut()
+            .find(|p| p.page == page && p.wiki == wiki)
+    }
+
+    /// Pages of active wikis that match `filter`, not excluded.
+    fn candidate_pages<'a>(
+        &'a self,
+        exclude_ids: &'a [u64],
+        ignore_status: &'a [&str],
+        filter: impl Fn(&PageStatus) -> bool + 'a,
+    ) -> impl Iterator<Item = &'a PageStatus> {
+        let active: Vec<&str> = self
+            .wikis
+            .iter()
+            .filter(|w| w.status.eq_ignore_ascii_case("ACTIVE"))
+            .map(|w| w.name.as_str())
+            .collect();
+        self.pages
+            .iter()
+            .filter(move |p| active.contains(&p.wiki.as_str()))
+            .filter(move |p| !exclude_ids.contains(&p.id))
+            .filter(move |p| !ignore_status.contains(&p.status.as_str()))
+            .filter(move |p| filter(p))
+    }
+
+    /// The longest-waiting page matching `filter`, not excluded.
+    fn find_page(
+        &self,
+        exclude_ids: &[u64],
+        ignore_status: &[&str],
+        filter: impl Fn(&PageStatus) -> bool,
+    ) -> Option<PageToProcess> {
+        self.candidate_pages(exclude_ids, ignore_status, filter)
+            .min_by(|a, b| a.timestamp.cmp(&b.timestamp).then(a.id.cmp(&b.id)))
+            .map(PageStatus::to_page_to_process)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FileStorage {
+    path: Option<PathBuf>,
+    state: Arc<Mutex<FileState>>,
+}
+
+impl FileStorage {
+    /// Opens the store at `path`, starting empty if the file does not exist yet.
+    pub async fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let state = match tokio::fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| anyhow!("Could not parse storage file {}: {e}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => FileState::default(),
+            Err(e) => {
+                return Err(anyhow!(
+                    "Could not read storage file {}: {e}",
+                    path.display()
+                ));
+            }
+        };
+        Ok(Self {
+            path: Some(path),
+            state: Arc::new(Mutex::new(state)),
+        })
+    }
+
+    /// A store that is never written to disk.
+    #[must_use]
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Applies `f` to the state and writes the result back to disk.
+    async fn modify<T>(&self, f: impl FnOnce(&mut FileState) -> T) -> Result<T> {
+        let mut state = self.state.lock().await;
+        let ret = f(&mut state);
+        self.save(&state).await?;
+        Ok(ret)
+    }
+
+    async fn save(&self, state: &FileState) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let json = serde_json::to_vec(state)?;
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+        tokio::fs::write(&tmp, json).await?;
+        tokio::fs::rename(&tmp, path).await?;
+        Ok(())
+    }
+}
+
+impl Storage for FileStorage {
+    async fn check_schema(&self) -> Result<()> {
+        // Missing fields are filled in with their defaults when the file is read
+        Ok(())
+    }
+
+    async fn reset_running(&self) -> Result<()> {
+        self.modify(|state| {
+            let now = storage_timestamp();
+            for p in state.pages.iter_mut().filter(|p| p.status == "RUNNING") {
+                p.message = format!(
+                    "Bot restarted while page was processing (running since {})",
+                    p.timestamp
+                );
+                p.status = "FAIL".to_string();
+                p.priority = false;
+                p.timestamp = now.clone();
+            }
+        })
+        .await
+    }
+
+    async fn release_running_page(&self, page: &PageToProcess) -> Result<()> {
+        self.modify(|state| {
+            if let Some(p) = state
+                .pages
+                .iter_mut()
+                .find(|p| p.id == page.id() && p.status == "RUNNING")
+            {
+                p.status = page.status().to_string();
+                p.message = RELEASED_MESSAGE.to_string();
+            }
+        })
+        .await
+    }
+
+    async fn clear_deleted(&self) -> Result<()> {
+        self.modify(|state| {
+            state.pages.retain(|p| p.status != "DELETED");
+            let ids: HashSet<u64> = state.pages.iter().map(|p| p.id).collect();
+            state.page_items.retain(|id, _| ids.contains(id));
+        })
+        .await
+    }
+
+    async fn clear_deferred(&self) -> Result<()> {
+        self.modify(|state| {
+            for p in state.pages.iter_mut().filter(|p| p.status == "DEFERRED") {
+                p.status = "FAIL".to_string();
+                p.message = "cleared from DEFERRED on bot startup".to_string();
+            }
+        })
+        .await
+    }
+
+    async fn recover_deferred(&self, wiki: Option<&str>) -> Result<()> {
+        self.modify(|state| {
+            for p in state
+                .pages
+                .iter_mut()
+                .filter(|p| p.status == "DEFERRED" && wiki.is_none_or(|wiki| p.wiki == wiki))
+            {
+                p.status = "FAIL".to_string();
+                p.message = "cleared from DEFERRED after the circuit closed".to_string();
+            }
+        })
+        .await
+    }
+
+    async fn release_deferred_probe(&self, wiki: Option<&str>) -> Result<()> {
+        self.modify(|state| {
+            if let Some(p) = state
+                .pages
+                .iter_mut()
+                .filter(|p| p.status == "DEFERRED" && wiki.is_none_or(|wiki| p.wiki == wiki))
+                .min_by(|a, b| a.timestamp.cmp(&b.timestamp).then(a.id.cmp(&b.id)))
+            {
+                p.status = "FAIL".to_string();
+                p.message = "released from DEFERRED as a circuit breaker probe".to_string();
+            }
+        })
+        .await
+    }
+
+    async fn set_runtime(&self, pagestatus_id: u64, seconds: u64) -> Result<()> {
+        self.modify(|state| {
+            if let Some(p) = state.pages.iter_mut().find(|p| p.id == pagestatus_id) {
+                p.last_runtime_sec = Some(seconds);
+            }
+        })
+        .await
+    }
+
+    async fn update_page_status_with_code(
+        &self,
+        page: &str,
+        wiki: &str,
+        status: &str,
+        message: &str,
+        error_code: Option<&str>,
+    ) -> Result<()> {
+        self.modify(|state| {
+            if let Some(p) = state.page_mut(page, wiki) {
+                p.status = status.to_string();
+                p.message = message.chars().take(200).collect();
+                p.error_code = error_code.map(str::to_string);
+                match Backoff::for_status(status, error_code) {
+                    Backoff::Keep => {}
+                    Backoff::Reset => {
+                        p.fail_count = 0;
+                        p.next_attempt = None;
+                    }
+                    Backoff::Fail(base) => {
+                        p.fail_count = p.fail_count.saturating_add(1);
+                        let delay = backoff_delay(base, p.fail_count);
+                        p.next_attempt = Some(storage_timestamp_after(delay));
+                    }
+                }
+                p.timestamp = storage_timestamp();
+                if status != "RUNNING" {
+                    p.priority = false;
+                }
+            }
+        })
+        .await
+    }
+
+    async fn set_last_edit(&self, page: &str, wiki: &str) -> Result<()> {
+        self.modify(|state| {
+            if let Some(p) = state.page_mut(page, wiki) {
+                p.last_edit = Some(storage_timestamp());
+            }
+        })
+        .await
+    }
+
+    async fn find_priority_page(
+        &self,
+        exclude_ids: &[u64],
+        ignore_status: &[&str],
+    ) -> Result<Option<PageToProcess>> {
+        let state = self.state.lock().await;
+        Ok(state.find_page(exclude_ids, ignore_status, |p| p.priority))
+    }
+
+    async fn find_oldest_page(
+        &self,
+        exclude_ids: &[u64],
+        ignore_status: &[&str],
+    ) -> Result<Option<PageToProcess>> {
+        let state = self.state.lock().await;
+        let now = storage_timestamp();
+        Ok(state.find_page(exclude_ids, ignore_status, |p| {
+            p.next_attempt.as_ref().is_none_or(|t| *t <= now)
+        }))
+    }
+
+    async fn find_oldest_page_for_wiki(
+        &self,
+        wiki: &str,
+        exclude_ids: &[u64],
+        ignore_status: &[&str],
+        updated_before: &str,
+    ) -> Result<Option<PageToProcess>> {
+        let state = self.state.lock().await;
+        let now = storage_timestamp();
+        Ok(state.find_page(exclude_ids, ignore_status, |p| {
+            p.wiki == wiki && p.is_due(&now, updated_before)
+        }))
+    }
+
+    async fn get_queue_depths(
+        &self,
+        ignore_status: &[&str],
+        updated_before: &str,
+    ) -> Result<HashMap<String, usize>> {
+        let state = self.state.lock().await;
+        let now = storage_timestamp();
+        let mut depths = HashMap::new();
+        for page in state.candidate_pages(&[], ignore_status, |p| p.is_due(&now, updated_before)) {
+            *depths.entry(page.wiki.clone()).or_insert(0) += 1;
+        }
+        Ok(depths)
+    }
+
+    async fn get_page_statuses(&self, wiki: &str) -> Result<Vec<PageStatus>> {
+        let state = self.state.lock().await;
+        Ok(state
+            .pages
+            .iter()
+            .filter(|p| p.wiki == wiki)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_backoff_pages(&self, limit: usize) -> Result<Vec<PageStatus>> {
+        let state = self.state.lock().await;
+        let mut pages: Vec<PageStatus> = state
+            .pages
+            .iter()
+            .filter(|p| p.fail_count > 0)
+            .cloned()
+            .collect();
+        pages.sort_by(|a, b| a.next_attempt.cmp(&b.next_attempt));
+        pages.truncate(limit);
+        Ok(pages)
+    }
+
+    async fn set_priority(&self, wiki: &str, pages: &[String]) -> Result<()> {
+        if pages.is_empty() {
+            return Ok(());
+        }
+        self.modify(|state| {
+            for p in state
+                .pages
+                .iter_mut()
+                .filter(|p| p.wiki == wiki && pages.contains(&p.page))
+            {
+                p.priority = true;
+            }
+        })
+        .await
+    }
+
+    async fn get_all_wikis(&self) -> Result<HashMap<String, Wiki>> {
+        let state = self.state.lock().await;
+        Ok(state
+            .wikis
+            .iter()
+            .filter_map(|w| {
+                Wiki::from_row((
+                    w.id as usize,
+                    w.name.clone(),
+                    w.status.clone(),
+                    w.timestamp.clone(),
+                    true,
+                    true,
+                ))
+                .ok()
+            })
+            .map(|wiki| (wiki.name().to_string(), wiki))
+            .collect())
+    }
+
+    async fn add_wikis(&self, new_wikis: &[String]) -> Result<()> {
+        if new_wikis.is_empty() {
+            return Ok(());
+        }
+        self.modify(|state| {
+            for name in new_wikis {
+                if state.wikis.iter().any(|w| w.name == *name) {
+                    continue;
+                }
+                state.last_wiki_id += 1;
+                state.wikis.push(WikiRecord {
+                    id: state.last_wiki_id,
+                    name: name.clone(),
+                    status: "ACTIVE".to_string(),
+                    timestamp: storage_timestamp(),
+                });
+            }
+        })
+        .await
+    }
+
+    async fn get_wiki_id(&self, wiki: &str) -> Result<u64> {
+        let state = self.state.lock().await;
+        state
+            .wikis
+            .iter()
+            .find(|w| w.name == wiki)
+            .map(|w| w.id)
+            .ok_or_else(|| anyhow!("Wiki {wiki} not known"))
+    }
+
+    async fn get_pages_for_wiki(&self, wiki: &str) -> Result<Vec<String>> {
+        let state = self.state.lock().await;
+        Ok(state
+            .pages
+            .iter()
+            .filter(|p| p.wiki == wiki)
+            .map(|p| p.page.clone())
+            .collect())
+    }
+
+    async fn add_pages_for_wiki(&self, wiki_id: u64, new_pages: &[String]) -> Result<()> {
+        if new_pages.is_empty() {
+            return Ok(());
+        }
+        self.modify(|state| {
+            let Some(wiki) = state
+                .wikis
+                .iter()
+                .find(|w| w.id == wiki_id)
+                .map(|w| w.name.clone())
+            else {
+                return Err(anyhow!("Wiki #{wiki_id} not known"));
+            };
+            for page in new_pages {
+                if state
+                    .pages
+                    .iter()
+                    .any(|p| p.wiki == wiki && p.page == *page)
+                {
+                    continue;
+                }
+                state.last_page_id += 1;
+                state.pages.push(PageStatus {
+                    id: state.last_page_id,
+                    wiki: wiki.clone(),
+                    page: page.clone(),
+                    status: "WAITING".to_string(),
+                    ..Default::default()
+                });
+            }
+            Ok(())
+        })
+        .await?
+    }
+
+    async fn set_page_items(&self, wiki: &str, page: &str, items: &[String]) -> Result<()> {
+        self.modify(|state| {
+            let Some(id) = state.page_mut(page, wiki).map(|p| p.id) else {
+                return;
+            };
+            state.page_items.insert(id, items.to_vec());
+        })
+        .await
+    }
+
+    async fn get_pages_for_items(&self, items: &[String]) -> Result<Vec<(String, String)>> {
+        let state = self.state.lock().await;
+        Ok(state
+            .pages
+            .iter()
+            .filter(|p| {
+                state
+                    .page_items
+                    .get(&p.id)
+                    .is_some_and(|listed| listed.iter().any(|item| items.contains(item)))
+            })
+            .map(|p| (p.wiki.clone(), p.page.clone()))
+            .collect())
+    }
+
+    async fn log_profile(
+        &self,
+        wiki: &str,
+        page: &str,
+        timestamp: &str,
+        diff_ms: i64,
+        message: &str,
+    ) -> Result<()> {
+        // Not written on its own, see the module docs
+        let mut state = self.state.lock().await;
+        state.profile_log.push(ProfileEntry {
+            wiki: wiki.to_string(),
+            page: page.to_string(),
+            timestamp: timestamp.to_string(),
+            diff_ms,
+            message: message.to_string(),
+        });
+        let excess = state.profile_log.len().saturating_sub(MAX_PROFILE_ENTRIES);
+        state.profile_log.drain(..excess);
+        Ok(())
+    }
+
+    async fn clear_profile_log(&self) -> Result<()> {
+        self.modify(|state| state.profile_log.clear()).await
+    }
+
+    async fn flush(&self) -> Result<()> {
+        let state = self.state.lock().await;
+        self.save(&state).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{IGNORE_STATUS, storage_timestamp_before};
+
+    async fn store_with_pages(store: &FileStorage, pages: &[&str]) -> u64 {
+        store.add_wikis(&["wiki".to_string()]).await.unwrap();
+        let wiki_id = store.get_wiki_id("wiki").await.unwrap();
+        let pages: Vec<String> = pages.iter().map(|p| p.to_string()).collect();
+        store.add_pages_for_wiki(wiki_id, &pages).await.unwrap();
+        wiki_id
+    }
+
+    #[tokio::test]
+    async fn test_add_wikis_and_pages_is_idempotent() {
+        let store = FileStorage::in_memory();
+        let wiki_id = store_with_pages(&store, &["A", "B"]).await;
+        store.add_wikis(&["wiki".to_string()]).await.unwrap();
+        store
+            .add_pages_for_wiki(wiki_id, &["B".to_string(), "C".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(store.get_all_wikis().await.unwrap().len(), 1);
+        assert_eq!(
+            store.get_pages_for_wiki("wiki").await.unwrap(),
+            vec!["A", "B", "C"]
+        );
+        assert!(
+            store
+                .add_pages_for_wiki(99, &["X".to_string()])
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_oldest_page_skips_excluded_and_ignored() {
+        let store = FileStorage::in_memory();
+        store_with_pages(&store, &["A", "B", "C"]).await;
+        store
+            .update_page_status("A", "wiki", "RUNNING", "PREPARING")
+            .await
+            .unwrap();
+        // New pages have no timestamp, so they come first, by ID
+        let page = store.find_oldest_page(&[], IGNORE_STATUS).await.unwrap();
+        assert_eq!(page.unwrap().title(), "B");
+        let ids: Vec<u64> = vec![2];
+        let page = store.find_oldest_page(&ids, IGNORE_STATUS).await.unwrap();
+        assert_eq!(page.unwrap().title(), "C");
+        assert!(
+            store
+                .find_priority_page(&[], IGNORE_STATUS)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    /// Pages last updated over an hour ago are ready.
+    fn hour_ago() -> String {
+        storage_timestamp_before(std::time::Duration::from_secs(3600))
+    }
+
+    #[tokio::test]
+    async fn test_find_oldest_page_for_wiki_and_queue_depths() {
+        let store = FileStorage::in_memory();
+        store_with_pages(&store, &["A", "B"]).await;
+        store.add_wikis(&["other".to_string()]).await.unwrap();
+        let other_id = store.get_wiki_id("other").await.unwrap();
+        store
+            .add_pages_for_wiki(other_id, &["X".to_string()])
+            .await
+            .unwrap();
+        store
+            .update_page_status("A", "wiki", "RUNNING", "PREPARING")
+            .await
+            .unwrap();
+        let page = store
+            .find_oldest_page_for_wiki("other", &[], IGNORE_STATUS, &hour_ago())
+            .await
+            .unwrap();
+        assert_eq!(page.unwrap().title(), "X");
+        let page = store
+            .find_oldest_page_for_wiki("wiki", &[], IGNORE_STATUS, &hour_ago())
+            .await
+            .unwrap();
+        assert_eq!(page.unwrap().title(), "B");
+
+        // Failed pages waiting for their backoff are not queued
+        store
+            .update_page_status("X", "other", "FAIL", "error")
+            .await
+            .unwrap();
+        let depths = store
+            .get_queue_depths(IGNORE_STATUS, &hour_ago())
+            .await
+            .unwrap();
+        assert_eq!(depths, HashMap::from([("wiki".to_string(), 1)]));
+    }
+
+    #[tokio::test]
+    async fn test_just_updated_page_is_not_ready() {
+        let store = FileStorage::in_memory();
+        store_with_pages(&store, &["A"]).await;
+        let depths = store
+            .get_queue_depths(IGNORE_STATUS, &hour_ago())
+            .await
+            .unwrap();
+        assert_eq!(depths, HashMap::from([("wiki".to_string(), 1)]));
+
+        // The wiki's only page finished; it is not picked again right away
+        store
+            .update_page_status("A", "wiki", "OK", "")
+            .await
+            .unwrap();
+        let depths = store
+            .get_queue_depths(IGNORE_STATUS, &hour_ago())
+            .await
+            .unwrap();
+        assert!(depths.is_empty());
+        assert!(
+            store
+                .find_oldest_page_for_wiki("wiki", &[], IGNORE_STATUS, &hour_ago())
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        // Once it is old enough, it is
+        let later = storage_timestamp_after(std::time::Duration::from_secs(60));
+        let page = store
+            .find_oldest_page_for_wiki("wiki", &[], IGNORE_STATUS, &later)
+            .await
+            .unwrap();
+        assert_eq!(page.unwrap().title(), "A");
+
+        // A page released from DEFERRED does not wait
+        store
+            .update_page_status("A", "wiki", "DEFERRED", "")
+            .await
+            .unwrap();
+        store.recover_deferred(None).await.unwrap();
+        let page = store
+            .find_oldest_page_for_wiki("wiki", &[], IGNORE_STATUS, &hour_ago())
+            .await
+            .unwrap();
+        assert_eq!(page.unwrap().title(), "A");
+    }
+
+    #[tokio::test]
+    async fn test_reset_running_and_clear() {
+        let store = FileStorage::in_memory();
+        store_with_pages(&store, &["A", "B", "C"]).await;
+        store
+            .update_page_status("A", "wiki", "RUNNING", "")
+            .await
+            .unwrap();
+        store
+            .update_page_status("B", "wiki", "DELETED", "")
+            .await
+            .unwrap();
+        store
+            .update_page_status("C", "wiki", "DEFERRED", "")
+            .await
+            .unwrap();
+        store.reset_running().await.unwrap();
+        store.clear_deleted().await.unwrap();
+        store.clear_deferred().await.unwrap();
+        let pages = store.get_page_statuses("wiki").await.unwrap();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].status, "FAIL");
+        assert!(pages[0].message.starts_with("Bot restarted"));
+        assert_eq!(pages[1].status, "FAIL");
+        assert_eq!(pages[1].message, "cleared from DEFERRED on bot startup");
+    }
+
+    #[tokio::test]
+    async fn test_failed_pages_back_off() {
+        let store = FileStorage::in_memory();
+        store_with_pages(&store, &["A"]).await;
+        store
+            .update_page_status_with_code("A", "wiki", "BROKEN", "", Some("PROTECTED_PAGE"))
+            .await
+            .unwrap();
+        store
+            .update_page_status("A", "wiki", "RUNNING", "")
+            .await
+            .unwrap();
+        store
+            .update_page_status("A", "wiki", "FAIL", "")
+            .await
+            .unwrap();
+        let pages = store.get_page_statuses("wiki").await.unwrap();
+        assert_eq!(pages[0].error_code, None);
+        assert_eq!(pages[0].fail_count, 2);
+        assert!(pages[0].next_attempt.as_deref() > Some(storage_timestamp().as_str()));
+        assert_eq!(store.get_backoff_pages(10).await.unwrap().len(), 1);
+        assert!(
+            store
+                .find_oldest_page(&[], IGNORE_STATUS)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        store
+            .set_priority("wiki", &["A".to_string()])
+            .await
+            .unwrap();
+        assert!(
+            store
+                .find_priority_page(&[], IGNORE_STATUS)
+                .await
+                .unwrap()
+                .is_some()
+        );
+        store
+            .update_page_status("A", "wiki", "OK", "")
+            .await
+            .unwrap();
+        let pages = store.get_page_statuses("wiki").await.unwrap();
+        assert_eq!(pages[0].fail_count, 0);
+        assert_eq!(pages[0].next_attempt, None);
+        assert!(store.get_backoff_pages(10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_recover_deferred() {
+        let store = FileStorage::in_memory();
+        store.add_wikis(&["other".to_string()]).await.unwrap();
+        store_with_pages(&store, &["A"]).await;
+        let other_id = store.get_wiki_id("other").await.unwrap();
+        store
+            .add_pages_for_wiki(other_id, &["B".to_string()])
+            .await
+            .unwrap();
+        store
+            .update_page_status("A", "wiki", "DEFERRED", "")
+            .await
+            .unwrap();
+        store
+            .update_page_status("B", "other", "DEFERRED", "")
+            .await
+            .unwrap();
+        store.recover_deferred(Some("wiki")).await.unwrap();
+        assert_eq!(
+            store.get_page_statuses("wiki").await.unwrap()[0].status,
+            "FAIL"
+        );
+        assert_eq!(
+            store.get_page_statuses("other").await.unwrap()[0].status,
+            "DEFERRED"
+        );
+        store.recover_deferred(None).await.unwrap();
+        assert_eq!(
+            store.get_page_statuses("other").await.unwrap()[0].status,
+            "FAIL"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_release_deferred_probe_of_all_deferred_wiki() {
+        let store = FileStorage::in_memory();
+        store_with_pages(&store, &["A", "B"]).await;
+        for page in ["A", "B"] {
+            store
+                .update_page_status(page, "wiki", "DEFERRED", "")
+                .await
+                .unwrap();
+        }
+        // Nothing of the wiki can run, so its breaker never sees a request
+        assert!(
+            store
+                .find_oldest_page_for_wiki("wiki", &[], IGNORE_STATUS, &hour_ago())
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        // One page is let through as the probe
+        store.release_deferred_probe(Some("wiki")).await.unwrap();
+        let statuses = store.get_page_statuses("wiki").await.unwrap();
+        let released: Vec<&str> = statuses
+            .iter()
+            .filter(|p| p.status == "FAIL")
+            .map(|p| p.page.as_str())
+            .collect();
+        assert_eq!(released, ["A"]);
+        let page = store
+            .find_oldest_page_for_wiki("wiki", &[], IGNORE_STATUS, &hour_ago())
+            .await
+            .unwrap();
+        assert_eq!(page.unwrap().title(), "A");
+    }
+
+    #[tokio::test]
+    async fn test_state_persists_across_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("listeria.json");
+        {
+            let store = FileStorage::open(&path).await.unwrap();
+            store_with_pages(&store, &["A"]).await;
+            let id = store
+                .find_oldest_page(&[], &[])
+                .await
+                .unwrap()
+                .unwrap()
+                .id();
+            store
+                .update_page_status("A", "wiki", "OK", "")
+                .await
+                .unwrap();
+            store.set_runtime(id, 12).await.unwrap();
+            store.set_last_edit("A", "wiki").await.unwrap();
+            store
+                .log_profile("wiki", "A", "20240101000000", 5, "START")
+                .await
+                .unwrap();
+            store.flush().await.unwrap();
+        }
+        let store = FileStorage::open(&path).await.unwrap();
+        let pages = store.get_page_statuses("wiki").await.unwrap();
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].status, "OK");
+        assert_eq!(pages[0].last_runtime_sec, Some(12));
+        assert!(pages[0].last_edit.is_some());
+        assert_eq!(store.state.lock().await.profile_log.len(), 1);
+        // IDs keep counting from where they were
+        let wiki_id = store.get_wiki_id("wiki").await.unwrap();
+        store
+            .add_pages_for_wiki(wiki_id, &["B".to_string()])
+            .await
+            .unwrap();
+        let pages = store.get_page_statuses("wiki").await.unwrap();
+        assert_eq!(pages[1].id, 2);
+    }
+
+    #[tokio::test]
+    async fn test_profile_log_is_written_with_next_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("listeria.json");
+        let store = FileStorage::open(&path).await.unwrap();
+        store_with_pages(&store, &["A"]).await;
+        store
+            .log_profile("wiki", "A", "20240101000000", 5, "START")
+            .await
+            .unwrap();
+        let reopened = FileStorage::open(&path).await.unwrap();
+        assert!(reopened.state.lock().await.profile_log.is_empty());
+        store
+            .update_page_status("A", "wiki", "OK", "")
+            .await
+            .unwrap();
+        let reopened = FileStorage::open(&path).await.unwrap();
+        assert_eq!(reopened.state.lock().await.profile_log.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_release_running_page() {
+        let store = FileStorage::in_memory();
+        store_with_pages(&store, &["A", "B"]).await;
+        store
+            .update_page_status_with_code("A", "wiki", "FAIL", "error", Some("ENTITY_LOAD"))
+            .await
+            .unwrap();
+        store
+            .set_priority("wiki", &["A".to_string()])
+            .await
+            .unwrap();
+        let page = store
+            .find_priority_page(&[], IGNORE_STATUS)
+            .await
+            .unwrap()
+            .unwrap();
+        store
+            .update_page_status("A", "wiki", "RUNNING", "PREPARING")
+            .await
+            .unwrap();
+        store.release_running_page(&page).await.unwrap();
+        let a = &store.get_page_statuses("wiki").await.unwrap()[0];
+        assert_eq!(a.status, "FAIL");
+        assert_eq!(a.message, RELEASED_MESSAGE);
+        assert!(a.priority);
+        assert_eq!(a.fail_count, 1);
+
+        // Pages that finished in the meantime are left alone
+        store
+            .update_page_status("A", "wiki", "OK", "")
+            .await
+            .unwrap();
+        store.release_running_page(&page).await.unwrap();
+        assert_eq!(
+            store.get_page_statuses("wiki").await.unwrap()[0].status,
+            "OK"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_open_rejects_corrupt_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("listeria.json");
+        tokio::fs::write(&path, "not json").await.unwrap();
+        assert!(FileStorage::open(&path).await.is_err());
+    }
+}
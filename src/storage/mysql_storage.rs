@@ -0,0 +1,260 @@
+//! [`Storage`] on the bot's MySQL tables.
+
+use super::{PageStatus, Storage};
+use crate::database_pool::DatabasePool;
+use crate::item_page_repository::ItemPageRepository;
+use crate::page_to_process::PageToProcess;
+use crate::pagestatus_repository::PageStatusRepository;
+use crate::wiki::Wiki;
+use crate::wiki_repository::WikiRepository;
+use anyhow::Result;
+use mysql_async::{params, prelude::*};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub struct MysqlStorage {
+    pool: DatabasePool,
+    pagestatus: PageStatusRepository,
+    wikis: WikiRepository,
+    items: ItemPageRepository,
+}
+
+impl MysqlStorage {
+    #[must_use]
+    pub fn new(pool: DatabasePool) -> Self {
+        Self {
+            pagestatus: PageStatusRepository::new(pool.clone()),
+            wikis: WikiRepository::new(pool.clone()),
+            items: ItemPageRepository::new(pool.clone()),
+            pool,
+        }
+    }
+
+    /// `exclude_ids` as an SQL list; `0` (never a row ID) when empty.
+    fn id_list(exclude_ids: &[u64]) -> String {
+        if exclude_ids.is_empty() {
+            return "0".to_string();
+        }
+        let mut ids = exclude_ids.to_vec();
+        ids.sort_unstable();
+        ids.iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// `ignore_status` as a quoted SQL list; `''` (never a status) when empty.
+    fn status_list(ignore_status: &[&str]) -> String {
+        if ignore_status.is_empty() {
+            return "''".to_string();
+        }
+        ignore_status
+            .iter()
+            .map(|s| format!("'{}'", s.replace('\'', "''")))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+impl Storage for MysqlStorage {
+    async fn check_schema(&self) -> Result<()> {
+        self.pagestatus.check_schema().await
+    }
+
+    async fn reset_running(&self) -> Result<()> {
+        self.pagestatus.reset_running().await
+    }
+
+    async fn clear_deleted(&self) -> Result<()> {
+        self.pagestatus.clear_deleted().await
+    }
+
+    async fn clear_deferred(&self) -> Result<()> {
+        self.pagestatus.clear_deferred().await
+    }
+
+    async fn recover_deferred(&self, wiki: Option<&str>) -> Result<()> {
+        self.pagestatus.recover_deferred(wiki).await
+    }
+
+    async fn release_deferred_probe(&self, wiki: Option<&str>) -> Result<()> {
+        self.pagestatus.release_deferred_probe(wiki).await
+    }
+
+    async fn release_running_page(&self, page: &PageToProcess) -> Result<()> {
+        self.pagestatus
+            .release_running_page(page.id(), page.status())
+            .await
+    }
+
+    async fn set_runtime(&self, pagestatus_id: u64, seconds: u64) -> Result<()> {
+        self.pagestatus.set_runtime(pagestatus_id, seconds).await
+    }
+
+    async fn update_page_status_with_code(
+        &self,
+        page: &str,
+        wiki: &str,
+        status: &str,
+        message: &str,
+        error_code: Option<&str>,
+    ) -> Result<()> {
+        self.pagestatus
+            .update_page_status(page, wiki, status, message, error_code)
+            .await
+    }
+
+    async fn set_last_edit(&self, page: &str, wiki: &str) -> Result<()> {
+        self.pagestatus.set_last_edit(page, wiki).await
+    }
+
+    async fn find_priority_page(
+        &self,
+        exclude_ids: &[u64],
+        ignore_status: &[&str],
+    ) -> Result<Option<PageToProcess>> {
+        self.pagestatus
+            .find_priority_page(
+                &Self::id_list(exclude_ids),
+                &Self::status_list(ignore_status),
+            )
+            .await
+    }
+
+    async fn find_oldest_page(
+        &self,
+        exclude_ids: &[u64],
+        ignore_status: &[&str],
+    ) -> Result<Option<PageToProcess>> {
+        self.pagestatus
+            .find_oldest_page(
+                &Self::id_list(exclude_ids),
+                &Self::status_list(ignore_status),
+            )
+            .await
+    }
+
+    async fn find_oldest_page_for_wiki(
+        &self,
+        wiki: &str,
+        exclude_ids: &[u64],
+        ignore_status: &[&str],
+        updated_before: &str,
+    ) -> Result<Option<PageToProcess>> {
+        self.pagestatus
+            .find_oldest_page_for_wiki(
+                wiki,
+                &Self::id_list(exclude_ids),
+                &Self::status_list(ignore_status),
+                updated_before,
+            )
+            .await
+    }
+
+    async fn get_queue_depths(
+        &self,
+        ignore_status: &[&str],
+        updated_before: &str,
+    ) -> Result<HashMap<String, usize>> {
+        self.pagestatus
+            .get_queue_depths(&Self::status_list(ignore_status), updated_before)
+            .await
+    }
+
+    async fn get_page_statuses(&self, wiki: &str) -> Result<Vec<PageStatus>> {
+        self.pagestatus.get_page_statuses(wiki).await
+    }
+
+    async fn get_backoff_pages(&self, limit: usize) -> Result<Vec<PageStatus>> {
+        self.pagestatus.get_backoff_pages(limit).await
+    }
+
+    async fn set_priority(&self, wiki: &str, pages: &[String]) -> Result<()> {
+        self.pagestatus.set_priority(wiki, pages).await
+    }
+
+    async fn get_all_wikis(&self) -> Result<HashMap<String, Wiki>> {
+        self.wikis.get_all_wikis().await
+    }
+
+    async fn add_wikis(&self, new_wikis: &[String]) -> Result<()> {
+        self.wikis.add_wikis(new_wikis).await
+    }
+
+    async fn get_wiki_id(&self, wiki: &str) -> Result<u64> {
+        self.wikis.get_wiki_id(wiki).await
+    }
+
+    async fn get_pages_for_wiki(&self, wiki: &str) -> Result<Vec<String>> {
+        self.wikis.get_pages_for_wiki(wiki).await
+    }
+
+    async fn add_pages_for_wiki(&self, wiki_id: u64, new_pages: &[String]) -> Result<()> {
+        self.wikis.add_pages_for_wiki(wiki_id, new_pages).await
+    }
+
+    async fn set_page_items(&self, wiki: &str, page: &str, items: &[String]) -> Result<()> {
+        self.items.set_page_items(wiki, page, items).await
+    }
+
+    async fn get_pages_for_items(&self, items: &[String]) -> Result<Vec<(String, String)>> {
+        self.items.get_pages_for_items(items).await
+    }
+
+    async fn log_profile(
+        &self,
+        wiki: &str,
+        page: &str,
+        timestamp: &str,
+        diff_ms: i64,
+        message: &str,
+    ) -> Result<()> {
+        self.pool
+            .with_timeout("log_profile", || async {
+                let sql = "REPLACE INTO list_log (wiki, page, timestamp, diff_ms, message) VALUES (:wiki, :page, :timestamp, :diff_ms, :message)";
+                self.pool
+                    .get_conn()
+                    .await?
+                    .exec_drop(sql, params! {wiki, page, timestamp, diff_ms, message})
+                    .await?;
+                Ok(())
+            })
+            .await
+    }
+
+    async fn clear_profile_log(&self) -> Result<()> {
+        self.pool
+            .with_timeout("clear_profile_log", || async {
+                let sql = "TRUNCATE `list_log`";
+                self.pool.get_conn().await?.exec_iter(sql, ()).await?;
+                Ok(())
+            })
+            .await
+    }
+
+    async fn flush(&self) -> Result<()> {
+        // Every change is written to the database right away
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_id_list() {
+        assert_eq!(MysqlStorage::id_list(&[]), "0");
+        assert_eq!(MysqlStorage::id_list(&[3, 1, 2]), "1,2,3");
+    }
+
+    #[test]
+    fn test_status_list() {
+        assert_eq!(MysqlStorage::status_list(&[]), "''");
+        assert_eq!(
+            MysqlStorage::status_list(&["RUNNING", "DELETED"]),
+            "'RUNNING','DELETED'"
+        );
+        assert_eq!(MysqlStorage::status_list(&["O'K"]), "'O''K'");
+    }
+}
@@ -1,6 +1,6 @@
 //! Template parsing and parameter extraction.
 
-use anyhow::{Result, anyhow};
+use anyhow::Result;
 use std::collections::HashMap;
 
 #[derive(Debug, Clone, Default)]
@@ -9,77 +9,230 @@ pub struct Template {
 }
 
 impl Template {
+    /// Parses the inside of a start template, `key=value|key=value…`.
+    /// Parameters without a key are ignored.
     pub fn new_from_params(text: &str) -> Result<Self> {
-        let mut curly_braces: i32 = 0;
-        let mut parts: Vec<String> = Vec::new();
+        let params: HashMap<String, String> = Self::split_parts(text)
+            .into_iter()
+            .filter_map(|(part, equals)| {
+                let k = part.get(..equals?)?.trim().to_string();
+                let v = part.get(equals? + 1..)?.trim().to_string();
+                Some((k, v)).filter(|(k, _)| !k.is_empty())
+            })
+            .collect();
+        Ok(Self { params })
+    }
+
+    /// Splits `text` at its top-level pipes, with the byte position of the
+    /// first top-level `=` of each part.
+    ///
+    /// Pipes and `=` do not count inside nested templates, parser functions
+    /// and magic words (`{{…}}`), SPARQL groups (`{…}`), wikilinks
+    /// (`[[File:a.jpg|thumb]]`) and quoted strings. HTML comments are
+    /// dropped; `<nowiki>` tags are removed and their content kept as it is.
+    fn split_parts(text: &str) -> Vec<(String, Option<usize>)> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut parts = Vec::new();
         let mut part = String::new();
-        let mut quoted = false;
-        let mut quote_char: char = ' ';
-        for c in text.chars() {
+        let mut equals = None;
+        let mut braces: usize = 0;
+        let mut links: usize = 0;
+        let mut pos = 0;
+        while pos < chars.len() {
+            let rest = &chars[pos..];
+            if Self::starts_with(rest, "<!--") {
+                pos = Self::find(&chars, pos + 4, "-->").map_or(chars.len(), |end| end + 3);
+                continue;
+            }
+            if Self::starts_with(rest, "<nowiki/>") {
+                pos += "<nowiki/>".len();
+                continue;
+            }
+            if Self::starts_with(rest, "<nowiki>") {
+                let from = pos + "<nowiki>".len();
+                let to = Self::find(&chars, from, "</nowiki>").unwrap_or(chars.len());
+                part.extend(&chars[from..to]);
+                pos = (to + "</nowiki>".len()).min(chars.len());
+                continue;
+            }
+            if Self::starts_with(rest, "[[") || Self::starts_with(rest, "]]") {
+                links = if rest[0] == '[' {
+                    links + 1
+                } else {
+                    links.saturating_sub(1)
+                };
+                part.extend(&rest[..2]);
+                pos += 2;
+                continue;
+            }
+            if links == 0
+                && let Some(end) = Self::closing_quote(&chars, pos)
+            {
+                part.extend(&chars[pos..=end]);
+                pos = end + 1;
+                continue;
+            }
+            let c = chars[pos];
+            pos += 1;
             match c {
-                '\'' | '"' => {
-                    if quoted {
-                        if quote_char == c {
-                            quoted = false;
-                        }
-                    } else {
-                        quoted = true;
-                        quote_char = c;
-                    }
-                    part.push(c);
-                }
-                '{' => {
-                    curly_braces += 1;
-                    part.push(c);
+                '{' => braces += 1,
+                '}' => braces = braces.saturating_sub(1),
+                '|' if braces == 0 && links == 0 => {
+                    parts.push((std::mem::take(&mut part), equals.take()));
+                    continue;
                 }
-                '}' => {
-                    curly_braces -= 1;
-                    part.push(c);
-                }
-                '|' if curly_braces == 0 && !quoted => {
-                    parts.push(std::mem::take(&mut part));
-                }
-                _ => {
-                    part.push(c);
+                '=' if braces == 0 && links == 0 && equals.is_none() => equals = Some(part.len()),
+                _ => {}
+            }
+            part.push(c);
+        }
+        parts.push((part, equals));
+        parts
+    }
+
+    fn starts_with(chars: &[char], s: &str) -> bool {
+        let mut chars = chars.iter();
+        s.chars()
+            .all(|c| chars.next().is_some_and(|d| d.eq_ignore_ascii_case(&c)))
+    }
+
+    /// The position of the next `s` in `chars`, from `from` on.
+    fn find(chars: &[char], from: usize, s: &str) -> Option<usize> {
+        (from..chars.len()).find(|pos| Self::starts_with(&chars[*pos..], s))
+    }
+
+    /// If a quoted string starts at `pos`, where it ends. Quotes without a
+    /// closing one, apostrophes within words (`Children's`) and runs of
+    /// apostrophes (`''italics''`) do not start one. The closing quote has to
+    /// be in the same part: a top-level `|key=` ends the search, so a stray
+    /// `5"` does not swallow the parameters after it.
+    fn closing_quote(chars: &[char], pos: usize) -> Option<usize> {
+        let quote = chars[pos];
+        match quote {
+            '"' => {}
+            '\'' => {
+                let before = pos.checked_sub(1).map(|p| chars[p]);
+                if before.is_some_and(|c| c.is_alphanumeric() || c == '\'')
+                    || chars.get(pos + 1) == Some(&'\'')
+                {
+                    return None;
                 }
             }
+            _ => return None,
         }
-        parts.push(part);
-        if quoted {
-            return Err(anyhow!("Unclosed quote: {quote_char}"));
+        let mut braces: usize = 0;
+        for (p, c) in chars.iter().enumerate().skip(pos + 1) {
+            match c {
+                c if *c == quote => return Some(p),
+                '{' => braces += 1,
+                '}' => braces = braces.saturating_sub(1),
+                '|' if braces == 0 && Self::starts_param(&chars[p + 1..]) => return None,
+                _ => {}
+            }
         }
+        None
+    }
 
-        let params: HashMap<String, String> = parts
+    /// Whether `chars` start with a parameter name followed by `=`.
+    fn starts_param(chars: &[char]) -> bool {
+        let name = chars
             .iter()
-            .filter_map(|part_tmp| {
-                let pos = part_tmp.find('=')?;
-                let k = part_tmp.get(0..pos)?.trim().to_string();
-                let v = part_tmp.get(pos + 1..)?.trim().to_string();
-                Some((k, v))
-            })
-            .collect();
-        Ok(Self { params })
+            .take_while(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | ' '))
+            .count();
+        name > 0 && chars.get(name) == Some(&'=')
     }
 
     pub const fn params(&self) -> &HashMap<String, String> {
         &self.params
     }
 
+    /// Replaces the `{{!}}` and `{{=}}` magic words with `|` and `=`. Those
+    /// within other templates are left alone, as replacing them there would
+    /// change the arguments of those templates; they get their pipes when
+    /// expanded (see [`Self::keys_to_expand`]).
     pub fn fix_values(&mut self) {
         for v in self.params.values_mut() {
-            if v.contains("{{!}}") {
-                *v = v.replace("{{!}}", "|");
+            if v.contains("{{!}}") || v.contains("{{=}}") {
+                *v = Self::replace_magic_words(v);
             }
         }
-        // TODO proper template replacement
+    }
+
+    /// Only `{{`/`}}` pairs nest: single braces are SPARQL groups, and the
+    /// magic words within them are replaced.
+    fn replace_magic_words(value: &str) -> String {
+        let mut ret = String::with_capacity(value.len());
+        let mut templates: usize = 0;
+        let mut rest = value;
+        while let Some(c) = rest.chars().next() {
+            if templates == 0 {
+                if let Some(after) = rest.strip_prefix("{{!}}") {
+                    ret.push('|');
+                    rest = after;
+                    continue;
+                }
+                if let Some(after) = rest.strip_prefix("{{=}}") {
+                    ret.push('=');
+                    rest = after;
+                    continue;
+                }
+            }
+            if let Some(after) = rest.strip_prefix("{{") {
+                templates += 1;
+                ret += "{{";
+                rest = after;
+                continue;
+            }
+            if templates > 0
+                && let Some(after) = rest.strip_prefix("}}")
+            {
+                templates -= 1;
+                ret += "}}";
+                rest = after;
+                continue;
+            }
+            ret.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+        ret
+    }
+
+    /// Keys of the values with templates or magic words in them, to be
+    /// expanded with `action=expandtemplates`. `sparql` is expanded just
+    /// before the query runs, and the templates of a `pattern` are meant to
+    /// get each row's values, so neither is included.
+    #[must_use]
+    pub fn keys_to_expand(&self) -> Vec<String> {
+        let mut ret: Vec<String> = self
+            .params
+            .iter()
+            .filter(|(k, v)| {
+                v.contains("{{") && !["sparql", "pattern"].contains(&k.to_lowercase().as_str())
+            })
+            .map(|(k, _v)| k.to_owned())
+            .collect();
+        ret.sort();
+        ret
+    }
+
+    pub fn set_value(&mut self, key: &str, value: String) {
+        self.params.insert(key.to_string(), value);
     }
 
     /// Get a template parameter value by key (case-insensitive).
     pub fn get_value(&self, key: &str) -> Option<String> {
-        self.params
-            .iter()
-            .find(|(k, _v)| k.eq_ignore_ascii_case(key))
-            .map(|(_k, v)| v.clone())
+        self.get(key).cloned()
+    }
+
+    /// A template parameter value by key; an exact match first, then
+    /// ignoring case.
+    pub fn get(&self, key: &str) -> Option<&String> {
+        self.params.get(key).or_else(|| {
+            self.params
+                .iter()
+                .find(|(k, _v)| k.eq_ignore_ascii_case(key))
+                .map(|(_k, v)| v)
+        })
     }
 }
 
@@ -142,8 +295,22 @@ mod tests {
 
     #[test]
     fn test_new_from_params_unclosed_quote() {
-        let result = Template::new_from_params("param1=\"unclosed");
-        assert!(result.is_err());
+        // A quote without a closing one is just a character
+        let t = Template::new_from_params("param1=\"unclosed|param2=it's").unwrap();
+        assert_eq!(t.params.get("param1"), Some(&"\"unclosed".to_string()));
+        assert_eq!(t.params.get("param2"), Some(&"it's".to_string()));
+    }
+
+    #[test]
+    fn test_new_from_params_quote_ends_at_next_param() {
+        let t = Template::new_from_params("summary_label=5\" records|columns=label|sort=\"x\"")
+            .unwrap();
+        assert_eq!(
+            t.params.get("summary_label"),
+            Some(&"5\" records".to_string())
+        );
+        assert_eq!(t.params.get("columns"), Some(&"label".to_string()));
+        assert_eq!(t.params.get("sort"), Some(&"\"x\"".to_string()));
     }
 
     #[test]
@@ -188,6 +355,103 @@ mod tests {
         assert_eq!(t.params.get("p3"), Some(&"|start".to_string()));
     }
 
+    #[test]
+    fn test_new_from_params_wikilinks() {
+        let t = Template::new_from_params(
+            "summary_label=[[File:A.jpg|20px]] [[Foo|bar]]|p2=[[Help:Links|x=y]]",
+        )
+        .unwrap();
+        assert_eq!(
+            t.params.get("summary_label"),
+            Some(&"[[File:A.jpg|20px]] [[Foo|bar]]".to_string())
+        );
+        assert_eq!(t.params.get("p2"), Some(&"[[Help:Links|x=y]]".to_string()));
+    }
+
+    #[test]
+    fn test_new_from_params_apostrophes() {
+        let t = Template::new_from_params(
+            "sparql=SELECT ?item { ?item rdfs:label \"O'Brien|x\"@en }|summary_label=''Kids'' books|sort=label",
+        )
+        .unwrap();
+        assert_eq!(
+            t.params.get("sparql"),
+            Some(&"SELECT ?item { ?item rdfs:label \"O'Brien|x\"@en }".to_string())
+        );
+        assert_eq!(
+            t.params.get("summary_label"),
+            Some(&"''Kids'' books".to_string())
+        );
+        assert_eq!(t.params.get("sort"), Some(&"label".to_string()));
+    }
+
+    #[test]
+    fn test_new_from_params_comments_and_nowiki() {
+        let t = Template::new_from_params(
+            "sort=label<!-- or P569|links=red -->|pattern=<nowiki>$label | $p569</nowiki>|p3=a<nowiki/>b",
+        )
+        .unwrap();
+        assert_eq!(t.params.len(), 3);
+        assert_eq!(t.params.get("sort"), Some(&"label".to_string()));
+        assert_eq!(t.params.get("pattern"), Some(&"$label | $p569".to_string()));
+        assert_eq!(t.params.get("p3"), Some(&"ab".to_string()));
+    }
+
+    #[test]
+    fn test_new_from_params_nested_parser_functions() {
+        let t = Template::new_from_params(
+            "section={{#if:{{{1|}}}|P31|{{#switch:x|a=P17|P131}}}}|freq=7",
+        )
+        .unwrap();
+        assert_eq!(
+            t.params.get("section"),
+            Some(&"{{#if:{{{1|}}}|P31|{{#switch:x|a=P17|P131}}}}".to_string())
+        );
+        assert_eq!(t.params.get("freq"), Some(&"7".to_string()));
+        // A positional parameter with `=` inside a template has no key
+        let t = Template::new_from_params("{{foo|a=b}}|c=d").unwrap();
+        assert_eq!(t.params.len(), 1);
+        assert_eq!(t.params.get("c"), Some(&"d".to_string()));
+    }
+
+    #[test]
+    fn test_fix_values_leaves_other_templates() {
+        let mut t = Template::new_from_params("a=x{{=}}y|b={{#if:1|p{{!}}q}}").unwrap();
+        t.fix_values();
+        assert_eq!(t.params.get("a"), Some(&"x=y".to_string()));
+        assert_eq!(t.params.get("b"), Some(&"{{#if:1|p{{!}}q}}".to_string()));
+    }
+
+    #[test]
+    fn test_fix_values_inside_sparql_groups() {
+        let mut t = Template::new_from_params(
+            "sparql=SELECT ?item { ?item wdt:P31 wd:Q5 . { ?item wdt:P27 wd:Q183 } UNION { ?item wdt:P27 wd:Q40 } FILTER(?a {{!}}{{!}} ?b) }",
+        )
+        .unwrap();
+        t.fix_values();
+        assert_eq!(
+            t.params.get("sparql"),
+            Some(&"SELECT ?item { ?item wdt:P31 wd:Q5 . { ?item wdt:P27 wd:Q183 } UNION { ?item wdt:P27 wd:Q40 } FILTER(?a || ?b) }".to_string())
+        );
+    }
+
+    #[test]
+    fn test_fix_values_next_to_other_templates() {
+        let mut t = Template::new_from_params("a=a{{!}}b {{foo}}|b={{x|{{=}}}}{{=}}").unwrap();
+        t.fix_values();
+        assert_eq!(t.params.get("a"), Some(&"a|b {{foo}}".to_string()));
+        assert_eq!(t.params.get("b"), Some(&"{{x|{{=}}}}=".to_string()));
+    }
+
+    #[test]
+    fn test_keys_to_expand() {
+        let t = Template::new_from_params(
+            "sparql=SELECT {{X}}|pattern={{flag|$p17}}|summary_label={{PAGENAME}}|sort=label",
+        )
+        .unwrap();
+        assert_eq!(t.keys_to_expand(), ["summary_label"]);
+    }
+
     // --- get_value (case-insensitive lookup) ---
 
     #[test]
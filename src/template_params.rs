@@ -1,9 +1,113 @@
 //! Template parameter definitions and validation.
 
-use crate::{configuration::Configuration, template::Template};
+use crate::{
+    column_type::ColumnType, configuration::Configuration, date_format::DateFormat,
+    list_pattern::ListPattern, template::Template,
+};
 use regex::Regex;
 use std::sync::LazyLock;
 
+/// A parameter with a fixed set of values: the words a list can use
+/// (compared ignoring case) and what they stand for. The parsers below and
+/// [`crate::param_validation`] both go by these.
+pub trait ParamValue: Copy + 'static {
+    const VALUES: &'static [(&'static str, Self)];
+
+    /// What `s` stands for; `None` if it is not one of the values.
+    fn from_value(s: &str) -> Option<Self> {
+        let s = s.trim();
+        Self::VALUES
+            .iter()
+            .find(|(value, _)| value.eq_ignore_ascii_case(s))
+            .map(|(_, ret)| *ret)
+    }
+
+    /// The words, in the order of [`Self::VALUES`].
+    fn values() -> Vec<&'static str> {
+        Self::VALUES.iter().map(|(value, _)| *value).collect()
+    }
+}
+
+/// `yes` / `no`.
+impl ParamValue for bool {
+    const VALUES: &'static [(&'static str, Self)] = &[("yes", true), ("no", false)];
+}
+
+/// What a parameter accepts, for [`crate::param_validation`].
+#[derive(Debug, Clone, Copy)]
+pub enum ParamValues {
+    /// Free text, or syntax checked elsewhere.
+    Any,
+    OneOf(fn() -> Vec<&'static str>),
+    /// Any number of these, separated by commas, semicolons or spaces.
+    Options(fn() -> Vec<&'static str>),
+    Number,
+    /// `label`, `family_name`, a property or a `?variable`.
+    Sort,
+    /// A property (with or without `P`) or an `@variable`.
+    Section,
+}
+
+/// The parameters a list understands. Names are compared ignoring case.
+pub const PARAMETERS: &[(&str, ParamValues)] = &[
+    ("sparql", ParamValues::Any),
+    ("columns", ParamValues::Any),
+    ("sort", ParamValues::Sort),
+    ("sort_order", ParamValues::OneOf(SortOrder::values)),
+    ("section", ParamValues::Section),
+    ("min_section", ParamValues::Number),
+    ("links", ParamValues::OneOf(LinksType::values)),
+    ("language", ParamValues::Any),
+    ("thumb", ParamValues::Number),
+    ("row_template", ParamValues::Any),
+    (
+        "row_template_params",
+        ParamValues::Options(RowTemplateParams::values),
+    ),
+    ("header_template", ParamValues::Any),
+    (
+        "autolist",
+        ParamValues::OneOf(TemplateParams::autodesc_values),
+    ),
+    (
+        "autodesc",
+        ParamValues::OneOf(TemplateParams::autodesc_values),
+    ),
+    (
+        "summary",
+        ParamValues::OneOf(TemplateParams::summary_values),
+    ),
+    ("summary_label", ParamValues::Any),
+    ("skip_table", ParamValues::Any),
+    ("one_row_per_item", ParamValues::OneOf(bool::values)),
+    ("wdedit", ParamValues::OneOf(bool::values)),
+    (
+        "references",
+        ParamValues::OneOf(ReferencesParameter::values),
+    ),
+    ("references_group", ParamValues::Any),
+    ("wikibase", ParamValues::Any),
+    ("freq", ParamValues::Number),
+    ("date_format", ParamValues::Any),
+    ("julian", ParamValues::OneOf(bool::values)),
+    (
+        "sort_values",
+        ParamValues::OneOf(SortValuesParameter::values),
+    ),
+    ("images", ParamValues::Any),
+    ("format", ParamValues::OneOf(OutputFormat::values)),
+    ("pattern", ParamValues::Any),
+    (
+        "disambiguation",
+        ParamValues::OneOf(DisambiguationMode::values),
+    ),
+    (
+        "redirects",
+        ParamValues::Options(RedirectsParameter::values),
+    ),
+    ("diagnostics", ParamValues::OneOf(bool::values)),
+];
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum LinksType {
     All,
@@ -14,16 +118,116 @@ pub enum LinksType {
     Reasonator,
 }
 
+impl ParamValue for LinksType {
+    const VALUES: &'static [(&'static str, Self)] = &[
+        ("all", Self::All),
+        ("local", Self::Local),
+        ("red", Self::Red),
+        ("red_only", Self::RedOnly),
+        ("text", Self::Text),
+        ("reasonator", Self::Reasonator),
+    ];
+}
+
 impl LinksType {
     pub fn new_from_string(s: String) -> Self {
-        match s.trim().to_uppercase().as_str() {
-            "LOCAL" => Self::Local,
-            "RED" => Self::Red,
-            "RED_ONLY" => Self::RedOnly,
-            "TEXT" => Self::Text,
-            "REASONATOR" => Self::Reasonator,
-            _ => Self::All, // Fallback, default
+        Self::from_value(&s).unwrap_or(Self::All) // Fallback, default
+    }
+}
+
+/// The `redirects=` parameter: how links to local redirects are treated,
+/// e.g. `resolve, sections_missing`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RedirectsParameter {
+    /// Link straight to the redirect target.
+    resolve: bool,
+    /// With `links=red_only`, treat an article that is only a redirect to a
+    /// section of another one as missing.
+    sections_missing: bool,
+}
+
+/// Each option on its own.
+impl ParamValue for RedirectsParameter {
+    const VALUES: &'static [(&'static str, Self)] = &[
+        (
+            "resolve",
+            Self {
+                resolve: true,
+                ..Self::none()
+            },
+        ),
+        (
+            "sections_missing",
+            Self {
+                sections_missing: true,
+                ..Self::none()
+            },
+        ),
+    ];
+}
+
+impl RedirectsParameter {
+    pub const fn none() -> Self {
+        Self {
+            resolve: false,
+            sections_missing: false,
+        }
+    }
+
+    /// Unknown options are skipped with a warning.
+    pub fn new(os: Option<&String>) -> Self {
+        let mut ret = Self::none();
+        let Some(s) = os else {
+            return ret;
+        };
+        for option in s.split([',', ';', ' ']).map(str::trim).filter(|o| !o.is_empty()) {
+            match Self::from_value(option) {
+                Some(other) => {
+                    ret.resolve |= other.resolve;
+                    ret.sections_missing |= other.sections_missing;
+                }
+                None => log::warn!("Unknown redirects option '{option}'"),
+            }
         }
+        ret
+    }
+
+    pub const fn resolve(self) -> bool {
+        self.resolve
+    }
+
+    pub const fn sections_missing(self) -> bool {
+        self.sections_missing
+    }
+}
+
+/// The `disambiguation=` parameter: what `links=red`/`red_only` do when a
+/// label-derived page title turns out to be a disambiguation page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DisambiguationMode {
+    /// Keep the link as it is (the default).
+    Plain,
+    /// Keep the link, marked with a `listeria-disambiguation` class.
+    Flag,
+    /// Link to `Label (description)` instead.
+    Description,
+    /// Link to the item on Wikidata instead.
+    Wikidata,
+}
+
+impl ParamValue for DisambiguationMode {
+    const VALUES: &'static [(&'static str, Self)] = &[
+        ("plain", Self::Plain),
+        ("flag", Self::Flag),
+        ("description", Self::Description),
+        ("wikidata", Self::Wikidata),
+        ("item", Self::Wikidata),
+    ];
+}
+
+impl DisambiguationMode {
+    pub fn new(os: Option<&String>) -> Self {
+        os.and_then(|s| Self::from_value(s)).unwrap_or(Self::Plain)
     }
 }
 
@@ -69,39 +273,349 @@ pub enum SortOrder {
     Descending,
 }
 
+impl ParamValue for SortOrder {
+    const VALUES: &'static [(&'static str, Self)] =
+        &[("asc", Self::Ascending), ("desc", Self::Descending)];
+}
+
 impl SortOrder {
     #[must_use]
     pub fn new(os: Option<&String>) -> Self {
-        match os {
-            Some(s) => {
-                if s.to_uppercase().trim() == "DESC" {
-                    Self::Descending
-                } else {
-                    Self::Ascending
-                }
-            }
-            None => Self::Ascending,
-        }
+        os.and_then(|s| Self::from_value(s))
+            .unwrap_or(Self::Ascending)
     }
 }
 
+/// The `references=` parameter: which statement references to show.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ReferencesParameter {
     None,
     All,
+    /// Only the first reference of each statement.
+    First,
+    /// All references except those imported from a Wikimedia project (P143).
+    External,
+}
+
+impl ParamValue for ReferencesParameter {
+    const VALUES: &'static [(&'static str, Self)] = &[
+        ("none", Self::None),
+        ("all", Self::All),
+        ("first", Self::First),
+        ("external", Self::External),
+        ("no_wikipedia", Self::External),
+    ];
 }
 
 impl ReferencesParameter {
     pub fn new(os: Option<&String>) -> Self {
-        match os {
-            Some(s) => {
-                if s.to_uppercase().trim() == "ALL" {
-                    Self::All
-                } else {
-                    Self::None
+        os.and_then(|s| Self::from_value(s)).unwrap_or(Self::None)
+    }
+}
+
+/// The `sort_values=` parameter: which cells get a `data-sort-value` so
+/// that client-side table sorting follows the data rather than the text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortValuesParameter {
+    /// No sort values at all.
+    None,
+    /// Dates and quantities, in tables only (the default).
+    Default,
+    /// Dates and quantities, also passed to row templates.
+    Dates,
+    /// Dates, quantities and entity labels, also passed to row templates.
+    All,
+}
+
+impl ParamValue for SortValuesParameter {
+    const VALUES: &'static [(&'static str, Self)] = &[
+        ("default", Self::Default),
+        ("no", Self::None),
+        ("none", Self::None),
+        ("yes", Self::Dates),
+        ("dates", Self::Dates),
+        ("all", Self::All),
+        ("labels", Self::All),
+    ];
+}
+
+impl SortValuesParameter {
+    pub fn new(os: Option<&String>) -> Self {
+        os.and_then(|s| Self::from_value(s))
+            .unwrap_or(Self::Default)
+    }
+
+    pub const fn entity_labels(self) -> bool {
+        matches!(self, Self::All)
+    }
+
+    /// Whether a `row_template` gets the sort values as `<key>_sort`
+    /// parameters; only when the list asks for them.
+    pub const fn row_template(self) -> bool {
+        matches!(self, Self::Dates | Self::All)
+    }
+}
+
+/// The `row_template_params=` parameter: what a `row_template` gets besides
+/// the rendered cells, e.g. `labels, raw`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RowTemplateParams {
+    /// Name cell parameters after explicit column labels (`P569:born`)
+    /// rather than the column keys.
+    labels: bool,
+    /// Also pass each cell's plain value as `<name>_raw`.
+    raw: bool,
+    /// Pass the row's item and section as `_item` and `_section`.
+    item: bool,
+}
+
+/// Each option on its own.
+impl ParamValue for RowTemplateParams {
+    const VALUES: &'static [(&'static str, Self)] = &[
+        (
+            "labels",
+            Self {
+                labels: true,
+                ..Self::none()
+            },
+        ),
+        (
+            "label",
+            Self {
+                labels: true,
+                ..Self::none()
+            },
+        ),
+        (
+            "raw",
+            Self {
+                raw: true,
+                ..Self::none()
+            },
+        ),
+        (
+            "item",
+            Self {
+                item: true,
+                ..Self::none()
+            },
+        ),
+        (
+            "items",
+            Self {
+                item: true,
+                ..Self::none()
+            },
+        ),
+    ];
+}
+
+impl RowTemplateParams {
+    pub const fn none() -> Self {
+        Self {
+            labels: false,
+            raw: false,
+            item: false,
+        }
+    }
+
+    /// Unknown options are skipped with a warning.
+    pub fn new(os: Option<&String>) -> Self {
+        let mut ret = Self::none();
+        let Some(s) = os else {
+            return ret;
+        };
+        for option in s.split([',', ';', ' ']).map(str::trim).filter(|o| !o.is_empty()) {
+            match Self::from_value(option) {
+                Some(other) => {
+                    ret.labels |= other.labels;
+                    ret.raw |= other.raw;
+                    ret.item |= other.item;
                 }
+                None => log::warn!("Unknown row_template_params option '{option}'"),
             }
-            None => Self::None,
+        }
+        ret
+    }
+
+    pub const fn labels(self) -> bool {
+        self.labels
+    }
+
+    pub const fn raw(self) -> bool {
+        self.raw
+    }
+
+    pub const fn item(self) -> bool {
+        self.item
+    }
+}
+
+/// The `date_format=` parameter: a list-wide format and/or per-column
+/// formats, e.g. `long` or `long, P571:year, P580/P585:iso`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DateFormatParameter {
+    default: Option<DateFormat>,
+    columns: Vec<(ColumnType, DateFormat)>,
+}
+
+impl DateFormatParameter {
+    pub const fn none() -> Self {
+        Self {
+            default: None,
+            columns: Vec::new(),
+        }
+    }
+
+    /// Unknown formats are skipped with a warning.
+    pub fn new(os: Option<&String>) -> Self {
+        let mut ret = Self::none();
+        let Some(s) = os else { return ret };
+        for entry in s.split([',', ';']).map(str::trim).filter(|e| !e.is_empty()) {
+            let (column, format) = match entry.rsplit_once(':') {
+                Some((column, format)) => (Some(ColumnType::new(column)), format),
+                None => (None, entry),
+            };
+            let Some(format) = DateFormat::new(format) else {
+                log::warn!("Ignoring unknown date_format entry: {entry}");
+                continue;
+            };
+            match column {
+                Some(column) => ret.columns.push((column, format)),
+                None => ret.default = Some(format),
+            }
+        }
+        ret
+    }
+
+    /// The format for `column`, or the list-wide one; `None` if neither is set.
+    pub fn for_column(&self, column: Option<&ColumnType>) -> Option<DateFormat> {
+        column
+            .and_then(|column| {
+                self.columns
+                    .iter()
+                    .find(|(c, _)| c == column)
+                    .map(|(_, f)| *f)
+            })
+            .or(self.default)
+    }
+}
+
+/// Per-column image options, from the `images=` parameter.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImageOptions {
+    /// Thumbnail width in pixels; the list's `thumb=` size if unset.
+    pub size: Option<u64>,
+    /// Qualifier whose monolingual text in the list language is shown as
+    /// the caption, e.g. P2096 (media legend).
+    pub caption: Option<String>,
+    /// Keep all images instead of the first one, as a small gallery.
+    pub all: bool,
+}
+
+impl ImageOptions {
+    /// Space-separated options: `200px`, `caption=P2096`, `all`. Returns
+    /// `None` if any of them is unknown.
+    fn new(s: &str) -> Option<Self> {
+        static RE_CAPTION: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"^(?i)caption=P(\d+)$").expect("RE_CAPTION does not parse")
+        });
+        let mut ret = Self::default();
+        for option in s.split_whitespace() {
+            if option.eq_ignore_ascii_case("all") {
+                ret.all = true;
+            } else if let Some(caps) = RE_CAPTION.captures(option) {
+                ret.caption = Some(format!("P{}", &caps[1]));
+            } else {
+                let size = option.strip_suffix("px").unwrap_or(option);
+                ret.size = Some(size.parse::<u64>().ok().filter(|size| *size > 0)?);
+            }
+        }
+        Some(ret)
+    }
+
+    /// `self`, with the options it does not set taken from `other`.
+    fn or(&self, other: &Self) -> Self {
+        Self {
+            size: self.size.or(other.size),
+            caption: self.caption.clone().or_else(|| other.caption.clone()),
+            all: self.all || other.all,
+        }
+    }
+}
+
+/// The `images=` parameter: options for all image columns and/or per column,
+/// e.g. `200px` or `P18:200px caption=P2096 all, P94:64px`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImageOptionsParameter {
+    default: ImageOptions,
+    columns: Vec<(ColumnType, ImageOptions)>,
+}
+
+impl ImageOptionsParameter {
+    /// Entries with unknown options are skipped with a warning.
+    pub fn new(os: Option<&String>) -> Self {
+        let mut ret = Self::default();
+        let Some(s) = os else { return ret };
+        for entry in s.split([',', ';']).map(str::trim).filter(|e| !e.is_empty()) {
+            let (column, options) = match entry.split_once(':') {
+                Some((column, options)) => (Some(ColumnType::new(column)), options),
+                None => (None, entry),
+            };
+            let Some(options) = ImageOptions::new(options) else {
+                log::warn!("Ignoring unknown images entry: {entry}");
+                continue;
+            };
+            match column {
+                Some(column) => ret.columns.push((column, options)),
+                None => ret.default = options,
+            }
+        }
+        ret
+    }
+
+    /// The options for `column`, completed by the list-wide ones.
+    pub fn for_column(&self, column: Option<&ColumnType>) -> ImageOptions {
+        column
+            .and_then(|column| self.columns.iter().find(|(c, _)| c == column))
+            .map_or_else(|| self.default.clone(), |(_, o)| o.or(&self.default))
+    }
+}
+
+/// The `format=` parameter: how the rows are laid out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Table,
+    /// A `<gallery>` of the rows' first images, captioned with the other
+    /// columns.
+    Gallery,
+    /// A bulleted list, one `pattern=` line per row.
+    List,
+    /// A numbered list, one `pattern=` line per row.
+    OrderedList,
+}
+
+impl ParamValue for OutputFormat {
+    const VALUES: &'static [(&'static str, Self)] = &[
+        ("table", Self::Table),
+        ("gallery", Self::Gallery),
+        ("list", Self::List),
+        ("ul", Self::List),
+        ("ol", Self::OrderedList),
+    ];
+}
+
+impl OutputFormat {
+    pub fn new(os: Option<&String>) -> Self {
+        os.and_then(|s| Self::from_value(s)).unwrap_or(Self::Table)
+    }
+
+    /// The wikitext list marker, for the list formats.
+    pub const fn list_marker(self) -> Option<&'static str> {
+        match self {
+            Self::List => Some("*"),
+            Self::OrderedList => Some("#"),
+            Self::Table | Self::Gallery => None,
         }
     }
 }
@@ -146,6 +660,10 @@ pub struct TemplateParams {
     section: SectionType,
     min_section: u64,
     row_template: Option<String>,
+    row_template_params: RowTemplateParams,
+    disambiguation: DisambiguationMode,
+    redirects: RedirectsParameter,
+    diagnostics: Option<bool>,
     header_template: Option<String>,
     autodesc: Option<String>,
     summary: Option<String>,
@@ -157,6 +675,13 @@ pub struct TemplateParams {
     sort_order: SortOrder,
     wikibase: String,
     freq: u64,
+    date_format: DateFormatParameter,
+    julian: Option<bool>,
+    sort_values: SortValuesParameter,
+    references_group: Option<String>,
+    images: ImageOptionsParameter,
+    format: OutputFormat,
+    pattern: Option<ListPattern>,
 }
 
 impl Default for TemplateParams {
@@ -173,6 +698,10 @@ impl TemplateParams {
             section: SectionType::None,
             min_section: 2,
             row_template: None,
+            row_template_params: RowTemplateParams::none(),
+            disambiguation: DisambiguationMode::Plain,
+            redirects: RedirectsParameter::none(),
+            diagnostics: None,
             header_template: None,
             autodesc: None,
             summary: None,
@@ -184,33 +713,60 @@ impl TemplateParams {
             sort_order: SortOrder::Ascending,
             wikibase: String::new(),
             freq: 0,
+            date_format: DateFormatParameter::none(),
+            julian: None,
+            sort_values: SortValuesParameter::Default,
+            references_group: None,
+            images: ImageOptionsParameter {
+                default: ImageOptions {
+                    size: None,
+                    caption: None,
+                    all: false,
+                },
+                columns: Vec::new(),
+            },
+            format: OutputFormat::Table,
+            pattern: None,
         }
     }
 
     pub fn new_from_params(template: &Template, config: &Configuration) -> Self {
         Self {
             links: LinksType::All,
-            sort: SortMode::new(template.params().get("sort")),
-            section: SectionType::new_from_string_option(template.params().get("section")),
+            sort: SortMode::new(template.get("sort")),
+            section: SectionType::new_from_string_option(template.get("section")),
             min_section: Self::parse_min_section(template),
-            row_template: template.params().get("row_template").map(|s| s.trim().to_string()),
-            header_template: template.params().get("header_template").map(|s| s.trim().to_string()),
+            row_template: template.get("row_template").map(|s| s.trim().to_string()),
+            row_template_params: RowTemplateParams::new(template.get("row_template_params")),
+            disambiguation: DisambiguationMode::new(template.get("disambiguation")),
+            redirects: RedirectsParameter::new(template.get("redirects")),
+            diagnostics: Self::parse_yes_no(template, "diagnostics"),
+            header_template: template.get("header_template").map(|s| s.trim().to_string()),
             autodesc: Self::parse_autodesc(template),
-            summary: template.params().get("summary").map(|s| s.trim().to_uppercase()),
-            summary_label: template.params().get("summary_label").map(|s| s.trim().to_string()),
-            skip_table: template.params().contains_key("skip_table"),
+            summary: template.get("summary").map(|s| s.trim().to_uppercase()),
+            summary_label: template.get("summary_label").map(|s| s.trim().to_string()),
+            skip_table: template.get("skip_table").is_some(),
             one_row_per_item: Self::parse_one_row_per_item(template),
             wdedit: Self::parse_flag_yes(template, "wdedit"),
-            references: ReferencesParameter::new(template.params().get("references")),
-            sort_order: SortOrder::new(template.params().get("sort_order")),
+            references: ReferencesParameter::new(template.get("references")),
+            sort_order: SortOrder::new(template.get("sort_order")),
             wikibase: Self::parse_wikibase(template, config),
-            freq: template.params().get("freq").and_then(|s| s.trim().parse::<u64>().ok()).unwrap_or(0),
+            freq: template.get("freq").and_then(|s| s.trim().parse::<u64>().ok()).unwrap_or(0),
+            date_format: DateFormatParameter::new(template.get("date_format")),
+            julian: Self::parse_yes_no(template, "julian"),
+            sort_values: SortValuesParameter::new(template.get("sort_values")),
+            references_group: Self::parse_references_group(template),
+            images: ImageOptionsParameter::new(template.get("images")),
+            format: OutputFormat::new(template.get("format")),
+            pattern: template
+                .get("pattern")
+                .filter(|s| !s.trim().is_empty())
+                .map(|s| ListPattern::new(s)),
         }
     }
 
     fn parse_min_section(template: &Template) -> u64 {
         template
-            .params()
             .get("min_section")
             .and_then(|s| s.parse::<u64>().ok())
             .unwrap_or(2)
@@ -218,31 +774,45 @@ impl TemplateParams {
 
     fn parse_autodesc(template: &Template) -> Option<String> {
         template
-            .params()
             .get("autolist")
-            .or_else(|| template.params().get("autodesc"))
+            .or_else(|| template.get("autodesc"))
             .map(|s| s.trim().to_uppercase())
     }
 
+    /// The values of `autolist=` / `autodesc=` that have an effect.
+    fn autodesc_values() -> Vec<&'static str> {
+        vec!["fallback"]
+    }
+
+    /// The values of `summary=` that have an effect.
+    fn summary_values() -> Vec<&'static str> {
+        vec!["itemnumber"]
+    }
+
     fn parse_one_row_per_item(template: &Template) -> bool {
-        template
-            .params()
-            .get("one_row_per_item")
-            .map(|s| s.trim().to_uppercase())
-            != Some("NO".to_string())
+        Self::parse_yes_no(template, "one_row_per_item") != Some(false)
     }
 
     fn parse_flag_yes(template: &Template, key: &str) -> bool {
+        Self::parse_yes_no(template, key) == Some(true)
+    }
+
+    /// `yes` / `no`, or `None` if the parameter is absent or anything else.
+    fn parse_yes_no(template: &Template, key: &str) -> Option<bool> {
+        bool::from_value(template.get(key)?)
+    }
+
+    /// `references_group=`; quotes are dropped as they would end the
+    /// `group="…"` attribute.
+    fn parse_references_group(template: &Template) -> Option<String> {
         template
-            .params()
-            .get(key)
-            .map(|s| s.trim().to_uppercase())
-            == Some("YES".to_string())
+            .get("references_group")
+            .map(|s| s.replace('"', "").trim().to_string())
+            .filter(|s| !s.is_empty())
     }
 
     fn parse_wikibase(template: &Template, config: &Configuration) -> String {
         template
-            .params()
             .get("wikibase")
             .map(|s| s.trim().to_uppercase())
             .unwrap_or_else(|| config.get_default_api().to_string())
@@ -292,10 +862,33 @@ impl TemplateParams {
         self.summary_label.as_deref().unwrap_or("items")
     }
 
+    /// The `summary_label=` value, if the template sets one. Without it the
+    /// renderer uses the localised default label.
+    pub fn custom_summary_label(&self) -> Option<&str> {
+        self.summary_label.as_deref()
+    }
+
     pub const fn row_template(&self) -> &Option<String> {
         &self.row_template
     }
 
+    pub const fn row_template_params(&self) -> RowTemplateParams {
+        self.row_template_params
+    }
+
+    pub const fn disambiguation(&self) -> DisambiguationMode {
+        self.disambiguation
+    }
+
+    pub const fn redirects(&self) -> RedirectsParameter {
+        self.redirects
+    }
+
+    /// `diagnostics=yes`/`no`; `None` to follow the wiki's setting.
+    pub const fn diagnostics(&self) -> Option<bool> {
+        self.diagnostics
+    }
+
     pub const fn header_template(&self) -> &Option<String> {
         &self.header_template
     }
@@ -315,6 +908,38 @@ impl TemplateParams {
     pub const fn freq(&self) -> u64 {
         self.freq
     }
+
+    pub const fn date_format(&self) -> &DateFormatParameter {
+        &self.date_format
+    }
+
+    /// The `julian=yes|no` override for annotating Julian-calendar dates.
+    pub const fn julian(&self) -> Option<bool> {
+        self.julian
+    }
+
+    pub const fn sort_values(&self) -> SortValuesParameter {
+        self.sort_values
+    }
+
+    /// The `references_group=` name; references then go into a
+    /// `<references group=… />` block after the list.
+    pub fn references_group(&self) -> Option<&str> {
+        self.references_group.as_deref()
+    }
+
+    pub const fn images(&self) -> &ImageOptionsParameter {
+        &self.images
+    }
+
+    pub const fn format(&self) -> OutputFormat {
+        self.format
+    }
+
+    /// The `pattern=` for list lines; without one, the cells are joined.
+    pub const fn pattern(&self) -> Option<&ListPattern> {
+        self.pattern.as_ref()
+    }
 }
 
 #[cfg(test)]
@@ -506,6 +1131,15 @@ mod tests {
             ReferencesParameter::All
         );
 
+        assert_eq!(
+            ReferencesParameter::new(Some(&" first ".to_string())),
+            ReferencesParameter::First
+        );
+        assert_eq!(
+            ReferencesParameter::new(Some(&"external".to_string())),
+            ReferencesParameter::External
+        );
+
         assert_eq!(
             ReferencesParameter::new(Some(&"NONE".to_string())),
             ReferencesParameter::None
@@ -517,6 +1151,149 @@ mod tests {
         assert_eq!(ReferencesParameter::new(None), ReferencesParameter::None);
     }
 
+    #[test]
+    fn test_sort_values_parameter_new() {
+        assert_eq!(SortValuesParameter::new(None), SortValuesParameter::Default);
+        assert_eq!(
+            SortValuesParameter::new(Some(&" no ".to_string())),
+            SortValuesParameter::None
+        );
+        assert_eq!(
+            SortValuesParameter::new(Some(&"all".to_string())),
+            SortValuesParameter::All
+        );
+        assert_eq!(
+            SortValuesParameter::new(Some(&"anything".to_string())),
+            SortValuesParameter::Default
+        );
+        assert_eq!(
+            SortValuesParameter::new(Some(&"yes".to_string())),
+            SortValuesParameter::Dates
+        );
+        assert!(SortValuesParameter::All.entity_labels());
+        assert!(!SortValuesParameter::Default.entity_labels());
+        assert!(!SortValuesParameter::Dates.entity_labels());
+        // Row templates get sort values only when asked for
+        assert!(!SortValuesParameter::Default.row_template());
+        assert!(!SortValuesParameter::None.row_template());
+        assert!(SortValuesParameter::Dates.row_template());
+        assert!(SortValuesParameter::All.row_template());
+    }
+
+    #[test]
+    fn test_date_format_parameter_new() {
+        let p = DateFormatParameter::new(Some(&"long, P571:year; P580/P585:iso, P1:fancy".to_string()));
+        assert_eq!(p.for_column(None), Some(DateFormat::Long));
+        assert_eq!(
+            p.for_column(Some(&ColumnType::new("P571"))),
+            Some(DateFormat::Year)
+        );
+        assert_eq!(
+            p.for_column(Some(&ColumnType::new("P580/P585"))),
+            Some(DateFormat::Iso)
+        );
+        // Unlisted columns use the list-wide format; the bad entry is ignored
+        assert_eq!(
+            p.for_column(Some(&ColumnType::new("P1"))),
+            Some(DateFormat::Long)
+        );
+        assert_eq!(DateFormatParameter::new(None).for_column(None), None);
+    }
+
+    #[test]
+    fn test_image_options_parameter_new() {
+        let p = ImageOptionsParameter::new(Some(
+            &"200px, P18:300 caption=p2096 all; P94:64px, P41:huge".to_string(),
+        ));
+        assert_eq!(p.for_column(None).size, Some(200));
+        assert_eq!(
+            p.for_column(Some(&ColumnType::new("P18"))),
+            ImageOptions {
+                size: Some(300),
+                caption: Some("P2096".to_string()),
+                all: true,
+            }
+        );
+        let logo = p.for_column(Some(&ColumnType::new("P94")));
+        assert_eq!(logo.size, Some(64));
+        assert!(!logo.all);
+        // The bad entry is ignored, so P41 gets the list-wide options
+        assert_eq!(p.for_column(Some(&ColumnType::new("P41"))).size, Some(200));
+        assert_eq!(
+            ImageOptionsParameter::new(None).for_column(None),
+            ImageOptions::default()
+        );
+    }
+
+    #[test]
+    fn test_redirects_parameter_new() {
+        assert_eq!(RedirectsParameter::new(None), RedirectsParameter::none());
+        let redirects = RedirectsParameter::new(Some(&"resolve, sections_missing".to_string()));
+        assert!(redirects.resolve());
+        assert!(redirects.sections_missing());
+        let redirects = RedirectsParameter::new(Some(&"Resolve".to_string()));
+        assert!(redirects.resolve());
+        assert!(!redirects.sections_missing());
+    }
+
+    #[test]
+    fn test_disambiguation_mode_new() {
+        assert_eq!(DisambiguationMode::new(None), DisambiguationMode::Plain);
+        assert_eq!(
+            DisambiguationMode::new(Some(&"flag".to_string())),
+            DisambiguationMode::Flag
+        );
+        assert_eq!(
+            DisambiguationMode::new(Some(&" Description".to_string())),
+            DisambiguationMode::Description
+        );
+        assert_eq!(
+            DisambiguationMode::new(Some(&"wikidata".to_string())),
+            DisambiguationMode::Wikidata
+        );
+        assert_eq!(
+            DisambiguationMode::new(Some(&"bogus".to_string())),
+            DisambiguationMode::Plain
+        );
+    }
+
+    #[test]
+    fn test_row_template_params_new() {
+        assert_eq!(RowTemplateParams::new(None), RowTemplateParams::none());
+        let params = RowTemplateParams::new(Some(&"labels, RAW".to_string()));
+        assert!(params.labels());
+        assert!(params.raw());
+        assert!(!params.item());
+        let params = RowTemplateParams::new(Some(&"raw bogus".to_string()));
+        assert!(!params.labels());
+        assert!(params.raw());
+        let params = RowTemplateParams::new(Some(&"item".to_string()));
+        assert!(params.item());
+        assert!(!params.raw());
+    }
+
+    #[test]
+    fn test_output_format_new() {
+        assert_eq!(
+            OutputFormat::new(Some(&" Gallery ".to_string())),
+            OutputFormat::Gallery
+        );
+        assert_eq!(
+            OutputFormat::new(Some(&"table".to_string())),
+            OutputFormat::Table
+        );
+        assert_eq!(OutputFormat::new(None), OutputFormat::Table);
+        assert_eq!(
+            OutputFormat::new(Some(&"ol".to_string())).list_marker(),
+            Some("#")
+        );
+        assert_eq!(
+            OutputFormat::new(Some(&"list".to_string())).list_marker(),
+            Some("*")
+        );
+        assert_eq!(OutputFormat::Gallery.list_marker(), None);
+    }
+
     #[test]
     fn test_section_type_new_property() {
         match SectionType::new_from_string_option(Some(&"P31".to_string())) {
@@ -591,6 +1368,29 @@ mod tests {
         assert_eq!(params.freq(), 0);
     }
 
+    #[test]
+    fn test_new_from_params_ignores_name_case() {
+        let template = Template::new_from_params("Sort=P31|SORT_ORDER=desc|Format=OL").unwrap();
+        let params = TemplateParams::new_from_params(&template, &Configuration::default());
+        assert!(matches!(params.sort(), SortMode::Property(p) if p == "P31"));
+        assert_eq!(params.sort_order(), &SortOrder::Descending);
+        assert_eq!(params.format(), OutputFormat::OrderedList);
+    }
+
+    #[test]
+    fn test_param_value_from_value() {
+        assert_eq!(
+            LinksType::from_value(" Red_Only "),
+            Some(LinksType::RedOnly)
+        );
+        assert_eq!(LinksType::from_value("blue"), None);
+        assert_eq!(bool::from_value("YES"), Some(true));
+        assert_eq!(
+            RowTemplateParams::values(),
+            ["labels", "label", "raw", "item", "items"]
+        );
+    }
+
     #[test]
     fn test_set_links() {
         let mut params = TemplateParams::new();
@@ -625,6 +1425,12 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_custom_summary_label_none_by_default() {
+        let params = TemplateParams::new();
+        assert_eq!(params.custom_summary_label(), None);
+    }
+
     #[test]
     fn test_summary_label_default() {
         let params = TemplateParams::new();
@@ -161,7 +161,8 @@ impl WikiApis {
             "{}/w/api.php",
             self.site_matrix.get_server_url_for_wiki(wiki)?
         );
-        self.create_wiki_api_from_api_url(&api_url, self.config.oauth2_token())
+        let edit_delay = self.config.wiki_settings(wiki).ms_delay_after_edit;
+        self.create_wiki_api_from_api_url(&api_url, self.config.oauth2_token(), edit_delay)
             .await
     }
 
@@ -169,6 +170,7 @@ impl WikiApis {
         &self,
         api_url: &str,
         oauth2_token: &str,
+        ms_delay_after_edit: Option<u64>,
     ) -> Result<ApiArc> {
         let builder = wikimisc::mediawiki::reqwest::Client::builder()
             .timeout(self.config.api_timeout())
@@ -178,7 +180,7 @@ impl WikiApis {
             .brotli(true);
         let mut mw_api = Api::new_from_builder(api_url, builder).await?;
         mw_api.set_oauth2(oauth2_token);
-        mw_api.set_edit_delay(self.config.ms_delay_after_edit()); // Slow down editing a bit
+        mw_api.set_edit_delay(ms_delay_after_edit); // Slow down editing a bit
         let mw_api = Arc::new(mw_api);
         Ok(mw_api)
     }
@@ -1,5 +1,6 @@
 //! Result tracking for processed wiki pages.
 
+use crate::listeria_error::{ErrorCode, ListeriaError};
 use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone)]
@@ -10,6 +11,10 @@ pub struct WikiPageResult {
     message: String,
     duration: Option<Duration>,
     completed: Option<Instant>,
+    /// The bot edited the page.
+    edited: bool,
+    /// Why the page failed, if it did; `message` has the details.
+    code: Option<ErrorCode>,
 }
 
 impl WikiPageResult {
@@ -22,6 +27,27 @@ impl WikiPageResult {
             message,
             duration: None,
             completed: None,
+            edited: false,
+            code: None,
+        }
+    }
+
+    /// The result for a page that failed with `code`; the status follows
+    /// from the code (see [`ErrorCode::status`]).
+    #[must_use]
+    pub fn failed_with(wiki: &str, page: &str, code: ErrorCode, message: String) -> Self {
+        let mut ret = Self::new(wiki, page, code.status(), message);
+        ret.code = Some(code);
+        ret
+    }
+
+    /// The result for a page that failed with `error`. Untyped errors are
+    /// FAIL, and [`Self::standardize_message`] may still find a code for them.
+    #[must_use]
+    pub fn from_error(wiki: &str, page: &str, error: &anyhow::Error) -> Self {
+        match ListeriaError::from_anyhow(error) {
+            Some(e) => Self::failed_with(wiki, page, e.code(), e.to_string()),
+            None => Self::fail(wiki, page, &error.to_string()),
         }
     }
 
@@ -45,6 +71,11 @@ impl WikiPageResult {
         &self.message
     }
 
+    #[must_use]
+    pub const fn code(&self) -> Option<ErrorCode> {
+        self.code
+    }
+
     #[must_use]
     pub fn fail(wiki: &str, page: &str, message: &str) -> Self {
         Self::new(wiki, page, "FAIL", message.to_string())
@@ -66,16 +97,32 @@ impl WikiPageResult {
         self.completed = Some(completed);
     }
 
+    pub const fn edited(&self) -> bool {
+        self.edited
+    }
+
+    pub const fn set_edited(&mut self, edited: bool) {
+        self.edited = edited;
+    }
+
+    /// Derives a code from the message of an untyped error, and shortens
+    /// the message for the common ones. Results that already have a code
+    /// are left alone.
     pub fn standardize_message(&mut self) {
+        if self.code.is_some() {
+            return;
+        }
         // Circuit-open errors should NOT be marked FAIL: the page itself is
         // fine, the upstream is just temporarily blocked. Use the DEFERRED
-        // status so the row sits out of the dispatcher queue until cleared
-        // on the next bot startup (PageStatusRepository::clear_deferred).
+        // status so the row sits out of the dispatcher queue until the
+        // circuit closes again (ListeriaBot::recover_deferred) or the bot
+        // restarts.
         // Matched first so it wins over any of the more specific error
         // patterns below.
         if self.message.contains("circuit open") {
             self.result = "DEFERRED".into();
             self.message = "CIRCUIT_OPEN".into();
+            self.code = Some(ErrorCode::CircuitOpen);
             return;
         }
         if self
@@ -84,33 +131,40 @@ impl WikiPageResult {
         {
             self.result = "TRANSLATION".into();
             self.message = "This page is a translation".into();
+            self.code = Some(ErrorCode::TranslationPage);
         }
         if self
             .message
             .contains("Connection reset by peer (os error 104)")
         {
             self.message = "104_RESET_BY_PEER".into();
+            self.code = Some(ErrorCode::ConnectionReset);
         }
         if self.message.contains("api.php): operation timed out") {
             self.message = "WIKI_TIMEOUT".into();
+            self.code = Some(ErrorCode::WikiTimeout);
         }
         if self.message.contains("/sparql): operation timed out") {
             self.message = "SPARQL_TIMEOUT".into();
+            self.code = Some(ErrorCode::SparqlTimeout);
         }
         if self
             .message
             .contains("expected value at line 1 column 1: SPARQL-QUERY:")
         {
             self.message = "SPARQL_ERROR".into();
+            self.code = Some(ErrorCode::SparqlError);
         }
         if self.message.contains("No 'sparql' parameter in Template") {
-            self.message = format!("SPARQL_ERROR {}", self.message);
+            self.message = format!("MISSING_SPARQL_PARAM {}", self.message);
+            self.code = Some(ErrorCode::MissingSparqlParam);
         }
         if self
             .message
             .contains("Could not determine SPARQL variable for item")
         {
             self.message = format!("SPARQL_ERROR {}", self.message);
+            self.code = Some(ErrorCode::SparqlError);
         }
     }
 }
@@ -141,6 +195,14 @@ mod tests {
         assert_eq!(result.message(), "Something went wrong");
     }
 
+    #[test]
+    fn test_set_edited() {
+        let mut result = WikiPageResult::new("enwiki", "Test", "OK", "Done".to_string());
+        assert!(!result.edited());
+        result.set_edited(true);
+        assert!(result.edited());
+    }
+
     #[test]
     fn test_set_runtime() {
         let mut result = WikiPageResult::new("enwiki", "Test", "OK", "Done".to_string());
@@ -259,12 +321,13 @@ mod tests {
 
         result.standardize_message();
 
-        assert!(result.message().starts_with("SPARQL_ERROR"));
+        assert!(result.message().starts_with("MISSING_SPARQL_PARAM"));
         assert!(
             result
                 .message()
                 .contains("No 'sparql' parameter in Template")
         );
+        assert_eq!(result.code(), Some(ErrorCode::MissingSparqlParam));
     }
 
     #[test]
@@ -316,6 +379,7 @@ mod tests {
         result.standardize_message();
         assert_eq!(result.result(), "DEFERRED");
         assert_eq!(result.message(), "CIRCUIT_OPEN");
+        assert_eq!(result.code(), Some(ErrorCode::CircuitOpen));
     }
 
     #[test]
@@ -364,6 +428,31 @@ mod tests {
         assert_eq!(result.message(), original_message);
     }
 
+    #[test]
+    fn test_from_error_typed() {
+        let error: anyhow::Error = ListeriaError::ProtectedPage("Protected".to_string()).into();
+        let mut result = WikiPageResult::from_error("enwiki", "Test", &error);
+        result.standardize_message();
+        assert_eq!(result.result(), "BROKEN");
+        assert_eq!(result.code(), Some(ErrorCode::ProtectedPage));
+        assert_eq!(result.message(), "The page is protected: Protected");
+
+        let error: anyhow::Error = ListeriaError::PageMissing.into();
+        let result = WikiPageResult::from_error("enwiki", "Test", &error);
+        assert_eq!(result.result(), "DELETED");
+    }
+
+    #[test]
+    fn test_from_error_untyped() {
+        let error = anyhow::anyhow!("Error calling api.php): operation timed out");
+        let mut result = WikiPageResult::from_error("enwiki", "Test", &error);
+        assert_eq!(result.result(), "FAIL");
+        assert_eq!(result.code(), None);
+        result.standardize_message();
+        assert_eq!(result.message(), "WIKI_TIMEOUT");
+        assert_eq!(result.code(), Some(ErrorCode::WikiTimeout));
+    }
+
     #[test]
     fn test_getters_with_different_values() {
         let result = WikiPageResult::new(